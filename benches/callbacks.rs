@@ -0,0 +1,389 @@
+//! Cost of every `PlanetAI` callback of the AI, in random and adaptive mode, with logging
+//! disabled and enabled.
+//!
+//! The AI runs in a real planet, wrapped in an AI that times each callback and counts the
+//! allocations made during it on the planet thread. Run with `cargo bench`; an argument keeps
+//! the benchmarks whose name contains it, e.g. `cargo bench -- adaptive/logging`, and
+//! `ICB_BENCH_ROUNDS` changes the number of rounds of each workload.
+
+use common_game::components::planet::{
+    DummyPlanetState, Planet, PlanetAI, PlanetState, PlanetType,
+};
+use common_game::components::resource::{
+    BasicResource, BasicResourceType, Combinator, ComplexResourceRequest, ComplexResourceType,
+    Generator,
+};
+use common_game::components::rocket::Rocket;
+use common_game::components::sunray::Sunray;
+use common_game::protocols::orchestrator_planet::OrchestratorToPlanet;
+use common_game::protocols::planet_explorer::{ExplorerToPlanet, PlanetToExplorer};
+use common_game::utils::ID;
+use immutable_cosmic_borrow::Ai;
+use immutable_cosmic_borrow::bridge::PlanetOptions;
+use immutable_cosmic_borrow::testing::{PlanetHarness, expect};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+const DEFAULT_ROUNDS: usize = 2000;
+const EXPLORER: ID = 1;
+
+// Names of the measured callbacks
+const CALLBACKS: [&str; 12] = [
+    "sunray",
+    "asteroid",
+    "internal_state",
+    "explorer/supported_resource",
+    "explorer/supported_combination",
+    "explorer/generate_resource",
+    "explorer/combine_resource",
+    "explorer/available_cells",
+    "explorer_arrival",
+    "explorer_departure",
+    "start",
+    "stop",
+];
+
+// Counts the allocations of each thread
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<u64> = const { Cell::new(0) };
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn count_allocation() {
+    // The counter may already be gone while the thread exits
+    let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+}
+
+fn allocations() -> u64 {
+    ALLOCATIONS.try_with(Cell::get).unwrap_or(0)
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count_allocation();
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        count_allocation();
+        unsafe { System.alloc_zeroed(layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        count_allocation();
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+// Formats every record into a line like a real logger would, without writing it anywhere
+struct SinkLogger;
+
+static LOGGER: SinkLogger = SinkLogger;
+
+impl log::Log for SinkLogger {
+    fn enabled(&self, _: &log::Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record) {
+        let line = format!("{} {}\n", record.level(), record.args());
+        let _ = io::sink().write_all(line.as_bytes());
+    }
+
+    fn flush(&self) {}
+}
+
+#[derive(Clone, Copy)]
+struct Sample {
+    time: Duration,
+    allocations: u64,
+}
+
+type Samples = Arc<Mutex<BTreeMap<&'static str, Vec<Sample>>>>;
+
+// The AI under test, measured callback by callback
+struct Measured {
+    ai: Ai,
+    samples: Samples,
+}
+
+impl Measured {
+    fn measure<T>(&mut self, callback: &'static str, f: impl FnOnce(&mut Ai) -> T) -> T {
+        let allocations_before = allocations();
+        let start = Instant::now();
+        let result = f(&mut self.ai);
+        let sample = Sample {
+            time: start.elapsed(),
+            allocations: allocations() - allocations_before,
+        };
+        self.samples
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(callback)
+            .or_default()
+            .push(sample);
+        result
+    }
+}
+
+impl PlanetAI for Measured {
+    fn handle_sunray(
+        &mut self,
+        state: &mut PlanetState,
+        generator: &Generator,
+        combinator: &Combinator,
+        sunray: Sunray,
+    ) {
+        self.measure("sunray", |ai| {
+            ai.handle_sunray(state, generator, combinator, sunray);
+        });
+    }
+
+    fn handle_asteroid(
+        &mut self,
+        state: &mut PlanetState,
+        generator: &Generator,
+        combinator: &Combinator,
+    ) -> Option<Rocket> {
+        self.measure("asteroid", |ai| {
+            ai.handle_asteroid(state, generator, combinator)
+        })
+    }
+
+    fn handle_internal_state_req(
+        &mut self,
+        state: &mut PlanetState,
+        generator: &Generator,
+        combinator: &Combinator,
+    ) -> DummyPlanetState {
+        self.measure("internal_state", |ai| {
+            ai.handle_internal_state_req(state, generator, combinator)
+        })
+    }
+
+    fn handle_explorer_msg(
+        &mut self,
+        state: &mut PlanetState,
+        generator: &Generator,
+        combinator: &Combinator,
+        msg: ExplorerToPlanet,
+    ) -> Option<PlanetToExplorer> {
+        let callback = match msg {
+            ExplorerToPlanet::SupportedResourceRequest { .. } => "explorer/supported_resource",
+            ExplorerToPlanet::SupportedCombinationRequest { .. } => {
+                "explorer/supported_combination"
+            }
+            ExplorerToPlanet::GenerateResourceRequest { .. } => "explorer/generate_resource",
+            ExplorerToPlanet::CombineResourceRequest { .. } => "explorer/combine_resource",
+            ExplorerToPlanet::AvailableEnergyCellRequest { .. } => "explorer/available_cells",
+        };
+        self.measure(callback, |ai| {
+            ai.handle_explorer_msg(state, generator, combinator, msg)
+        })
+    }
+
+    fn on_explorer_arrival(
+        &mut self,
+        state: &mut PlanetState,
+        generator: &Generator,
+        combinator: &Combinator,
+        explorer_id: ID,
+    ) {
+        self.measure("explorer_arrival", |ai| {
+            ai.on_explorer_arrival(state, generator, combinator, explorer_id);
+        });
+    }
+
+    fn on_explorer_departure(
+        &mut self,
+        state: &mut PlanetState,
+        generator: &Generator,
+        combinator: &Combinator,
+        explorer_id: ID,
+    ) {
+        self.measure("explorer_departure", |ai| {
+            ai.on_explorer_departure(state, generator, combinator, explorer_id);
+        });
+    }
+
+    fn on_start(&mut self, state: &PlanetState, generator: &Generator, combinator: &Combinator) {
+        self.measure("start", |ai| ai.on_start(state, generator, combinator));
+    }
+
+    fn on_stop(&mut self, state: &PlanetState, generator: &Generator, combinator: &Combinator) {
+        self.measure("stop", |ai| ai.on_stop(state, generator, combinator));
+    }
+}
+
+fn spawn(options: &PlanetOptions, samples: &Samples) -> PlanetHarness {
+    let ai = Measured {
+        ai: options.create_ai(),
+        samples: samples.clone(),
+    };
+    let mut harness = PlanetHarness::spawn(|orchestrator_channels, explorers_receiver| {
+        Planet::new(
+            options.id,
+            options.planet_type,
+            Box::new(ai),
+            options.gen_rules.clone(),
+            options.comb_rules.clone(),
+            orchestrator_channels,
+            explorers_receiver,
+        )
+    })
+    .expect("Planet creation failed");
+    harness.start();
+    harness.arrive(EXPLORER);
+    harness
+}
+
+fn generate(harness: &mut PlanetHarness, resource: BasicResourceType) -> Option<BasicResource> {
+    harness.explorer_send_and_expect(
+        ExplorerToPlanet::GenerateResourceRequest {
+            explorer_id: EXPLORER,
+            resource,
+        },
+        expect::generated(),
+    )
+}
+
+// Every callback on the default planet
+fn session(options: &PlanetOptions, rounds: usize, samples: &Samples) {
+    let mut harness = spawn(options, samples);
+    for round in 0..rounds {
+        harness.sunray();
+        harness.sunray();
+        generate(&mut harness, BasicResourceType::Hydrogen);
+        for msg in [
+            ExplorerToPlanet::AvailableEnergyCellRequest {
+                explorer_id: EXPLORER,
+            },
+            ExplorerToPlanet::SupportedResourceRequest {
+                explorer_id: EXPLORER,
+            },
+            ExplorerToPlanet::SupportedCombinationRequest {
+                explorer_id: EXPLORER,
+            },
+        ] {
+            harness.explorer_send_and_expect(msg, expect::any_explorer());
+        }
+        harness.asteroid();
+        harness.send_and_expect(
+            OrchestratorToPlanet::InternalStateRequest,
+            expect::internal_state(),
+        );
+        if round % 10 == 9 {
+            harness.depart(EXPLORER);
+            harness.stop();
+            harness.start();
+            harness.arrive(EXPLORER);
+        }
+    }
+}
+
+// Combinations of water on a type B planet, the ingredients are generated first
+fn crafting(options: &PlanetOptions, rounds: usize, samples: &Samples) {
+    let options = PlanetOptions {
+        planet_type: PlanetType::B,
+        gen_rules: vec![BasicResourceType::Hydrogen, BasicResourceType::Oxygen],
+        comb_rules: vec![ComplexResourceType::Water],
+        ..options.clone()
+    };
+    let mut harness = spawn(&options, samples);
+    for _ in 0..rounds {
+        harness.sunray();
+        let hydrogen = generate(&mut harness, BasicResourceType::Hydrogen);
+        harness.sunray();
+        let oxygen = generate(&mut harness, BasicResourceType::Oxygen);
+        harness.sunray();
+        let (Some(hydrogen), Some(oxygen)) = (hydrogen, oxygen) else {
+            continue;
+        };
+        let msg = ComplexResourceRequest::Water(
+            hydrogen.to_hydrogen().expect("Hydrogen expected"),
+            oxygen.to_oxygen().expect("Oxygen expected"),
+        );
+        let _ = harness.explorer_send_and_expect(
+            ExplorerToPlanet::CombineResourceRequest {
+                explorer_id: EXPLORER,
+                msg,
+            },
+            expect::combined(),
+        );
+    }
+}
+
+fn report(name: &str, samples: &[Sample]) {
+    let mut times: Vec<Duration> = samples.iter().map(|sample| sample.time).collect();
+    times.sort_unstable();
+    let percentile = |p: usize| times[(times.len() - 1) * p / 100];
+    let mean = times.iter().sum::<Duration>() / u32::try_from(times.len()).unwrap_or(u32::MAX);
+    let allocations =
+        samples.iter().map(|sample| sample.allocations).sum::<u64>() as f64 / samples.len() as f64;
+    println!(
+        "{name:<55} time: [p50 {:>9.2?}  mean {mean:>9.2?}  p99 {:>9.2?}]  allocs/call: {allocations:>6.1}  ({} calls)",
+        percentile(50),
+        percentile(99),
+        times.len(),
+    );
+}
+
+fn main() {
+    // `cargo bench` passes `--bench`
+    let filter = std::env::args().skip(1).find(|arg| !arg.starts_with("--"));
+    let rounds = std::env::var("ICB_BENCH_ROUNDS")
+        .ok()
+        .and_then(|rounds| rounds.parse().ok())
+        .unwrap_or(DEFAULT_ROUNDS);
+    log::set_logger(&LOGGER).expect("Logger already set");
+
+    for (mode, random_mode) in [("random", true), ("adaptive", false)] {
+        for (logging, level) in [
+            ("no_logging", log::LevelFilter::Off),
+            ("logging", log::LevelFilter::Trace),
+        ] {
+            let prefix = format!("{mode}/{logging}/");
+            let selected = |name: &str| filter.as_ref().is_none_or(|filter| name.contains(filter));
+            if !CALLBACKS
+                .iter()
+                .any(|callback| selected(&format!("{prefix}{callback}")))
+            {
+                continue;
+            }
+            log::set_max_level(level);
+
+            let options = PlanetOptions {
+                random_mode,
+                basic_gen_coeff: 0.3,
+                complex_gen_coeff: 0.3,
+                ..PlanetOptions::default()
+            };
+            let samples = Samples::default();
+            session(&options, rounds, &samples);
+            crafting(&options, rounds / 4, &samples);
+
+            for (callback, samples) in samples
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .iter()
+            {
+                let name = format!("{prefix}{callback}");
+                if selected(&name) {
+                    report(&name, samples);
+                }
+            }
+        }
+    }
+}
@@ -1,18 +1,61 @@
 mod asteroid;
+mod cells;
+mod checkpoint;
+mod control;
 mod decide;
+mod energy;
+mod explain;
 mod explorer;
 mod orchestrator;
+mod policy;
+mod record;
+mod recorder;
+mod stats;
+#[cfg(test)]
+mod tests;
+mod view;
 
-use crate::frequency_counter::FrequencyCounter;
+pub use checkpoint::Checkpoint;
+
+use crate::ai::energy::{ChargePurpose, EnergyLedger};
+use crate::ai::view::PlanetView;
+use crate::clock::{Clock, SystemClock};
+use crate::frequency_counter::{FrequencyCounter, PauseMode};
+use crate::logging::{LogAction, LogConfig, Logger};
+use crate::priors::EstimatorPrior;
+use crate::replay::{RecordedDecision, SessionConfig, SessionInput, SessionRequest, SessionWriter};
+use crate::rules;
 use common_game::components::planet::PlanetAI;
 use common_game::components::planet::PlanetState;
 use common_game::components::resource::{Combinator, Generator};
 use common_game::components::rocket::Rocket;
-use common_game::logging::{ActorType, Channel, EventType, LogEvent, Participant, Payload};
+use common_game::logging::{ActorType, Channel, EventType, Participant, Payload};
 use common_game::protocols::orchestrator_planet::PlanetToOrchestrator;
 use common_game::protocols::planet_explorer::{ExplorerToPlanet, PlanetToExplorer};
 use common_game::utils::ID;
-use std::time::Duration;
+use crossbeam_channel::{Receiver, Sender};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+pub(crate) use explorer::requested_recipe;
+
+pub use control::{AiControl, AiSetting};
+pub use explain::{DecisionExplanation, OutcomeChange};
+pub use policy::{AdaptivePolicy, DecisionPolicy, PolicyInputs, PolicyOutcome, RandomPolicy};
+pub use record::{
+    DecisionInputs, DecisionOutcome, DecisionPolicyKind, DecisionReason, DecisionRecord,
+    DecisionRequest, DecisionSink,
+};
+pub use recorder::{FlightEntry, FlightEvent, FlightRecorder};
+pub use stats::{
+    AiStats, DISAGREEMENT_BACKLOG, DecisionCounters, EstimatorStats, LATENCY_BUCKETS_SECS,
+    LatencyHistogram, PlanetStats, RequestCounters, ShadowStats, StatsHandle,
+};
 
 pub struct Ai {
     is_ai_active: bool,
@@ -20,6 +63,28 @@ pub struct Ai {
     pub(crate) basic_gen_coeff: f32,
     pub(crate) complex_gen_coeff: f32,
     counters: Option<FrequencyCounter>,
+    energy: EnergyLedger,
+    stats: StatsHandle,
+    decision_sinks: Vec<Box<dyn DecisionSink>>,
+    // Policies deciding on every request alongside the active one, without acting on it
+    shadow_policies: Vec<Box<dyn DecisionPolicy>>,
+    // Policy replacing the random and adaptive modes, if any
+    policy: Option<Box<dyn DecisionPolicy>>,
+    // Requests served to each explorer, the tier of the explorer for the policies
+    served: BTreeMap<ID, u64>,
+    flight_recorder: Option<FlightRecorder>,
+    clock: Arc<dyn Clock>,
+    // Seeded generator for random mode, the thread generator is used if unset
+    rng: Option<(u64, StdRng)>,
+    session: Option<SessionWriter>,
+    checkpoint_path: Option<PathBuf>,
+    // Checkpoint loaded at construction, restored when the planet starts
+    pending_checkpoint: Option<Checkpoint>,
+    prior: Option<EstimatorPrior>,
+    log: Logger,
+    // Changes queued by the control handles, the sender is cloned into each handle
+    settings_sender: Sender<AiSetting>,
+    settings: Receiver<AiSetting>,
 }
 
 impl PlanetAI for Ai {
@@ -30,18 +95,29 @@ impl PlanetAI for Ai {
         combinator: &Combinator,
         msg: ExplorerToPlanet,
     ) -> Option<PlanetToExplorer> {
+        self.apply_settings(state);
+        self.record_input(SessionInput::ExplorerMessage(
+            msg.explorer_id(),
+            SessionRequest::from_message(&msg),
+        ));
         // Delegate to explorer::handle_message
-        explorer::handle_message(self, state, generator, combinator, msg)
+        let response = explorer::handle_message(self, state, generator, combinator, msg);
+        self.sync_planet_stats(state);
+        response
     }
 
     fn handle_asteroid(
         &mut self,
         state: &mut PlanetState,
-        generator: &Generator,
-        combinator: &Combinator,
+        _generator: &Generator,
+        _combinator: &Combinator,
     ) -> Option<Rocket> {
+        self.apply_settings(state);
+        self.record_input(SessionInput::Asteroid);
         // Delegate to asteroid::handle_asteroid
-        asteroid::handle_asteroid(self, state, generator, combinator)
+        let rocket = asteroid::handle_asteroid(self, state);
+        self.sync_planet_stats(state);
+        rocket
     }
 
     fn handle_sunray(
@@ -51,8 +127,11 @@ impl PlanetAI for Ai {
         _combinator: &Combinator,
         sunray: common_game::components::sunray::Sunray,
     ) {
+        self.apply_settings(state);
+        self.record_input(SessionInput::Sunray);
         // Delegate to orchestrator::handle_sunray
         orchestrator::handle_sunray(self, state, sunray);
+        self.sync_planet_stats(state);
     }
 
     fn handle_internal_state_req(
@@ -61,8 +140,10 @@ impl PlanetAI for Ai {
         _generator: &Generator,
         _combinator: &Combinator,
     ) -> common_game::components::planet::DummyPlanetState {
+        self.apply_settings(state);
+        self.record_input(SessionInput::InternalState);
         if let PlanetToOrchestrator::InternalStateResponse { planet_state, .. } =
-            orchestrator::handle_internal_state_request(state)
+            orchestrator::handle_internal_state_request(self, state)
         {
             return planet_state;
         }
@@ -71,28 +152,55 @@ impl PlanetAI for Ai {
 
     fn on_explorer_arrival(
         &mut self,
-        _state: &mut PlanetState,
+        state: &mut PlanetState,
         _generator: &Generator,
         _combinator: &Combinator,
-        _explorer_id: common_game::utils::ID,
+        explorer_id: common_game::utils::ID,
     ) {
+        self.apply_settings(state);
+        self.record_input(SessionInput::ExplorerArrival(explorer_id));
+        self.record_stats(|stats| {
+            if let Err(index) = stats.planet.explorers.binary_search(&explorer_id) {
+                stats.planet.explorers.insert(index, explorer_id);
+            }
+        });
     }
 
     fn on_explorer_departure(
         &mut self,
-        _state: &mut PlanetState,
+        state: &mut PlanetState,
         _generator: &Generator,
         _combinator: &Combinator,
-        _explorer_id: common_game::utils::ID,
+        explorer_id: common_game::utils::ID,
     ) {
+        self.apply_settings(state);
+        self.record_input(SessionInput::ExplorerDeparture(explorer_id));
+        self.record_stats(|stats| stats.planet.explorers.retain(|id| *id != explorer_id));
     }
 
-    fn on_start(&mut self, state: &PlanetState, _generator: &Generator, _combinator: &Combinator) {
+    fn on_start(&mut self, state: &PlanetState, generator: &Generator, combinator: &Combinator) {
+        self.start_session(state, generator, combinator);
+        self.apply_settings(state);
+        self.record_input(SessionInput::Start);
+        if let Some(checkpoint) = self.pending_checkpoint.take() {
+            self.restore_checkpoint(&checkpoint);
+        }
         orchestrator::handle_start_ai(self, state);
+        self.sync_planet_stats(state);
     }
 
     fn on_stop(&mut self, state: &PlanetState, _generator: &Generator, _combinator: &Combinator) {
+        self.apply_settings(state);
+        self.record_input(SessionInput::Stop);
         orchestrator::handle_stop_ai(self, state);
+        self.save_checkpoint_file();
+    }
+}
+
+impl Drop for Ai {
+    // The planet is dropped when it is killed, keep what was learned until then
+    fn drop(&mut self) {
+        self.save_checkpoint_file();
     }
 }
 
@@ -108,36 +216,612 @@ impl Ai {
         //check that coefficients are in bounds and eventually correct them
         let checked_basic_gen_coeff = basic_gen_coeff.clamp(0.0, 1.0);
         let checked_complex_gen_coeff = complex_gen_coeff.clamp(0.0, 1.0);
+        let (settings_sender, settings) = crossbeam_channel::unbounded();
         let ai = Ai {
             is_ai_active: false,
             random_mode,
             basic_gen_coeff: checked_basic_gen_coeff,
             complex_gen_coeff: checked_complex_gen_coeff,
             counters: Some(FrequencyCounter::new(half_life, min_time_constant)),
+            energy: EnergyLedger::new(Duration::ZERO),
+            stats: StatsHandle::default(),
+            decision_sinks: Vec::new(),
+            shadow_policies: Vec::new(),
+            policy: None,
+            served: BTreeMap::new(),
+            flight_recorder: None,
+            clock: Arc::new(SystemClock),
+            rng: None,
+            session: None,
+            checkpoint_path: None,
+            pending_checkpoint: None,
+            prior: None,
+            log: Logger::new(LogConfig::new()),
+            settings_sender,
+            settings,
         };
+        ai.sync_estimator_stats();
 
-        let mut payload = Payload::new();
-        payload.insert("random_mode".into(), random_mode.to_string());
-        payload.insert(
-            "basic_gen_coeff".into(),
-            format!("{checked_basic_gen_coeff:.4}"),
-        );
-        payload.insert(
-            "complex_gen_coeff".into(),
-            format!("{checked_complex_gen_coeff:.4}"),
+        ai.log.emit(
+            LogAction::Configuration,
+            None,
+            None,
+            EventType::InternalPlanetAction,
+            Channel::Debug,
+            || {
+                let mut payload = Payload::new();
+                payload.insert("random_mode".into(), random_mode.to_string());
+                payload.insert(
+                    "basic_gen_coeff".into(),
+                    format!("{checked_basic_gen_coeff:.4}"),
+                );
+                payload.insert(
+                    "complex_gen_coeff".into(),
+                    format!("{checked_complex_gen_coeff:.4}"),
+                );
+                payload.insert(
+                    "half_life_secs".into(),
+                    format!("{:.4}", half_life.as_secs_f32()),
+                );
+                payload.insert(
+                    "min_time_constant_secs".into(),
+                    format!("{:.4}", min_time_constant.as_secs_f32()),
+                );
+                payload
+            },
         );
-        payload.insert(
-            "half_life_secs".into(),
-            format!("{:.4}", half_life.as_secs_f32()),
+
+        ai
+    }
+
+    /// Reserves the energy cell charge for defense for `window` after every asteroid.
+    /// While the reservation is active, explorer requests are denied.
+    #[must_use]
+    pub fn with_defense_reservation(mut self, window: Duration) -> Self {
+        self.energy.set_defense_window(window);
+        self
+    }
+
+    /// Reserves the energy cell charge for defense for `window`, starting now.
+    pub fn reserve_energy_for(&mut self, window: Duration) {
+        self.energy.reserve_for(window);
+    }
+
+    /// Drops any active defense reservation.
+    pub fn release_energy_reservation(&mut self) {
+        self.energy.release();
+    }
+
+    #[must_use]
+    pub fn is_energy_reserved(&self) -> bool {
+        self.energy.is_reserved()
+    }
+
+    #[must_use]
+    pub fn defense_reservation_window(&self) -> Duration {
+        self.energy.defense_window()
+    }
+
+    /// Returns a snapshot of the statistics collected so far.
+    #[must_use]
+    pub fn stats(&self) -> AiStats {
+        self.stats.snapshot()
+    }
+
+    /// Returns a handle to the statistics that stays valid after the AI is moved into a planet.
+    #[must_use]
+    pub fn stats_handle(&self) -> StatsHandle {
+        self.stats.clone()
+    }
+
+    /// Explains the last generation or combination decision taken for `explorer_id`, `None`
+    /// if the explorer made no such request yet.
+    #[must_use]
+    pub fn explain_last_decision(&self, explorer_id: ID) -> Option<DecisionExplanation> {
+        self.stats.explain_last_decision(explorer_id)
+    }
+
+    /// Returns a handle changing the settings of the AI after it is moved into a planet.
+    #[must_use]
+    pub fn control_handle(&self) -> AiControl {
+        AiControl {
+            sender: self.settings_sender.clone(),
+        }
+    }
+
+    // Applies the changes queued by the control handles
+    fn apply_settings(&mut self, state: &impl PlanetView) {
+        while let Ok(setting) = self.settings.try_recv() {
+            self.record_input(SessionInput::Reconfigure(setting));
+            self.apply_setting(state, setting);
+        }
+    }
+
+    pub(crate) fn apply_setting(&mut self, state: &impl PlanetView, setting: AiSetting) {
+        match setting {
+            AiSetting::RandomMode(random_mode) => self.random_mode = random_mode,
+            AiSetting::BasicGenCoeff(coeff) => self.basic_gen_coeff = coeff,
+            AiSetting::ComplexGenCoeff(coeff) => self.complex_gen_coeff = coeff,
+            AiSetting::HalfLife(half_life) => {
+                if let Some(counters) = &mut self.counters {
+                    counters.set_half_life(half_life);
+                }
+            }
+            AiSetting::MinTimeConstant(min_time_constant) => {
+                if let Some(counters) = &mut self.counters {
+                    counters.set_min_time_constant(min_time_constant);
+                }
+            }
+            AiSetting::DefenseWindow(window) => self.energy.set_defense_window(window),
+            AiSetting::PauseMode(pause_mode) => {
+                if let Some(counters) = &mut self.counters {
+                    counters.set_pause_mode(pause_mode);
+                }
+            }
+            AiSetting::LogConfig(config) => self.set_log_config(config),
+        }
+        self.sync_estimator_stats();
+
+        self.log_planet_event(
+            LogAction::Configuration,
+            state,
+            None,
+            EventType::InternalPlanetAction,
+            Channel::Info,
+            || {
+                let mut payload = Payload::new();
+                payload.insert("action".into(), "reconfigure".into());
+                payload.insert("setting".into(), setting.name().into());
+                payload.insert("value".into(), setting.value_label());
+                payload
+            },
         );
-        payload.insert(
-            "min_time_constant_secs".into(),
-            format!("{:.4}", min_time_constant.as_secs_f32()),
+    }
+
+    /// Sends every decision record to `sink`, in addition to the log.
+    #[must_use]
+    pub fn with_decision_sink(mut self, sink: impl DecisionSink + 'static) -> Self {
+        self.add_decision_sink(Box::new(sink));
+        self
+    }
+
+    pub fn add_decision_sink(&mut self, sink: Box<dyn DecisionSink>) {
+        self.decision_sinks.push(sink);
+    }
+
+    /// Runs `policy` on every request the active policy decides on, without acting on it,
+    /// and counts where they disagree in [`AiStats::shadows`].
+    #[must_use]
+    pub fn with_shadow_policy(mut self, policy: impl DecisionPolicy + 'static) -> Self {
+        self.add_shadow_policy(Box::new(policy));
+        self
+    }
+
+    pub fn add_shadow_policy(&mut self, policy: Box<dyn DecisionPolicy>) {
+        self.record_stats(|stats| stats.shadows.push(ShadowStats::new(policy.name())));
+        self.shadow_policies.push(policy);
+    }
+
+    /// Decides on the requests with `policy` instead of the random or adaptive mode, once a
+    /// charge can be spent. The policy is told what followed its decisions, see
+    /// [`DecisionPolicy::observe`].
+    #[must_use]
+    pub fn with_decision_policy(self, policy: impl DecisionPolicy + 'static) -> Self {
+        self.with_boxed_decision_policy(Box::new(policy))
+    }
+
+    #[must_use]
+    pub fn with_boxed_decision_policy(mut self, policy: Box<dyn DecisionPolicy>) -> Self {
+        self.policy = Some(policy);
+        self
+    }
+
+    pub(crate) fn served_requests(&self, explorer_id: ID) -> u64 {
+        self.served.get(&explorer_id).copied().unwrap_or_default()
+    }
+
+    // Counts a served request and tells the active policy what followed its decisions
+    pub(crate) fn observe_outcome(&mut self, outcome: PolicyOutcome) {
+        if let PolicyOutcome::Served { explorer_id } = outcome {
+            *self.served.entry(explorer_id).or_default() += 1;
+        }
+        if let Some(policy) = &mut self.policy {
+            policy.observe(outcome);
+        }
+    }
+
+    /// Logs a decision record, counts it and hands it to the decision sinks.
+    pub(crate) fn emit_decision(&mut self, state: &impl PlanetView, record: &DecisionRecord) {
+        self.log_planet_event(
+            LogAction::Decisions,
+            state,
+            None,
+            EventType::InternalPlanetAction,
+            Channel::Debug,
+            || record.to_payload(),
         );
 
-        LogEvent::system(EventType::InternalPlanetAction, Channel::Debug, payload).emit();
+        let explanation = DecisionExplanation {
+            record: record.clone(),
+            estimator: self.estimator_stats(),
+            can_have_rocket: state.can_have_rocket(),
+        };
+        self.record_stats(|stats| {
+            stats.last_decisions.insert(record.explorer_id, explanation);
+            let decisions = &mut stats.decisions;
+            match (record.outcome, record.reason) {
+                (DecisionOutcome::Accepted, _) => decisions.accepted += 1,
+                (DecisionOutcome::Denied, DecisionReason::CellNotCharged) => {
+                    decisions.denied_no_charge += 1;
+                }
+                (DecisionOutcome::Denied, DecisionReason::ReservedForDefense) => {
+                    decisions.denied_reserved += 1;
+                }
+                (DecisionOutcome::Denied, _) => decisions.denied_by_policy += 1,
+                (DecisionOutcome::Unsupported, _) => decisions.unsupported += 1,
+                (DecisionOutcome::Failed, _) => decisions.failed += 1,
+            }
+        });
 
-        ai
+        for sink in &mut self.decision_sinks {
+            sink.record(record);
+        }
+        self.record_flight(FlightEvent::Decision(record.clone()));
+        self.write_session(|session, _| session.decision(&RecordedDecision::from(record)));
+    }
+
+    /// Uses `clock` for the estimator and the defense reservations, e.g. a
+    /// [`MockClock`](crate::MockClock) in tests.
+    #[must_use]
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        if let Some(counters) = &mut self.counters {
+            counters.set_clock(Arc::clone(&clock));
+        }
+        self.energy.set_clock(Arc::clone(&clock));
+        self.clock = clock;
+        self
+    }
+
+    /// Logs only the events enabled by `config`, see [`LogConfig`].
+    #[must_use]
+    pub fn with_log_config(mut self, config: LogConfig) -> Self {
+        self.set_log_config(config);
+        self
+    }
+
+    fn set_log_config(&mut self, config: LogConfig) {
+        if let Some(counters) = &mut self.counters {
+            counters.set_log_config(config);
+        }
+        self.log.set_config(config);
+    }
+
+    #[must_use]
+    pub fn log_config(&self) -> LogConfig {
+        self.log.config()
+    }
+
+    /// Draws the random mode samples from a generator seeded with `seed`, making them
+    /// reproducible.
+    #[must_use]
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = Some((seed, StdRng::seed_from_u64(seed)));
+        self
+    }
+
+    /// Sets what the threat estimator does with the time the planet spends stopped, frozen
+    /// by default.
+    #[must_use]
+    pub fn with_pause_mode(mut self, pause_mode: PauseMode) -> Self {
+        if let Some(counters) = &mut self.counters {
+            counters.set_pause_mode(pause_mode);
+        }
+        self
+    }
+
+    /// Starts the threat estimator from `prior` instead of even odds. A checkpoint, when
+    /// there is one, still takes precedence.
+    #[must_use]
+    pub fn with_estimator_prior(mut self, prior: EstimatorPrior) -> Self {
+        if let Some(counters) = &mut self.counters {
+            counters.set_prior(prior.sun_intensity(), prior.asteroid_intensity());
+        }
+        self.prior = Some(prior);
+        self.sync_estimator_stats();
+        self
+    }
+
+    /// Records every callback input and decision to a session file at `path`, which can be
+    /// replayed with [`replay`](crate::replay::replay). The recording starts when the planet is
+    /// started. If no seed was given, a random one is picked so the session can be replayed.
+    ///
+    /// # Errors
+    /// Returns an error if the file can not be created.
+    pub fn with_session_recording(mut self, path: impl Into<PathBuf>) -> Result<Self, String> {
+        self.session = Some(SessionWriter::create(&path.into())?);
+        if self.rng.is_none() {
+            self = self.with_seed(rand::random());
+        }
+        Ok(self)
+    }
+
+    /// Loads the learned state saved at `path`, if any, and saves it back there when the
+    /// planet is stopped or dropped, so a planet relaunched with the same file starts warm.
+    /// The state is restored when the planet is started, and written to the session header
+    /// when the session is recorded.
+    ///
+    /// # Errors
+    /// Returns an error if the file exists but is not a valid checkpoint.
+    pub fn with_checkpoint_file(mut self, path: impl Into<PathBuf>) -> Result<Self, String> {
+        let path = path.into();
+        self.pending_checkpoint = Checkpoint::load(&path)?;
+        self.checkpoint_path = Some(path);
+        Ok(self)
+    }
+
+    // Restores `checkpoint` when the planet is started, without saving it back, e.g. to
+    // replay a session recorded with a checkpoint file
+    pub(crate) fn with_restored_checkpoint(mut self, checkpoint: Checkpoint) -> Self {
+        self.pending_checkpoint = Some(checkpoint);
+        self
+    }
+
+    /// Saves the learned state to `path`, see [`Ai::with_checkpoint_file`].
+    ///
+    /// # Errors
+    /// Returns an error if the file can not be written.
+    pub fn save_checkpoint(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        self.checkpoint().save(path.as_ref())
+    }
+
+    fn checkpoint(&self) -> Checkpoint {
+        // Not started yet, nothing new was learned
+        if let Some(checkpoint) = &self.pending_checkpoint {
+            return checkpoint.clone();
+        }
+        Checkpoint {
+            estimator: self.counters.as_ref().map(FrequencyCounter::learned_state),
+            reserved_for: self.energy.remaining_reservation(),
+            served: self.served.clone(),
+        }
+    }
+
+    fn restore_checkpoint(&mut self, checkpoint: &Checkpoint) {
+        if let (Some(counters), Some(estimator)) = (&mut self.counters, &checkpoint.estimator) {
+            counters.restore(estimator);
+        }
+        if let Some(window) = checkpoint.reserved_for {
+            self.energy.reserve_for(window);
+        }
+        self.served.clone_from(&checkpoint.served);
+        self.sync_estimator_stats();
+    }
+
+    fn save_checkpoint_file(&self) {
+        let Some(path) = &self.checkpoint_path else {
+            return;
+        };
+        if let Err(err) = self.save_checkpoint(path) {
+            self.log.emit(
+                LogAction::Failures,
+                None,
+                None,
+                EventType::InternalPlanetAction,
+                Channel::Warning,
+                || {
+                    let mut payload = Payload::new();
+                    payload.insert("action".into(), "checkpoint_failed".into());
+                    payload.insert("error".into(), err);
+                    payload
+                },
+            );
+        }
+    }
+
+    pub(crate) fn now(&self) -> Instant {
+        self.clock.now()
+    }
+
+    pub(crate) fn random_sample(&mut self) -> f32 {
+        match &mut self.rng {
+            Some((_, rng)) => rng.random(),
+            None => rand::random(),
+        }
+    }
+
+    // Writes the session header on the first start
+    fn start_session(
+        &mut self,
+        state: &PlanetState,
+        generator: &Generator,
+        combinator: &Combinator,
+    ) {
+        if self.session.as_ref().is_none_or(SessionWriter::is_started) {
+            return;
+        }
+        let config = SessionConfig {
+            random_mode: self.random_mode,
+            basic_gen_coeff: self.basic_gen_coeff,
+            complex_gen_coeff: self.complex_gen_coeff,
+            half_life: self
+                .counters
+                .as_ref()
+                .map_or(Duration::ZERO, FrequencyCounter::half_life),
+            min_time_constant: self
+                .counters
+                .as_ref()
+                .map_or(Duration::ZERO, FrequencyCounter::min_time_constant),
+            defense_window: self.energy.defense_window(),
+            seed: self.rng.as_ref().map_or(0, |(seed, _)| *seed),
+            planet_id: state.id(),
+            planet_type: rules::planet_type_of(state.cells_count(), state.can_have_rocket()),
+            gen_rules: generator.all_available_recipes().into_iter().collect(),
+            comb_rules: combinator.all_available_recipes().into_iter().collect(),
+            prior: self.prior,
+            pause_mode: self
+                .counters
+                .as_ref()
+                .map_or(PauseMode::default(), FrequencyCounter::pause_mode),
+            policy: self.policy.as_ref().map(|policy| policy.name().to_string()),
+            // Restored right after the header is written, at the first start
+            checkpoint: self.pending_checkpoint.clone(),
+        };
+        self.write_session(|session, now| session.start(now, &config));
+    }
+
+    fn record_input(&mut self, input: SessionInput) {
+        self.write_session(|session, now| session.input(now, input));
+    }
+
+    // Stops the recording on the first write error
+    fn write_session(&mut self, f: impl FnOnce(&mut SessionWriter, Instant) -> io::Result<()>) {
+        let now = self.now();
+        let Some(session) = &mut self.session else {
+            return;
+        };
+        if let Err(err) = f(session, now) {
+            self.session = None;
+            self.log.emit(
+                LogAction::Failures,
+                None,
+                None,
+                EventType::InternalPlanetAction,
+                Channel::Warning,
+                || {
+                    let mut payload = Payload::new();
+                    payload.insert("action".into(), "session_recording_failed".into());
+                    payload.insert("error".into(), err.to_string());
+                    payload
+                },
+            );
+        }
+    }
+
+    /// Keeps the last events of the AI in `recorder`, see [`FlightRecorder`].
+    #[must_use]
+    pub fn with_flight_recorder(mut self, recorder: FlightRecorder) -> Self {
+        self.flight_recorder = Some(recorder);
+        self
+    }
+
+    /// Returns a handle to the flight recorder, if any.
+    #[must_use]
+    pub fn flight_recorder(&self) -> Option<FlightRecorder> {
+        self.flight_recorder.clone()
+    }
+
+    pub(crate) fn record_flight(&self, event: FlightEvent) {
+        if let Some(recorder) = &self.flight_recorder {
+            recorder.push(event);
+        }
+    }
+
+    // Appends the flight recorder buffer to its dump file, if any
+    pub(crate) fn dump_flight(&self, state: &impl PlanetView, reason: &str) {
+        let Some(recorder) = &self.flight_recorder else {
+            return;
+        };
+        if let Err(err) = recorder.dump_to_file(state.id(), reason) {
+            self.log_planet_event(
+                LogAction::Failures,
+                state,
+                None,
+                EventType::InternalPlanetAction,
+                Channel::Warning,
+                || {
+                    let mut payload = Payload::new();
+                    payload.insert("action".into(), "flight_recorder_dump_failed".into());
+                    payload.insert("reason".into(), reason.into());
+                    payload.insert("error".into(), err.to_string());
+                    payload
+                },
+            );
+        }
+    }
+
+    pub(crate) fn record_stats(&self, f: impl FnOnce(&mut AiStats)) {
+        self.stats.update(f);
+    }
+
+    // Copies the current estimator values into the statistics
+    pub(crate) fn sync_estimator_stats(&self) {
+        let estimator = self.estimator_stats();
+        self.record_stats(|stats| stats.estimator = estimator);
+    }
+
+    // Keeps the current estimator values in the flight recorder
+    pub(crate) fn record_estimator(&self) {
+        if let Some(estimator) = self.estimator_stats() {
+            self.record_flight(FlightEvent::Estimator(estimator));
+        }
+    }
+
+    // Copies the planet state into the statistics, after every callback
+    fn sync_planet_stats(&self, state: &impl PlanetView) {
+        self.record_stats(|stats| {
+            stats.planet.energy_cells = state.cells_count();
+            stats.planet.charged_cells = cells::charged_cells(state);
+            stats.planet.has_rocket = state.has_rocket();
+        });
+    }
+
+    fn estimator_stats(&self) -> Option<EstimatorStats> {
+        self.counters.as_ref().map(|counters| {
+            let (sun_intensity, asteroid_intensity) = counters.debug_stats();
+            EstimatorStats {
+                sun_intensity,
+                asteroid_intensity,
+                sunray_probability: counters.last_sunray_probability(),
+                time_constant_secs: counters.current_tau(),
+            }
+        })
+    }
+
+    pub(crate) fn energy(&self) -> &EnergyLedger {
+        &self.energy
+    }
+
+    pub(crate) fn energy_mut(&mut self) -> &mut EnergyLedger {
+        &mut self.energy
+    }
+
+    /// Logs and counts a charge spent on `purpose` from cell `cell`.
+    pub(crate) fn record_consumption(
+        &mut self,
+        state: &impl PlanetView,
+        cell: usize,
+        purpose: ChargePurpose,
+    ) {
+        self.energy.record_consumption();
+        self.log_planet_event(
+            LogAction::Energy,
+            state,
+            None,
+            EventType::InternalPlanetAction,
+            Channel::Debug,
+            || {
+                let mut payload = Payload::new();
+                payload.insert("action".into(), "energy_consumed".into());
+                payload.insert("purpose".into(), purpose.label());
+                payload.insert("cell".into(), cell.to_string());
+                payload.insert(
+                    "charged_cells".into(),
+                    cells::charged_cells(state).to_string(),
+                );
+                payload.insert("reserved".into(), self.energy.is_reserved().to_string());
+                payload.insert(
+                    "consumed_charges".into(),
+                    self.energy.consumed_charges().to_string(),
+                );
+                payload
+            },
+        );
+        self.record_stats(|stats| {
+            stats.charges_consumed += 1;
+            if purpose == ChargePurpose::Rocket {
+                stats.rockets_built += 1;
+            }
+        });
     }
 
     pub(crate) fn counters_mut(&mut self) -> &mut Option<FrequencyCounter> {
@@ -159,7 +843,7 @@ impl Ai {
         self.complex_gen_coeff
     }
 
-    pub(crate) fn planet_participant(state: &PlanetState) -> Participant {
+    pub(crate) fn planet_participant(state: &impl PlanetView) -> Participant {
         Participant::new(ActorType::Planet, state.id())
     }
 
@@ -171,20 +855,23 @@ impl Ai {
         Participant::new(ActorType::Explorer, explorer_id)
     }
 
+    /// Logs an event sent by the planet, `payload` is only called if the event is logged.
     pub(crate) fn log_planet_event(
-        state: &PlanetState,
+        &self,
+        action: LogAction,
+        state: &impl PlanetView,
         receiver: Option<Participant>,
         event_type: EventType,
         channel: Channel,
-        payload: Payload,
+        payload: impl FnOnce() -> Payload,
     ) {
-        LogEvent::new(
+        self.log.emit(
+            action,
             Some(Ai::planet_participant(state)),
             receiver,
             event_type,
             channel,
             payload,
-        )
-        .emit();
+        );
     }
 }
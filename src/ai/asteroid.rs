@@ -1,89 +1,90 @@
+use crate::ai::cells;
+use crate::ai::energy::ChargePurpose;
+use crate::ai::policy::PolicyOutcome;
+use crate::ai::recorder::FlightEvent;
+use crate::ai::view::PlanetView;
 use crate::ai::{self, Ai};
-use common_game::components::planet::PlanetState;
-use common_game::components::resource::{Combinator, Generator};
-use common_game::components::rocket::Rocket;
+use crate::logging::LogAction;
 use common_game::logging::{Channel, EventType, Payload};
 
-pub fn handle_asteroid(
-    ai: &mut Ai,
-    state: &mut PlanetState,
-    _generator: &Generator,
-    _combinator: &Combinator,
-) -> Option<Rocket> {
+pub fn handle_asteroid<P: PlanetView>(ai: &mut Ai, state: &mut P) -> Option<P::Rocket> {
     if !ai.is_ai_active {
-        let mut payload = Payload::new();
-        payload.insert("action".into(), "ignore_asteroid_ai_inactive".into());
-        ai::Ai::log_planet_event(
-            state,
-            Some(ai::Ai::orchestrator_participant()),
-            EventType::InternalPlanetAction,
-            Channel::Debug,
-            payload,
-        );
+        log_action(ai, state, "ignore_asteroid_ai_inactive");
         return None;
     }
-    let counter_payload = if let Some(counters) = ai.counters_mut() {
+    let estimate = ai.counters_mut().as_mut().map(|counters| {
         counters.update_asteroid();
+        (counters.debug_stats(), counters.sunray_probability())
+    });
 
-        let (sun_intensity, asteroid_intensity) = counters.debug_stats();
-        let probability = counters.sunray_probability();
+    ai.sync_estimator_stats();
+    ai.record_estimator();
 
-        let mut payload = Payload::new();
-        payload.insert("action".into(), "update_asteroid_counter".into());
-        payload.insert("sun_intensity".into(), format!("{sun_intensity:.6}"));
-        payload.insert(
-            "asteroid_intensity".into(),
-            format!("{asteroid_intensity:.6}"),
-        );
-        payload.insert("sunray_probability".into(), format!("{probability:.6}"));
-
-        Some(payload)
-    } else {
-        None
-    };
-
-    if let Some(payload) = counter_payload {
-        ai::Ai::log_planet_event(
+    if let Some(((sun_intensity, asteroid_intensity), probability)) = estimate {
+        ai.log_planet_event(
+            LogAction::CounterUpdates,
             state,
             Some(ai::Ai::orchestrator_participant()),
             EventType::InternalPlanetAction,
             Channel::Debug,
-            payload,
+            || {
+                let mut payload = Payload::new();
+                payload.insert("action".into(), "update_asteroid_counter".into());
+                payload.insert("sun_intensity".into(), format!("{sun_intensity:.6}"));
+                payload.insert(
+                    "asteroid_intensity".into(),
+                    format!("{asteroid_intensity:.6}"),
+                );
+                payload.insert("sunray_probability".into(), format!("{probability:.6}"));
+                payload
+            },
         );
     }
-    if state.has_rocket() {
-        let mut payload = Payload::new();
-        payload.insert("action".into(), "launch_existing_rocket".into());
-        ai::Ai::log_planet_event(
-            state,
-            Some(ai::Ai::orchestrator_participant()),
-            EventType::InternalPlanetAction,
-            Channel::Debug,
-            payload,
-        );
+
+    // An attack is in progress: keep the next charge for the next rocket
+    ai.energy_mut().reserve_after_attack();
+
+    let rocket = if state.has_rocket() {
+        log_action(ai, state, "launch_existing_rocket");
         state.take_rocket()
-    } else if state.cell(0).is_charged() {
-        let _ = state.build_rocket(0);
-        let mut payload = Payload::new();
-        payload.insert("action".into(), "build_and_launch_rocket".into());
-        ai::Ai::log_planet_event(
-            state,
-            Some(ai::Ai::orchestrator_participant()),
-            EventType::InternalPlanetAction,
-            Channel::Debug,
-            payload,
-        );
+    } else if let Some(cell) = cells::rocket_cell(state) {
+        if state.build_rocket(cell).is_ok() {
+            ai.record_consumption(state, cell, ChargePurpose::Rocket);
+        }
+        log_action(ai, state, "build_and_launch_rocket");
         state.take_rocket()
     } else {
-        let mut payload = Payload::new();
-        payload.insert("action".into(), "no_rocket_available".into());
-        ai::Ai::log_planet_event(
-            state,
-            Some(ai::Ai::orchestrator_participant()),
-            EventType::InternalPlanetAction,
-            Channel::Debug,
-            payload,
-        );
+        log_action(ai, state, "no_rocket_available");
         None
+    };
+
+    ai.record_stats(|stats| {
+        if rocket.is_some() {
+            stats.asteroids_survived += 1;
+        } else {
+            stats.asteroids_lost += 1;
+        }
+    });
+    ai.record_flight(FlightEvent::Asteroid {
+        rocket: rocket.is_some(),
+    });
+    ai.observe_outcome(PolicyOutcome::Asteroid {
+        destroyed: rocket.is_none(),
+    });
+    if rocket.is_none() {
+        ai.dump_flight(state, "no_rocket_available");
     }
+
+    rocket
+}
+
+fn log_action(ai: &Ai, state: &impl PlanetView, action: &str) {
+    ai.log_planet_event(
+        LogAction::Defense,
+        state,
+        Some(ai::Ai::orchestrator_participant()),
+        EventType::InternalPlanetAction,
+        Channel::Debug,
+        || Payload::from([("action".into(), action.into())]),
+    );
 }
@@ -0,0 +1,33 @@
+use crate::ai::view::PlanetView;
+
+/// Returns the number of charged energy cells of the planet.
+pub(crate) fn charged_cells(state: &impl PlanetView) -> usize {
+    (0..state.cells_count())
+        .filter(|cell| state.is_charged(*cell))
+        .count()
+}
+
+/// Returns the index of the cell used to build rockets: the first charged one.
+pub(crate) fn rocket_cell(state: &impl PlanetView) -> Option<usize> {
+    if !state.can_have_rocket() {
+        return None;
+    }
+    (0..state.cells_count()).find(|cell| state.is_charged(*cell))
+}
+
+/// Returns the index of the cell explorer requests are served from.
+///
+/// Explorers are served from the last charged cell, so that the first one is left for
+/// rockets. When `keep_for_defense` is set, a cell is returned only if another charge
+/// remains available to build a rocket.
+pub(crate) fn spendable_cell(state: &impl PlanetView, keep_for_defense: bool) -> Option<usize> {
+    let cell = (0..state.cells_count())
+        .rev()
+        .find(|cell| state.is_charged(*cell))?;
+
+    if keep_for_defense && rocket_cell(state) == Some(cell) {
+        None
+    } else {
+        Some(cell)
+    }
+}
@@ -0,0 +1,139 @@
+use crate::frequency_counter::LearnedState;
+use crate::json::Json;
+use common_game::utils::ID;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const VERSION: f64 = 2.0;
+
+// Checkpoints written before the explorer reputation was saved
+const VERSION_WITHOUT_REPUTATION: f64 = 1.0;
+
+/// Learned state of an [`Ai`](super::Ai), saved to disk so a relaunched planet starts warm,
+/// see [`Ai::with_checkpoint_file`](super::Ai::with_checkpoint_file).
+///
+/// Only what the AI learns from the orchestrator is saved, the configuration (mode,
+/// coefficients, half-life) always comes from the constructor.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Checkpoint {
+    pub(crate) estimator: Option<LearnedState>,
+    // Remaining defense reservation, if any
+    pub(crate) reserved_for: Option<Duration>,
+    // Requests served to each explorer, their tier for the policies
+    pub(crate) served: BTreeMap<ID, u64>,
+}
+
+impl Checkpoint {
+    pub(crate) fn to_json(&self) -> Json {
+        let saved_at_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| {
+                u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX)
+            });
+        let estimator = self.estimator.as_ref().map(|estimator| {
+            Json::object([
+                ("sun_intensity", estimator.sun_intensity.into()),
+                ("asteroid_intensity", estimator.asteroid_intensity.into()),
+                ("sunray_probability", estimator.sunray_probability.into()),
+                (
+                    "since_last_update_ns",
+                    estimator.since_last_update.map(Json::nanos).into(),
+                ),
+            ])
+        });
+
+        Json::object([
+            ("version", Json::from(VERSION)),
+            ("saved_at_ms", saved_at_ms.into()),
+            ("estimator", estimator.into()),
+            ("reserved_for_ns", self.reserved_for.map(Json::nanos).into()),
+            (
+                "served",
+                self.served
+                    .iter()
+                    .map(|(&explorer_id, &served)| {
+                        Json::object([
+                            ("explorer_id", Json::from(explorer_id)),
+                            ("served", served.into()),
+                        ])
+                    })
+                    .collect::<Vec<_>>()
+                    .into(),
+            ),
+        ])
+    }
+
+    pub(crate) fn from_json(json: &Json) -> Result<Self, String> {
+        let version = json.f64_field("version")?;
+        let is_version = |expected: f64| (version - expected).abs() <= f64::EPSILON;
+        if !is_version(VERSION) && !is_version(VERSION_WITHOUT_REPUTATION) {
+            return Err(format!("Unsupported checkpoint version {version}"));
+        }
+
+        #[allow(clippy::cast_possible_truncation)]
+        let estimator = match json.get("estimator") {
+            None | Some(Json::Null) => None,
+            Some(estimator) => Some(LearnedState {
+                sun_intensity: estimator.f64_field("sun_intensity")? as f32,
+                asteroid_intensity: estimator.f64_field("asteroid_intensity")? as f32,
+                sunray_probability: estimator.f64_field("sunray_probability")? as f32,
+                since_last_update: match estimator.get("since_last_update_ns") {
+                    None | Some(Json::Null) => None,
+                    Some(_) => Some(estimator.duration_field("since_last_update_ns")?),
+                },
+            }),
+        };
+        let reserved_for = match json.get("reserved_for_ns") {
+            None | Some(Json::Null) => None,
+            Some(_) => Some(json.duration_field("reserved_for_ns")?),
+        };
+
+        // Version 1 checkpoints have no reputation, every explorer starts new
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let served = match json.get("served") {
+            None if is_version(VERSION_WITHOUT_REPUTATION) => BTreeMap::new(),
+            None => return Err("Missing field served".to_string()),
+            Some(served) => served
+                .as_array()
+                .ok_or("Invalid field served")?
+                .iter()
+                .map(|entry| {
+                    Ok((
+                        entry.f64_field("explorer_id")? as ID,
+                        entry.f64_field("served")? as u64,
+                    ))
+                })
+                .collect::<Result<_, String>>()?,
+        };
+
+        Ok(Self {
+            estimator,
+            reserved_for,
+            served,
+        })
+    }
+
+    /// Writes the checkpoint to `path`, replacing it atomically.
+    pub(crate) fn save(&self, path: &Path) -> Result<(), String> {
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, format!("{}\n", self.to_json()))
+            .and_then(|()| fs::rename(&tmp, path))
+            .map_err(|err| format!("Can not write checkpoint {}: {err}", path.display()))
+    }
+
+    /// Reads the checkpoint at `path`, `None` if there is none yet.
+    pub(crate) fn load(path: &Path) -> Result<Option<Self>, String> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(format!("Can not read checkpoint {}: {err}", path.display())),
+        };
+        Json::parse(contents.trim())
+            .and_then(|json| Self::from_json(&json))
+            .map(Some)
+            .map_err(|err| format!("Invalid checkpoint {}: {err}", path.display()))
+    }
+}
@@ -0,0 +1,87 @@
+use crate::frequency_counter::PauseMode;
+use crate::logging::LogConfig;
+use crossbeam_channel::Sender;
+use std::time::Duration;
+
+/// Setting of a running [`Ai`](super::Ai), changed through an [`AiControl`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AiSetting {
+    RandomMode(bool),
+    BasicGenCoeff(f32),
+    ComplexGenCoeff(f32),
+    HalfLife(Duration),
+    MinTimeConstant(Duration),
+    /// Defense reservation window after each asteroid, zero to disable it
+    DefenseWindow(Duration),
+    PauseMode(PauseMode),
+    /// Events logged by the planet, see [`LogConfig`]
+    LogConfig(LogConfig),
+}
+
+/// Handle changing the settings of an [`Ai`](super::Ai) after it is moved into a planet.
+///
+/// Changes are validated when sent and applied at the next callback of the planet, in the
+/// order they were sent.
+#[derive(Debug, Clone)]
+pub struct AiControl {
+    pub(super) sender: Sender<AiSetting>,
+}
+
+impl AiSetting {
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            AiSetting::RandomMode(_) => "random_mode",
+            AiSetting::BasicGenCoeff(_) => "basic_gen_coeff",
+            AiSetting::ComplexGenCoeff(_) => "complex_gen_coeff",
+            AiSetting::HalfLife(_) => "half_life",
+            AiSetting::MinTimeConstant(_) => "min_time_constant",
+            AiSetting::DefenseWindow(_) => "defense_window",
+            AiSetting::PauseMode(_) => "pause_mode",
+            AiSetting::LogConfig(_) => "log_config",
+        }
+    }
+
+    pub(crate) fn value_label(self) -> String {
+        match self {
+            AiSetting::RandomMode(random_mode) => random_mode.to_string(),
+            AiSetting::BasicGenCoeff(coeff) | AiSetting::ComplexGenCoeff(coeff) => {
+                format!("{coeff:.4}")
+            }
+            AiSetting::HalfLife(duration)
+            | AiSetting::MinTimeConstant(duration)
+            | AiSetting::DefenseWindow(duration) => format!("{:.4}", duration.as_secs_f32()),
+            AiSetting::PauseMode(pause_mode) => pause_mode.label().to_string(),
+            AiSetting::LogConfig(config) => config.label(),
+        }
+    }
+
+    /// Checks that the value is usable, unlike the constructor it does not clamp it.
+    ///
+    /// # Errors
+    /// Returns an error if a coefficient is outside `[0, 1]` or the half-life is zero.
+    pub fn validate(self) -> Result<Self, String> {
+        match self {
+            AiSetting::BasicGenCoeff(coeff) | AiSetting::ComplexGenCoeff(coeff)
+                if !(0.0..=1.0).contains(&coeff) =>
+            {
+                Err(format!("{} must be in [0, 1], got {coeff}", self.name()))
+            }
+            AiSetting::HalfLife(half_life) if half_life.is_zero() => {
+                Err("half_life must be positive".to_string())
+            }
+            _ => Ok(self),
+        }
+    }
+}
+
+impl AiControl {
+    /// Queues a change, applied at the next callback of the planet.
+    ///
+    /// # Errors
+    /// Returns an error if the setting is invalid or the AI was dropped.
+    pub fn set(&self, setting: AiSetting) -> Result<(), String> {
+        self.sender
+            .send(setting.validate()?)
+            .map_err(|_| "The AI was dropped".to_string())
+    }
+}
@@ -1,123 +1,154 @@
-use crate::ai;
-
 use super::Ai;
-use common_game::components::planet::PlanetState;
-use common_game::logging::{Channel, EventType, Payload};
+use super::cells;
+use super::policy::PolicyInputs;
+use super::record::{
+    DecisionInputs, DecisionOutcome, DecisionPolicyKind, DecisionReason, DecisionRecord,
+    DecisionRequest,
+};
+use super::view::PlanetView;
+use crate::frequency_counter::FrequencyCounter;
+use common_game::utils::ID;
+use std::time::SystemTime;
 
-pub fn generate_basic_resource(ai: &mut Ai, state: &PlanetState) -> bool {
-    // Check if energy cell is charged first
-    if !state.cell(0).is_charged() {
-        let mut payload = Payload::new();
-        payload.insert("action".into(), "generate_basic_resource".into());
-        payload.insert("reason".into(), "cell_not_charged".into());
-        ai::Ai::log_planet_event(
-            state,
+/// Builds the record of a request refused before any decision, e.g. a missing recipe.
+pub fn refuse(
+    ai: &Ai,
+    state: &impl PlanetView,
+    explorer_id: ID,
+    request: DecisionRequest,
+    outcome: DecisionOutcome,
+    reason: DecisionReason,
+) -> DecisionRecord {
+    DecisionRecord {
+        timestamp: SystemTime::now(),
+        explorer_id,
+        request,
+        inputs: base_inputs(ai, state, threshold(ai, request)),
+        policy: policy_kind(ai),
+        outcome,
+        reason,
+        cell: None,
+    }
+}
+
+/// Decides whether to serve a request, and from which cell.
+pub fn decide(
+    ai: &mut Ai,
+    state: &impl PlanetView,
+    explorer_id: ID,
+    request: DecisionRequest,
+) -> DecisionRecord {
+    let threshold = threshold(ai, request);
+    let policy = policy_kind(ai);
+    let mut inputs = base_inputs(ai, state, threshold);
+    let record = |outcome, reason, cell, inputs| DecisionRecord {
+        timestamp: SystemTime::now(),
+        explorer_id,
+        request,
+        inputs,
+        policy,
+        outcome,
+        reason,
+        cell,
+    };
+
+    // Check if any energy cell is charged first
+    if cells::spendable_cell(state, false).is_none() {
+        return record(
+            DecisionOutcome::Denied,
+            DecisionReason::CellNotCharged,
             None,
-            EventType::InternalPlanetAction,
-            Channel::Debug,
-            payload,
+            inputs,
         );
-        return false;
     }
 
-    let mut payload = Payload::new();
-    payload.insert("action".into(), "generate_basic_resource".into());
-    payload.insert("random_mode".into(), ai.random_mode().to_string());
-    payload.insert("has_rocket".into(), state.has_rocket().to_string());
+    // The charge is kept for building rockets while a defense reservation is active
+    let Some(cell) = cells::spendable_cell(state, inputs.reserved) else {
+        return record(
+            DecisionOutcome::Denied,
+            DecisionReason::ReservedForDefense,
+            None,
+            inputs,
+        );
+    };
 
-    let decision = if ai.random_mode() {
-        let sample = rand::random::<f32>();
-        payload.insert("random_sample".into(), format!("{sample:.6}"));
-        payload.insert("threshold".into(), format!("{:.6}", ai.basic_gen_coeff));
-        sample > ai.basic_gen_coeff
+    let (accepted, reason) = if ai.policy.is_some() {
+        // The estimator is read like in adaptive mode
+        let p_sunray = ai
+            .counters
+            .as_mut()
+            .map(FrequencyCounter::sunray_probability);
+        inputs.p_sunray = p_sunray;
+        inputs.p_asteroid = p_sunray.map(|p_sunray| 1.0 - p_sunray);
+        let policy_inputs = PolicyInputs {
+            explorer_id,
+            request,
+            coefficient: threshold,
+            has_rocket: inputs.has_rocket,
+            charged_cells: inputs.charged_cells,
+            sunray_probability: p_sunray,
+            served_requests: ai.served_requests(explorer_id),
+        };
+        let accepted = ai
+            .policy
+            .as_mut()
+            .is_some_and(|policy| policy.decide(&policy_inputs));
+        (accepted, DecisionReason::CustomPolicy)
+    } else if ai.random_mode() {
+        let sample = ai.random_sample();
+        inputs.random_sample = Some(sample);
+        (sample > threshold, DecisionReason::RandomSample)
     } else if let Some(counters) = &mut ai.counters {
         // Use sunray probability to decide: help if asteroid risk is low enough
         let p_sunray = counters.sunray_probability();
         let p_asteroid = 1.0 - p_sunray;
-
-        payload.insert("p_sunray".into(), format!("{p_sunray:.6}"));
-        payload.insert("p_asteroid".into(), format!("{p_asteroid:.6}"));
+        inputs.p_sunray = Some(p_sunray);
+        inputs.p_asteroid = Some(p_asteroid);
 
         // If we have a rocket, evaluate risk of 2 asteroids before next sunray
         if state.has_rocket() {
             let p_squared = p_asteroid * p_asteroid;
-            payload.insert("p_asteroid_squared".into(), format!("{p_squared:.6}"));
-            p_squared <= ai.basic_gen_coeff
+            inputs.p_asteroid_squared = Some(p_squared);
+            (p_squared <= threshold, DecisionReason::DoubleAsteroidRisk)
         } else {
             // No rocket - evaluate single asteroid risk
-            p_asteroid <= ai.basic_gen_coeff
+            (p_asteroid <= threshold, DecisionReason::AsteroidRisk)
         }
     } else {
-        false
+        (false, DecisionReason::NoEstimator)
     };
+    ai.sync_estimator_stats();
 
-    payload.insert("decision".into(), decision.to_string());
-    ai::Ai::log_planet_event(
-        state,
-        None,
-        EventType::InternalPlanetAction,
-        Channel::Debug,
-        payload,
-    );
-
-    decision
+    if accepted {
+        record(DecisionOutcome::Accepted, reason, Some(cell), inputs)
+    } else {
+        record(DecisionOutcome::Denied, reason, None, inputs)
+    }
 }
 
-pub fn generate_complex_resource(ai: &mut Ai, state: &PlanetState) -> bool {
-    // Check if energy cell is charged first
-    if !state.cell(0).is_charged() {
-        let mut payload = Payload::new();
-        payload.insert("action".into(), "generate_complex_resource".into());
-        payload.insert("reason".into(), "cell_not_charged".into());
-        ai::Ai::log_planet_event(
-            state,
-            None,
-            EventType::InternalPlanetAction,
-            Channel::Debug,
-            payload,
-        );
-        return false;
+fn base_inputs(ai: &Ai, state: &impl PlanetView, threshold: f32) -> DecisionInputs {
+    DecisionInputs {
+        has_rocket: state.has_rocket(),
+        charged_cells: cells::charged_cells(state),
+        reserved: ai.energy().is_reserved(),
+        threshold,
+        ..DecisionInputs::default()
     }
+}
 
-    let mut payload = Payload::new();
-    payload.insert("action".into(), "generate_complex_resource".into());
-    payload.insert("random_mode".into(), ai.random_mode().to_string());
-    payload.insert("has_rocket".into(), state.has_rocket().to_string());
-
-    let decision = if ai.random_mode() {
-        let sample = rand::random::<f32>();
-        payload.insert("random_sample".into(), format!("{sample:.6}"));
-        payload.insert("threshold".into(), format!("{:.6}", ai.complex_gen_coeff));
-        sample > ai.complex_gen_coeff
-    } else if let Some(counters) = &mut ai.counters {
-        // Use sunray probability to decide: help if asteroid risk is low enough
-        let p_sunray = counters.sunray_probability();
-        let p_asteroid = 1.0 - p_sunray;
-
-        payload.insert("p_sunray".into(), format!("{p_sunray:.6}"));
-        payload.insert("p_asteroid".into(), format!("{p_asteroid:.6}"));
+fn threshold(ai: &Ai, request: DecisionRequest) -> f32 {
+    match request {
+        DecisionRequest::GenerateResource(_) => ai.basic_gen_coeff,
+        DecisionRequest::CombineResource(_) => ai.complex_gen_coeff,
+    }
+}
 
-        // If we have a rocket, evaluate risk of 2 asteroids before next sunray
-        if state.has_rocket() {
-            let p_squared = p_asteroid * p_asteroid;
-            payload.insert("p_asteroid_squared".into(), format!("{p_squared:.6}"));
-            p_squared <= ai.complex_gen_coeff
-        } else {
-            // No rocket - evaluate single asteroid risk
-            p_asteroid <= ai.complex_gen_coeff
-        }
+fn policy_kind(ai: &Ai) -> DecisionPolicyKind {
+    if ai.policy.is_some() {
+        DecisionPolicyKind::Custom
+    } else if ai.random_mode() {
+        DecisionPolicyKind::Random
     } else {
-        false
-    };
-
-    payload.insert("decision".into(), decision.to_string());
-    ai::Ai::log_planet_event(
-        state,
-        None,
-        EventType::InternalPlanetAction,
-        Channel::Debug,
-        payload,
-    );
-
-    decision
+        DecisionPolicyKind::Adaptive
+    }
 }
@@ -0,0 +1,110 @@
+use crate::ai::record::DecisionRequest;
+use crate::clock::{Clock, SystemClock};
+use common_game::components::resource::{BasicResourceType, ComplexResourceType};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// What an energy cell charge has been spent on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ChargePurpose {
+    /// Generation of a basic resource requested by an explorer
+    Generation(BasicResourceType),
+    /// Combination of a complex resource requested by an explorer
+    Combination(ComplexResourceType),
+    /// Rocket construction
+    Rocket,
+}
+
+impl From<DecisionRequest> for ChargePurpose {
+    fn from(request: DecisionRequest) -> Self {
+        match request {
+            DecisionRequest::GenerateResource(resource) => ChargePurpose::Generation(resource),
+            DecisionRequest::CombineResource(resource) => ChargePurpose::Combination(resource),
+        }
+    }
+}
+
+impl ChargePurpose {
+    pub(crate) fn label(self) -> String {
+        match self {
+            ChargePurpose::Generation(resource) => format!("generation:{resource:?}"),
+            ChargePurpose::Combination(resource) => format!("combination:{resource:?}"),
+            ChargePurpose::Rocket => "rocket".into(),
+        }
+    }
+}
+
+/// Energy accounting layer of the AI.
+///
+/// Keeps track of defense reservations: while a reservation is active, the charge
+/// of the energy cell is kept for building rockets and explorer requests are denied.
+pub(crate) struct EnergyLedger {
+    // Window reserved automatically after every asteroid (zero disables it)
+    defense_window: Duration,
+    reserved_until: Option<Instant>,
+    consumed_charges: u64,
+    clock: Arc<dyn Clock>,
+}
+
+impl EnergyLedger {
+    pub fn new(defense_window: Duration) -> Self {
+        Self {
+            defense_window,
+            reserved_until: None,
+            consumed_charges: 0,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    pub fn defense_window(&self) -> Duration {
+        self.defense_window
+    }
+
+    pub fn set_defense_window(&mut self, defense_window: Duration) {
+        self.defense_window = defense_window;
+    }
+
+    /// Reserves the charge for defense for `window`, extending any running reservation.
+    pub fn reserve_for(&mut self, window: Duration) {
+        if window.is_zero() {
+            return;
+        }
+        let until = self.clock.now() + window;
+        self.reserved_until = Some(self.reserved_until.map_or(until, |prev| prev.max(until)));
+    }
+
+    /// Reserves the charge for the configured defense window, called when an attack is detected.
+    pub fn reserve_after_attack(&mut self) {
+        self.reserve_for(self.defense_window);
+    }
+
+    pub fn release(&mut self) {
+        self.reserved_until = None;
+    }
+
+    pub fn is_reserved(&self) -> bool {
+        self.reserved_until
+            .is_some_and(|until| self.clock.now() < until)
+    }
+
+    /// Time left on the active reservation, if any.
+    pub fn remaining_reservation(&self) -> Option<Duration> {
+        let now = self.clock.now();
+        self.reserved_until
+            .filter(|until| now < *until)
+            .map(|until| until - now)
+    }
+
+    /// Records that a charge has been spent.
+    pub fn record_consumption(&mut self) {
+        self.consumed_charges += 1;
+    }
+
+    pub fn consumed_charges(&self) -> u64 {
+        self.consumed_charges
+    }
+}
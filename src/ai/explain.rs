@@ -0,0 +1,237 @@
+use super::record::{DecisionOutcome, DecisionReason, DecisionRecord};
+use super::stats::EstimatorStats;
+use std::fmt;
+
+/// Why the AI took its last decision for an explorer, see
+/// [`Ai::explain_last_decision`](crate::Ai::explain_last_decision).
+///
+/// The structured values are the fields and [`changes`](Self::changes), `Display` writes them
+/// as a few sentences for humans.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecisionExplanation {
+    /// The decision, with the policy that ran, its inputs and the threshold
+    pub record: DecisionRecord,
+    /// Estimator values when the decision was taken, `None` if the AI keeps no estimator
+    pub estimator: Option<EstimatorStats>,
+    /// Whether the planet type allows rockets at all
+    pub can_have_rocket: bool,
+}
+
+/// Input of a decision that, changed, would have given the other outcome.
+///
+/// When the request was denied before the policy ran (no charge or a defense reservation),
+/// the change only lets the policy run.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutcomeChange {
+    /// A charged energy cell
+    ChargedCell,
+    /// A charge besides the one kept to build a rocket
+    AnotherChargedCell,
+    /// No defense reservation after the last asteroid
+    NoDefenseReservation,
+    /// A coefficient of at least this value
+    CoefficientAtLeast(f32),
+    /// A coefficient below this value
+    CoefficientBelow(f32),
+    /// A sunray probability of at least this value
+    SunrayProbabilityAtLeast(f32),
+    /// A sunray probability below this value
+    SunrayProbabilityBelow(f32),
+    /// A rocket ready, so that the risk of two asteroids is compared instead of one
+    RocketReady,
+    /// No rocket ready, so that the risk of one asteroid is compared instead of two
+    NoRocket,
+    /// A recipe of the planet for the resource
+    Recipe,
+    /// Ingredients the recipe accepts
+    Ingredients,
+}
+
+impl DecisionExplanation {
+    /// Whether a rocket was ready, so the policy compared the squared asteroid risk (the risk
+    /// of two asteroids before the next sunray) instead of the risk of one.
+    #[must_use]
+    pub fn rocket_squared(&self) -> bool {
+        self.record.reason == DecisionReason::DoubleAsteroidRisk
+    }
+
+    /// Inputs that would have changed the outcome, each on its own.
+    #[must_use]
+    pub fn changes(&self) -> Vec<OutcomeChange> {
+        let accepted = self.record.outcome == DecisionOutcome::Accepted;
+        let inputs = &self.record.inputs;
+        let threshold = inputs.threshold;
+        let mut changes = Vec::new();
+        // Coefficients stay in [0, 1]. The adaptive policy accepts with a coefficient of at
+        // least `value`, the random one with a coefficient below it
+        let coefficient = |changes: &mut Vec<_>, value: f32, accept_at_least: bool| {
+            if accept_at_least != accepted {
+                if value <= 1.0 {
+                    changes.push(OutcomeChange::CoefficientAtLeast(value));
+                }
+            } else if value > 0.0 {
+                changes.push(OutcomeChange::CoefficientBelow(value));
+            }
+        };
+        // The adaptive policy accepts with a sunray probability of at least `value`, the
+        // estimator never reaches 1
+        let sunray = |changes: &mut Vec<_>, value: f32| {
+            if accepted && value > 0.0 {
+                changes.push(OutcomeChange::SunrayProbabilityBelow(value));
+            } else if !accepted && value < 1.0 {
+                changes.push(OutcomeChange::SunrayProbabilityAtLeast(value));
+            }
+        };
+
+        match self.record.reason {
+            DecisionReason::CellNotCharged => changes.push(OutcomeChange::ChargedCell),
+            DecisionReason::ReservedForDefense => {
+                changes.push(OutcomeChange::NoDefenseReservation);
+                changes.push(OutcomeChange::AnotherChargedCell);
+            }
+            DecisionReason::RandomSample => {
+                if let Some(sample) = inputs.random_sample {
+                    coefficient(&mut changes, sample, false);
+                }
+            }
+            DecisionReason::AsteroidRisk => {
+                if let Some(p_asteroid) = inputs.p_asteroid {
+                    coefficient(&mut changes, p_asteroid, true);
+                    sunray(&mut changes, 1.0 - threshold);
+                    if !accepted && self.can_have_rocket && p_asteroid * p_asteroid <= threshold {
+                        changes.push(OutcomeChange::RocketReady);
+                    }
+                }
+            }
+            DecisionReason::DoubleAsteroidRisk => {
+                if let (Some(p_asteroid), Some(p_squared)) =
+                    (inputs.p_asteroid, inputs.p_asteroid_squared)
+                {
+                    coefficient(&mut changes, p_squared, true);
+                    sunray(&mut changes, 1.0 - threshold.sqrt());
+                    if accepted && p_asteroid > threshold {
+                        changes.push(OutcomeChange::NoRocket);
+                    }
+                }
+            }
+            DecisionReason::NoEstimator | DecisionReason::CustomPolicy => {}
+            DecisionReason::MissingRecipe => changes.push(OutcomeChange::Recipe),
+            DecisionReason::CraftingFailed => changes.push(OutcomeChange::Ingredients),
+        }
+        changes
+    }
+
+    // Why the policy (or the checks before it) ended with the outcome
+    fn reason_sentence(&self) -> String {
+        let record = &self.record;
+        let inputs = &record.inputs;
+        let threshold = inputs.threshold;
+        let compared = |accepted| {
+            if accepted { "within" } else { "above" }
+        };
+        let accepted = record.outcome == DecisionOutcome::Accepted;
+        match record.reason {
+            DecisionReason::CellNotCharged => "No energy cell was charged.".to_string(),
+            DecisionReason::ReservedForDefense => {
+                "The only charge was kept to build a rocket, as an asteroid hit recently."
+                    .to_string()
+            }
+            DecisionReason::RandomSample => format!(
+                "The random sample {:.3} was {} the coefficient {threshold:.3}.",
+                inputs.random_sample.unwrap_or_default(),
+                if accepted { "above" } else { "not above" }
+            ),
+            DecisionReason::AsteroidRisk => format!(
+                "No rocket was ready, and the risk of an asteroid before the next sunray, {:.3}, \
+                 was {} the coefficient {threshold:.3}.",
+                inputs.p_asteroid.unwrap_or_default(),
+                compared(accepted)
+            ),
+            DecisionReason::DoubleAsteroidRisk => format!(
+                "A rocket was ready, so the risk of two asteroids before the next sunray was used: \
+                 {:.3} squared is {:.3}, {} the coefficient {threshold:.3}.",
+                inputs.p_asteroid.unwrap_or_default(),
+                inputs.p_asteroid_squared.unwrap_or_default(),
+                compared(accepted)
+            ),
+            DecisionReason::NoEstimator => "The AI keeps no estimator to assess the risk.".into(),
+            DecisionReason::CustomPolicy => {
+                "The custom policy decided on the state of the planet.".to_string()
+            }
+            DecisionReason::MissingRecipe => format!(
+                "The planet has no recipe for {}.",
+                record.request.resource()
+            ),
+            DecisionReason::CraftingFailed => {
+                "A charge was granted, but crafting failed (e.g. wrong ingredients).".to_string()
+            }
+        }
+    }
+}
+
+impl fmt::Display for DecisionExplanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let record = &self.record;
+        write!(
+            f,
+            "Explorer {} {} {}: {} by the {} policy. {}",
+            record.explorer_id,
+            record.request.label(),
+            record.request.resource(),
+            record.outcome.label(),
+            record.policy.label(),
+            self.reason_sentence()
+        )?;
+        if let Some(estimator) = &self.estimator {
+            write!(
+                f,
+                " Estimator: sunray probability {:.3}, sun intensity {:.3}, asteroid intensity \
+                 {:.3}, time constant {:.1}s.",
+                estimator.sunray_probability,
+                estimator.sun_intensity,
+                estimator.asteroid_intensity,
+                estimator.time_constant_secs
+            )?;
+        }
+
+        let changes = self.changes();
+        let Some((last, rest)) = changes.split_last() else {
+            return write!(f, " No single change of its inputs would have changed it.");
+        };
+        let other = if record.outcome == DecisionOutcome::Accepted {
+            "denied"
+        } else {
+            "accepted"
+        };
+        write!(f, " It would have been {other} with ")?;
+        if !rest.is_empty() {
+            let rest: Vec<String> = rest.iter().map(ToString::to_string).collect();
+            write!(f, "{} or ", rest.join(", "))?;
+        }
+        write!(f, "{last}.")
+    }
+}
+
+impl fmt::Display for OutcomeChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutcomeChange::ChargedCell => write!(f, "a charged energy cell"),
+            OutcomeChange::AnotherChargedCell => write!(f, "another charged energy cell"),
+            OutcomeChange::NoDefenseReservation => write!(f, "no defense reservation"),
+            OutcomeChange::CoefficientAtLeast(value) => {
+                write!(f, "a coefficient of at least {value:.3}")
+            }
+            OutcomeChange::CoefficientBelow(value) => write!(f, "a coefficient below {value:.3}"),
+            OutcomeChange::SunrayProbabilityAtLeast(value) => {
+                write!(f, "a sunray probability of at least {value:.3}")
+            }
+            OutcomeChange::SunrayProbabilityBelow(value) => {
+                write!(f, "a sunray probability below {value:.3}")
+            }
+            OutcomeChange::RocketReady => write!(f, "a rocket ready"),
+            OutcomeChange::NoRocket => write!(f, "no rocket ready"),
+            OutcomeChange::Recipe => write!(f, "a recipe for the resource"),
+            OutcomeChange::Ingredients => write!(f, "the right ingredients"),
+        }
+    }
+}
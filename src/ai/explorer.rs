@@ -1,18 +1,26 @@
 use crate::ai;
+use crate::logging::LogAction;
 
 use super::Ai;
-use super::decide::{generate_basic_resource, generate_complex_resource};
-use common_game::components::planet::PlanetState;
+use super::cells;
+use super::decide::{decide, refuse};
+use super::energy::ChargePurpose;
+use super::policy::{self, PolicyOutcome};
+use super::record::{DecisionOutcome, DecisionReason, DecisionRecord, DecisionRequest};
+use super::recorder::FlightEvent;
+use super::view::PlanetView;
 use common_game::components::resource::{
-    BasicResource, BasicResourceType, Combinator, ComplexResource, ComplexResourceRequest,
-    Generator,
+    BasicResourceType, Combinator, ComplexResource, ComplexResourceRequest, ComplexResourceType,
+    Generator, GenericResource,
 };
 use common_game::logging::{Channel, EventType, Payload};
 use common_game::protocols::planet_explorer::{ExplorerToPlanet, PlanetToExplorer};
+use common_game::utils::ID;
+use std::time::Instant;
 
 pub(super) fn handle_message(
     ai: &mut Ai,
-    state: &mut PlanetState,
+    state: &mut impl PlanetView,
     generator: &Generator,
     combinator: &Combinator,
     msg: ExplorerToPlanet,
@@ -23,36 +31,57 @@ pub(super) fn handle_message(
         return None;
     }
 
+    ai.record_stats(|stats| match &msg {
+        ExplorerToPlanet::SupportedResourceRequest { .. } => {
+            stats.requests.supported_resource += 1;
+        }
+        ExplorerToPlanet::SupportedCombinationRequest { .. } => {
+            stats.requests.supported_combination += 1;
+        }
+        ExplorerToPlanet::GenerateResourceRequest { .. } => stats.requests.generate_resource += 1,
+        ExplorerToPlanet::CombineResourceRequest { .. } => stats.requests.combine_resource += 1,
+        ExplorerToPlanet::AvailableEnergyCellRequest { .. } => {
+            stats.requests.available_energy_cell += 1;
+        }
+    });
+    ai.record_flight(FlightEvent::ExplorerRequest {
+        explorer_id,
+        request: request_label(&msg),
+    });
+
     let response = match msg {
         ExplorerToPlanet::SupportedResourceRequest { .. } => Some(supported_resources(generator)),
 
         ExplorerToPlanet::SupportedCombinationRequest { .. } => {
             Some(supported_combinations(combinator))
         }
-        ExplorerToPlanet::GenerateResourceRequest { resource, .. } => {
-            Some(generate_resource(ai, state, generator, resource))
-        }
+        ExplorerToPlanet::GenerateResourceRequest { resource, .. } => Some(generate_resource(
+            ai,
+            state,
+            generator,
+            explorer_id,
+            resource,
+        )),
 
         ExplorerToPlanet::CombineResourceRequest { msg, .. } => {
-            Some(combine_resource(ai, state, combinator, msg))
+            Some(combine_resource(ai, state, combinator, explorer_id, msg))
         }
 
         ExplorerToPlanet::AvailableEnergyCellRequest { .. } => {
             Some(PlanetToExplorer::AvailableEnergyCellResponse {
-                available_cells: u32::from(state.cell(0).is_charged()),
+                available_cells: u32::try_from(cells::charged_cells(state)).unwrap_or(u32::MAX),
             })
         }
     };
 
     if let Some(ref resp) = response {
-        let mut payload = Payload::new();
-        payload.insert("response".into(), response_label(resp).into());
-        ai::Ai::log_planet_event(
+        ai.log_planet_event(
+            LogAction::Acks,
             state,
             Some(ai::Ai::explorer_participant(explorer_id)),
             EventType::MessagePlanetToExplorer,
             Channel::Trace,
-            payload,
+            || Payload::from([("response".into(), response_label(resp).into())]),
         );
     }
 
@@ -76,160 +105,143 @@ fn supported_combinations(combinator: &Combinator) -> PlanetToExplorer {
 /// Return the optional Basic resource generated
 fn generate_resource(
     ai: &mut Ai,
-    state: &mut PlanetState,
+    state: &mut impl PlanetView,
     generator: &Generator,
+    explorer_id: ID,
     to_generate: BasicResourceType,
 ) -> PlanetToExplorer {
-    let mut payload = Payload::new();
-    payload.insert("requested_resource".into(), format!("{to_generate:?}"));
-
-    if !generate_basic_resource(ai, state) {
-        payload.insert("decision".into(), "denied".into());
-        ai::Ai::log_planet_event(
-            state,
-            None,
-            EventType::InternalPlanetAction,
-            Channel::Debug,
-            payload,
-        );
-        return PlanetToExplorer::GenerateResourceResponse { resource: None };
-    }
-
-    let resource = match to_generate {
-        BasicResourceType::Hydrogen => generator
-            .make_hydrogen(state.cell_mut(0))
-            .ok()
-            .map(BasicResource::Hydrogen),
-        _ => panic!("ICB planet can not generate any resource other than Hydrogen"),
-    };
-
-    let mut payload = Payload::new();
-    payload.insert("requested_resource".into(), format!("{to_generate:?}"));
-    payload.insert("generated".into(), resource.is_some().to_string());
-    ai::Ai::log_planet_event(
+    let mut record = plan(
+        ai,
         state,
-        None,
-        EventType::InternalPlanetAction,
-        Channel::Debug,
-        payload,
+        explorer_id,
+        DecisionRequest::GenerateResource(to_generate),
+        generator.contains(to_generate),
     );
 
+    let resource = record
+        .cell
+        .and_then(|cell| generator.try_make(to_generate, state.cell_mut(cell)).ok());
+    settle(ai, state, &mut record, resource.is_some());
+
     PlanetToExplorer::GenerateResourceResponse { resource }
 }
 
 /// Returns the optional complex resource created
 fn combine_resource(
     ai: &mut Ai,
-    state: &mut PlanetState,
+    state: &mut impl PlanetView,
     combinator: &Combinator,
+    explorer_id: ID,
     msg: ComplexResourceRequest,
 ) -> PlanetToExplorer {
-    let mut payload = Payload::new();
-    payload.insert("requested_combination".into(), format!("{msg:?}"));
-
-    if !generate_complex_resource(ai, state) {
-        let response = match msg {
-            ComplexResourceRequest::Water(r1, r2) => Err((
-                "Keeping the energy cell".to_string(),
-                r1.to_generic(),
-                r2.to_generic(),
-            )),
-
-            ComplexResourceRequest::Diamond(r1, r2) => Err((
-                "Keeping the energy cell".to_string(),
-                r1.to_generic(),
-                r2.to_generic(),
-            )),
-
-            ComplexResourceRequest::Life(r1, r2) => Err((
-                "Keeping the energy cell".to_string(),
-                r1.to_generic(),
-                r2.to_generic(),
-            )),
-
-            ComplexResourceRequest::Robot(r1, r2) => Err((
-                "Keeping the energy cell".to_string(),
-                r1.to_generic(),
-                r2.to_generic(),
-            )),
-
-            ComplexResourceRequest::Dolphin(r1, r2) => Err((
-                "Keeping the energy cell".to_string(),
-                r1.to_generic(),
-                r2.to_generic(),
-            )),
-
-            ComplexResourceRequest::AIPartner(r1, r2) => Err((
-                "Keeping the energy cell".to_string(),
-                r1.to_generic(),
-                r2.to_generic(),
-            )),
-        };
-
-        payload.insert("decision".into(), "denied".into());
-        ai::Ai::log_planet_event(
+    let recipe = requested_recipe(&msg);
+    let mut record = plan(
+        ai,
+        state,
+        explorer_id,
+        DecisionRequest::CombineResource(recipe),
+        combinator.contains(recipe),
+    );
+
+    //trying to craft resource
+    let complex_response = match record.cell {
+        Some(cell) => combinator.try_make(msg, state.cell_mut(cell)),
+        None if record.outcome == DecisionOutcome::Unsupported => {
+            refuse_combination(msg, format!("Missing recipe for {recipe:?}"))
+        }
+        None => refuse_combination(msg, "Keeping the energy cell".to_string()),
+    };
+    settle(ai, state, &mut record, complex_response.is_ok());
+
+    PlanetToExplorer::CombineResourceResponse { complex_response }
+}
+
+/// Decides on a request, which is refused before any decision if the planet has no recipe
+/// for it. The record has a cell if the request is accepted.
+pub(super) fn plan(
+    ai: &mut Ai,
+    state: &impl PlanetView,
+    explorer_id: ID,
+    request: DecisionRequest,
+    has_recipe: bool,
+) -> DecisionRecord {
+    if !has_recipe {
+        return refuse(
+            ai,
             state,
-            None,
-            EventType::InternalPlanetAction,
-            Channel::Debug,
-            payload,
+            explorer_id,
+            request,
+            DecisionOutcome::Unsupported,
+            DecisionReason::MissingRecipe,
         );
-
-        return PlanetToExplorer::CombineResourceResponse {
-            complex_response: response,
-        };
     }
 
-    //trying to craft resource
-    let complex_response = match msg {
-        ComplexResourceRequest::Water(r1, r2) => combinator
-            .make_water(r1, r2, state.cell_mut(0))
-            .map(ComplexResource::Water)
-            .map_err(|(s, r1, r2)| (s, r1.to_generic(), r2.to_generic())),
-
-        ComplexResourceRequest::Diamond(r1, r2) => combinator
-            .make_diamond(r1, r2, state.cell_mut(0))
-            .map(ComplexResource::Diamond)
-            .map_err(|(s, r1, r2)| (s, r1.to_generic(), r2.to_generic())),
-
-        ComplexResourceRequest::Life(r1, r2) => combinator
-            .make_life(r1, r2, state.cell_mut(0))
-            .map(ComplexResource::Life)
-            .map_err(|(s, r1, r2)| (s, r1.to_generic(), r2.to_generic())),
-
-        ComplexResourceRequest::Robot(r1, r2) => combinator
-            .make_robot(r1, r2, state.cell_mut(0))
-            .map(ComplexResource::Robot)
-            .map_err(|(s, r1, r2)| (s, r1.to_generic(), r2.to_generic())),
-
-        ComplexResourceRequest::Dolphin(r1, r2) => combinator
-            .make_dolphin(r1, r2, state.cell_mut(0))
-            .map(ComplexResource::Dolphin)
-            .map_err(|(s, r1, r2)| (s, r1.to_generic(), r2.to_generic())),
-
-        ComplexResourceRequest::AIPartner(r1, r2) => combinator
-            .make_aipartner(r1, r2, state.cell_mut(0))
-            .map(ComplexResource::AIPartner)
-            .map_err(|(s, r1, r2)| (s, r1.to_generic(), r2.to_generic())),
-    };
+    let started = Instant::now();
+    let record = decide(ai, state, explorer_id, request);
+    ai.record_stats(|stats| stats.decision_latency.observe(started.elapsed()));
+    policy::run_shadows(ai, state, &record);
+    record
+}
 
-    payload.insert(
-        "decision".into(),
-        if complex_response.is_ok() {
-            "crafted".into()
+/// Accounts for the charge of an accepted request, spent if the resource was `crafted`, and
+/// emits the record.
+pub(super) fn settle(
+    ai: &mut Ai,
+    state: &impl PlanetView,
+    record: &mut DecisionRecord,
+    crafted: bool,
+) {
+    if let Some(cell) = record.cell {
+        if crafted {
+            ai.record_consumption(state, cell, ChargePurpose::from(record.request));
+            ai.observe_outcome(PolicyOutcome::Served {
+                explorer_id: record.explorer_id,
+            });
         } else {
-            "failed".into()
-        },
-    );
-    ai::Ai::log_planet_event(
-        state,
-        None,
-        EventType::InternalPlanetAction,
-        Channel::Debug,
-        payload,
-    );
+            record.outcome = DecisionOutcome::Failed;
+            record.reason = DecisionReason::CraftingFailed;
+        }
+    }
+    ai.emit_decision(state, record);
+}
 
-    PlanetToExplorer::CombineResourceResponse { complex_response }
+/// Returns the complex resource type a combination request asks for
+pub(crate) fn requested_recipe(msg: &ComplexResourceRequest) -> ComplexResourceType {
+    match msg {
+        ComplexResourceRequest::Water(..) => ComplexResourceType::Water,
+        ComplexResourceRequest::Diamond(..) => ComplexResourceType::Diamond,
+        ComplexResourceRequest::Life(..) => ComplexResourceType::Life,
+        ComplexResourceRequest::Robot(..) => ComplexResourceType::Robot,
+        ComplexResourceRequest::Dolphin(..) => ComplexResourceType::Dolphin,
+        ComplexResourceRequest::AIPartner(..) => ComplexResourceType::AIPartner,
+    }
+}
+
+/// Gives the ingredients of a refused combination back to the explorer
+fn refuse_combination(
+    msg: ComplexResourceRequest,
+    reason: String,
+) -> Result<ComplexResource, (String, GenericResource, GenericResource)> {
+    match msg {
+        ComplexResourceRequest::Water(r1, r2) => Err((reason, r1.to_generic(), r2.to_generic())),
+        ComplexResourceRequest::Diamond(r1, r2) => Err((reason, r1.to_generic(), r2.to_generic())),
+        ComplexResourceRequest::Life(r1, r2) => Err((reason, r1.to_generic(), r2.to_generic())),
+        ComplexResourceRequest::Robot(r1, r2) => Err((reason, r1.to_generic(), r2.to_generic())),
+        ComplexResourceRequest::Dolphin(r1, r2) => Err((reason, r1.to_generic(), r2.to_generic())),
+        ComplexResourceRequest::AIPartner(r1, r2) => {
+            Err((reason, r1.to_generic(), r2.to_generic()))
+        }
+    }
+}
+
+fn request_label(msg: &ExplorerToPlanet) -> &'static str {
+    match msg {
+        ExplorerToPlanet::SupportedResourceRequest { .. } => "supported_resource",
+        ExplorerToPlanet::SupportedCombinationRequest { .. } => "supported_combination",
+        ExplorerToPlanet::GenerateResourceRequest { .. } => "generate_resource",
+        ExplorerToPlanet::CombineResourceRequest { .. } => "combine_resource",
+        ExplorerToPlanet::AvailableEnergyCellRequest { .. } => "available_energy_cell",
+    }
 }
 
 fn response_label(resp: &PlanetToExplorer) -> &'static str {
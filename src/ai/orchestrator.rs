@@ -1,6 +1,11 @@
 use crate::ai;
+use crate::logging::LogAction;
 
 use super::Ai;
+use super::cells;
+use super::energy::ChargePurpose;
+use super::recorder::FlightEvent;
+use super::view::PlanetView;
 use common_game::components::planet::PlanetState;
 use common_game::components::sunray::Sunray;
 use common_game::logging::{Channel, EventType, Payload};
@@ -8,53 +13,66 @@ use common_game::protocols::orchestrator_planet::PlanetToOrchestrator;
 
 pub(crate) fn handle_sunray(
     ai: &mut Ai,
-    state: &mut PlanetState,
+    state: &mut impl PlanetView,
     sunray: Sunray,
 ) -> PlanetToOrchestrator {
-    if state.cell(0).is_charged() && !state.has_rocket() {
-        let _ = state.build_rocket(0);
+    // Spend an existing charge on a rocket before storing the new one
+    if !state.has_rocket()
+        && let Some(cell) = cells::rocket_cell(state)
+        && state.build_rocket(cell).is_ok()
+    {
+        ai.record_consumption(state, cell, ChargePurpose::Rocket);
     }
 
-    state.cell_mut(0).charge(sunray);
+    // Charge the first empty cell, the sunray is wasted if every cell is full
+    state.charge(sunray);
 
-    let counter_payload = if let Some(counters) = ai.counters_mut() {
+    let estimate = ai.counters_mut().as_mut().map(|counters| {
         counters.update_sunray();
+        (counters.debug_stats(), counters.sunray_probability())
+    });
 
-        let (sun_intensity, asteroid_intensity) = counters.debug_stats();
-        let probability = counters.sunray_probability();
-
-        let mut payload = Payload::new();
-        payload.insert("action".into(), "update_sunray_counter".into());
-        payload.insert("sun_intensity".into(), format!("{sun_intensity:.6}"));
-        payload.insert(
-            "asteroid_intensity".into(),
-            format!("{asteroid_intensity:.6}"),
-        );
-        payload.insert("sunray_probability".into(), format!("{probability:.6}"));
-
-        Some(payload)
-    } else {
-        None
-    };
+    ai.sync_estimator_stats();
+    ai.record_stats(|stats| stats.sunrays_received += 1);
+    ai.record_flight(FlightEvent::Sunray);
+    ai.record_estimator();
 
-    if let Some(payload) = counter_payload {
-        ai::Ai::log_planet_event(
+    if let Some(((sun_intensity, asteroid_intensity), probability)) = estimate {
+        ai.log_planet_event(
+            LogAction::CounterUpdates,
             state,
             Some(ai::Ai::orchestrator_participant()),
             EventType::InternalPlanetAction,
             Channel::Debug,
-            payload,
+            || {
+                let mut payload = Payload::new();
+                payload.insert("action".into(), "update_sunray_counter".into());
+                payload.insert("sun_intensity".into(), format!("{sun_intensity:.6}"));
+                payload.insert(
+                    "asteroid_intensity".into(),
+                    format!("{asteroid_intensity:.6}"),
+                );
+                payload.insert("sunray_probability".into(), format!("{probability:.6}"));
+                payload
+            },
         );
     }
 
-    let mut ack_payload = Payload::new();
-    ack_payload.insert("message".into(), "SunrayAck".into());
-    ai::Ai::log_planet_event(
+    ai.log_planet_event(
+        LogAction::Acks,
         state,
         Some(ai::Ai::orchestrator_participant()),
         EventType::MessagePlanetToOrchestrator,
         Channel::Trace,
-        ack_payload,
+        || {
+            let mut payload = Payload::new();
+            payload.insert("message".into(), "SunrayAck".into());
+            payload.insert(
+                "charged_cells".into(),
+                cells::charged_cells(state).to_string(),
+            );
+            payload
+        },
     );
 
     PlanetToOrchestrator::SunrayAck {
@@ -62,55 +80,60 @@ pub(crate) fn handle_sunray(
     }
 }
 
-pub(crate) fn handle_start_ai(ai: &mut Ai, state: &PlanetState) -> PlanetToOrchestrator {
+pub(crate) fn handle_start_ai(ai: &mut Ai, state: &impl PlanetView) -> PlanetToOrchestrator {
     ai.is_ai_active = true;
     if let Some(counter) = ai.counters_mut() {
         counter.restart();
     }
+    ai.sync_estimator_stats();
+    ai.record_flight(FlightEvent::Start);
 
-    let mut ack_payload = Payload::new();
-    ack_payload.insert("message".into(), "StartPlanetAIResult".into());
-    ai::Ai::log_planet_event(
+    ai.log_planet_event(
+        LogAction::Acks,
         state,
         Some(ai::Ai::orchestrator_participant()),
         EventType::MessagePlanetToOrchestrator,
         Channel::Trace,
-        ack_payload,
+        || Payload::from([("message".into(), "StartPlanetAIResult".into())]),
     );
     PlanetToOrchestrator::StartPlanetAIResult {
         planet_id: state.id(),
     }
 }
 
-pub(crate) fn handle_stop_ai(ai: &mut Ai, state: &PlanetState) -> PlanetToOrchestrator {
+pub(crate) fn handle_stop_ai(ai: &mut Ai, state: &impl PlanetView) -> PlanetToOrchestrator {
     ai.is_ai_active = false;
     if let Some(counter) = ai.counters_mut() {
         counter.stop();
     }
+    ai.sync_estimator_stats();
+    ai.record_flight(FlightEvent::Stop);
+    ai.dump_flight(state, "stop");
 
-    let mut ack_payload = Payload::new();
-    ack_payload.insert("message".into(), "StopPlanetAIResult".into());
-    ai::Ai::log_planet_event(
+    ai.log_planet_event(
+        LogAction::Acks,
         state,
         Some(ai::Ai::orchestrator_participant()),
         EventType::MessagePlanetToOrchestrator,
         Channel::Trace,
-        ack_payload,
+        || Payload::from([("message".into(), "StopPlanetAIResult".into())]),
     );
     PlanetToOrchestrator::StopPlanetAIResult {
         planet_id: state.id(),
     }
 }
 
-pub(crate) fn handle_internal_state_request(state: &mut PlanetState) -> PlanetToOrchestrator {
-    let mut response_payload = Payload::new();
-    response_payload.insert("message".into(), "InternalStateResponse".into());
-    ai::Ai::log_planet_event(
+pub(crate) fn handle_internal_state_request(
+    ai: &Ai,
+    state: &mut PlanetState,
+) -> PlanetToOrchestrator {
+    ai.log_planet_event(
+        LogAction::Acks,
         state,
         Some(ai::Ai::orchestrator_participant()),
         EventType::MessagePlanetToOrchestrator,
         Channel::Debug,
-        response_payload,
+        || Payload::from([("message".into(), "InternalStateResponse".into())]),
     );
     PlanetToOrchestrator::InternalStateResponse {
         planet_id: state.id(),
@@ -0,0 +1,184 @@
+use super::Ai;
+use super::record::{DecisionOutcome, DecisionReason, DecisionRecord, DecisionRequest};
+use super::stats::DISAGREEMENT_BACKLOG;
+use super::view::PlanetView;
+use crate::frequency_counter::FrequencyCounter;
+use crate::logging::LogAction;
+use common_game::logging::{Channel, EventType, Payload};
+use common_game::utils::ID;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// What a policy knows when it decides on a request, once a charge can be spent on it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PolicyInputs {
+    pub explorer_id: ID,
+    pub request: DecisionRequest,
+    /// Coefficient of the request type
+    pub coefficient: f32,
+    pub has_rocket: bool,
+    pub charged_cells: usize,
+    /// Estimated probability that the next event is a sunray, `None` without an estimator
+    pub sunray_probability: Option<f32>,
+    /// Requests of the explorer served by the planet so far
+    pub served_requests: u64,
+}
+
+/// What followed the decisions of the active policy, see [`DecisionPolicy::observe`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PolicyOutcome {
+    /// The resource of an accepted request was handed to the explorer
+    Served { explorer_id: ID },
+    /// An asteroid hit the planet, destroying it if no rocket was launched
+    Asteroid { destroyed: bool },
+}
+
+/// Policy deciding whether to spend a charge on a generation or combination request.
+///
+/// Run as a shadow with [`Ai::with_shadow_policy`](crate::Ai::with_shadow_policy), a policy
+/// decides on every request the active policy decides on, without acting on it. Made the
+/// active policy with [`Ai::with_decision_policy`](crate::Ai::with_decision_policy), it
+/// decides once a charge can be spent and is told what followed.
+pub trait DecisionPolicy: Send {
+    /// Name of the policy in the statistics and the logs.
+    fn name(&self) -> &str;
+
+    /// Returns true to accept the request.
+    fn decide(&mut self, inputs: &PolicyInputs) -> bool;
+
+    /// Called with what followed the decisions, only for the active policy.
+    fn observe(&mut self, _outcome: PolicyOutcome) {}
+}
+
+/// Policy of the adaptive mode: accept if the risk of an asteroid before the next sunray is
+/// within the coefficient, the risk of two asteroids when a rocket is ready.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AdaptivePolicy;
+
+/// Policy of the random mode: accept if a random sample is above the coefficient.
+#[derive(Debug, Clone)]
+pub struct RandomPolicy {
+    rng: StdRng,
+}
+
+impl DecisionPolicy for AdaptivePolicy {
+    fn name(&self) -> &'static str {
+        "adaptive"
+    }
+
+    fn decide(&mut self, inputs: &PolicyInputs) -> bool {
+        let Some(p_sunray) = inputs.sunray_probability else {
+            return false;
+        };
+        let p_asteroid = 1.0 - p_sunray;
+        let risk = if inputs.has_rocket {
+            p_asteroid * p_asteroid
+        } else {
+            p_asteroid
+        };
+        risk <= inputs.coefficient
+    }
+}
+
+impl RandomPolicy {
+    /// Draws the samples from a generator seeded with `seed`, not from the one of the AI.
+    #[must_use]
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl DecisionPolicy for RandomPolicy {
+    fn name(&self) -> &'static str {
+        "random"
+    }
+
+    fn decide(&mut self, inputs: &PolicyInputs) -> bool {
+        self.rng.random::<f32>() > inputs.coefficient
+    }
+}
+
+/// Runs the shadow policies on a request the active policy decided on, and counts where they
+/// disagree with it. Requests refused before any policy ran are the same for all of them.
+pub(crate) fn run_shadows(ai: &mut Ai, state: &impl PlanetView, record: &DecisionRecord) {
+    let policy_ran = matches!(
+        record.reason,
+        DecisionReason::RandomSample
+            | DecisionReason::AsteroidRisk
+            | DecisionReason::DoubleAsteroidRisk
+            | DecisionReason::NoEstimator
+            | DecisionReason::CustomPolicy
+    );
+    if ai.shadow_policies.is_empty() || !policy_ran {
+        return;
+    }
+
+    // Only reads the estimator, decaying it here would change the next active decisions
+    let inputs = PolicyInputs {
+        explorer_id: record.explorer_id,
+        request: record.request,
+        coefficient: record.inputs.threshold,
+        has_rocket: record.inputs.has_rocket,
+        charged_cells: record.inputs.charged_cells,
+        sunray_probability: record.inputs.p_sunray.or_else(|| {
+            ai.counters
+                .as_ref()
+                .map(FrequencyCounter::last_sunray_probability)
+        }),
+        served_requests: ai.served_requests(record.explorer_id),
+    };
+    let accepted = record.outcome == DecisionOutcome::Accepted;
+
+    let mut policies = std::mem::take(&mut ai.shadow_policies);
+    for (index, policy) in policies.iter_mut().enumerate() {
+        let shadow_accepted = policy.decide(&inputs);
+        ai.record_stats(|stats| {
+            let shadow = &mut stats.shadows[index];
+            shadow.decisions += 1;
+            if shadow_accepted == accepted {
+                return;
+            }
+            if shadow_accepted {
+                shadow.accepted_by_shadow_only += 1;
+            } else {
+                shadow.denied_by_shadow_only += 1;
+            }
+            if shadow.disagreements.len() == DISAGREEMENT_BACKLOG {
+                shadow.disagreements.pop_front();
+            }
+            shadow.disagreements.push_back(record.clone());
+        });
+        if shadow_accepted != accepted {
+            log_disagreement(ai, state, policy.name(), record);
+        }
+    }
+    ai.shadow_policies = policies;
+}
+
+fn log_disagreement(ai: &Ai, state: &impl PlanetView, policy: &str, record: &DecisionRecord) {
+    ai.log_planet_event(
+        LogAction::Decisions,
+        state,
+        None,
+        EventType::InternalPlanetAction,
+        Channel::Debug,
+        || {
+            let shadow_outcome = if record.outcome == DecisionOutcome::Accepted {
+                DecisionOutcome::Denied
+            } else {
+                DecisionOutcome::Accepted
+            };
+            let mut payload = Payload::new();
+            payload.insert("action".into(), "shadow_disagreement".into());
+            payload.insert("policy".into(), policy.into());
+            payload.insert("explorer_id".into(), record.explorer_id.to_string());
+            payload.insert("request".into(), record.request.label().into());
+            payload.insert("resource".into(), record.request.resource());
+            payload.insert("outcome".into(), record.outcome.label().into());
+            payload.insert("shadow_outcome".into(), shadow_outcome.label().into());
+            payload
+        },
+    );
+}
@@ -0,0 +1,246 @@
+use crate::rules;
+use common_game::components::resource::{BasicResourceType, ComplexResourceType};
+use common_game::logging::Payload;
+use common_game::utils::ID;
+use crossbeam_channel::Sender;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Explorer request a decision was taken on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecisionRequest {
+    GenerateResource(BasicResourceType),
+    CombineResource(ComplexResourceType),
+}
+
+/// Policy that took a decision.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecisionPolicyKind {
+    /// Accept if a random sample is above the coefficient
+    Random,
+    /// Accept if the estimated asteroid risk is below the coefficient
+    Adaptive,
+    /// Policy given to [`Ai::with_decision_policy`](crate::Ai::with_decision_policy)
+    Custom,
+}
+
+/// Final outcome of an explorer request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecisionOutcome {
+    /// A charge was spent and the resource was handed to the explorer
+    Accepted,
+    /// The charge was kept
+    Denied,
+    /// The planet has no rule for the requested resource
+    Unsupported,
+    /// A charge was granted but crafting failed (e.g. wrong ingredients)
+    Failed,
+}
+
+/// Why a decision ended with its outcome.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecisionReason {
+    /// No energy cell was charged
+    CellNotCharged,
+    /// The last charge was reserved for defense
+    ReservedForDefense,
+    /// Random sample compared against the coefficient
+    RandomSample,
+    /// Risk of one asteroid compared against the coefficient (no rocket ready)
+    AsteroidRisk,
+    /// Risk of two consecutive asteroids compared against the coefficient (rocket ready)
+    DoubleAsteroidRisk,
+    /// No estimator is available in adaptive mode
+    NoEstimator,
+    /// Decided by the policy given to [`Ai::with_decision_policy`](crate::Ai::with_decision_policy)
+    CustomPolicy,
+    /// The planet has no rule for the requested resource
+    MissingRecipe,
+    /// The generator or combinator refused the request
+    CraftingFailed,
+}
+
+/// Values the decision was taken on. Probabilities are set only in adaptive mode,
+/// the random sample only in random mode.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DecisionInputs {
+    pub has_rocket: bool,
+    pub charged_cells: usize,
+    pub reserved: bool,
+    pub threshold: f32,
+    pub random_sample: Option<f32>,
+    pub p_sunray: Option<f32>,
+    pub p_asteroid: Option<f32>,
+    pub p_asteroid_squared: Option<f32>,
+}
+
+/// Typed audit record of a decision on a generation or combination request.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecisionRecord {
+    pub timestamp: SystemTime,
+    pub explorer_id: ID,
+    pub request: DecisionRequest,
+    pub inputs: DecisionInputs,
+    pub policy: DecisionPolicyKind,
+    pub outcome: DecisionOutcome,
+    pub reason: DecisionReason,
+    /// Energy cell granted to the request, if any
+    pub cell: Option<usize>,
+}
+
+impl DecisionRequest {
+    #[must_use]
+    pub fn label(self) -> &'static str {
+        match self {
+            DecisionRequest::GenerateResource(_) => "generate_resource",
+            DecisionRequest::CombineResource(_) => "combine_resource",
+        }
+    }
+
+    /// Parses a request from its label and resource name, as written by [`DecisionRecord::to_payload`].
+    pub(crate) fn from_labels(request: &str, resource: &str) -> Option<Self> {
+        match request {
+            "generate_resource" => {
+                rules::basic_from_name(resource).map(DecisionRequest::GenerateResource)
+            }
+            "combine_resource" => {
+                rules::complex_from_name(resource).map(DecisionRequest::CombineResource)
+            }
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub fn resource(self) -> String {
+        match self {
+            DecisionRequest::GenerateResource(resource) => format!("{resource:?}"),
+            DecisionRequest::CombineResource(resource) => format!("{resource:?}"),
+        }
+    }
+}
+
+impl DecisionPolicyKind {
+    #[must_use]
+    pub fn label(self) -> &'static str {
+        match self {
+            DecisionPolicyKind::Random => "random",
+            DecisionPolicyKind::Adaptive => "adaptive",
+            DecisionPolicyKind::Custom => "custom",
+        }
+    }
+}
+
+impl DecisionOutcome {
+    const ALL: [DecisionOutcome; 4] = [
+        DecisionOutcome::Accepted,
+        DecisionOutcome::Denied,
+        DecisionOutcome::Unsupported,
+        DecisionOutcome::Failed,
+    ];
+
+    pub(crate) fn from_label(label: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|outcome| outcome.label() == label)
+    }
+
+    #[must_use]
+    pub fn label(self) -> &'static str {
+        match self {
+            DecisionOutcome::Accepted => "accepted",
+            DecisionOutcome::Denied => "denied",
+            DecisionOutcome::Unsupported => "unsupported",
+            DecisionOutcome::Failed => "failed",
+        }
+    }
+}
+
+impl DecisionReason {
+    const ALL: [DecisionReason; 9] = [
+        DecisionReason::CellNotCharged,
+        DecisionReason::ReservedForDefense,
+        DecisionReason::RandomSample,
+        DecisionReason::AsteroidRisk,
+        DecisionReason::DoubleAsteroidRisk,
+        DecisionReason::NoEstimator,
+        DecisionReason::CustomPolicy,
+        DecisionReason::MissingRecipe,
+        DecisionReason::CraftingFailed,
+    ];
+
+    pub(crate) fn from_label(label: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|reason| reason.label() == label)
+    }
+
+    #[must_use]
+    pub fn label(self) -> &'static str {
+        match self {
+            DecisionReason::CellNotCharged => "cell_not_charged",
+            DecisionReason::ReservedForDefense => "cell_reserved_for_defense",
+            DecisionReason::RandomSample => "random_sample",
+            DecisionReason::AsteroidRisk => "asteroid_risk",
+            DecisionReason::DoubleAsteroidRisk => "double_asteroid_risk",
+            DecisionReason::NoEstimator => "no_estimator",
+            DecisionReason::CustomPolicy => "custom_policy",
+            DecisionReason::MissingRecipe => "missing_recipe",
+            DecisionReason::CraftingFailed => "crafting_failed",
+        }
+    }
+}
+
+impl DecisionRecord {
+    /// Converts the record to a log payload.
+    #[must_use]
+    pub fn to_payload(&self) -> Payload {
+        let timestamp_ms = self
+            .timestamp
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_millis());
+
+        let mut payload = Payload::new();
+        payload.insert("action".into(), "decision".into());
+        payload.insert("timestamp_ms".into(), timestamp_ms.to_string());
+        payload.insert("explorer_id".into(), self.explorer_id.to_string());
+        payload.insert("request".into(), self.request.label().into());
+        payload.insert("resource".into(), self.request.resource());
+        payload.insert("policy".into(), self.policy.label().into());
+        payload.insert("outcome".into(), self.outcome.label().into());
+        payload.insert("reason".into(), self.reason.label().into());
+        payload.insert("has_rocket".into(), self.inputs.has_rocket.to_string());
+        payload.insert(
+            "charged_cells".into(),
+            self.inputs.charged_cells.to_string(),
+        );
+        payload.insert("reserved".into(), self.inputs.reserved.to_string());
+        payload.insert("threshold".into(), format!("{:.6}", self.inputs.threshold));
+        if let Some(cell) = self.cell {
+            payload.insert("cell".into(), cell.to_string());
+        }
+
+        for (key, value) in [
+            ("random_sample", self.inputs.random_sample),
+            ("p_sunray", self.inputs.p_sunray),
+            ("p_asteroid", self.inputs.p_asteroid),
+            ("p_asteroid_squared", self.inputs.p_asteroid_squared),
+        ] {
+            if let Some(value) = value {
+                payload.insert(key.into(), format!("{value:.6}"));
+            }
+        }
+
+        payload
+    }
+}
+
+/// Consumer of the decision records of an [`Ai`](crate::Ai), e.g. an analysis tool.
+///
+/// Sinks are called on the planet thread, so they should not block.
+pub trait DecisionSink: Send {
+    fn record(&mut self, record: &DecisionRecord);
+}
+
+/// Forwards every record on a channel, dropping it if the channel is full or disconnected.
+impl DecisionSink for Sender<DecisionRecord> {
+    fn record(&mut self, record: &DecisionRecord) {
+        let _ = self.try_send(record.clone());
+    }
+}
@@ -0,0 +1,183 @@
+use super::record::DecisionRecord;
+use super::stats::EstimatorStats;
+use crate::json::Json;
+use common_game::utils::ID;
+use std::collections::VecDeque;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Event kept by the flight recorder.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FlightEvent {
+    Start,
+    Stop,
+    Sunray,
+    /// Asteroid received, and whether a rocket was launched against it
+    Asteroid {
+        rocket: bool,
+    },
+    ExplorerRequest {
+        explorer_id: ID,
+        request: &'static str,
+    },
+    Decision(DecisionRecord),
+    Estimator(EstimatorStats),
+}
+
+/// Timestamped entry of the flight recorder.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlightEntry {
+    pub timestamp: SystemTime,
+    pub event: FlightEvent,
+}
+
+impl FlightEntry {
+    pub(crate) fn to_json(&self) -> Json {
+        let timestamp_ms = self
+            .timestamp
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| {
+                u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX)
+            });
+
+        let mut fields: Vec<(&str, Json)> = vec![("timestamp_ms", timestamp_ms.into())];
+        match &self.event {
+            FlightEvent::Start => fields.push(("event", "start".into())),
+            FlightEvent::Stop => fields.push(("event", "stop".into())),
+            FlightEvent::Sunray => fields.push(("event", "sunray".into())),
+            FlightEvent::Asteroid { rocket } => {
+                fields.push(("event", "asteroid".into()));
+                fields.push(("rocket", (*rocket).into()));
+            }
+            FlightEvent::ExplorerRequest {
+                explorer_id,
+                request,
+            } => {
+                fields.push(("event", "explorer_request".into()));
+                fields.push(("explorer_id", (*explorer_id).into()));
+                fields.push(("request", (*request).into()));
+            }
+            FlightEvent::Decision(record) => {
+                fields.push(("event", "decision".into()));
+                fields.push((
+                    "decision",
+                    Json::object(
+                        record
+                            .to_payload()
+                            .into_iter()
+                            .map(|(key, value)| (key, Json::String(value))),
+                    ),
+                ));
+            }
+            FlightEvent::Estimator(estimator) => {
+                fields.push(("event", "estimator".into()));
+                fields.push(("sun_intensity", estimator.sun_intensity.into()));
+                fields.push(("asteroid_intensity", estimator.asteroid_intensity.into()));
+                fields.push(("sunray_probability", estimator.sunray_probability.into()));
+            }
+        }
+        Json::object(fields)
+    }
+}
+
+struct Recorder {
+    capacity: usize,
+    entries: VecDeque<FlightEntry>,
+    dump_path: Option<PathBuf>,
+}
+
+/// Bounded ring buffer of the last events seen by an [`Ai`](crate::Ai).
+///
+/// The capacity is shared by all kinds of events: it keeps the last events overall, so a
+/// burst of sunrays or estimator updates pushes the older decisions out.
+///
+/// The handle can be cloned and kept after the AI is moved into a planet, to dump the
+/// buffer on demand. If a dump file is configured, the buffer is also appended to it
+/// when the planet is stopped and when an asteroid hits with no rocket launched.
+#[derive(Clone)]
+pub struct FlightRecorder {
+    inner: Arc<Mutex<Recorder>>,
+}
+
+impl FlightRecorder {
+    /// Creates a recorder keeping the last `capacity` events.
+    #[must_use]
+    pub fn new(capacity: usize, dump_path: Option<PathBuf>) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(Recorder {
+                capacity,
+                entries: VecDeque::with_capacity(capacity),
+                dump_path,
+            })),
+        }
+    }
+
+    pub(crate) fn push(&self, event: FlightEvent) {
+        let mut recorder = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+        if recorder.capacity == 0 {
+            return;
+        }
+        if recorder.entries.len() == recorder.capacity {
+            recorder.entries.pop_front();
+        }
+        recorder.entries.push_back(FlightEntry {
+            timestamp: SystemTime::now(),
+            event,
+        });
+    }
+
+    /// Returns a copy of the recorded events, oldest first.
+    #[must_use]
+    pub fn entries(&self) -> Vec<FlightEntry> {
+        self.inner
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entries
+            .iter()
+            .cloned()
+            .collect()
+    }
+
+    /// Writes the recorded events as JSON lines, oldest first.
+    ///
+    /// # Errors
+    /// Returns any error of the writer.
+    pub fn dump(&self, writer: &mut impl Write) -> io::Result<()> {
+        for entry in self.entries() {
+            writeln!(writer, "{}", entry.to_json())?;
+        }
+        writer.flush()
+    }
+
+    /// Appends the recorded events to the dump file, preceded by a header line with
+    /// `reason`. Does nothing if no dump file is configured.
+    pub(crate) fn dump_to_file(&self, planet_id: ID, reason: &str) -> io::Result<()> {
+        let Some(path) = self
+            .inner
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .dump_path
+            .clone()
+        else {
+            return Ok(());
+        };
+
+        let entries = self.entries();
+        let header = Json::object([
+            ("event", Json::from("dump")),
+            ("reason", reason.into()),
+            ("planet_id", planet_id.into()),
+            ("entries", entries.len().into()),
+        ]);
+
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        writeln!(file, "{header}")?;
+        for entry in entries {
+            writeln!(file, "{}", entry.to_json())?;
+        }
+        file.flush()
+    }
+}
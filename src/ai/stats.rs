@@ -0,0 +1,232 @@
+use super::explain::DecisionExplanation;
+use super::record::DecisionRecord;
+use common_game::utils::ID;
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+/// Number of explorer requests handled, per request type.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RequestCounters {
+    pub supported_resource: u64,
+    pub supported_combination: u64,
+    pub generate_resource: u64,
+    pub combine_resource: u64,
+    pub available_energy_cell: u64,
+}
+
+/// Outcome of the generation and combination requests handled.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DecisionCounters {
+    /// The AI spent a charge on the request
+    pub accepted: u64,
+    /// The AI decided to keep the charge
+    pub denied_by_policy: u64,
+    /// No energy cell was charged
+    pub denied_no_charge: u64,
+    /// The charge was reserved for defense
+    pub denied_reserved: u64,
+    /// The planet has no rule for the requested resource
+    pub unsupported: u64,
+    /// The request was accepted but crafting failed (e.g. wrong ingredients)
+    pub failed: u64,
+}
+
+/// Current values of the sunray/asteroid estimator.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EstimatorStats {
+    pub sun_intensity: f32,
+    pub asteroid_intensity: f32,
+    pub sunray_probability: f32,
+    pub time_constant_secs: f32,
+}
+
+/// Planet state seen by the AI at its last callback.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PlanetStats {
+    pub energy_cells: usize,
+    pub charged_cells: usize,
+    pub has_rocket: bool,
+    /// Explorers currently on the planet, sorted
+    pub explorers: Vec<ID>,
+}
+
+/// Upper bounds (in seconds) of the decision latency histogram buckets.
+pub const LATENCY_BUCKETS_SECS: [f64; 10] = [
+    0.000_001, 0.000_005, 0.000_01, 0.000_05, 0.000_1, 0.000_5, 0.001, 0.005, 0.01, 0.05,
+];
+
+/// Histogram of the time spent deciding on explorer requests.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LatencyHistogram {
+    // Observations per bucket, the last slot counts the ones above every bound
+    counts: [u64; LATENCY_BUCKETS_SECS.len() + 1],
+    sum_secs: f64,
+}
+
+impl LatencyHistogram {
+    pub(crate) fn observe(&mut self, latency: Duration) {
+        let secs = latency.as_secs_f64();
+        let bucket = LATENCY_BUCKETS_SECS
+            .iter()
+            .position(|bound| secs <= *bound)
+            .unwrap_or(LATENCY_BUCKETS_SECS.len());
+        self.counts[bucket] += 1;
+        self.sum_secs += secs;
+    }
+
+    /// Returns `(upper bound, cumulative count)` for every bucket, `+Inf` included.
+    #[must_use]
+    pub fn cumulative_buckets(&self) -> Vec<(f64, u64)> {
+        let mut total = 0;
+        LATENCY_BUCKETS_SECS
+            .iter()
+            .copied()
+            .chain(std::iter::once(f64::INFINITY))
+            .zip(self.counts)
+            .map(|(bound, count)| {
+                total += count;
+                (bound, total)
+            })
+            .collect()
+    }
+
+    #[must_use]
+    pub fn count(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    #[must_use]
+    pub fn sum_secs(&self) -> f64 {
+        self.sum_secs
+    }
+}
+
+/// Disagreements kept for each shadow policy, older ones are dropped.
+pub const DISAGREEMENT_BACKLOG: usize = 64;
+
+/// Agreement of a shadow policy with the active one, see
+/// [`DecisionPolicy`](crate::DecisionPolicy).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ShadowStats {
+    pub name: String,
+    /// Requests the active policy decided on
+    pub decisions: u64,
+    /// Requests denied by the active policy that the shadow would have accepted
+    pub accepted_by_shadow_only: u64,
+    /// Requests accepted by the active policy that the shadow would have denied
+    pub denied_by_shadow_only: u64,
+    /// Last decisions of the active policy the shadow disagreed with, the oldest first
+    pub disagreements: VecDeque<DecisionRecord>,
+}
+
+impl ShadowStats {
+    pub(crate) fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            ..Self::default()
+        }
+    }
+
+    #[must_use]
+    pub fn disagreement_count(&self) -> u64 {
+        self.accepted_by_shadow_only + self.denied_by_shadow_only
+    }
+
+    /// Share of the decisions the shadow disagreed with, `None` before any.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn disagreement_ratio(&self) -> Option<f64> {
+        (self.decisions > 0).then(|| self.disagreement_count() as f64 / self.decisions as f64)
+    }
+}
+
+impl fmt::Display for ShadowStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} disagreements in {} decisions ({:.1}%), {} accepted and {} denied by the \
+             shadow only",
+            self.name,
+            self.disagreement_count(),
+            self.decisions,
+            self.disagreement_ratio().unwrap_or_default() * 100.0,
+            self.accepted_by_shadow_only,
+            self.denied_by_shadow_only
+        )
+    }
+}
+
+/// Read-only snapshot of what the AI has seen and decided so far.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AiStats {
+    pub requests: RequestCounters,
+    pub decisions: DecisionCounters,
+    pub sunrays_received: u64,
+    pub asteroids_survived: u64,
+    pub asteroids_lost: u64,
+    pub rockets_built: u64,
+    pub charges_consumed: u64,
+    pub decision_latency: LatencyHistogram,
+    /// `None` if the AI keeps no estimator
+    pub estimator: Option<EstimatorStats>,
+    pub planet: PlanetStats,
+    /// Last generation or combination decision for each explorer
+    pub last_decisions: BTreeMap<ID, DecisionExplanation>,
+    /// Agreement of each shadow policy, in the order they were added
+    pub shadows: Vec<ShadowStats>,
+}
+
+impl AiStats {
+    /// Share of the generation and combination requests the AI accepted, `None` before any.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn accept_ratio(&self) -> Option<f64> {
+        let DecisionCounters {
+            accepted,
+            denied_by_policy,
+            denied_no_charge,
+            denied_reserved,
+            ..
+        } = self.decisions;
+        let decided = accepted + denied_by_policy + denied_no_charge + denied_reserved;
+        (decided > 0).then(|| accepted as f64 / decided as f64)
+    }
+}
+
+/// Shared handle to the statistics of an [`Ai`](crate::Ai).
+///
+/// Obtain it with [`Ai::stats_handle`](crate::Ai::stats_handle) before the AI is boxed
+/// into the planet, then read snapshots from any thread.
+#[derive(Debug, Clone, Default)]
+pub struct StatsHandle {
+    inner: Arc<Mutex<AiStats>>,
+}
+
+impl StatsHandle {
+    /// Returns a copy of the current statistics.
+    #[must_use]
+    pub fn snapshot(&self) -> AiStats {
+        self.inner
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Explains the last decision for `explorer_id`, like
+    /// [`Ai::explain_last_decision`](crate::Ai::explain_last_decision).
+    #[must_use]
+    pub fn explain_last_decision(&self, explorer_id: ID) -> Option<DecisionExplanation> {
+        self.inner
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .last_decisions
+            .get(&explorer_id)
+            .cloned()
+    }
+
+    pub(crate) fn update(&self, f: impl FnOnce(&mut AiStats)) {
+        f(&mut self.inner.lock().unwrap_or_else(PoisonError::into_inner));
+    }
+}
@@ -0,0 +1,422 @@
+// Unit tests of the handler branches on a fake planet, without threads or channels
+
+use super::view::{FakePlanet, FakeRocket, PlanetView};
+use super::*;
+use crate::clock::MockClock;
+use crate::testing::sequences::{SequenceCheck, Step};
+use common_game::components::resource::BasicResourceType;
+use common_game::components::sunray::Sunray;
+
+fn ai(random_mode: bool, coeff: f32) -> Ai {
+    let mut ai = Ai::new(
+        random_mode,
+        coeff,
+        coeff,
+        Duration::from_secs(10),
+        Duration::from_secs(1),
+    )
+    .with_clock(Arc::new(MockClock::new()))
+    .with_seed(7);
+    ai.is_ai_active = true;
+    ai
+}
+
+fn plan(ai: &mut Ai, planet: &FakePlanet) -> DecisionRecord {
+    explorer::plan(
+        ai,
+        planet,
+        3,
+        DecisionRequest::GenerateResource(BasicResourceType::Carbon),
+        true,
+    )
+}
+
+fn verdict(record: &DecisionRecord) -> (DecisionOutcome, DecisionReason, Option<usize>) {
+    (record.outcome, record.reason, record.cell)
+}
+
+#[test]
+fn test_decide_without_charge() {
+    let record = plan(&mut ai(true, 0.0), &FakePlanet::new(2));
+    assert_eq!(
+        verdict(&record),
+        (
+            DecisionOutcome::Denied,
+            DecisionReason::CellNotCharged,
+            None
+        )
+    );
+}
+
+#[test]
+fn test_decide_reserved_for_defense() {
+    let mut ai = ai(true, 0.0);
+    ai.reserve_energy_for(Duration::from_secs(5));
+
+    // The only charge is kept for a rocket
+    let record = plan(&mut ai, &FakePlanet::new(2).charged(1));
+    assert_eq!(
+        verdict(&record),
+        (
+            DecisionOutcome::Denied,
+            DecisionReason::ReservedForDefense,
+            None
+        )
+    );
+    assert!(record.inputs.reserved);
+
+    // A second charge can be spent, and so can the only one without rockets
+    let record = plan(&mut ai, &FakePlanet::new(2).charged(2));
+    assert_eq!(record.cell, Some(1));
+    let record = plan(&mut ai, &FakePlanet::new(1).charged(1).without_rockets());
+    assert_eq!(record.cell, Some(0));
+}
+
+#[test]
+fn test_decide_random() {
+    let planet = FakePlanet::new(3).charged(2);
+    let record = plan(&mut ai(true, 0.0), &planet);
+    assert_eq!(
+        verdict(&record),
+        (
+            DecisionOutcome::Accepted,
+            DecisionReason::RandomSample,
+            Some(1)
+        )
+    );
+    assert_eq!(record.policy, DecisionPolicyKind::Random);
+    assert!(record.inputs.random_sample.is_some());
+
+    let record = plan(&mut ai(true, 1.0), &planet);
+    assert_eq!(
+        verdict(&record),
+        (DecisionOutcome::Denied, DecisionReason::RandomSample, None)
+    );
+}
+
+#[test]
+fn test_decide_adaptive() {
+    // Even odds at first, the clock does not move
+    let planet = FakePlanet::new(1).charged(1);
+    let record = plan(&mut ai(false, 0.5), &planet);
+    assert_eq!(
+        verdict(&record),
+        (
+            DecisionOutcome::Accepted,
+            DecisionReason::AsteroidRisk,
+            Some(0)
+        )
+    );
+    assert_eq!(record.inputs.p_asteroid, Some(0.5));
+    assert_eq!(record.policy, DecisionPolicyKind::Adaptive);
+
+    let record = plan(&mut ai(false, 0.4), &planet);
+    assert_eq!(
+        verdict(&record),
+        (DecisionOutcome::Denied, DecisionReason::AsteroidRisk, None)
+    );
+
+    // With a rocket, the risk is two asteroids in a row
+    let record = plan(
+        &mut ai(false, 0.4),
+        &FakePlanet::new(1).charged(1).with_rocket(),
+    );
+    assert_eq!(
+        verdict(&record),
+        (
+            DecisionOutcome::Accepted,
+            DecisionReason::DoubleAsteroidRisk,
+            Some(0)
+        )
+    );
+    assert_eq!(record.inputs.p_asteroid_squared, Some(0.25));
+}
+
+#[test]
+fn test_decide_without_estimator() {
+    let mut ai = ai(false, 1.0);
+    ai.counters = None;
+    let record = plan(&mut ai, &FakePlanet::new(1).charged(1));
+    assert_eq!(
+        verdict(&record),
+        (DecisionOutcome::Denied, DecisionReason::NoEstimator, None)
+    );
+}
+
+#[test]
+fn test_plan_without_recipe() {
+    let record = explorer::plan(
+        &mut ai(true, 0.0),
+        &FakePlanet::new(1).charged(1),
+        3,
+        DecisionRequest::GenerateResource(BasicResourceType::Oxygen),
+        false,
+    );
+    assert_eq!(
+        verdict(&record),
+        (
+            DecisionOutcome::Unsupported,
+            DecisionReason::MissingRecipe,
+            None
+        )
+    );
+}
+
+#[test]
+fn test_settle() {
+    let mut ai = ai(true, 0.0);
+    let planet = FakePlanet::new(1).charged(1);
+
+    let mut record = plan(&mut ai, &planet);
+    explorer::settle(&mut ai, &planet, &mut record, true);
+    assert_eq!(record.outcome, DecisionOutcome::Accepted);
+    let stats = ai.stats();
+    assert_eq!((stats.decisions.accepted, stats.charges_consumed), (1, 1));
+
+    let mut record = plan(&mut ai, &planet);
+    explorer::settle(&mut ai, &planet, &mut record, false);
+    assert_eq!(
+        verdict(&record),
+        (
+            DecisionOutcome::Failed,
+            DecisionReason::CraftingFailed,
+            Some(0)
+        )
+    );
+    let stats = ai.stats();
+    assert_eq!((stats.decisions.failed, stats.charges_consumed), (1, 1));
+
+    // Denied requests have nothing to settle
+    let mut record = plan(&mut ai, &FakePlanet::new(1));
+    explorer::settle(&mut ai, &planet, &mut record, false);
+    assert_eq!(record.outcome, DecisionOutcome::Denied);
+    assert_eq!(ai.stats().decisions.denied_no_charge, 1);
+}
+
+#[test]
+fn test_explorer_messages() {
+    let (generator, combinator) = (Generator::new(), Combinator::new());
+    let mut ai = ai(true, 0.0);
+    let mut planet = FakePlanet::new(3).charged(2);
+
+    let response = explorer::handle_message(
+        &mut ai,
+        &mut planet,
+        &generator,
+        &combinator,
+        ExplorerToPlanet::AvailableEnergyCellRequest { explorer_id: 3 },
+    );
+    assert!(matches!(
+        response,
+        Some(PlanetToExplorer::AvailableEnergyCellResponse { available_cells: 2 })
+    ));
+
+    // Without a recipe the charge is kept
+    let response = explorer::handle_message(
+        &mut ai,
+        &mut planet,
+        &generator,
+        &combinator,
+        ExplorerToPlanet::GenerateResourceRequest {
+            explorer_id: 3,
+            resource: BasicResourceType::Carbon,
+        },
+    );
+    assert!(matches!(
+        response,
+        Some(PlanetToExplorer::GenerateResourceResponse { resource: None })
+    ));
+    assert_eq!(planet.charged_cells(), 2);
+    assert_eq!(ai.stats().decisions.unsupported, 1);
+    assert_eq!(ai.stats().requests.generate_resource, 1);
+
+    // Stopped, nothing is answered
+    ai.is_ai_active = false;
+    assert!(
+        explorer::handle_message(
+            &mut ai,
+            &mut planet,
+            &generator,
+            &combinator,
+            ExplorerToPlanet::SupportedResourceRequest { explorer_id: 3 },
+        )
+        .is_none()
+    );
+}
+
+#[test]
+fn test_asteroid_launches_existing_rocket() {
+    let mut ai = ai(false, 0.5);
+    let mut planet = FakePlanet::new(1).charged(1).with_rocket();
+    assert_eq!(
+        asteroid::handle_asteroid(&mut ai, &mut planet),
+        Some(FakeRocket)
+    );
+    assert!(!planet.has_rocket());
+    assert_eq!(planet.charged_cells(), 1);
+    let stats = ai.stats();
+    assert_eq!((stats.asteroids_survived, stats.rockets_built), (1, 0));
+}
+
+#[test]
+fn test_asteroid_builds_rocket() {
+    let mut ai = ai(false, 0.5).with_defense_reservation(Duration::from_secs(5));
+    let mut planet = FakePlanet::new(2).charged(2);
+    assert_eq!(
+        asteroid::handle_asteroid(&mut ai, &mut planet),
+        Some(FakeRocket)
+    );
+    // Built from the first cell
+    assert!(!planet.is_charged(0) && planet.is_charged(1));
+    let stats = ai.stats();
+    assert_eq!((stats.asteroids_survived, stats.rockets_built), (1, 1));
+    assert!(ai.is_energy_reserved());
+}
+
+#[test]
+fn test_asteroid_without_rocket() {
+    // No charge
+    let mut ai = ai(false, 0.5);
+    assert_eq!(
+        asteroid::handle_asteroid(&mut ai, &mut FakePlanet::new(1)),
+        None
+    );
+    // Charged, but no rockets allowed
+    let mut planet = FakePlanet::new(1).charged(1).without_rockets();
+    assert_eq!(asteroid::handle_asteroid(&mut ai, &mut planet), None);
+    assert_eq!(planet.charged_cells(), 1);
+    assert_eq!(ai.stats().asteroids_lost, 2);
+}
+
+#[test]
+fn test_sunray() {
+    let mut ai = ai(false, 0.5);
+
+    // Charges the first empty cell
+    let mut planet = FakePlanet::new(2);
+    orchestrator::handle_sunray(&mut ai, &mut planet, Sunray::default());
+    assert!(planet.is_charged(0) && !planet.is_charged(1));
+    assert!(!planet.has_rocket());
+
+    // The existing charge becomes a rocket before the new one is stored
+    let mut planet = FakePlanet::new(1).charged(1);
+    orchestrator::handle_sunray(&mut ai, &mut planet, Sunray::default());
+    assert!(planet.has_rocket());
+    assert_eq!(planet.charged_cells(), 1);
+
+    // Full with a rocket, the sunray is wasted
+    orchestrator::handle_sunray(&mut ai, &mut planet, Sunray::default());
+    assert_eq!(planet.charged_cells(), 1);
+
+    let stats = ai.stats();
+    assert_eq!((stats.sunrays_received, stats.rockets_built), (3, 1));
+}
+
+#[test]
+fn test_start_and_stop() {
+    let mut ai = ai(false, 0.5);
+    let planet = FakePlanet::new(1);
+    orchestrator::handle_stop_ai(&mut ai, &planet);
+    assert!(!ai.is_ai_active);
+    assert!(ai.counters.as_ref().unwrap().is_paused());
+    orchestrator::handle_start_ai(&mut ai, &planet);
+    assert!(ai.is_ai_active);
+    assert!(!ai.counters.as_ref().unwrap().is_paused());
+}
+
+// Calls the handlers like the planet would for `step`, also while the AI is inactive, and
+// checks that an inactive AI answers nothing and that a rocket is only launched if one was
+// ready or could be built from a charge
+fn play_step(ai: &mut Ai, planet: &mut FakePlanet, step: Step) -> Result<(), String> {
+    let (generator, combinator) = (Generator::new(), Combinator::new());
+    let charged = planet.charged_cells();
+    let explorer_msg = match step {
+        Step::Start => {
+            orchestrator::handle_start_ai(ai, planet);
+            return Ok(());
+        }
+        Step::Stop => {
+            orchestrator::handle_stop_ai(ai, planet);
+            return Ok(());
+        }
+        Step::Sunray if ai.is_ai_active => {
+            orchestrator::handle_sunray(ai, planet, Sunray::default());
+            return Ok(());
+        }
+        Step::Asteroid => {
+            let had_rocket = planet.has_rocket();
+            let rocket = asteroid::handle_asteroid(ai, planet);
+            let could_build = planet.can_have_rocket() && charged > 0;
+            if !ai.is_ai_active && (rocket.is_some() || planet.has_rocket() != had_rocket) {
+                return Err("Inactive AI used its rocket".to_string());
+            }
+            if ai.is_ai_active && rocket.is_some() != (had_rocket || could_build) {
+                return Err(format!(
+                    "Rocket {rocket:?}, with a rocket ready: {had_rocket}, {charged} charged"
+                ));
+            }
+            return Ok(());
+        }
+        // Served from a charge through a recipe the fake generator does not have
+        Step::Generate(explorer_id, BasicResourceType::Hydrogen) if ai.is_ai_active => {
+            let request = DecisionRequest::GenerateResource(BasicResourceType::Hydrogen);
+            let mut record = explorer::plan(ai, planet, explorer_id, request, true);
+            if let Some(cell) = record.cell {
+                planet.cell_mut(cell).discharge()?;
+                explorer::settle(ai, planet, &mut record, true);
+            }
+            return Ok(());
+        }
+        Step::Generate(explorer_id, resource) => ExplorerToPlanet::GenerateResourceRequest {
+            explorer_id,
+            resource,
+        },
+        Step::SupportedResources(explorer_id) => {
+            ExplorerToPlanet::SupportedResourceRequest { explorer_id }
+        }
+        Step::SupportedCombinations(explorer_id) => {
+            ExplorerToPlanet::SupportedCombinationRequest { explorer_id }
+        }
+        Step::AvailableCells(explorer_id) => {
+            ExplorerToPlanet::AvailableEnergyCellRequest { explorer_id }
+        }
+        // Resources for combinations can't be created outside common-game
+        _ => return Ok(()),
+    };
+
+    let what = format!("{explorer_msg:?}");
+    let response = explorer::handle_message(ai, planet, &generator, &combinator, explorer_msg);
+    match response {
+        Some(_) if !ai.is_ai_active => Err(format!("Inactive AI answered {what}")),
+        None if ai.is_ai_active => Err(format!("No answer to {what}")),
+        Some(PlanetToExplorer::AvailableEnergyCellResponse { available_cells })
+            if available_cells as usize != charged =>
+        {
+            Err(format!(
+                "{available_cells} available cells, {charged} charged"
+            ))
+        }
+        _ if planet.charged_cells() != charged => {
+            Err(format!("{what} spent a charge without a recipe"))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[test]
+fn test_random_sequences() {
+    for (cells, rockets, random_mode) in [(1, true, false), (5, true, true), (5, false, false)] {
+        SequenceCheck::new().run(
+            |rng| Step::random(rng, 2),
+            |steps| {
+                let mut ai = ai(random_mode, 0.3);
+                ai.is_ai_active = false;
+                let mut planet = FakePlanet::new(cells);
+                planet.can_have_rocket = rockets;
+                steps
+                    .iter()
+                    .try_for_each(|step| play_step(&mut ai, &mut planet, *step))
+            },
+        );
+    }
+}
@@ -0,0 +1,168 @@
+use common_game::components::energy_cell::EnergyCell;
+use common_game::components::planet::PlanetState;
+use common_game::components::rocket::Rocket;
+use common_game::components::sunray::Sunray;
+use common_game::utils::ID;
+
+/// What the handlers read and change of the planet.
+///
+/// Implemented by [`PlanetState`], which only `common-game` can create, and by `FakePlanet`
+/// in the unit tests.
+pub(crate) trait PlanetView {
+    /// Only `common-game` can build real rockets, the fake uses a placeholder
+    type Rocket;
+
+    fn id(&self) -> ID;
+    fn cells_count(&self) -> usize;
+    fn is_charged(&self, cell: usize) -> bool;
+    fn can_have_rocket(&self) -> bool;
+    fn has_rocket(&self) -> bool;
+    /// Charges the first empty cell, returns `false` if every cell was full.
+    fn charge(&mut self, sunray: Sunray) -> bool;
+    /// Spends the charge of `cell` on a rocket kept on the planet.
+    fn build_rocket(&mut self, cell: usize) -> Result<(), String>;
+    fn take_rocket(&mut self) -> Option<Self::Rocket>;
+    /// Cell whose charge is consumed by a generator or a combinator.
+    fn cell_mut(&mut self, cell: usize) -> &mut EnergyCell;
+}
+
+impl PlanetView for PlanetState {
+    type Rocket = Rocket;
+
+    fn id(&self) -> ID {
+        PlanetState::id(self)
+    }
+
+    fn cells_count(&self) -> usize {
+        PlanetState::cells_count(self)
+    }
+
+    fn is_charged(&self, cell: usize) -> bool {
+        self.cell(cell).is_charged()
+    }
+
+    fn can_have_rocket(&self) -> bool {
+        PlanetState::can_have_rocket(self)
+    }
+
+    fn has_rocket(&self) -> bool {
+        PlanetState::has_rocket(self)
+    }
+
+    fn charge(&mut self, sunray: Sunray) -> bool {
+        self.charge_cell(sunray).is_none()
+    }
+
+    fn build_rocket(&mut self, cell: usize) -> Result<(), String> {
+        PlanetState::build_rocket(self, cell)
+    }
+
+    fn take_rocket(&mut self) -> Option<Rocket> {
+        PlanetState::take_rocket(self)
+    }
+
+    fn cell_mut(&mut self, cell: usize) -> &mut EnergyCell {
+        PlanetState::cell_mut(self, cell)
+    }
+}
+
+/// Planet of the unit tests, following the rules of [`PlanetState`].
+#[cfg(test)]
+pub(crate) struct FakePlanet {
+    pub cells: Vec<EnergyCell>,
+    pub can_have_rocket: bool,
+    pub rocket: bool,
+}
+
+#[cfg(test)]
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct FakeRocket;
+
+#[cfg(test)]
+impl FakePlanet {
+    /// Planet with `cells` empty cells, able to keep a rocket like a type C planet.
+    pub fn new(cells: usize) -> Self {
+        Self {
+            cells: (0..cells).map(|_| EnergyCell::new()).collect(),
+            can_have_rocket: true,
+            rocket: false,
+        }
+    }
+
+    /// Charges the first `n` cells.
+    pub fn charged(mut self, n: usize) -> Self {
+        for _ in 0..n {
+            self.charge(Sunray::default());
+        }
+        self
+    }
+
+    pub fn with_rocket(mut self) -> Self {
+        self.rocket = true;
+        self
+    }
+
+    pub fn without_rockets(mut self) -> Self {
+        self.can_have_rocket = false;
+        self
+    }
+
+    pub fn charged_cells(&self) -> usize {
+        self.cells.iter().filter(|cell| cell.is_charged()).count()
+    }
+}
+
+#[cfg(test)]
+impl PlanetView for FakePlanet {
+    type Rocket = FakeRocket;
+
+    fn id(&self) -> ID {
+        1
+    }
+
+    fn cells_count(&self) -> usize {
+        self.cells.len()
+    }
+
+    fn is_charged(&self, cell: usize) -> bool {
+        self.cells[cell].is_charged()
+    }
+
+    fn can_have_rocket(&self) -> bool {
+        self.can_have_rocket
+    }
+
+    fn has_rocket(&self) -> bool {
+        self.rocket
+    }
+
+    fn charge(&mut self, sunray: Sunray) -> bool {
+        match self.cells.iter_mut().find(|cell| !cell.is_charged()) {
+            Some(cell) => {
+                cell.charge(sunray);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn build_rocket(&mut self, cell: usize) -> Result<(), String> {
+        if !self.can_have_rocket {
+            Err("This planet type can't have rockets.".to_string())
+        } else if self.rocket {
+            Err("This planet already has a rocket.".to_string())
+        } else {
+            self.cells[cell].discharge()?;
+            self.rocket = true;
+            Ok(())
+        }
+    }
+
+    fn take_rocket(&mut self) -> Option<FakeRocket> {
+        std::mem::take(&mut self.rocket).then_some(FakeRocket)
+    }
+
+    fn cell_mut(&mut self, cell: usize) -> &mut EnergyCell {
+        &mut self.cells[cell]
+    }
+}
@@ -0,0 +1,175 @@
+//! Live terminal dashboard of a planet, either served by `icb-planet --tcp/--unix` or run in
+//! this process with generated traffic (`--demo`).
+
+use immutable_cosmic_borrow::bridge::socket::BridgeAddress;
+use immutable_cosmic_borrow::bridge::{PlanetBridge, PlanetOptions};
+use immutable_cosmic_borrow::dashboard::{BridgeFeed, Dashboard, DashboardFeed, LocalFeed};
+use rand::Rng;
+use std::io::{self, Write};
+use std::net::{Ipv4Addr, SocketAddr};
+use std::process::ExitCode;
+use std::thread;
+use std::time::Duration;
+
+const USAGE: &str = "\
+Usage: icb-dashboard (--tcp PORT | --unix PATH | --demo [PLANET OPTIONS]) [OPTIONS]
+  --tcp PORT          watch the planet served by icb-planet on localhost:PORT
+  --unix PATH         watch the planet served by icb-planet on a Unix socket
+  --demo              run a planet in this process with random sunrays, asteroids and explorers
+  --interval-ms MS    refresh period (500)
+  --frames N          exit after N frames
+  --plain             print the frames one after another, without clearing the screen
+Planet options, with --demo:";
+
+struct Args {
+    source: Option<Source>,
+    options: PlanetOptions,
+    interval: Duration,
+    frames: Option<u64>,
+    plain: bool,
+}
+
+enum Source {
+    Bridge(BridgeAddress),
+    Demo,
+}
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("icb-dashboard: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run() -> Result<(), String> {
+    let Some(args) = parse_args()? else {
+        println!("{USAGE}");
+        println!("{}", PlanetOptions::USAGE);
+        return Ok(());
+    };
+
+    let (mut feed, mut dashboard): (Box<dyn DashboardFeed>, _) = match args
+        .source
+        .ok_or("Missing --tcp, --unix or --demo, see --help")?
+    {
+        Source::Bridge(address) => (
+            Box::new(BridgeFeed::connect(&address)?),
+            Dashboard::new(None),
+        ),
+        Source::Demo => (
+            Box::new(spawn_demo(&args.options)?),
+            Dashboard::new(Some(args.options.id)),
+        ),
+    };
+
+    let (width, height) = terminal_size();
+    let mut stdout = io::stdout().lock();
+    let mut frame = 0;
+    loop {
+        feed.poll(&mut dashboard)?;
+        let screen = dashboard.render(width, height.saturating_sub(1));
+        if args.plain {
+            writeln!(stdout, "{screen}")
+        } else {
+            // Cursor home and clear screen
+            write!(stdout, "\x1b[H\x1b[2J{screen}")
+        }
+        .and_then(|()| stdout.flush())
+        .map_err(|err| err.to_string())?;
+
+        frame += 1;
+        if args.frames.is_some_and(|frames| frame >= frames) {
+            return Ok(());
+        }
+        thread::sleep(args.interval);
+    }
+}
+
+fn parse_args() -> Result<Option<Args>, String> {
+    let mut args = Args {
+        source: None,
+        options: PlanetOptions::default(),
+        interval: Duration::from_millis(500),
+        frames: None,
+        plain: false,
+    };
+    let rest = args.options.parse_args(std::env::args().skip(1))?;
+    let mut rest = rest.into_iter();
+    while let Some(arg) = rest.next() {
+        let mut value = || rest.next().ok_or(format!("Missing value after {arg}"));
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "--tcp" => {
+                let port = parse(&arg, &value()?)?;
+                args.source = Some(Source::Bridge(BridgeAddress::Tcp(SocketAddr::from((
+                    Ipv4Addr::LOCALHOST,
+                    port,
+                )))));
+            }
+            #[cfg(unix)]
+            "--unix" => args.source = Some(Source::Bridge(BridgeAddress::Unix(value()?.into()))),
+            "--demo" => args.source = Some(Source::Demo),
+            "--interval-ms" => args.interval = Duration::from_millis(parse(&arg, &value()?)?),
+            "--frames" => args.frames = Some(parse(&arg, &value()?)?),
+            "--plain" => args.plain = true,
+            _ => return Err(format!("Unknown argument {arg}, see --help")),
+        }
+    }
+    Ok(Some(args))
+}
+
+fn parse<T: std::str::FromStr>(option: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Invalid value {value} for {option}"))
+}
+
+// Terminal size from the shell variables, there is no portable way without a dependency
+fn terminal_size() -> (usize, usize) {
+    let var = |name: &str, default: usize| {
+        std::env::var(name)
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(default)
+    };
+    (var("COLUMNS", 100), var("LINES", 30))
+}
+
+// Runs the planet with random traffic on its own thread, feeding the dashboard directly
+fn spawn_demo(options: &PlanetOptions) -> Result<LocalFeed, String> {
+    let ai = options.create_ai();
+    let stats = ai.stats_handle();
+    let (tx_decisions, decisions) = crossbeam_channel::unbounded();
+    let ai = ai.with_decision_sink(tx_decisions);
+    let mut bridge = PlanetBridge::spawn(|orchestrator_channels, explorers_receiver| {
+        options.create_planet_with_ai(ai, orchestrator_channels, explorers_receiver)
+    })?;
+    let resource = format!("{:?}", options.gen_rules[0]);
+
+    thread::spawn(move || {
+        let mut rng = rand::rng();
+        let mut send = |command: String| bridge.handle_line(&command);
+        send(r#"{"type":"start"}"#.to_string());
+        loop {
+            let explorer_id = rng.random_range(1..=4);
+            let command = match rng.random_range(0..10) {
+                0..4 => r#"{"type":"sunray"}"#.to_string(),
+                4 => r#"{"type":"asteroid"}"#.to_string(),
+                5 => format!(r#"{{"type":"explorer_arrival","explorer_id":{explorer_id}}}"#),
+                6 => format!(r#"{{"type":"explorer_departure","explorer_id":{explorer_id}}}"#),
+                _ => format!(
+                    r#"{{"type":"generate_resource","explorer_id":{explorer_id},"resource":"{resource}"}}"#
+                ),
+            };
+            if send(command).contains("killed") {
+                return;
+            }
+            thread::sleep(Duration::from_millis(rng.random_range(20..200)));
+        }
+    });
+
+    Ok(LocalFeed::new(stats, decisions))
+}
@@ -0,0 +1,103 @@
+//! Runs a planet and drives it with JSON lines on stdin, answering on stdout, or with
+//! frames on a local socket.
+//!
+//! See the `bridge` module documentation for the commands and responses.
+
+use immutable_cosmic_borrow::bridge::socket::{BridgeAddress, BridgeServer};
+use immutable_cosmic_borrow::bridge::{DECISION_BACKLOG, PlanetBridge, PlanetOptions};
+use std::io::{self, BufRead, Write};
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("icb-planet: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run() -> Result<(), String> {
+    let mut options = PlanetOptions::default();
+    let rest = options.parse_args(std::env::args().skip(1))?;
+    let mut listen = None;
+    let mut rest = rest.into_iter();
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("Usage: icb-planet [OPTIONS] [--tcp PORT | --unix PATH]");
+                println!(
+                    "Reads JSON line commands on stdin and writes the planet responses on stdout."
+                );
+                println!("{}", PlanetOptions::USAGE);
+                println!("  --tcp PORT            serve length-prefixed frames on localhost:PORT");
+                println!("  --unix PATH           serve length-prefixed frames on a Unix socket");
+                return Ok(());
+            }
+            "--tcp" | "--unix" => {
+                let value = rest.next().ok_or(format!("Missing value for {arg}"))?;
+                listen = Some((arg, value));
+            }
+            _ => return Err(format!("Unknown argument {arg}, see --help")),
+        }
+    }
+
+    // Keep a feed of the AI for the stats and decisions commands, e.g. for icb-dashboard
+    let ai = options.create_ai();
+    let stats = ai.stats_handle();
+    let (tx_decisions, decisions) = crossbeam_channel::bounded(DECISION_BACKLOG);
+    let ai = ai.with_decision_sink(tx_decisions);
+    let bridge = PlanetBridge::spawn(|orchestrator_channels, explorers_receiver| {
+        options.create_planet_with_ai(ai, orchestrator_channels, explorers_receiver)
+    })?
+    .with_feed(stats, decisions);
+
+    match listen {
+        None => run_stdio(bridge),
+        Some((kind, value)) => {
+            let server = if kind == "--tcp" {
+                let port = value.parse().map_err(|_| format!("Invalid port {value}"))?;
+                BridgeServer::bind_tcp(bridge, port)?
+            } else {
+                bind_unix(bridge, &value)?
+            };
+            match server.address() {
+                BridgeAddress::Tcp(address) => eprintln!("icb-planet: listening on {address}"),
+                #[cfg(unix)]
+                BridgeAddress::Unix(path) => {
+                    eprintln!("icb-planet: listening on {}", path.display());
+                }
+            }
+            server.wait();
+            Ok(())
+        }
+    }
+}
+
+#[cfg(unix)]
+fn bind_unix(bridge: PlanetBridge, path: &str) -> Result<BridgeServer, String> {
+    BridgeServer::bind_unix(bridge, path)
+}
+
+#[cfg(not(unix))]
+fn bind_unix(_bridge: PlanetBridge, _path: &str) -> Result<BridgeServer, String> {
+    Err("Unix sockets are not supported on this platform".to_string())
+}
+
+fn run_stdio(mut bridge: PlanetBridge) -> Result<(), String> {
+    let mut stdout = io::stdout().lock();
+    for line in io::stdin().lock().lines() {
+        let line = line.map_err(|err| err.to_string())?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        writeln!(stdout, "{}", bridge.handle_line(&line))
+            .and_then(|()| stdout.flush())
+            .map_err(|err| err.to_string())?;
+        if bridge.is_killed() {
+            break;
+        }
+    }
+    Ok(())
+}
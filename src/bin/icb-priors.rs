@@ -0,0 +1,49 @@
+//! Fits a warm-start prior for the threat estimator from planet logs and recorded sessions.
+//!
+//! Usage: `icb-priors [-o PRIOR] FILE...`
+
+use immutable_cosmic_borrow::priors::PriorFitter;
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    match run(std::env::args().skip(1)) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("icb-priors: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(mut args: impl Iterator<Item = String>) -> Result<(), String> {
+    let mut output = None;
+    let mut files = Vec::new();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" | "--output" => output = Some(args.next().ok_or("Missing path after -o")?),
+            "-h" | "--help" => {
+                println!("Usage: icb-priors [-o PRIOR] FILE...");
+                println!("Fits estimator priors from planet logs and recorded sessions.");
+                return Ok(());
+            }
+            _ => files.push(arg),
+        }
+    }
+    if files.is_empty() {
+        return Err("No input file, see --help".to_string());
+    }
+
+    let mut fitter = PriorFitter::new();
+    for file in &files {
+        let events = fitter.load(file)?;
+        println!("{file}: {events} sunrays and asteroids");
+    }
+    let fit = fitter.fit()?;
+    println!("{fit}");
+
+    if let Some(output) = output {
+        fit.save(&output)?;
+        println!("prior written to {output}");
+    }
+    Ok(())
+}
@@ -0,0 +1,82 @@
+//! Trains a Q-learning decision policy in the simulator and saves the learned table, to be
+//! loaded with the `--q-table` option of `icb-planet`.
+//!
+//! Usage: `icb-qlearn [-o TABLE] [--episodes N] [--max-steps N] [--seed SEED] [OPTIONS]`
+
+use immutable_cosmic_borrow::bridge::PlanetOptions;
+use immutable_cosmic_borrow::qlearning::{QLearningPolicy, Simulator};
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("icb-qlearn: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run() -> Result<(), String> {
+    let mut options = PlanetOptions::default();
+    let rest = options.parse_args(std::env::args().skip(1))?;
+    // A table given with --q-table is trained further instead of being the baseline
+    let table = options.q_table.take();
+    let mut output = None;
+    let (mut episodes, mut max_steps, mut seed) = (500, 500, 0);
+    let mut rest = rest.into_iter();
+    while let Some(arg) = rest.next() {
+        let mut value = || rest.next().ok_or(format!("Missing value after {arg}"));
+        match arg.as_str() {
+            "-o" | "--output" => output = Some(value()?),
+            "--episodes" => episodes = parse(&arg, &value()?)?,
+            "--max-steps" => max_steps = parse(&arg, &value()?)?,
+            "--seed" => seed = parse(&arg, &value()?)?,
+            "-h" | "--help" => {
+                println!(
+                    "Usage: icb-qlearn [-o TABLE] [--episodes N] [--max-steps N] [--seed SEED] \
+                     [OPTIONS]"
+                );
+                println!(
+                    "Trains a Q-learning policy on simulated traffic, then compares the frozen \
+                     table with the mode of the options."
+                );
+                println!("{}", PlanetOptions::USAGE);
+                println!("                              (the table to train further)");
+                println!("  --episodes N                training episodes (500)");
+                println!("  --max-steps N               steps of an episode at most (500)");
+                println!("  --seed SEED                 seed of the traffic and the policy (0)");
+                return Ok(());
+            }
+            _ => return Err(format!("Unknown argument {arg}, see --help")),
+        }
+    }
+
+    let simulator = Simulator::new(options)
+        .with_episodes(episodes)
+        .with_max_steps(max_steps)
+        .with_seed(seed);
+    let mut policy = QLearningPolicy::new(seed);
+    if let Some(table) = table {
+        policy = policy.with_table(table);
+    }
+    println!("training: {}", simulator.train(&policy)?);
+
+    // Evaluated on other episodes than the training ones
+    let evaluation = simulator.with_seed(seed.wrapping_add(episodes as u64));
+    let frozen = QLearningPolicy::frozen(policy.table());
+    println!("frozen table: {}", evaluation.train(&frozen)?);
+    println!("baseline: {}", evaluation.run(|_| None)?);
+
+    if let Some(output) = output {
+        policy.table().save(&output)?;
+        println!("table written to {output}");
+    }
+    Ok(())
+}
+
+fn parse<T: std::str::FromStr>(arg: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Invalid value {value} for {arg}"))
+}
@@ -0,0 +1,54 @@
+//! Builds session reports from planet logs.
+//!
+//! Usage: `icb-report [--csv DIR] LOG...`
+
+use immutable_cosmic_borrow::report::{self, ReportBuilder};
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    match run(std::env::args().skip(1)) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("icb-report: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(mut args: impl Iterator<Item = String>) -> Result<(), String> {
+    let mut csv = None;
+    let mut files = Vec::new();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--csv" => csv = Some(args.next().ok_or("Missing directory after --csv")?),
+            "-h" | "--help" => {
+                println!("Usage: icb-report [--csv DIR] LOG...");
+                println!("Reports the threat timeline, decisions, rockets and empty cell time");
+                println!("of every planet in the logs, as text and optionally as CSV tables.");
+                return Ok(());
+            }
+            _ => files.push(arg),
+        }
+    }
+    if files.is_empty() {
+        return Err("No log file, see --help".to_string());
+    }
+
+    let mut builder = ReportBuilder::new();
+    for file in &files {
+        builder.load(file)?;
+    }
+    let reports = builder.finish();
+    if reports.is_empty() {
+        return Err("No planet event found".to_string());
+    }
+    for report in &reports {
+        println!("{report}\n");
+    }
+
+    if let Some(dir) = csv {
+        report::write_csv(&reports, &dir)?;
+        println!("CSV tables written to {dir}");
+    }
+    Ok(())
+}
@@ -0,0 +1,979 @@
+//! JSON lines bridge between a planet and an out-of-process driver.
+//!
+//! A [`PlanetBridge`] runs a planet on its own thread and stands in for both the
+//! orchestrator and the explorers: each command line is turned into the matching
+//! `OrchestratorToPlanet` or `ExplorerToPlanet` message, and the planet response is returned
+//! as a JSON line. Every command gets exactly one response line.
+//!
+//! Commands are objects with a `type`:
+//! - orchestrator: `start`, `stop`, `kill`, `sunray`, `asteroid`, `internal_state`,
+//!   `explorer_arrival` and `explorer_departure` (with `explorer_id`)
+//! - explorer, with `explorer_id`: `supported_resource`, `supported_combination`,
+//!   `generate_resource` and `combine_resource` (with `resource`), `available_energy_cell`
+//!
+//! Responses are `start_result`, `stop_result`, `kill_result`, `stopped`, `sunray_ack`,
+//! `asteroid_ack` (with `rocket`), `internal_state`, `incoming_explorer_response`,
+//! `outgoing_explorer_response` (with `ok` and `error`) for the orchestrator, and
+//! `supported_resource_response`, `supported_combination_response`,
+//! `generate_resource_response`, `combine_resource_response`,
+//! `available_energy_cell_response` and `stopped` (with `explorer_id`) for the explorers.
+//! Invalid commands get an `error` response with a `message`.
+//!
+//! Resources can not cross the process boundary, so the bridge keeps the ones given to each
+//! explorer and takes the ingredients of a `combine_resource` from them.
+//!
+//! A command the planet does not answer within 2 seconds gets an `error` response. Its answer
+//! may still come later, it is then handled (the resources it carries go to the explorer) and
+//! dropped before the answer to the next command.
+//!
+//! The same commands can be sent over a local socket, see [`socket`].
+//!
+//! A bridge given the feed of its AI with [`PlanetBridge::with_feed`] also answers three
+//! commands that do not reach the planet: `stats`, with the current statistics of the AI,
+//! `decisions`, with the decisions taken since the previous `decisions` command (the
+//! last [`DECISION_BACKLOG`] at most), and `explain` (with `explorer_id`), with an
+//! `explanation` of the last decision taken for the explorer.
+
+pub mod socket;
+
+use crate::ai::{Ai, AiStats, DecisionExplanation, DecisionRecord, StatsHandle};
+use crate::json::Json;
+use crate::logging::{self, LogConfig};
+use crate::qlearning::{QLearningPolicy, QTable};
+use crate::replay::RecordedDecision;
+use crate::rules;
+use common_game::components::asteroid::Asteroid;
+use common_game::components::planet::{Planet, PlanetType};
+use common_game::components::resource::{
+    BasicResourceType, ComplexResourceRequest, ComplexResourceType, GenericResource, ResourceType,
+};
+use common_game::components::sunray::Sunray;
+use common_game::protocols::orchestrator_planet::{OrchestratorToPlanet, PlanetToOrchestrator};
+use common_game::protocols::planet_explorer::{ExplorerToPlanet, PlanetToExplorer};
+use common_game::utils::ID;
+use crossbeam_channel::{Receiver, RecvTimeoutError, SendError, Sender};
+use std::collections::{BTreeMap, VecDeque};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+const NO_FEED: &str = "The bridge has no feed of the planet AI";
+const DISCONNECTED: &str = "The planet disconnected";
+
+// Time to wait for the planet to answer a command
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Decisions kept for the next `decisions` command, older ones are dropped.
+pub const DECISION_BACKLOG: usize = 256;
+
+/// Parameters of the planet run by a bridge, mirroring [`create_planet`](crate::create_planet).
+#[derive(Debug, Clone)]
+pub struct PlanetOptions {
+    pub random_mode: bool,
+    pub basic_gen_coeff: f32,
+    pub complex_gen_coeff: f32,
+    pub half_life: Duration,
+    pub min_time_constant: Duration,
+    pub id: ID,
+    pub planet_type: PlanetType,
+    pub gen_rules: Vec<BasicResourceType>,
+    pub comb_rules: Vec<ComplexResourceType>,
+    pub log_config: LogConfig,
+    /// Learned table the requests are decided with, by a frozen [`QLearningPolicy`]
+    pub q_table: Option<QTable>,
+}
+
+/// Planet running on its own thread, driven through JSON lines, see the
+/// [module documentation](self).
+pub struct PlanetBridge {
+    planet_id: ID,
+    to_planet: Sender<OrchestratorToPlanet>,
+    from_planet: Receiver<PlanetToOrchestrator>,
+    explorers_to_planet: Sender<ExplorerToPlanet>,
+    explorers: BTreeMap<ID, Explorer>,
+    // Channels of the explorers whose arrival was not answered yet
+    arrivals: BTreeMap<ID, Receiver<PlanetToExplorer>>,
+    // Commands that timed out, whose answers come before the next ones
+    late: usize,
+    running: bool,
+    handle: Option<JoinHandle<Result<(), String>>>,
+    feed: Option<Feed>,
+}
+
+struct Feed {
+    stats: StatsHandle,
+    decisions: Receiver<DecisionRecord>,
+    backlog: VecDeque<RecordedDecision>,
+}
+
+struct Explorer {
+    from_planet: Receiver<PlanetToExplorer>,
+    // Resources received from the planet, used as combination ingredients
+    bag: ResourceBag,
+    late: usize,
+}
+
+/// Resources held by an explorer, the ingredients of its combination requests.
+#[derive(Default)]
+pub(crate) struct ResourceBag {
+    resources: Vec<GenericResource>,
+}
+
+#[derive(Clone, Copy)]
+enum Command {
+    Orchestrator(OrchestratorCommand),
+    Explorer(ID, ExplorerCommand),
+    Stats,
+    Decisions,
+    Explain(ID),
+}
+
+#[derive(Clone, Copy)]
+enum OrchestratorCommand {
+    Start,
+    Stop,
+    Kill,
+    Sunray,
+    Asteroid,
+    InternalState,
+    ExplorerArrival(ID),
+    ExplorerDeparture(ID),
+}
+
+#[derive(Clone, Copy)]
+enum ExplorerCommand {
+    SupportedResource,
+    SupportedCombination,
+    GenerateResource(BasicResourceType),
+    CombineResource(ComplexResourceType),
+    AvailableEnergyCell,
+}
+
+impl Default for PlanetOptions {
+    fn default() -> Self {
+        Self {
+            random_mode: false,
+            basic_gen_coeff: 0.5,
+            complex_gen_coeff: 0.5,
+            half_life: Duration::from_secs(10),
+            min_time_constant: Duration::from_secs(1),
+            id: 0,
+            planet_type: PlanetType::C,
+            gen_rules: rules::DEFAULT_GEN_RULES.to_vec(),
+            comb_rules: rules::DEFAULT_COMB_RULES.to_vec(),
+            log_config: LogConfig::new(),
+            q_table: None,
+        }
+    }
+}
+
+impl PlanetOptions {
+    pub const USAGE: &str = "\
+  --id ID                     planet id (0)
+  --type A|B|C|D              planet type (C)
+  --random                    random mode instead of adaptive
+  --basic-coeff COEFF         basic generation coefficient (0.5)
+  --complex-coeff COEFF       complex generation coefficient (0.5)
+  --half-life-ms MS           estimator half-life (10000)
+  --min-time-constant-ms MS   estimator minimum time constant (1000)
+  --gen RESOURCE,...          basic resources generated (Hydrogen)
+  --comb RESOURCE,...|none    complex resources combined (all for type C, Water for B,
+                              none for A and D)
+  --log-channel CHANNEL       least important channel logged: error, warning, info, debug
+                              or trace (trace)
+  --log-off ACTION,...        actions not logged: counter_updates, decisions, acks,
+                              defense, energy, configuration or failures (none)
+  --log-sample ACTION/N,...   log one event in N of an action (all)
+  --q-table FILE              decide with a Q-learning table trained by icb-qlearn";
+
+    /// Parses the options described in [`USAGE`](Self::USAGE), returning the arguments it
+    /// does not know.
+    ///
+    /// # Errors
+    /// Returns an error if an option value is missing or invalid.
+    pub fn parse_args(
+        &mut self,
+        args: impl IntoIterator<Item = String>,
+    ) -> Result<Vec<String>, String> {
+        let mut args = args.into_iter();
+        let mut rest = Vec::new();
+        let (mut type_given, mut comb_given) = (false, false);
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("Missing value after {arg}"));
+            match arg.as_str() {
+                "--id" => self.id = parse_value(&arg, &value()?)?,
+                "--type" => {
+                    let name = value()?;
+                    self.planet_type = rules::planet_type_from_name(&name)
+                        .ok_or(format!("Invalid planet type {name}"))?;
+                    type_given = true;
+                }
+                "--random" => self.random_mode = true,
+                "--basic-coeff" => self.basic_gen_coeff = parse_value(&arg, &value()?)?,
+                "--complex-coeff" => self.complex_gen_coeff = parse_value(&arg, &value()?)?,
+                "--half-life-ms" => {
+                    self.half_life = Duration::from_millis(parse_value(&arg, &value()?)?);
+                }
+                "--min-time-constant-ms" => {
+                    self.min_time_constant = Duration::from_millis(parse_value(&arg, &value()?)?);
+                }
+                "--gen" => {
+                    self.gen_rules = names(&value()?, rules::basic_from_name)?;
+                }
+                "--comb" => {
+                    self.comb_rules = names(&value()?, rules::complex_from_name)?;
+                    comb_given = true;
+                }
+                "--log-channel" => {
+                    let name = value()?;
+                    let channel = logging::channel_from_label(&name)
+                        .ok_or(format!("Invalid log channel {name}"))?;
+                    self.log_config = self.log_config.with_min_channel(&channel);
+                }
+                "--log-off" => self.log_config = self.log_config.with_disabled_labels(&value()?)?,
+                "--log-sample" => {
+                    self.log_config = self.log_config.with_sampling_labels(&value()?)?;
+                }
+                "--q-table" => self.q_table = Some(QTable::load(value()?)?),
+                _ => rest.push(arg),
+            }
+        }
+        if type_given && !comb_given {
+            self.comb_rules = rules::default_comb_rules(self.planet_type);
+        }
+        Ok(rest)
+    }
+
+    /// Creates the planet like [`create_planet`](crate::create_planet), logging as set by
+    /// `log_config`.
+    ///
+    /// # Errors
+    /// Returns an error if the rules are not valid for the planet type.
+    pub fn create_planet(
+        &self,
+        orchestrator_channels: (Receiver<OrchestratorToPlanet>, Sender<PlanetToOrchestrator>),
+        explorers_receiver: Receiver<ExplorerToPlanet>,
+    ) -> Result<Planet, String> {
+        self.create_planet_with_ai(self.create_ai(), orchestrator_channels, explorers_receiver)
+    }
+
+    /// Creates the AI [`create_planet`](Self::create_planet) would give the planet, to be
+    /// customized before [`create_planet_with_ai`](Self::create_planet_with_ai).
+    #[must_use]
+    pub fn create_ai(&self) -> Ai {
+        let ai = Ai::new(
+            self.random_mode,
+            self.basic_gen_coeff,
+            self.complex_gen_coeff,
+            self.half_life,
+            self.min_time_constant,
+        )
+        .with_log_config(self.log_config);
+        match &self.q_table {
+            Some(table) => ai.with_decision_policy(QLearningPolicy::frozen(table.clone())),
+            None => ai,
+        }
+    }
+
+    /// Creates the planet with the given AI.
+    ///
+    /// # Errors
+    /// Returns an error if the rules are not valid for the planet type.
+    pub fn create_planet_with_ai(
+        &self,
+        ai: Ai,
+        orchestrator_channels: (Receiver<OrchestratorToPlanet>, Sender<PlanetToOrchestrator>),
+        explorers_receiver: Receiver<ExplorerToPlanet>,
+    ) -> Result<Planet, String> {
+        rules::validate_rules(&self.gen_rules, &self.comb_rules)?;
+        Planet::new(
+            self.id,
+            self.planet_type,
+            Box::new(ai),
+            self.gen_rules.clone(),
+            self.comb_rules.clone(),
+            orchestrator_channels,
+            explorers_receiver,
+        )
+    }
+}
+
+impl PlanetBridge {
+    /// Creates the planet with `build`, given its channels, and runs it on a new thread. The
+    /// planet is stopped until a `start` command.
+    ///
+    /// # Errors
+    /// Returns the error of `build`.
+    pub fn spawn(
+        build: impl FnOnce(
+            (Receiver<OrchestratorToPlanet>, Sender<PlanetToOrchestrator>),
+            Receiver<ExplorerToPlanet>,
+        ) -> Result<Planet, String>,
+    ) -> Result<Self, String> {
+        let (to_planet, rx_orchestrator) = crossbeam_channel::bounded(1);
+        let (tx_orchestrator, from_planet) = crossbeam_channel::bounded(1);
+        let (explorers_to_planet, rx_explorers) = crossbeam_channel::unbounded();
+
+        let mut planet = build((rx_orchestrator, tx_orchestrator), rx_explorers)?;
+        let planet_id = planet.id();
+        let handle = thread::spawn(move || planet.run());
+
+        Ok(Self {
+            planet_id,
+            to_planet,
+            from_planet,
+            explorers_to_planet,
+            explorers: BTreeMap::new(),
+            arrivals: BTreeMap::new(),
+            late: 0,
+            running: false,
+            handle: Some(handle),
+            feed: None,
+        })
+    }
+
+    /// Answers the `stats` and `decisions` commands from the statistics and the decision
+    /// records of the planet AI, given to it with [`Ai::with_decision_sink`].
+    #[must_use]
+    pub fn with_feed(mut self, stats: StatsHandle, decisions: Receiver<DecisionRecord>) -> Self {
+        self.feed = Some(Feed {
+            stats,
+            decisions,
+            backlog: VecDeque::new(),
+        });
+        self
+    }
+
+    #[must_use]
+    pub fn planet_id(&self) -> ID {
+        self.planet_id
+    }
+
+    /// Whether the planet was killed, after which every command fails.
+    #[must_use]
+    pub fn is_killed(&self) -> bool {
+        self.handle.is_none()
+    }
+
+    /// Runs the command on a JSON line and returns the response line.
+    pub fn handle_line(&mut self, line: &str) -> String {
+        if let Some(feed) = &mut self.feed {
+            feed.collect();
+        }
+        let response = Json::parse(line.trim())
+            .and_then(|json| Command::from_json(&json))
+            .and_then(|command| self.run(command));
+        match response {
+            Ok(response) => response.to_string(),
+            Err(message) => {
+                Json::object([("type", Json::from("error")), ("message", message.into())])
+                    .to_string()
+            }
+        }
+    }
+
+    fn run(&mut self, command: Command) -> Result<Json, String> {
+        if self.is_killed() {
+            return Err("The planet was killed".to_string());
+        }
+        match command {
+            Command::Orchestrator(command) => self.run_orchestrator(command),
+            Command::Explorer(explorer_id, command) => self.run_explorer(explorer_id, command),
+            Command::Stats => {
+                let feed = self.feed.as_ref().ok_or(NO_FEED)?;
+                let mut json = stats_to_json(&feed.stats.snapshot());
+                if let Json::Object(fields) = &mut json {
+                    fields.insert("type".to_string(), "stats".into());
+                    fields.insert("planet_id".to_string(), self.planet_id.into());
+                }
+                Ok(json)
+            }
+            Command::Decisions => {
+                let feed = self.feed.as_mut().ok_or(NO_FEED)?;
+                let decisions: Vec<Json> = feed
+                    .backlog
+                    .drain(..)
+                    .map(|decision| decision.to_json())
+                    .collect();
+                Ok(Json::object([
+                    ("type", Json::from("decisions")),
+                    ("planet_id", self.planet_id.into()),
+                    ("decisions", decisions.into()),
+                ]))
+            }
+            Command::Explain(explorer_id) => {
+                let feed = self.feed.as_ref().ok_or(NO_FEED)?;
+                let explanation = feed
+                    .stats
+                    .explain_last_decision(explorer_id)
+                    .ok_or(format!("No decision taken for explorer {explorer_id}"))?;
+                let mut json = explanation_to_json(&explanation);
+                if let Json::Object(fields) = &mut json {
+                    fields.insert("planet_id".to_string(), self.planet_id.into());
+                }
+                Ok(json)
+            }
+        }
+    }
+
+    fn run_orchestrator(&mut self, command: OrchestratorCommand) -> Result<Json, String> {
+        // The planet does not answer a start while running
+        if matches!(command, OrchestratorCommand::Start) && self.running {
+            return Err("The planet is already running".to_string());
+        }
+
+        let msg = match command {
+            OrchestratorCommand::Start => OrchestratorToPlanet::StartPlanetAI,
+            OrchestratorCommand::Stop => OrchestratorToPlanet::StopPlanetAI,
+            OrchestratorCommand::Kill => OrchestratorToPlanet::KillPlanet,
+            OrchestratorCommand::Sunray => OrchestratorToPlanet::Sunray(Sunray::default()),
+            OrchestratorCommand::Asteroid => OrchestratorToPlanet::Asteroid(Asteroid::default()),
+            OrchestratorCommand::InternalState => OrchestratorToPlanet::InternalStateRequest,
+            OrchestratorCommand::ExplorerArrival(explorer_id) => {
+                let (new_sender, from_planet) = crossbeam_channel::unbounded();
+                self.arrivals.insert(explorer_id, from_planet);
+                OrchestratorToPlanet::IncomingExplorerRequest {
+                    explorer_id,
+                    new_sender,
+                }
+            }
+            OrchestratorCommand::ExplorerDeparture(explorer_id) => {
+                OrchestratorToPlanet::OutgoingExplorerRequest { explorer_id }
+            }
+        };
+
+        self.to_planet.send(msg).map_err(|_| DISCONNECTED)?;
+        let mut late = Vec::new();
+        let response = receive(&self.from_planet, &mut self.late, |response| {
+            late.push(response);
+        });
+        for response in late {
+            self.orchestrator_response(response);
+        }
+        Ok(self.orchestrator_response(response?))
+    }
+
+    fn orchestrator_response(&mut self, response: PlanetToOrchestrator) -> Json {
+        let planet_id = Json::from(self.planet_id);
+        let result = |kind: &str, explorer_id: ID, res: &Result<(), String>| {
+            Json::object([
+                ("type", Json::from(kind)),
+                ("planet_id", planet_id.clone()),
+                ("explorer_id", explorer_id.into()),
+                ("ok", res.is_ok().into()),
+                ("error", res.as_ref().err().cloned().into()),
+            ])
+        };
+        match response {
+            PlanetToOrchestrator::SunrayAck { .. } => self.ack("sunray_ack"),
+            PlanetToOrchestrator::AsteroidAck { rocket, .. } => Json::object([
+                ("type", Json::from("asteroid_ack")),
+                ("planet_id", planet_id.clone()),
+                ("rocket", rocket.is_some().into()),
+            ]),
+            PlanetToOrchestrator::StartPlanetAIResult { .. } => {
+                self.running = true;
+                self.ack("start_result")
+            }
+            PlanetToOrchestrator::StopPlanetAIResult { .. } => {
+                self.running = false;
+                self.ack("stop_result")
+            }
+            PlanetToOrchestrator::KillPlanetResult { .. } => {
+                self.running = false;
+                self.join();
+                self.ack("kill_result")
+            }
+            PlanetToOrchestrator::InternalStateResponse { planet_state, .. } => Json::object([
+                ("type", Json::from("internal_state")),
+                ("planet_id", planet_id.clone()),
+                ("energy_cells", planet_state.energy_cells.into()),
+                (
+                    "charged_cells_count",
+                    planet_state.charged_cells_count.into(),
+                ),
+                ("has_rocket", planet_state.has_rocket.into()),
+            ]),
+            PlanetToOrchestrator::IncomingExplorerResponse {
+                explorer_id, res, ..
+            } => {
+                let arriving = self.arrivals.remove(&explorer_id);
+                if let (Ok(()), Some(from_planet)) = (&res, arriving) {
+                    self.explorers.insert(
+                        explorer_id,
+                        Explorer {
+                            from_planet,
+                            bag: ResourceBag::default(),
+                            late: 0,
+                        },
+                    );
+                }
+                result("incoming_explorer_response", explorer_id, &res)
+            }
+            PlanetToOrchestrator::OutgoingExplorerResponse {
+                explorer_id, res, ..
+            } => {
+                if res.is_ok() {
+                    self.explorers.remove(&explorer_id);
+                }
+                result("outgoing_explorer_response", explorer_id, &res)
+            }
+            PlanetToOrchestrator::Stopped { .. } => self.ack("stopped"),
+        }
+    }
+
+    fn run_explorer(&mut self, explorer_id: ID, command: ExplorerCommand) -> Result<Json, String> {
+        let explorer = self
+            .explorers
+            .get_mut(&explorer_id)
+            .ok_or(format!("Explorer {explorer_id} is not on the planet"))?;
+        // Late answers that already came give back their resources before the bag is used
+        while explorer.late > 0
+            && let Ok(response) = explorer.from_planet.try_recv()
+        {
+            explorer.late -= 1;
+            explorer_response(&mut explorer.bag, explorer_id, response);
+        }
+
+        let msg = match command {
+            ExplorerCommand::SupportedResource => {
+                ExplorerToPlanet::SupportedResourceRequest { explorer_id }
+            }
+            ExplorerCommand::SupportedCombination => {
+                ExplorerToPlanet::SupportedCombinationRequest { explorer_id }
+            }
+            ExplorerCommand::GenerateResource(resource) => {
+                ExplorerToPlanet::GenerateResourceRequest {
+                    explorer_id,
+                    resource,
+                }
+            }
+            ExplorerCommand::CombineResource(resource) => {
+                ExplorerToPlanet::CombineResourceRequest {
+                    explorer_id,
+                    msg: explorer.bag.combination_request(resource)?,
+                }
+            }
+            ExplorerCommand::AvailableEnergyCell => {
+                ExplorerToPlanet::AvailableEnergyCellRequest { explorer_id }
+            }
+        };
+
+        if let Err(SendError(msg)) = self.explorers_to_planet.send(msg) {
+            if let ExplorerToPlanet::CombineResourceRequest { msg, .. } = msg {
+                explorer.bag.put_back(msg);
+            }
+            return Err(DISCONNECTED.to_string());
+        }
+        // The ingredients of a combination that timed out come back with its late answer
+        let bag = &mut explorer.bag;
+        let response = receive(&explorer.from_planet, &mut explorer.late, |response| {
+            explorer_response(bag, explorer_id, response);
+        })?;
+        Ok(explorer_response(bag, explorer_id, response))
+    }
+
+    fn ack(&self, kind: &str) -> Json {
+        Json::object([
+            ("type", Json::from(kind)),
+            ("planet_id", self.planet_id.into()),
+        ])
+    }
+
+    fn join(&mut self) {
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+// Turns the answer to an explorer into its response, keeping the resources it carries
+fn explorer_response(bag: &mut ResourceBag, explorer_id: ID, response: PlanetToExplorer) -> Json {
+    let mut fields = vec![("explorer_id", Json::from(explorer_id))];
+    let kind = match response {
+        PlanetToExplorer::SupportedResourceResponse { resource_list } => {
+            fields.push(("resources", sorted_names(resource_list)));
+            "supported_resource_response"
+        }
+        PlanetToExplorer::SupportedCombinationResponse { combination_list } => {
+            fields.push(("combinations", sorted_names(combination_list)));
+            "supported_combination_response"
+        }
+        PlanetToExplorer::GenerateResourceResponse { resource } => {
+            fields.push((
+                "resource",
+                resource
+                    .as_ref()
+                    .map(|resource| format!("{:?}", resource.get_type()))
+                    .into(),
+            ));
+            if let Some(resource) = resource {
+                bag.push(GenericResource::BasicResources(resource));
+            }
+            "generate_resource_response"
+        }
+        PlanetToExplorer::CombineResourceResponse { complex_response } => {
+            match complex_response {
+                Ok(resource) => {
+                    fields.push(("resource", format!("{:?}", resource.get_type()).into()));
+                    fields.push(("error", Json::Null));
+                    bag.push(GenericResource::ComplexResources(resource));
+                }
+                Err((error, first, second)) => {
+                    fields.push(("resource", Json::Null));
+                    fields.push(("error", error.into()));
+                    bag.push(first);
+                    bag.push(second);
+                }
+            }
+            "combine_resource_response"
+        }
+        PlanetToExplorer::AvailableEnergyCellResponse { available_cells } => {
+            fields.push(("available_cells", available_cells.into()));
+            "available_energy_cell_response"
+        }
+        PlanetToExplorer::Stopped => "stopped",
+    };
+    fields.insert(0, ("type", kind.into()));
+    Json::object(fields)
+}
+
+// Receives the answer to the last command sent, after the `late` answers to the commands that
+// timed out, which are given to `handle_late`
+fn receive<T>(
+    from_planet: &Receiver<T>,
+    late: &mut usize,
+    mut handle_late: impl FnMut(T),
+) -> Result<T, String> {
+    loop {
+        match from_planet.recv_timeout(RESPONSE_TIMEOUT) {
+            Ok(response) if *late > 0 => {
+                *late -= 1;
+                handle_late(response);
+            }
+            Ok(response) => return Ok(response),
+            Err(RecvTimeoutError::Timeout) => {
+                *late += 1;
+                return Err("The planet did not answer in time".to_string());
+            }
+            Err(RecvTimeoutError::Disconnected) => return Err(DISCONNECTED.to_string()),
+        }
+    }
+}
+
+impl Drop for PlanetBridge {
+    // Kills the planet so its thread does not outlive the bridge
+    fn drop(&mut self) {
+        if self.handle.is_some() {
+            let _ = self.to_planet.send(OrchestratorToPlanet::KillPlanet);
+            let _ = self.from_planet.recv_timeout(RESPONSE_TIMEOUT);
+            self.join();
+        }
+    }
+}
+
+impl Feed {
+    // Moves the pending records to the backlog, so the channel does not grow unbounded
+    fn collect(&mut self) {
+        for record in self.decisions.try_iter() {
+            if self.backlog.len() == DECISION_BACKLOG {
+                self.backlog.pop_front();
+            }
+            self.backlog.push_back(RecordedDecision::from(&record));
+        }
+    }
+}
+
+impl ResourceBag {
+    pub(crate) fn push(&mut self, resource: GenericResource) {
+        self.resources.push(resource);
+    }
+
+    // Puts back the ingredients of a combination request that could not be sent
+    fn put_back(&mut self, request: ComplexResourceRequest) {
+        let (first, second) = match request {
+            ComplexResourceRequest::Water(first, second) => {
+                (first.to_generic(), second.to_generic())
+            }
+            ComplexResourceRequest::Diamond(first, second) => {
+                (first.to_generic(), second.to_generic())
+            }
+            ComplexResourceRequest::Life(first, second) => {
+                (first.to_generic(), second.to_generic())
+            }
+            ComplexResourceRequest::Robot(first, second) => {
+                (first.to_generic(), second.to_generic())
+            }
+            ComplexResourceRequest::Dolphin(first, second) => {
+                (first.to_generic(), second.to_generic())
+            }
+            ComplexResourceRequest::AIPartner(first, second) => {
+                (first.to_generic(), second.to_generic())
+            }
+        };
+        self.push(first);
+        self.push(second);
+    }
+
+    fn take(&mut self, resource: ResourceType) -> Option<GenericResource> {
+        let index = self
+            .resources
+            .iter()
+            .position(|held| held.get_type() == resource)?;
+        Some(self.resources.swap_remove(index))
+    }
+
+    /// Takes the ingredients of `resource` from the bag, leaving it untouched if one is missing.
+    pub(crate) fn combination_request(
+        &mut self,
+        resource: ComplexResourceType,
+    ) -> Result<ComplexResourceRequest, String> {
+        use BasicResourceType::{Carbon, Hydrogen, Oxygen, Silicon};
+        use ComplexResourceType::{AIPartner, Diamond, Dolphin, Life, Robot, Water};
+        use ResourceType::{Basic, Complex};
+
+        let (first, second) = match resource {
+            Water => (Basic(Hydrogen), Basic(Oxygen)),
+            Diamond => (Basic(Carbon), Basic(Carbon)),
+            Life => (Complex(Water), Basic(Carbon)),
+            Robot => (Basic(Silicon), Complex(Life)),
+            Dolphin => (Complex(Water), Complex(Life)),
+            AIPartner => (Complex(Robot), Complex(Diamond)),
+        };
+        let Some(first) = self.take(first) else {
+            return Err(format!(
+                "Missing ingredient {} for {resource:?}",
+                resource_name(first)
+            ));
+        };
+        let Some(second) = self.take(second) else {
+            self.resources.push(first);
+            return Err(format!(
+                "Missing ingredient {} for {resource:?}",
+                resource_name(second)
+            ));
+        };
+
+        Ok(match resource {
+            Water => ComplexResourceRequest::Water(first.to_hydrogen()?, second.to_oxygen()?),
+            Diamond => ComplexResourceRequest::Diamond(first.to_carbon()?, second.to_carbon()?),
+            Life => ComplexResourceRequest::Life(first.to_water()?, second.to_carbon()?),
+            Robot => ComplexResourceRequest::Robot(first.to_silicon()?, second.to_life()?),
+            Dolphin => ComplexResourceRequest::Dolphin(first.to_water()?, second.to_life()?),
+            AIPartner => ComplexResourceRequest::AIPartner(first.to_robot()?, second.to_diamond()?),
+        })
+    }
+}
+
+impl Command {
+    fn from_json(json: &Json) -> Result<Self, String> {
+        let explorer_id = || -> Result<ID, String> {
+            let id = json.f64_field("explorer_id")?;
+            if id < 0.0 || id.fract() != 0.0 || id > f64::from(ID::MAX) {
+                return Err("Invalid field explorer_id".to_string());
+            }
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            Ok(id as ID)
+        };
+        let resource = || json.str_field("resource");
+
+        let orchestrator = |command| Ok(Command::Orchestrator(command));
+        let explorer = |command| Ok(Command::Explorer(explorer_id()?, command));
+        match json.str_field("type")? {
+            "start" => orchestrator(OrchestratorCommand::Start),
+            "stop" => orchestrator(OrchestratorCommand::Stop),
+            "kill" => orchestrator(OrchestratorCommand::Kill),
+            "sunray" => orchestrator(OrchestratorCommand::Sunray),
+            "asteroid" => orchestrator(OrchestratorCommand::Asteroid),
+            "internal_state" => orchestrator(OrchestratorCommand::InternalState),
+            "explorer_arrival" => {
+                orchestrator(OrchestratorCommand::ExplorerArrival(explorer_id()?))
+            }
+            "explorer_departure" => {
+                orchestrator(OrchestratorCommand::ExplorerDeparture(explorer_id()?))
+            }
+            "supported_resource" => explorer(ExplorerCommand::SupportedResource),
+            "supported_combination" => explorer(ExplorerCommand::SupportedCombination),
+            "generate_resource" => {
+                let name = resource()?;
+                explorer(ExplorerCommand::GenerateResource(
+                    rules::basic_from_name(name).ok_or(format!("Invalid basic resource {name}"))?,
+                ))
+            }
+            "combine_resource" => {
+                let name = resource()?;
+                explorer(ExplorerCommand::CombineResource(
+                    rules::complex_from_name(name)
+                        .ok_or(format!("Invalid complex resource {name}"))?,
+                ))
+            }
+            "available_energy_cell" => explorer(ExplorerCommand::AvailableEnergyCell),
+            "stats" => Ok(Command::Stats),
+            "decisions" => Ok(Command::Decisions),
+            "explain" => Ok(Command::Explain(explorer_id()?)),
+            other => Err(format!("Unknown command {other}")),
+        }
+    }
+}
+
+/// Fields of the `stats` response.
+pub(crate) fn stats_to_json(stats: &AiStats) -> Json {
+    let decisions = &stats.decisions;
+    let estimator = stats.estimator.as_ref();
+    Json::object([
+        (
+            "sunray_probability",
+            estimator
+                .map(|estimator| estimator.sunray_probability)
+                .into(),
+        ),
+        (
+            "sun_intensity",
+            estimator.map(|estimator| estimator.sun_intensity).into(),
+        ),
+        (
+            "asteroid_intensity",
+            estimator
+                .map(|estimator| estimator.asteroid_intensity)
+                .into(),
+        ),
+        ("energy_cells", stats.planet.energy_cells.into()),
+        ("charged_cells", stats.planet.charged_cells.into()),
+        ("has_rocket", stats.planet.has_rocket.into()),
+        ("explorers", stats.planet.explorers.clone().into()),
+        ("sunrays_received", stats.sunrays_received.into()),
+        ("asteroids_survived", stats.asteroids_survived.into()),
+        ("asteroids_lost", stats.asteroids_lost.into()),
+        ("rockets_built", stats.rockets_built.into()),
+        ("accepted", decisions.accepted.into()),
+        ("denied_by_policy", decisions.denied_by_policy.into()),
+        ("denied_no_charge", decisions.denied_no_charge.into()),
+        ("denied_reserved", decisions.denied_reserved.into()),
+    ])
+}
+
+/// Fields of the `explain` response.
+fn explanation_to_json(explanation: &DecisionExplanation) -> Json {
+    let record = &explanation.record;
+    let inputs = &record.inputs;
+    let estimator = explanation.estimator.as_ref();
+    let changes: Vec<String> = explanation
+        .changes()
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    Json::object([
+        ("type", Json::from("explanation")),
+        ("explorer_id", record.explorer_id.into()),
+        ("request", record.request.label().into()),
+        ("resource", record.request.resource().into()),
+        ("policy", record.policy.label().into()),
+        ("outcome", record.outcome.label().into()),
+        ("reason", record.reason.label().into()),
+        ("threshold", inputs.threshold.into()),
+        ("has_rocket", inputs.has_rocket.into()),
+        ("charged_cells", inputs.charged_cells.into()),
+        ("reserved", inputs.reserved.into()),
+        ("random_sample", inputs.random_sample.into()),
+        ("p_sunray", inputs.p_sunray.into()),
+        ("p_asteroid", inputs.p_asteroid.into()),
+        ("p_asteroid_squared", inputs.p_asteroid_squared.into()),
+        ("rocket_squared", explanation.rocket_squared().into()),
+        (
+            "sun_intensity",
+            estimator.map(|estimator| estimator.sun_intensity).into(),
+        ),
+        (
+            "asteroid_intensity",
+            estimator
+                .map(|estimator| estimator.asteroid_intensity)
+                .into(),
+        ),
+        ("changes", changes.into()),
+        ("text", explanation.to_string().into()),
+    ])
+}
+
+/// Reads the fields of a `stats` response back, the other statistics are left to their
+/// default.
+pub(crate) fn stats_from_json(json: &Json) -> Result<AiStats, String> {
+    let count = |key: &str| -> Result<u64, String> {
+        let value = json.f64_field(key)?;
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        Ok(value.max(0.0) as u64)
+    };
+    #[allow(clippy::cast_possible_truncation)]
+    let probability = |key: &str| json.get(key).and_then(Json::as_f64).map(|p| p as f32);
+    let explorers = json
+        .get("explorers")
+        .and_then(Json::as_array)
+        .ok_or("Missing field explorers")?
+        .iter()
+        .filter_map(Json::as_f64)
+        .map(|id| {
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            let id = id as ID;
+            id
+        })
+        .collect();
+
+    let mut stats = AiStats::default();
+    if let Some(sunray_probability) = probability("sunray_probability") {
+        stats.estimator = Some(crate::ai::EstimatorStats {
+            sun_intensity: probability("sun_intensity").unwrap_or_default(),
+            asteroid_intensity: probability("asteroid_intensity").unwrap_or_default(),
+            sunray_probability,
+            time_constant_secs: 0.0,
+        });
+    }
+    #[allow(clippy::cast_possible_truncation)]
+    {
+        stats.planet.energy_cells = count("energy_cells")? as usize;
+        stats.planet.charged_cells = count("charged_cells")? as usize;
+    }
+    stats.planet.has_rocket = json
+        .get("has_rocket")
+        .and_then(Json::as_bool)
+        .ok_or("Missing field has_rocket")?;
+    stats.planet.explorers = explorers;
+    stats.sunrays_received = count("sunrays_received")?;
+    stats.asteroids_survived = count("asteroids_survived")?;
+    stats.asteroids_lost = count("asteroids_lost")?;
+    stats.rockets_built = count("rockets_built")?;
+    stats.decisions.accepted = count("accepted")?;
+    stats.decisions.denied_by_policy = count("denied_by_policy")?;
+    stats.decisions.denied_no_charge = count("denied_no_charge")?;
+    stats.decisions.denied_reserved = count("denied_reserved")?;
+    Ok(stats)
+}
+
+fn resource_name(resource: ResourceType) -> String {
+    match resource {
+        ResourceType::Basic(basic) => format!("{basic:?}"),
+        ResourceType::Complex(complex) => format!("{complex:?}"),
+    }
+}
+
+fn sorted_names<T: std::fmt::Debug>(values: impl IntoIterator<Item = T>) -> Json {
+    let mut names: Vec<String> = values
+        .into_iter()
+        .map(|value| format!("{value:?}"))
+        .collect();
+    names.sort();
+    Json::from(names)
+}
+
+fn parse_value<T: std::str::FromStr>(option: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Invalid value {value} for {option}"))
+}
+
+// Parses a comma separated list, empty or `none` for no value
+fn names<T>(list: &str, from_name: fn(&str) -> Option<T>) -> Result<Vec<T>, String> {
+    if matches!(list.trim(), "" | "none") {
+        return Ok(Vec::new());
+    }
+    list.split(',')
+        .map(|name| from_name(name.trim()).ok_or(format!("Unknown resource {name}")))
+        .collect()
+}
@@ -0,0 +1,282 @@
+//! Serving a [`PlanetBridge`] on a local socket.
+//!
+//! A [`BridgeServer`] accepts any number of connections on a localhost TCP port or, on Unix,
+//! a Unix domain socket. Each connection can act as the orchestrator, as one or more
+//! explorers, or both: explorer commands carry their `explorer_id`, so several explorers
+//! can share a connection or use one each. Commands from all connections are run one at a
+//! time, in the order they are received.
+//!
+//! Each frame is a 4-byte big-endian payload length followed by the UTF-8 payload, a JSON
+//! command or response in the [bridge format](super). Every command frame gets exactly one
+//! response frame on the same connection, in order, so commands can be pipelined. Frames
+//! larger than [`MAX_FRAME_LEN`] close the connection.
+
+use super::PlanetBridge;
+use crossbeam_channel::{Receiver, Sender};
+use std::io::{self, Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread::{self, JoinHandle};
+
+pub const MAX_FRAME_LEN: usize = 1 << 20;
+
+/// Where a [`BridgeServer`] listens.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BridgeAddress {
+    Tcp(SocketAddr),
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+/// Local socket server running the commands of its clients on a planet, see the
+/// [module documentation](self).
+pub struct BridgeServer {
+    address: BridgeAddress,
+    jobs: Sender<Job>,
+    stopping: Arc<AtomicBool>,
+    accept: Option<JoinHandle<()>>,
+    worker: Option<JoinHandle<()>>,
+    killed: Receiver<()>,
+}
+
+/// Client side of a connection to a [`BridgeServer`].
+pub struct BridgeClient {
+    stream: Box<dyn Stream>,
+}
+
+enum Job {
+    Command(String, Arc<Mutex<Box<dyn Stream>>>),
+    Shutdown,
+}
+
+trait Stream: Read + Write + Send {
+    fn try_clone_stream(&self) -> io::Result<Box<dyn Stream>>;
+}
+
+impl Stream for TcpStream {
+    fn try_clone_stream(&self) -> io::Result<Box<dyn Stream>> {
+        Ok(Box::new(self.try_clone()?))
+    }
+}
+
+#[cfg(unix)]
+impl Stream for UnixStream {
+    fn try_clone_stream(&self) -> io::Result<Box<dyn Stream>> {
+        Ok(Box::new(self.try_clone()?))
+    }
+}
+
+/// Writes one frame.
+///
+/// # Errors
+/// Returns an error if the payload is larger than [`MAX_FRAME_LEN`] or can not be written.
+pub fn write_frame(writer: &mut impl Write, payload: &str) -> io::Result<()> {
+    if payload.len() > MAX_FRAME_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Frame too large",
+        ));
+    }
+    #[allow(clippy::cast_possible_truncation)]
+    let len = (payload.len() as u32).to_be_bytes();
+    writer.write_all(&len)?;
+    writer.write_all(payload.as_bytes())?;
+    writer.flush()
+}
+
+/// Reads one frame, `None` if the connection was closed between frames.
+///
+/// # Errors
+/// Returns an error if the frame is too large, not UTF-8 or truncated.
+pub fn read_frame(reader: &mut impl Read) -> io::Result<Option<String>> {
+    let mut len = [0; 4];
+    match reader.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(err) => return Err(err),
+    }
+    let len = u32::from_be_bytes(len) as usize;
+    if len > MAX_FRAME_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Frame too large",
+        ));
+    }
+    let mut payload = vec![0; len];
+    reader.read_exact(&mut payload)?;
+    String::from_utf8(payload)
+        .map(Some)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Frame is not UTF-8"))
+}
+
+impl BridgeServer {
+    /// Serves `bridge` on `port` of the loopback interface, any free port if it is 0.
+    ///
+    /// # Errors
+    /// Returns an error if the port can not be bound.
+    pub fn bind_tcp(bridge: PlanetBridge, port: u16) -> Result<Self, String> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))
+            .map_err(|err| format!("Can not listen on port {port}: {err}"))?;
+        let address = listener.local_addr().map_err(|err| err.to_string())?;
+        Ok(Self::serve(
+            bridge,
+            BridgeAddress::Tcp(address),
+            move || listener.accept().map(|(stream, _)| Box::new(stream) as _),
+        ))
+    }
+
+    /// Serves `bridge` on a Unix domain socket at `path`, which must not exist.
+    ///
+    /// # Errors
+    /// Returns an error if the socket can not be created.
+    #[cfg(unix)]
+    pub fn bind_unix(bridge: PlanetBridge, path: impl Into<PathBuf>) -> Result<Self, String> {
+        let path = path.into();
+        let listener = UnixListener::bind(&path)
+            .map_err(|err| format!("Can not listen on {}: {err}", path.display()))?;
+        Ok(Self::serve(bridge, BridgeAddress::Unix(path), move || {
+            listener.accept().map(|(stream, _)| Box::new(stream) as _)
+        }))
+    }
+
+    fn serve(
+        bridge: PlanetBridge,
+        address: BridgeAddress,
+        mut accept: impl FnMut() -> io::Result<Box<dyn Stream>> + Send + 'static,
+    ) -> Self {
+        let (jobs, rx_jobs) = crossbeam_channel::unbounded();
+        let stopping = Arc::new(AtomicBool::new(false));
+
+        let (tx_killed, killed) = crossbeam_channel::bounded(1);
+        let worker = thread::spawn(move || run_jobs(bridge, &rx_jobs, &tx_killed));
+        let accept = {
+            let jobs = jobs.clone();
+            let stopping = Arc::clone(&stopping);
+            thread::spawn(move || {
+                while let Ok(stream) = accept() {
+                    if stopping.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    let jobs = jobs.clone();
+                    thread::spawn(move || serve_connection(stream, &jobs));
+                }
+            })
+        };
+
+        Self {
+            address,
+            jobs,
+            stopping,
+            accept: Some(accept),
+            worker: Some(worker),
+            killed,
+        }
+    }
+
+    #[must_use]
+    pub fn address(&self) -> &BridgeAddress {
+        &self.address
+    }
+
+    /// Blocks until a client kills the planet, then stops the server once every command
+    /// received so far is answered.
+    pub fn wait(self) {
+        let _ = self.killed.recv();
+    }
+
+    /// Kills the planet if it is still running and stops the server.
+    pub fn shutdown(self) {
+        drop(self);
+    }
+}
+
+impl Drop for BridgeServer {
+    fn drop(&mut self) {
+        let _ = self.jobs.send(Job::Shutdown);
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+
+        // Wake the accept loop up with a connection of our own
+        self.stopping.store(true, Ordering::SeqCst);
+        let _ = BridgeClient::connect(&self.address);
+        if let Some(accept) = self.accept.take() {
+            let _ = accept.join();
+        }
+        #[cfg(unix)]
+        if let BridgeAddress::Unix(path) = &self.address {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+// Runs the commands of every connection on the bridge until shutdown. The response is
+// written here so it is sent before the server goes away
+fn run_jobs(mut bridge: PlanetBridge, jobs: &Receiver<Job>, killed: &Sender<()>) {
+    for job in jobs {
+        let Job::Command(line, writer) = job else {
+            break;
+        };
+        let response = bridge.handle_line(&line);
+        let mut writer = writer.lock().unwrap_or_else(PoisonError::into_inner);
+        let _ = write_frame(&mut *writer, &response);
+        if bridge.is_killed() {
+            let _ = killed.try_send(());
+        }
+    }
+}
+
+fn serve_connection(stream: Box<dyn Stream>, jobs: &Sender<Job>) {
+    let Ok(writer) = stream.try_clone_stream() else {
+        return;
+    };
+    let writer = Arc::new(Mutex::new(writer));
+    let mut reader = stream;
+
+    while let Ok(Some(line)) = read_frame(&mut reader) {
+        if jobs.send(Job::Command(line, Arc::clone(&writer))).is_err() {
+            let mut writer = writer.lock().unwrap_or_else(PoisonError::into_inner);
+            let _ = write_frame(
+                &mut *writer,
+                r#"{"message":"The bridge was shut down","type":"error"}"#,
+            );
+            break;
+        }
+    }
+}
+
+impl BridgeClient {
+    /// Connects to the server listening on `address`.
+    ///
+    /// # Errors
+    /// Returns an error if the connection fails.
+    pub fn connect(address: &BridgeAddress) -> Result<Self, String> {
+        let stream: Box<dyn Stream> = match address {
+            BridgeAddress::Tcp(address) => Box::new(
+                TcpStream::connect(address)
+                    .map_err(|err| format!("Can not connect to {address}: {err}"))?,
+            ),
+            #[cfg(unix)]
+            BridgeAddress::Unix(path) => Box::new(
+                UnixStream::connect(path)
+                    .map_err(|err| format!("Can not connect to {}: {err}", path.display()))?,
+            ),
+        };
+        Ok(Self { stream })
+    }
+
+    /// Sends a command and waits for its response.
+    ///
+    /// # Errors
+    /// Returns an error if the connection fails or is closed by the server.
+    pub fn request(&mut self, command: &str) -> Result<String, String> {
+        write_frame(&mut self.stream, command).map_err(|err| err.to_string())?;
+        read_frame(&mut self.stream)
+            .map_err(|err| err.to_string())?
+            .ok_or_else(|| "Connection closed by the bridge".to_string())
+    }
+}
@@ -0,0 +1,68 @@
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// Source of the current time for the AI estimator and energy reservations.
+pub trait Clock: Send + Sync + Debug {
+    fn now(&self) -> Instant;
+}
+
+/// The real monotonic clock, used by default.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// Clock that only moves when told to, for tests and replays.
+///
+/// Clones share the same time, so a test can keep a clone after handing the clock to an
+/// [`Ai`](crate::Ai).
+#[derive(Debug, Clone)]
+pub struct MockClock {
+    start: Instant,
+    elapsed: Arc<Mutex<Duration>>,
+}
+
+impl MockClock {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            elapsed: Arc::new(Mutex::new(Duration::ZERO)),
+        }
+    }
+
+    /// Moves the clock forward by `duration`.
+    pub fn advance(&self, duration: Duration) {
+        *self.elapsed.lock().unwrap_or_else(PoisonError::into_inner) += duration;
+    }
+
+    /// Sets the time elapsed since the clock was created. The clock never goes back,
+    /// earlier values are ignored.
+    pub fn set_elapsed(&self, elapsed: Duration) {
+        let mut current = self.elapsed.lock().unwrap_or_else(PoisonError::into_inner);
+        *current = (*current).max(elapsed);
+    }
+
+    /// Time elapsed since the clock was created.
+    #[must_use]
+    pub fn elapsed(&self) -> Duration {
+        *self.elapsed.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        self.start + self.elapsed()
+    }
+}
@@ -0,0 +1,295 @@
+//! Live terminal view of a planet.
+//!
+//! A [`Dashboard`] keeps the latest statistics of a planet AI, the history of its sunray
+//! probability and a log of its last decisions, and renders them as text. It is fed by a
+//! [`DashboardFeed`]: a [`LocalFeed`] reads the [`StatsHandle`] and decision records of a
+//! planet running in the same process, a [`BridgeFeed`] polls a planet served on a local
+//! socket (see [`bridge::socket`](crate::bridge::socket)).
+
+use crate::ai::{AiStats, DecisionRecord, StatsHandle};
+use crate::bridge::socket::{BridgeAddress, BridgeClient};
+use crate::json::Json;
+use crate::replay::RecordedDecision;
+use common_game::utils::ID;
+use crossbeam_channel::Receiver;
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+
+/// Probability samples kept for the history line.
+pub const HISTORY_LEN: usize = 240;
+/// Decisions kept for the log.
+pub const LOG_LEN: usize = 200;
+
+const SPARKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// How likely the planet is to be destroyed by the coming asteroids.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ThreatLevel {
+    Low,
+    Elevated,
+    High,
+    Critical,
+}
+
+/// Latest state of a planet, see the [module documentation](self).
+#[derive(Debug, Clone, Default)]
+pub struct Dashboard {
+    planet_id: Option<ID>,
+    stats: AiStats,
+    // Sunray probability at every update, `None` without an estimator
+    history: VecDeque<Option<f32>>,
+    log: VecDeque<RecordedDecision>,
+}
+
+/// Source of the updates of a [`Dashboard`].
+pub trait DashboardFeed {
+    /// Moves the latest statistics and the new decisions to the dashboard.
+    ///
+    /// # Errors
+    /// Returns an error if the planet can no longer be observed.
+    fn poll(&mut self, dashboard: &mut Dashboard) -> Result<(), String>;
+}
+
+/// Feed of a planet AI running in the same process.
+pub struct LocalFeed {
+    stats: StatsHandle,
+    decisions: Receiver<DecisionRecord>,
+}
+
+/// Feed of a planet served on a local socket, whose bridge has a feed of its AI.
+pub struct BridgeFeed {
+    client: BridgeClient,
+}
+
+impl ThreatLevel {
+    /// Level of `risk`, the probability of losing the planet: one asteroid without a rocket,
+    /// two in a row with one, as weighed by the adaptive policy.
+    #[must_use]
+    pub fn from_risk(risk: f32) -> Self {
+        match risk {
+            r if r < 0.1 => ThreatLevel::Low,
+            r if r < 0.3 => ThreatLevel::Elevated,
+            r if r < 0.6 => ThreatLevel::High,
+            _ => ThreatLevel::Critical,
+        }
+    }
+
+    #[must_use]
+    pub fn label(self) -> &'static str {
+        match self {
+            ThreatLevel::Low => "low",
+            ThreatLevel::Elevated => "elevated",
+            ThreatLevel::High => "high",
+            ThreatLevel::Critical => "critical",
+        }
+    }
+}
+
+impl Dashboard {
+    #[must_use]
+    pub fn new(planet_id: Option<ID>) -> Self {
+        Self {
+            planet_id,
+            ..Self::default()
+        }
+    }
+
+    /// Replaces the statistics and adds their sunray probability to the history.
+    pub fn update(&mut self, stats: AiStats) {
+        if self.history.len() == HISTORY_LEN {
+            self.history.pop_front();
+        }
+        self.history.push_back(
+            stats
+                .estimator
+                .as_ref()
+                .map(|estimator| estimator.sunray_probability),
+        );
+        self.stats = stats;
+    }
+
+    pub fn push_decision(&mut self, decision: RecordedDecision) {
+        if self.log.len() == LOG_LEN {
+            self.log.pop_front();
+        }
+        self.log.push_back(decision);
+    }
+
+    #[must_use]
+    pub fn stats(&self) -> &AiStats {
+        &self.stats
+    }
+
+    /// Logged decisions, oldest first.
+    pub fn decisions(&self) -> impl Iterator<Item = &RecordedDecision> {
+        self.log.iter()
+    }
+
+    /// Risk of losing the planet and its level, `None` without an estimator.
+    #[must_use]
+    pub fn threat(&self) -> Option<(f32, ThreatLevel)> {
+        let p_asteroid = 1.0 - self.stats.estimator.as_ref()?.sunray_probability;
+        let risk = if self.stats.planet.has_rocket {
+            p_asteroid * p_asteroid
+        } else {
+            p_asteroid
+        };
+        Some((risk, ThreatLevel::from_risk(risk)))
+    }
+
+    /// Renders the dashboard in `height` lines of at most `width` characters, the decision
+    /// log taking the lines left.
+    #[must_use]
+    pub fn render(&self, width: usize, height: usize) -> String {
+        let stats = &self.stats;
+        let planet = &stats.planet;
+        let mut lines = Vec::new();
+
+        lines.push(match self.planet_id {
+            Some(id) => format!("ICB planet {id}"),
+            None => "ICB planet".to_string(),
+        });
+        lines.push(match &stats.estimator {
+            Some(estimator) => format!(
+                "Sunray probability {:.3}  (sun {:.3}/s, asteroids {:.3}/s)",
+                estimator.sunray_probability, estimator.sun_intensity, estimator.asteroid_intensity
+            ),
+            None => "Sunray probability -  (no estimator)".to_string(),
+        });
+        let span = width.min(self.history.len());
+        lines.push(
+            self.history
+                .iter()
+                .skip(self.history.len() - span)
+                .map(|sample| sample.map_or(' ', spark))
+                .collect(),
+        );
+        lines.push(match self.threat() {
+            Some((risk, level)) => format!("Threat {} (risk {risk:.3})", level.label()),
+            None => "Threat -".to_string(),
+        });
+
+        let cells: String = (0..planet.energy_cells)
+            .map(|i| if i < planet.charged_cells { '#' } else { '-' })
+            .collect();
+        lines.push(format!(
+            "Cells [{cells}] {}/{} charged  Rocket {}",
+            planet.charged_cells,
+            planet.energy_cells,
+            if planet.has_rocket { "ready" } else { "none" }
+        ));
+        let explorers = if planet.explorers.is_empty() {
+            "none".to_string()
+        } else {
+            let ids: Vec<String> = planet.explorers.iter().map(ToString::to_string).collect();
+            ids.join(", ")
+        };
+        lines.push(format!("Explorers {explorers}"));
+
+        let decisions = &stats.decisions;
+        let mut totals = format!(
+            "Accepted {}  Denied {}",
+            decisions.accepted,
+            decisions.denied_by_policy + decisions.denied_no_charge + decisions.denied_reserved
+        );
+        let _ = write!(
+            totals,
+            "  Sunrays {}  Asteroids {} survived, {} lost  Rockets {}",
+            stats.sunrays_received,
+            stats.asteroids_survived,
+            stats.asteroids_lost,
+            stats.rockets_built
+        );
+        lines.push(totals);
+        lines.push("Decisions".to_string());
+
+        // Newest decisions at the bottom
+        let room = height.saturating_sub(lines.len());
+        let shown = room.min(self.log.len());
+        lines.extend(
+            self.log
+                .iter()
+                .skip(self.log.len() - shown)
+                .map(ToString::to_string),
+        );
+
+        let mut frame = String::new();
+        for line in lines.iter().take(height) {
+            frame.extend(line.chars().take(width));
+            frame.push('\n');
+        }
+        frame
+    }
+}
+
+impl LocalFeed {
+    /// Reads the AI statistics from `stats` and its decisions from `decisions`, a channel
+    /// given to the AI with [`Ai::with_decision_sink`](crate::Ai::with_decision_sink).
+    #[must_use]
+    pub fn new(stats: StatsHandle, decisions: Receiver<DecisionRecord>) -> Self {
+        Self { stats, decisions }
+    }
+}
+
+impl DashboardFeed for LocalFeed {
+    fn poll(&mut self, dashboard: &mut Dashboard) -> Result<(), String> {
+        for record in self.decisions.try_iter() {
+            dashboard.push_decision(RecordedDecision::from(&record));
+        }
+        dashboard.update(self.stats.snapshot());
+        Ok(())
+    }
+}
+
+impl BridgeFeed {
+    /// Connects to the bridge server at `address`.
+    ///
+    /// # Errors
+    /// Returns an error if the connection fails.
+    pub fn connect(address: &BridgeAddress) -> Result<Self, String> {
+        Ok(Self {
+            client: BridgeClient::connect(address)?,
+        })
+    }
+
+    fn request(&mut self, kind: &str) -> Result<Json, String> {
+        let command = Json::object([("type", Json::from(kind))]).to_string();
+        let response = Json::parse(&self.client.request(&command)?)?;
+        if response.str_field("type")? == "error" {
+            return Err(response.str_field("message")?.to_string());
+        }
+        Ok(response)
+    }
+}
+
+impl DashboardFeed for BridgeFeed {
+    fn poll(&mut self, dashboard: &mut Dashboard) -> Result<(), String> {
+        let decisions = self.request("decisions")?;
+        for decision in decisions
+            .get("decisions")
+            .and_then(Json::as_array)
+            .ok_or("Missing field decisions")?
+        {
+            dashboard.push_decision(RecordedDecision::from_json(decision)?);
+        }
+
+        let stats = self.request("stats")?;
+        if dashboard.planet_id.is_none() {
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            let id = stats.f64_field("planet_id")? as ID;
+            dashboard.planet_id = Some(id);
+        }
+        dashboard.update(crate::bridge::stats_from_json(&stats)?);
+        Ok(())
+    }
+}
+
+fn spark(probability: f32) -> char {
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::cast_precision_loss
+    )]
+    let index = (probability.clamp(0.0, 1.0) * (SPARKS.len() - 1) as f32).round() as usize;
+    SPARKS[index]
+}
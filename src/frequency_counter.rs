@@ -1,6 +1,50 @@
-use common_game::logging::{Channel, EventType, LogEvent, Payload};
+use crate::clock::{Clock, SystemClock};
+use crate::logging::{LogAction, LogConfig, Logger};
+use common_game::logging::{Channel, EventType, Payload};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
+/// Values learned by a [`FrequencyCounter`], saved across restarts of a planet.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct LearnedState {
+    pub sun_intensity: f32,
+    pub asteroid_intensity: f32,
+    pub sunray_probability: f32,
+    pub since_last_update: Option<Duration>,
+}
+
+/// What the threat estimator does with the time a planet spends stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PauseMode {
+    /// The time stopped does not count, the estimate resumes as it was left
+    #[default]
+    Freeze,
+    /// The time stopped counts like any other, the learned intensities fade but not the odds, so
+    /// the next event moves the estimate more
+    Forget,
+}
+
+impl PauseMode {
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            PauseMode::Freeze => "freeze",
+            PauseMode::Forget => "forget",
+        }
+    }
+
+    pub(crate) fn from_label(label: &str) -> Option<Self> {
+        [PauseMode::Freeze, PauseMode::Forget]
+            .into_iter()
+            .find(|pause_mode| pause_mode.label() == label)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CounterState {
+    Running,
+    Paused { since: Instant },
+}
+
 pub(crate) struct FrequencyCounter {
     // Half-life for exponential decay
     half_life: Duration,
@@ -19,9 +63,11 @@ pub(crate) struct FrequencyCounter {
     // Minimum time constant
     min_time_constant: Duration,
 
-    // Stop time tracking
-    stop_time: Option<Instant>,
-    restart_time: Option<Instant>,
+    state: CounterState,
+    pause_mode: PauseMode,
+
+    clock: Arc<dyn Clock>,
+    log: Logger,
 }
 
 impl FrequencyCounter {
@@ -37,11 +83,31 @@ impl FrequencyCounter {
             last_update: None,
             sunray_probability: 0.5,
             min_time_constant,
-            stop_time: None,
-            restart_time: None,
+            state: CounterState::Running,
+            pause_mode: PauseMode::default(),
+            clock: Arc::new(SystemClock),
+            log: Logger::new(LogConfig::new()),
         }
     }
 
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    pub fn set_log_config(&mut self, log: LogConfig) {
+        self.log.set_config(log);
+    }
+
+    /// Changes the half-life, the intensities learned so far are kept.
+    pub fn set_half_life(&mut self, half_life: Duration) {
+        self.half_life = half_life;
+        self.impulse = 1.0 / self.current_tau();
+    }
+
+    pub fn set_min_time_constant(&mut self, min_time_constant: Duration) {
+        self.min_time_constant = min_time_constant;
+    }
+
     pub fn update_sunray(&mut self) {
         self.update_event(true);
     }
@@ -68,9 +134,9 @@ impl FrequencyCounter {
     }
 
     fn update_no_event(&mut self, force_decay: bool) {
-        let now = Instant::now();
+        let now = self.now();
         if let Some(last) = self.last_update {
-            let elapsed = now.duration_since(last);
+            let elapsed = now.saturating_duration_since(last);
 
             if !force_decay && elapsed < self.min_time_constant {
                 return;
@@ -98,54 +164,247 @@ impl FrequencyCounter {
         self.sunray_probability = if s > 0.0 { self.sun_intensity / s } else { 0.5 };
     }
 
+    /// Resumes the counter after [`stop`](Self::stop). Does nothing if it is running.
     pub fn restart(&mut self) {
-        self.restart_time = Some(Instant::now());
+        let CounterState::Paused { since } = self.state else {
+            return;
+        };
+        let now = self.clock.now();
 
-        // Adjust last_update to account for the time spent stopped
-        if let (Some(stop), Some(restart), Some(last)) =
-            (self.stop_time, self.restart_time, self.last_update)
+        // Shift the last update by the time spent paused, so it does not decay the intensities
+        if self.pause_mode == PauseMode::Freeze
+            && let Some(last) = self.last_update
         {
-            let stopped_duration = restart.duration_since(stop);
-            self.last_update = Some(last + stopped_duration);
+            self.last_update = Some(last + now.saturating_duration_since(since));
         }
+        self.state = CounterState::Running;
 
         self.log_counter("restart");
     }
 
+    /// Pauses the counter. Does nothing if it is already paused.
     pub fn stop(&mut self) {
-        self.stop_time = Some(Instant::now());
+        if self.is_paused() {
+            return;
+        }
+        self.state = CounterState::Paused {
+            since: self.clock.now(),
+        };
 
         self.log_counter("stop");
     }
 
+    pub fn is_paused(&self) -> bool {
+        matches!(self.state, CounterState::Paused { .. })
+    }
+
+    pub fn set_pause_mode(&mut self, pause_mode: PauseMode) {
+        self.pause_mode = pause_mode;
+    }
+
+    pub fn pause_mode(&self) -> PauseMode {
+        self.pause_mode
+    }
+
+    // Current time for the decay. While frozen, time stands still at the pause.
+    fn now(&self) -> Instant {
+        match self.state {
+            CounterState::Paused { since } if self.pause_mode == PauseMode::Freeze => since,
+            _ => self.clock.now(),
+        }
+    }
+
+    /// Learned values, with the time since the last update. Time spent frozen is not counted.
+    pub fn learned_state(&self) -> LearnedState {
+        let now = self.now();
+        LearnedState {
+            sun_intensity: self.sun_intensity,
+            asteroid_intensity: self.asteroid_intensity,
+            sunray_probability: self.sunray_probability,
+            since_last_update: self
+                .last_update
+                .map(|last| now.saturating_duration_since(last)),
+        }
+    }
+
+    /// Restores learned values. The counter is paused until the next restart, so with
+    /// [`PauseMode::Freeze`] the time before the planet starts does not decay them.
+    pub fn restore(&mut self, state: &LearnedState) {
+        let now = self.clock.now();
+        self.sun_intensity = state.sun_intensity;
+        self.asteroid_intensity = state.asteroid_intensity;
+        self.sunray_probability = state.sunray_probability;
+        self.last_update = state
+            .since_last_update
+            .and_then(|since| now.checked_sub(since));
+        self.state = CounterState::Paused { since: now };
+
+        self.log_counter("restore");
+    }
+
+    /// Replaces the initial intensities, before any event is counted.
+    pub fn set_prior(&mut self, sun_intensity: f32, asteroid_intensity: f32) {
+        self.sun_intensity = sun_intensity;
+        self.asteroid_intensity = asteroid_intensity;
+        self.update_probability();
+
+        self.log_counter("prior");
+    }
+
     pub fn sunray_probability(&mut self) -> f32 {
         self.update_no_event(false);
         self.sunray_probability
     }
 
-    #[allow(dead_code)]
+    /// Last computed sunray probability, without applying any decay.
+    pub fn last_sunray_probability(&self) -> f32 {
+        self.sunray_probability
+    }
+
+    pub fn half_life(&self) -> Duration {
+        self.half_life
+    }
+
+    pub fn min_time_constant(&self) -> Duration {
+        self.min_time_constant
+    }
+
     pub fn current_tau(&self) -> f32 {
         self.half_life.as_secs_f32() / std::f32::consts::LN_2
     }
 
-    #[allow(dead_code)]
     pub fn debug_stats(&self) -> (f32, f32) {
         (self.sun_intensity, self.asteroid_intensity)
     }
 
     fn log_counter(&self, action: &str) {
-        let mut payload = Payload::new();
-        payload.insert("action".into(), action.into());
-        payload.insert("sun_intensity".into(), format!("{:.6}", self.sun_intensity));
-        payload.insert(
-            "asteroid_intensity".into(),
-            format!("{:.6}", self.asteroid_intensity),
+        self.log.emit(
+            LogAction::CounterUpdates,
+            None,
+            None,
+            EventType::InternalPlanetAction,
+            Channel::Debug,
+            || {
+                let mut payload = Payload::new();
+                payload.insert("action".into(), action.into());
+                payload.insert("sun_intensity".into(), format!("{:.6}", self.sun_intensity));
+                payload.insert(
+                    "asteroid_intensity".into(),
+                    format!("{:.6}", self.asteroid_intensity),
+                );
+                payload.insert(
+                    "sunray_probability".into(),
+                    format!("{:.6}", self.sunray_probability),
+                );
+                payload
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+
+    fn counter(pause_mode: PauseMode) -> (FrequencyCounter, MockClock) {
+        let clock = MockClock::new();
+        let mut counter = FrequencyCounter::new(Duration::from_secs(10), Duration::from_secs(1));
+        counter.set_clock(Arc::new(clock.clone()));
+        counter.set_pause_mode(pause_mode);
+        counter.update_sunray();
+        (counter, clock)
+    }
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-5, "{a} != {b}");
+    }
+
+    /// Test that a frozen counter does not decay while stopped and decays again once restarted
+    #[test]
+    fn test_stop_freeze() {
+        let (mut counter, clock) = counter(PauseMode::Freeze);
+        let (sun, asteroid) = counter.debug_stats();
+        counter.stop();
+        assert!(counter.is_paused());
+        clock.advance(Duration::from_secs(60));
+        // Stopping twice keeps the first pause
+        counter.stop();
+        clock.advance(Duration::from_secs(60));
+        assert_eq!(
+            counter.learned_state().since_last_update,
+            Some(Duration::ZERO)
         );
-        payload.insert(
-            "sunray_probability".into(),
-            format!("{:.6}", self.sunray_probability),
+
+        counter.restart();
+        assert!(!counter.is_paused());
+        clock.advance(Duration::from_secs(10));
+        counter.sunray_probability();
+        let (decayed_sun, decayed_asteroid) = counter.debug_stats();
+        assert_close(decayed_sun, sun / 2.0);
+        assert_close(decayed_asteroid, asteroid / 2.0);
+
+        // Restarting a running counter does nothing
+        counter.restart();
+        assert!(!counter.is_paused());
+    }
+
+    /// Test that a forgetting counter decays while stopped, keeping the odds but letting the
+    /// next event move them more than a frozen one
+    #[test]
+    fn test_stop_forget() {
+        let (mut frozen, frozen_clock) = counter(PauseMode::Freeze);
+        let (mut counter, clock) = counter(PauseMode::Forget);
+        let (sun, asteroid) = counter.debug_stats();
+        let probability = counter.sunray_probability();
+        for (counter, clock) in [(&mut frozen, &frozen_clock), (&mut counter, &clock)] {
+            counter.stop();
+            clock.advance(Duration::from_secs(10));
+            counter.restart();
+        }
+        assert_eq!(
+            counter.learned_state().since_last_update,
+            Some(Duration::from_secs(10))
         );
 
-        LogEvent::system(EventType::InternalPlanetAction, Channel::Debug, payload).emit();
+        assert_close(counter.sunray_probability(), probability);
+        let (decayed_sun, decayed_asteroid) = counter.debug_stats();
+        assert_close(decayed_sun, sun / 2.0);
+        assert_close(decayed_asteroid, asteroid / 2.0);
+
+        frozen.update_asteroid();
+        counter.update_asteroid();
+        assert!(counter.sunray_probability() < frozen.sunray_probability());
+    }
+
+    /// Test that restored values are paused until the restart, decaying in the meantime only
+    /// when forgetting
+    #[test]
+    fn test_restore() {
+        let (mut source, source_clock) = counter(PauseMode::Freeze);
+        source.update_asteroid();
+        source_clock.advance(Duration::from_secs(2));
+        let state = source.learned_state();
+        assert_eq!(state.since_last_update, Some(Duration::from_secs(2)));
+
+        for pause_mode in [PauseMode::Freeze, PauseMode::Forget] {
+            let clock = MockClock::new();
+            let mut counter =
+                FrequencyCounter::new(Duration::from_secs(10), Duration::from_secs(1));
+            counter.set_clock(Arc::new(clock.clone()));
+            counter.set_pause_mode(pause_mode);
+            counter.restore(&state);
+            assert!(counter.is_paused());
+            assert_eq!(counter.learned_state(), state);
+
+            clock.advance(Duration::from_secs(10));
+            counter.restart();
+            let since_last_update = counter.learned_state().since_last_update;
+            match pause_mode {
+                PauseMode::Freeze => assert_eq!(since_last_update, Some(Duration::from_secs(2))),
+                PauseMode::Forget => assert_eq!(since_last_update, Some(Duration::from_secs(12))),
+            }
+            assert_eq!(counter.debug_stats(), source.debug_stats());
+        }
     }
 }
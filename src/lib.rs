@@ -8,23 +8,48 @@ use common_game::utils::ID;
 use crossbeam_channel::{Receiver, Sender};
 
 mod ai;
+pub mod bridge;
+mod clock;
+pub mod dashboard;
 mod frequency_counter;
+mod json;
+mod logging;
+pub mod metrics;
+pub mod priors;
+pub mod qlearning;
+pub mod replay;
+pub mod report;
+mod rules;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 
-pub use ai::Ai;
+pub use ai::{
+    AdaptivePolicy, Ai, AiControl, AiSetting, AiStats, Checkpoint, DISAGREEMENT_BACKLOG,
+    DecisionCounters, DecisionExplanation, DecisionInputs, DecisionOutcome, DecisionPolicy,
+    DecisionPolicyKind, DecisionReason, DecisionRecord, DecisionRequest, DecisionSink,
+    EstimatorStats, FlightEntry, FlightEvent, FlightRecorder, LATENCY_BUCKETS_SECS,
+    LatencyHistogram, OutcomeChange, PlanetStats, PolicyInputs, PolicyOutcome, RandomPolicy,
+    RequestCounters, ShadowStats, StatsHandle,
+};
+pub use clock::{Clock, MockClock, SystemClock};
+pub use frequency_counter::PauseMode;
+pub use logging::{LogAction, LogConfig};
+pub use rules::{DEFAULT_COMB_RULES, DEFAULT_GEN_RULES, validate_rules};
 
 /// Creates a new Planet instance with the provided AI and communication channels.
 ///
 /// # Arguments
-/// * `planet_ai` - The AI implementation that will control the planet's behavior
+/// * `planet_type` - Type of the planet, which constrains the rules and number of energy cells
 /// * `gen_rules` - Vector of basic resource types that the planet can generate (must not be empty)
 /// * `comb_rules` - Vector of complex resource types that the planet can combine
 /// * `orchestrator_channels` - Tuple of (receiver, sender) for communication with the orchestrator
 /// * `explorers_receiver` - Receiver channel for messages from explorers
 ///
 /// # Returns
-/// * `Ok(Planet)` - Successfully created planet with ID 0 and type C
+/// * `Ok(Planet)` - Successfully created planet
 /// # Errors
-/// * `Err(String)` - Error message if planet creation fails (e.g., empty `gen_rules`)
+/// * `Err(String)` - Error message if planet creation fails (e.g., empty `gen_rules`, or
+///   rules repeated or not allowed by `planet_type`)
 #[allow(clippy::too_many_arguments)]
 pub fn create_planet(
     random_mode: bool,
@@ -33,12 +58,17 @@ pub fn create_planet(
     half_life: Duration,
     min_time_constant: Duration,
     id: ID,
+    planet_type: PlanetType,
+    gen_rules: Vec<BasicResourceType>,
+    comb_rules: Vec<ComplexResourceType>,
     orchestrator_channels: (Receiver<OrchestratorToPlanet>, Sender<PlanetToOrchestrator>),
     explorers_receiver: Receiver<ExplorerToPlanet>,
 ) -> Result<planet::Planet, String> {
+    validate_rules(&gen_rules, &comb_rules)?;
+
     planet::Planet::new(
         id,
-        PlanetType::C,
+        planet_type,
         Box::new(Ai::new(
             random_mode,
             basic_gen_coeff,
@@ -46,15 +76,8 @@ pub fn create_planet(
             half_life,
             min_time_constant,
         )),
-        vec![BasicResourceType::Hydrogen],
-        vec![
-            ComplexResourceType::AIPartner,
-            ComplexResourceType::Diamond,
-            ComplexResourceType::Dolphin,
-            ComplexResourceType::Life,
-            ComplexResourceType::Robot,
-            ComplexResourceType::Water,
-        ],
+        gen_rules,
+        comb_rules,
         orchestrator_channels,
         explorers_receiver,
     )
@@ -0,0 +1,297 @@
+use common_game::logging::{Channel, EventType, LogEvent, Participant, Payload};
+use std::cell::Cell;
+
+// Target of the records of `LogEvent::emit`, which logs from this module of common-game
+const LOG_TARGET: &str = "common_game::logging";
+
+const CHANNELS: [Channel; 5] = [
+    Channel::Error,
+    Channel::Warning,
+    Channel::Info,
+    Channel::Debug,
+    Channel::Trace,
+];
+
+/// Kind of the events logged by a planet, enabled and sampled separately.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogAction {
+    /// Updates of the threat estimator, after every sunray and asteroid
+    CounterUpdates,
+    /// Decisions on the explorer requests
+    Decisions,
+    /// Answers to the orchestrator and the explorers
+    Acks,
+    /// Rockets built and launched against the asteroids
+    Defense,
+    /// Energy cell charges spent
+    Energy,
+    /// Creation and reconfiguration of the AI
+    Configuration,
+    /// Failures of the recorders and the checkpoints
+    Failures,
+}
+
+const ACTIONS: [LogAction; 7] = [
+    LogAction::CounterUpdates,
+    LogAction::Decisions,
+    LogAction::Acks,
+    LogAction::Defense,
+    LogAction::Energy,
+    LogAction::Configuration,
+    LogAction::Failures,
+];
+
+/// Which events a planet logs, everything by default.
+///
+/// An event is only built when its channel is at least as important as the minimum channel,
+/// its action is enabled, it is not skipped by the sampling of its action and the installed
+/// `log` logger would print it, so disabled events cost no allocation.
+///
+/// The configuration has a text form, e.g. `channel=debug;off=acks,energy;sample=decisions/10`,
+/// used by the session files and the `icb-planet` options.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LogConfig {
+    // Index in `CHANNELS` of the least important channel logged
+    verbosity: usize,
+    // Bit `action as usize` set for the disabled actions
+    disabled: u8,
+    // One event in `sampling[action]` is logged
+    sampling: [u32; ACTIONS.len()],
+}
+
+/// Emits the events of a planet as set by its [`LogConfig`], counting them for sampling.
+pub(crate) struct Logger {
+    config: LogConfig,
+    seen: [Cell<u32>; ACTIONS.len()],
+}
+
+impl LogAction {
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            LogAction::CounterUpdates => "counter_updates",
+            LogAction::Decisions => "decisions",
+            LogAction::Acks => "acks",
+            LogAction::Defense => "defense",
+            LogAction::Energy => "energy",
+            LogAction::Configuration => "configuration",
+            LogAction::Failures => "failures",
+        }
+    }
+
+    pub(crate) fn from_label(label: &str) -> Option<Self> {
+        ACTIONS.into_iter().find(|action| action.label() == label)
+    }
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LogConfig {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            verbosity: CHANNELS.len() - 1,
+            disabled: 0,
+            sampling: [1; ACTIONS.len()],
+        }
+    }
+
+    /// Logs only the events on `channel` and the more important ones.
+    #[must_use]
+    pub fn with_min_channel(mut self, channel: &Channel) -> Self {
+        self.verbosity = rank(channel);
+        self
+    }
+
+    /// Enables or disables the events of `action`.
+    #[must_use]
+    pub fn with_action(mut self, action: LogAction, enabled: bool) -> Self {
+        if enabled {
+            self.disabled &= !(1 << action as u8);
+        } else {
+            self.disabled |= 1 << action as u8;
+        }
+        self
+    }
+
+    /// Logs the first event of `action` and then one in `every`, 1 logs them all.
+    #[must_use]
+    pub fn with_sampling(mut self, action: LogAction, every: u32) -> Self {
+        self.sampling[action as usize] = every.max(1);
+        self
+    }
+
+    #[must_use]
+    pub fn min_channel(self) -> Channel {
+        CHANNELS[self.verbosity].clone()
+    }
+
+    #[must_use]
+    pub fn is_action_enabled(self, action: LogAction) -> bool {
+        self.disabled & (1 << action as u8) == 0
+    }
+
+    #[must_use]
+    pub fn sampling(self, action: LogAction) -> u32 {
+        self.sampling[action as usize]
+    }
+
+    /// Returns true if events of `action` on `channel` would be logged, before sampling.
+    #[must_use]
+    pub fn enabled(self, action: LogAction, channel: &Channel) -> bool {
+        rank(channel) <= self.verbosity
+            && self.is_action_enabled(action)
+            && log::log_enabled!(target: LOG_TARGET, level(channel))
+    }
+
+    /// Text form of the configuration, only with what differs from the default.
+    #[must_use]
+    pub fn label(self) -> String {
+        let mut items = vec![format!("channel={}", channel_label(&self.min_channel()))];
+        let labels = |f: &dyn Fn(LogAction) -> Option<String>| {
+            ACTIONS
+                .into_iter()
+                .filter_map(f)
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+        let off =
+            labels(&|action| (!self.is_action_enabled(action)).then(|| action.label().to_string()));
+        if !off.is_empty() {
+            items.push(format!("off={off}"));
+        }
+        let sample = labels(&|action| {
+            let every = self.sampling(action);
+            (every > 1).then(|| format!("{}/{every}", action.label()))
+        });
+        if !sample.is_empty() {
+            items.push(format!("sample={sample}"));
+        }
+        items.join(";")
+    }
+
+    /// Parses the text form of [`label`](Self::label), the missing items keep their default.
+    ///
+    /// # Errors
+    /// Returns an error if an item, channel, action or sampling is invalid.
+    pub fn from_label(label: &str) -> Result<Self, String> {
+        let mut config = Self::new();
+        for item in label.split(';').filter(|item| !item.is_empty()) {
+            let (key, value) = item
+                .split_once('=')
+                .ok_or(format!("Invalid log setting {item}"))?;
+            config = match key {
+                "channel" => config.with_min_channel(
+                    &channel_from_label(value).ok_or(format!("Invalid log channel {value}"))?,
+                ),
+                "off" => config.with_disabled_labels(value)?,
+                "sample" => config.with_sampling_labels(value)?,
+                _ => return Err(format!("Unknown log setting {key}")),
+            };
+        }
+        Ok(config)
+    }
+
+    // Disables the comma separated actions of `labels`
+    pub(crate) fn with_disabled_labels(self, labels: &str) -> Result<Self, String> {
+        labels
+            .split(',')
+            .filter(|label| !label.is_empty())
+            .try_fold(self, |config, label| {
+                Ok(config.with_action(action_from_label(label)?, false))
+            })
+    }
+
+    // Sets the comma separated `action/every` samplings of `labels`
+    pub(crate) fn with_sampling_labels(self, labels: &str) -> Result<Self, String> {
+        labels
+            .split(',')
+            .filter(|label| !label.is_empty())
+            .try_fold(self, |config, label| {
+                let invalid = || format!("Invalid log sampling {label}");
+                let (action, every) = label.split_once('/').ok_or_else(invalid)?;
+                let every = every.parse().map_err(|_| invalid())?;
+                Ok(config.with_sampling(action_from_label(action)?, every))
+            })
+    }
+}
+
+impl Logger {
+    pub fn new(config: LogConfig) -> Self {
+        Self {
+            config,
+            seen: Default::default(),
+        }
+    }
+
+    pub fn config(&self) -> LogConfig {
+        self.config
+    }
+
+    /// Changes the configuration, the sampling goes on from the events already seen.
+    pub fn set_config(&mut self, config: LogConfig) {
+        self.config = config;
+    }
+
+    /// Builds the event with `payload` and emits it, if it is enabled and sampled.
+    pub fn emit(
+        &self,
+        action: LogAction,
+        sender: Option<Participant>,
+        receiver: Option<Participant>,
+        event_type: EventType,
+        channel: Channel,
+        payload: impl FnOnce() -> Payload,
+    ) {
+        if !self.config.enabled(action, &channel) {
+            return;
+        }
+        let seen = &self.seen[action as usize];
+        let index = seen.get();
+        seen.set(index.wrapping_add(1));
+        if index.is_multiple_of(self.config.sampling(action)) {
+            LogEvent::new(sender, receiver, event_type, channel, payload()).emit();
+        }
+    }
+}
+
+fn rank(channel: &Channel) -> usize {
+    CHANNELS
+        .iter()
+        .position(|known| known == channel)
+        .unwrap_or(CHANNELS.len() - 1)
+}
+
+// Level `LogEvent::emit` logs `channel` at
+fn level(channel: &Channel) -> log::Level {
+    match channel {
+        Channel::Error => log::Level::Error,
+        Channel::Warning => log::Level::Warn,
+        Channel::Info => log::Level::Info,
+        Channel::Debug => log::Level::Debug,
+        Channel::Trace => log::Level::Trace,
+    }
+}
+
+fn action_from_label(label: &str) -> Result<LogAction, String> {
+    LogAction::from_label(label).ok_or(format!("Invalid log action {label}"))
+}
+
+pub(crate) fn channel_label(channel: &Channel) -> &'static str {
+    match channel {
+        Channel::Error => "error",
+        Channel::Warning => "warning",
+        Channel::Info => "info",
+        Channel::Debug => "debug",
+        Channel::Trace => "trace",
+    }
+}
+
+pub(crate) fn channel_from_label(label: &str) -> Option<Channel> {
+    CHANNELS
+        .into_iter()
+        .find(|channel| channel_label(channel) == label)
+}
@@ -0,0 +1,389 @@
+//! Export of the AI statistics in the Prometheus text exposition format.
+//!
+//! Planets are registered in a [`MetricsRegistry`] with the [`StatsHandle`] of their AI,
+//! then the registry can be rendered on demand, written periodically to a file with
+//! [`MetricsExporter::write_to_file`] or served on a localhost HTTP endpoint with
+//! [`MetricsExporter::serve_http`].
+
+use crate::ai::{AiStats, StatsHandle};
+use common_game::utils::ID;
+use std::fmt::Write as _;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// Set of planets whose statistics are exported, labelled by planet id.
+#[derive(Debug, Clone, Default)]
+pub struct MetricsRegistry {
+    planets: Arc<Mutex<Vec<(ID, StatsHandle)>>>,
+}
+
+impl MetricsRegistry {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a planet to the registry, replacing any planet registered with the same id.
+    pub fn register(&self, planet_id: ID, stats: StatsHandle) {
+        let mut planets = self.planets.lock().unwrap_or_else(PoisonError::into_inner);
+        planets.retain(|(id, _)| *id != planet_id);
+        planets.push((planet_id, stats));
+    }
+
+    /// Removes a planet from the registry, e.g. after it has been destroyed.
+    pub fn unregister(&self, planet_id: ID) {
+        self.planets
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .retain(|(id, _)| *id != planet_id);
+    }
+
+    /// Renders the current statistics of every registered planet.
+    #[must_use]
+    pub fn render(&self) -> String {
+        let snapshots: Vec<(ID, AiStats)> = self
+            .planets
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .map(|(id, stats)| (*id, stats.snapshot()))
+            .collect();
+        render_prometheus(&snapshots)
+    }
+}
+
+// Labels and value of every sample of a metric family, for one planet
+type Samples = fn(&AiStats) -> Vec<(&'static str, f64)>;
+
+// (name, type, help, samples) of every exported family, except the latency histogram
+#[allow(clippy::cast_precision_loss)]
+const FAMILIES: [(&str, &str, &str, Samples); 9] = [
+    (
+        "icb_explorer_requests_total",
+        "counter",
+        "Explorer requests handled, by request type.",
+        |stats| {
+            let requests = &stats.requests;
+            vec![
+                (
+                    "request=\"supported_resource\"",
+                    requests.supported_resource as f64,
+                ),
+                (
+                    "request=\"supported_combination\"",
+                    requests.supported_combination as f64,
+                ),
+                (
+                    "request=\"generate_resource\"",
+                    requests.generate_resource as f64,
+                ),
+                (
+                    "request=\"combine_resource\"",
+                    requests.combine_resource as f64,
+                ),
+                (
+                    "request=\"available_energy_cell\"",
+                    requests.available_energy_cell as f64,
+                ),
+            ]
+        },
+    ),
+    (
+        "icb_decisions_total",
+        "counter",
+        "Generation and combination requests, by decision outcome.",
+        |stats| {
+            let decisions = &stats.decisions;
+            vec![
+                ("outcome=\"accepted\"", decisions.accepted as f64),
+                (
+                    "outcome=\"denied_by_policy\"",
+                    decisions.denied_by_policy as f64,
+                ),
+                (
+                    "outcome=\"denied_no_charge\"",
+                    decisions.denied_no_charge as f64,
+                ),
+                (
+                    "outcome=\"denied_reserved\"",
+                    decisions.denied_reserved as f64,
+                ),
+                ("outcome=\"unsupported\"", decisions.unsupported as f64),
+                ("outcome=\"failed\"", decisions.failed as f64),
+            ]
+        },
+    ),
+    (
+        "icb_accept_ratio",
+        "gauge",
+        "Share of the generation and combination requests accepted.",
+        |stats| {
+            stats
+                .accept_ratio()
+                .map(|ratio| ("", ratio))
+                .into_iter()
+                .collect()
+        },
+    ),
+    (
+        "icb_threat_estimate",
+        "gauge",
+        "Estimated probability that the next event is an asteroid.",
+        |stats| {
+            stats
+                .estimator
+                .as_ref()
+                .map(|estimator| ("", 1.0 - f64::from(estimator.sunray_probability)))
+                .into_iter()
+                .collect()
+        },
+    ),
+    (
+        "icb_sunrays_received_total",
+        "counter",
+        "Sunrays received from the orchestrator.",
+        |stats| vec![("", stats.sunrays_received as f64)],
+    ),
+    (
+        "icb_rockets_built_total",
+        "counter",
+        "Rockets built.",
+        |stats| vec![("", stats.rockets_built as f64)],
+    ),
+    (
+        "icb_rocket_launches_total",
+        "counter",
+        "Rockets launched against asteroids.",
+        |stats| vec![("", stats.asteroids_survived as f64)],
+    ),
+    (
+        "icb_asteroids_total",
+        "counter",
+        "Asteroids received, by outcome.",
+        |stats| {
+            vec![
+                ("outcome=\"survived\"", stats.asteroids_survived as f64),
+                ("outcome=\"lost\"", stats.asteroids_lost as f64),
+            ]
+        },
+    ),
+    (
+        "icb_energy_charges_consumed_total",
+        "counter",
+        "Energy cell charges spent, on rockets and explorer requests.",
+        |stats| vec![("", stats.charges_consumed as f64)],
+    ),
+];
+
+/// Renders the statistics of several planets in the Prometheus text exposition format.
+#[must_use]
+pub fn render_prometheus(planets: &[(ID, AiStats)]) -> String {
+    let mut out = String::new();
+
+    for (name, kind, help, samples) in FAMILIES {
+        let _ = writeln!(out, "# HELP {name} {help}");
+        let _ = writeln!(out, "# TYPE {name} {kind}");
+        for (planet_id, stats) in planets {
+            for (labels, value) in samples(stats) {
+                let separator = if labels.is_empty() { "" } else { "," };
+                let _ = writeln!(
+                    out,
+                    "{name}{{planet=\"{planet_id}\"{separator}{labels}}} {value}"
+                );
+            }
+        }
+    }
+
+    let name = "icb_decision_latency_seconds";
+    let _ = writeln!(
+        out,
+        "# HELP {name} Time spent deciding on explorer requests."
+    );
+    let _ = writeln!(out, "# TYPE {name} histogram");
+    for (planet_id, stats) in planets {
+        let histogram = &stats.decision_latency;
+        for (bound, count) in histogram.cumulative_buckets() {
+            let le = if bound.is_infinite() {
+                "+Inf".to_string()
+            } else {
+                bound.to_string()
+            };
+            let _ = writeln!(
+                out,
+                "{name}_bucket{{planet=\"{planet_id}\",le=\"{le}\"}} {count}"
+            );
+        }
+        let _ = writeln!(
+            out,
+            "{name}_sum{{planet=\"{planet_id}\"}} {}",
+            histogram.sum_secs()
+        );
+        let _ = writeln!(
+            out,
+            "{name}_count{{planet=\"{planet_id}\"}} {}",
+            histogram.count()
+        );
+    }
+
+    out
+}
+
+/// Background thread exporting a [`MetricsRegistry`]. The thread is stopped on drop.
+pub struct MetricsExporter {
+    stop: Arc<AtomicBool>,
+    local_addr: Option<SocketAddr>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl MetricsExporter {
+    /// Writes the rendered metrics to `path` every `interval`, e.g. for the node exporter
+    /// textfile collector. The file is replaced atomically at every write.
+    ///
+    /// # Errors
+    /// Returns an error if the first write fails.
+    pub fn write_to_file(
+        registry: MetricsRegistry,
+        path: impl Into<PathBuf>,
+        interval: Duration,
+    ) -> io::Result<Self> {
+        let path = path.into();
+        write_atomically(&path, &registry.render())?;
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = Arc::clone(&stop);
+        let thread = thread::spawn(move || {
+            while !sleep_unless_stopped(&thread_stop, interval) {
+                let _ = write_atomically(&path, &registry.render());
+            }
+        });
+
+        Ok(Self {
+            stop,
+            local_addr: None,
+            thread: Some(thread),
+        })
+    }
+
+    /// Serves the rendered metrics over HTTP on `addr`, which must be a loopback address.
+    /// Use port 0 to let the OS pick a free port, see [`MetricsExporter::local_addr`].
+    ///
+    /// # Errors
+    /// Returns an error if `addr` is not a loopback address or can not be bound.
+    pub fn serve_http(registry: MetricsRegistry, addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let addr = addr
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no address given"))?;
+        if !addr.ip().is_loopback() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("metrics are only served on localhost, got {addr}"),
+            ));
+        }
+
+        let listener = TcpListener::bind(addr)?;
+        let local_addr = listener.local_addr()?;
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = Arc::clone(&stop);
+        let thread = thread::spawn(move || {
+            for stream in listener.incoming() {
+                if thread_stop.load(Ordering::Relaxed) {
+                    break;
+                }
+                if let Ok(stream) = stream {
+                    let _ = respond(stream, &registry);
+                }
+            }
+        });
+
+        Ok(Self {
+            stop,
+            local_addr: Some(local_addr),
+            thread: Some(thread),
+        })
+    }
+
+    /// Address the HTTP endpoint is bound to, `None` for file exporters.
+    #[must_use]
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.local_addr
+    }
+
+    /// Stops the background thread and waits for it.
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        // Wake the listener up, it checks the stop flag on every connection
+        if let Some(addr) = self.local_addr {
+            let _ = TcpStream::connect(addr);
+        }
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for MetricsExporter {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+// Sleeps for `interval` in short steps, returns `true` as soon as a stop is requested
+fn sleep_unless_stopped(stop: &AtomicBool, interval: Duration) -> bool {
+    const STEP: Duration = Duration::from_millis(50);
+    let mut remaining = interval;
+    while !remaining.is_zero() {
+        if stop.load(Ordering::Relaxed) {
+            return true;
+        }
+        let nap = STEP.min(remaining);
+        thread::sleep(nap);
+        remaining = remaining.saturating_sub(nap);
+    }
+    stop.load(Ordering::Relaxed)
+}
+
+fn write_atomically(path: &PathBuf, contents: &str) -> io::Result<()> {
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, contents)?;
+    fs::rename(tmp, path)
+}
+
+// Answers a single HTTP request: the metrics on `GET /metrics` (or `/`), 404 otherwise
+fn respond(stream: TcpStream, registry: &MetricsRegistry) -> io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(1)))?;
+    let mut reader = BufReader::new(stream);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // Skip the headers
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (status, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics" | "/")) => ("200 OK", registry.render()),
+        _ => ("404 Not Found", "not found\n".to_string()),
+    };
+
+    let mut stream = reader.into_inner();
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()
+}
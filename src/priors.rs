@@ -0,0 +1,380 @@
+//! Warm-start priors for the threat estimator.
+//!
+//! A [`PriorFitter`] collects the sunrays and asteroids seen by past planets, either from
+//! the `update_sunray_counter`/`update_asteroid_counter` events in their logs or from
+//! recorded [sessions](crate::replay), and fits their rates. The resulting
+//! [`EstimatorPrior`] is given to [`Ai::with_estimator_prior`](crate::Ai) so a new planet
+//! starts from a realistic threat model instead of even odds.
+//!
+//! The estimator intensities converge to the rate of each event per second, so the prior
+//! intensities are the fitted rates. The estimator has no notion of bursts, so the asteroid
+//! burst patterns are only reported with the fit, to judge how far the traffic is from
+//! independent events.
+
+use crate::json::Json;
+use crate::replay::{Session, SessionEntry, SessionInput};
+use crate::report::LogLine;
+use common_game::utils::ID;
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter};
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+use std::time::Duration;
+
+const VERSION: f64 = 1.0;
+
+// Events added to each count, so a threat never seen still gets a small intensity
+const SMOOTHING: f64 = 0.5;
+
+/// Initial intensities of the threat estimator.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EstimatorPrior {
+    sun_intensity: f32,
+    asteroid_intensity: f32,
+}
+
+/// Rates fitted by a [`PriorFitter`], with the asteroid burst patterns the prior does not
+/// capture.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PriorFit {
+    pub sunrays: usize,
+    pub asteroids: usize,
+    /// Time covered by the events, without the time planets were stopped. Logs tell it only
+    /// when their start and stop acks were logged.
+    pub span: Duration,
+    /// Sunrays per second
+    pub sunray_rate: f64,
+    /// Asteroids per second
+    pub asteroid_rate: f64,
+    /// Mean number of asteroids in a row, without a sunray in between
+    pub mean_asteroid_burst: f64,
+    pub max_asteroid_burst: usize,
+    /// Probability that an asteroid is followed by another one
+    pub asteroid_after_asteroid: f64,
+}
+
+/// Collects sunrays and asteroids from logs and sessions, see the
+/// [module documentation](self).
+#[derive(Debug, Clone, Default)]
+pub struct PriorFitter {
+    // Events of each uninterrupted run of a planet, with their time and whether they are
+    // sunrays
+    segments: Vec<Vec<(Duration, bool)>>,
+}
+
+impl EstimatorPrior {
+    /// # Errors
+    /// Returns an error if an intensity is negative or not finite, or both are zero.
+    pub fn new(sun_intensity: f32, asteroid_intensity: f32) -> Result<Self, String> {
+        if !sun_intensity.is_finite()
+            || !asteroid_intensity.is_finite()
+            || sun_intensity < 0.0
+            || asteroid_intensity < 0.0
+        {
+            return Err("Intensities must be finite and non-negative".to_string());
+        }
+        if sun_intensity + asteroid_intensity <= 0.0 {
+            return Err("At least one intensity must be positive".to_string());
+        }
+        Ok(Self {
+            sun_intensity,
+            asteroid_intensity,
+        })
+    }
+
+    #[must_use]
+    pub fn sun_intensity(&self) -> f32 {
+        self.sun_intensity
+    }
+
+    #[must_use]
+    pub fn asteroid_intensity(&self) -> f32 {
+        self.asteroid_intensity
+    }
+
+    /// Sunray probability the estimator starts from.
+    #[must_use]
+    pub fn sunray_probability(&self) -> f32 {
+        self.sun_intensity / (self.sun_intensity + self.asteroid_intensity)
+    }
+
+    pub(crate) fn to_json(self) -> Json {
+        Json::object([
+            ("sun_intensity", self.sun_intensity.into()),
+            ("asteroid_intensity", self.asteroid_intensity.into()),
+        ])
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    pub(crate) fn from_json(json: &Json) -> Result<Self, String> {
+        Self::new(
+            json.f64_field("sun_intensity")? as f32,
+            json.f64_field("asteroid_intensity")? as f32,
+        )
+    }
+
+    /// Loads a prior file written by [`PriorFit::save`].
+    ///
+    /// # Errors
+    /// Returns an error if the file can not be read or is not a valid prior.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)
+            .map_err(|err| format!("Can not read prior {}: {err}", path.display()))?;
+        Json::parse(contents.trim())
+            .and_then(|json| {
+                let version = json.f64_field("version")?;
+                if (version - VERSION).abs() > f64::EPSILON {
+                    return Err(format!("Unsupported prior version {version}"));
+                }
+                Self::from_json(&json)
+            })
+            .map_err(|err| format!("Invalid prior {}: {err}", path.display()))
+    }
+}
+
+impl PriorFit {
+    /// Prior matching the fitted rates.
+    ///
+    /// # Errors
+    /// Returns an error if the rates are not valid intensities, see [`EstimatorPrior::new`].
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn prior(&self) -> Result<EstimatorPrior, String> {
+        EstimatorPrior::new(self.sunray_rate as f32, self.asteroid_rate as f32)
+    }
+
+    /// Writes the prior, with the fit it comes from, to `path`.
+    ///
+    /// # Errors
+    /// Returns an error if the prior is invalid or the file can not be written.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        let path = path.as_ref();
+        let prior = self.prior()?;
+        let json = Json::object([
+            ("version", Json::from(VERSION)),
+            ("sun_intensity", prior.sun_intensity.into()),
+            ("asteroid_intensity", prior.asteroid_intensity.into()),
+            (
+                "fit",
+                Json::object([
+                    ("sunrays", Json::from(self.sunrays)),
+                    ("asteroids", self.asteroids.into()),
+                    ("span_ns", Json::nanos(self.span)),
+                    ("sunray_rate", self.sunray_rate.into()),
+                    ("asteroid_rate", self.asteroid_rate.into()),
+                    ("mean_asteroid_burst", self.mean_asteroid_burst.into()),
+                    ("max_asteroid_burst", self.max_asteroid_burst.into()),
+                    (
+                        "asteroid_after_asteroid",
+                        self.asteroid_after_asteroid.into(),
+                    ),
+                ]),
+            ),
+        ]);
+        fs::write(path, format!("{json}\n"))
+            .map_err(|err| format!("Can not write prior {}: {err}", path.display()))
+    }
+}
+
+impl Display for PriorFit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} sunrays and {} asteroids over {:.3}s",
+            self.sunrays,
+            self.asteroids,
+            self.span.as_secs_f64()
+        )?;
+        writeln!(
+            f,
+            "rates: {:.4} sunrays/s, {:.4} asteroids/s",
+            self.sunray_rate, self.asteroid_rate
+        )?;
+        writeln!(
+            f,
+            "asteroid bursts: mean {:.2}, max {}, P(asteroid after asteroid) {:.3}",
+            self.mean_asteroid_burst, self.max_asteroid_burst, self.asteroid_after_asteroid
+        )?;
+        match self.prior() {
+            Ok(prior) => write!(
+                f,
+                "prior: sun_intensity {:.6}, asteroid_intensity {:.6}, sunray_probability {:.4}",
+                prior.sun_intensity,
+                prior.asteroid_intensity,
+                prior.sunray_probability()
+            ),
+            Err(err) => write!(f, "prior: {err}"),
+        }
+    }
+}
+
+impl PriorFitter {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the sunrays and asteroids of a recorded session, split at each stop.
+    pub fn add_session(&mut self, session: &Session) {
+        let mut segment = Vec::new();
+        for entry in &session.entries {
+            let SessionEntry::Input { elapsed, input } = entry else {
+                continue;
+            };
+            match input {
+                SessionInput::Sunray => segment.push((*elapsed, true)),
+                SessionInput::Asteroid => segment.push((*elapsed, false)),
+                SessionInput::Start | SessionInput::Stop => {
+                    self.push_segment(std::mem::take(&mut segment));
+                }
+                _ => {}
+            }
+        }
+        self.push_segment(segment);
+    }
+
+    /// Adds the counter updates found in log output, one segment per planet, split at the
+    /// start and stop acks when they were logged. Other lines are skipped. Returns the number
+    /// of updates found.
+    ///
+    /// Both the `Debug` and `Display` formats of `LogEvent` are understood, anywhere in a
+    /// line so logger prefixes do not matter. Log timestamps are in seconds.
+    ///
+    /// # Errors
+    /// Returns an error if the log can not be read.
+    pub fn read_log(&mut self, reader: impl BufRead) -> Result<usize, String> {
+        let mut planets: BTreeMap<Option<ID>, Vec<(Duration, bool)>> = BTreeMap::new();
+        let mut found = 0;
+        for line in reader.lines() {
+            let line = line.map_err(|err| err.to_string())?;
+            let Some(line) = LogLine::parse(&line) else {
+                continue;
+            };
+            let is_sunray = match (line.get("message"), line.get("action")) {
+                (Some("StartPlanetAIResult" | "StopPlanetAIResult"), _) => {
+                    if let Some(segment) = planets.remove(&line.planet_id) {
+                        self.push_segment(segment);
+                    }
+                    continue;
+                }
+                (_, Some("update_sunray_counter")) => true,
+                (_, Some("update_asteroid_counter")) => false,
+                _ => continue,
+            };
+            planets
+                .entry(line.planet_id)
+                .or_default()
+                .push((Duration::from_secs(line.timestamp), is_sunray));
+            found += 1;
+        }
+        for (_, segment) in planets {
+            self.push_segment(segment);
+        }
+        Ok(found)
+    }
+
+    /// Adds a session file or a log file, told apart by their first line. Returns the
+    /// number of sunrays and asteroids found.
+    ///
+    /// # Errors
+    /// Returns an error if the file can not be read or is an invalid session.
+    pub fn load(&mut self, path: impl AsRef<Path>) -> Result<usize, String> {
+        let path = path.as_ref();
+        let file = File::open(path).map_err(|err| format!("{}: {err}", path.display()))?;
+        let mut reader = BufReader::new(file);
+
+        let mut first = String::new();
+        reader
+            .read_line(&mut first)
+            .map_err(|err| format!("{}: {err}", path.display()))?;
+        let is_session =
+            Json::parse(first.trim()).is_ok_and(|json| json.str_field("event") == Ok("session"));
+
+        let before = self.events();
+        if is_session {
+            let session =
+                Session::load(path).map_err(|err| format!("{}: {err}", path.display()))?;
+            self.add_session(&session);
+        } else {
+            self.read_log(first.as_bytes().chain(reader))
+                .map_err(|err| format!("{}: {err}", path.display()))?;
+        }
+        Ok(self.events() - before)
+    }
+
+    fn push_segment(&mut self, segment: Vec<(Duration, bool)>) {
+        if !segment.is_empty() {
+            self.segments.push(segment);
+        }
+    }
+
+    fn events(&self) -> usize {
+        self.segments.iter().map(Vec::len).sum()
+    }
+
+    /// Fits the rates and burst patterns of the events collected so far.
+    ///
+    /// # Errors
+    /// Returns an error if there is no event, or they all happened at the same time.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn fit(&self) -> Result<PriorFit, String> {
+        let mut sunrays = 0;
+        let mut asteroids = 0;
+        let mut span = Duration::ZERO;
+        let mut max_asteroid_burst = 0;
+        let (mut bursts, mut followed, mut repeated) = (0u32, 0u32, 0u32);
+
+        for segment in &self.segments {
+            let (first, last) = (segment[0].0, segment[segment.len() - 1].0);
+            span += last.saturating_sub(first);
+
+            let mut burst = 0;
+            for &(_, is_sunray) in segment {
+                if burst > 0 {
+                    followed += 1;
+                }
+                if is_sunray {
+                    sunrays += 1;
+                    burst = 0;
+                } else {
+                    asteroids += 1;
+                    if burst > 0 {
+                        repeated += 1;
+                    } else {
+                        bursts += 1;
+                    }
+                    burst += 1;
+                    max_asteroid_burst = max_asteroid_burst.max(burst);
+                }
+            }
+        }
+
+        if sunrays + asteroids == 0 {
+            return Err("No sunray or asteroid found".to_string());
+        }
+        if span.is_zero() {
+            return Err("The events do not cover any time".to_string());
+        }
+
+        let secs = span.as_secs_f64();
+        Ok(PriorFit {
+            sunrays,
+            asteroids,
+            span,
+            sunray_rate: (sunrays as f64 + SMOOTHING) / secs,
+            asteroid_rate: (asteroids as f64 + SMOOTHING) / secs,
+            mean_asteroid_burst: if bursts > 0 {
+                asteroids as f64 / f64::from(bursts)
+            } else {
+                0.0
+            },
+            max_asteroid_burst,
+            asteroid_after_asteroid: if followed > 0 {
+                f64::from(repeated) / f64::from(followed)
+            } else {
+                0.0
+            },
+        })
+    }
+}
@@ -0,0 +1,390 @@
+//! Decision policy learning whether to accept requests with Q-learning.
+//!
+//! A [`QLearningPolicy`] made the active policy of an AI with
+//! [`Ai::with_decision_policy`](crate::Ai::with_decision_policy) learns the value of accepting
+//! and denying a request from what follows its decisions: a served request is rewarded, an
+//! asteroid destroying the planet costs much more. Decisions are taken on a [`QState`], the
+//! bucket of the asteroid risk, whether a rocket is ready, whether another cell is charged
+//! and the tier of the explorer.
+//!
+//! Policies are trained in the [`Simulator`], which plays random traffic on a real planet.
+//! The learned [`QTable`] is saved and, in production, loaded into a
+//! [frozen](QLearningPolicy::frozen) policy that always takes the best action and stops
+//! learning, e.g. with the `--q-table` option of `icb-planet`.
+
+mod simulator;
+
+pub use simulator::{SimulationReport, Simulator, Traffic};
+
+use crate::ai::{DecisionPolicy, PolicyInputs, PolicyOutcome};
+use crate::json::Json;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+const VERSION: f64 = 1.0;
+
+/// Buckets of the asteroid risk, the first one is the lowest risk.
+pub const THREAT_BUCKETS: usize = 5;
+
+/// Explorers by the requests the planet served them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExplorerTier {
+    /// Never served
+    New,
+    /// Served up to 4 times
+    Returning,
+    /// Served 5 times or more
+    Regular,
+}
+
+/// Discretized state a [`QLearningPolicy`] decides on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QState {
+    /// Bucket of the risk of an asteroid before the next sunray, the highest one without an
+    /// estimator
+    pub threat: usize,
+    pub has_rocket: bool,
+    /// Whether a cell is charged besides the one the request would spend
+    pub spare_charge: bool,
+    pub tier: ExplorerTier,
+}
+
+/// Values of denying and accepting a request in each [`QState`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct QTable {
+    // [deny, accept] for each state index
+    values: Vec<[f32; 2]>,
+}
+
+/// Q-learning decision policy, see the [module documentation](self).
+///
+/// While learning it explores with a random action now and then, and updates the value of
+/// each decision with the rewards received until the next one.
+pub struct QLearningPolicy {
+    // Shared with the policies created by `share`
+    table: Arc<Mutex<QTable>>,
+    learning_rate: f32,
+    discount: f32,
+    exploration: f32,
+    served_reward: f32,
+    destroyed_reward: f32,
+    frozen: bool,
+    rng: StdRng,
+    // Last decision, waiting for the next state to update its value
+    pending: Option<Transition>,
+}
+
+struct Transition {
+    state: usize,
+    accept: bool,
+    reward: f32,
+}
+
+impl ExplorerTier {
+    #[must_use]
+    pub fn of(served_requests: u64) -> Self {
+        match served_requests {
+            0 => ExplorerTier::New,
+            1..=4 => ExplorerTier::Returning,
+            _ => ExplorerTier::Regular,
+        }
+    }
+}
+
+impl QState {
+    /// Number of states.
+    pub const COUNT: usize = THREAT_BUCKETS * 2 * 2 * 3;
+
+    #[must_use]
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::cast_precision_loss
+    )]
+    pub fn from_inputs(inputs: &PolicyInputs) -> Self {
+        let threat = inputs
+            .sunray_probability
+            .map_or(THREAT_BUCKETS - 1, |p_sunray| {
+                let p_asteroid = (1.0 - p_sunray).clamp(0.0, 1.0);
+                ((p_asteroid * THREAT_BUCKETS as f32) as usize).min(THREAT_BUCKETS - 1)
+            });
+        Self {
+            threat,
+            has_rocket: inputs.has_rocket,
+            spare_charge: inputs.charged_cells > 1,
+            tier: ExplorerTier::of(inputs.served_requests),
+        }
+    }
+
+    fn index(self) -> usize {
+        let index = self.threat.min(THREAT_BUCKETS - 1);
+        let index = index * 2 + usize::from(self.has_rocket);
+        let index = index * 2 + usize::from(self.spare_charge);
+        index * 3 + self.tier as usize
+    }
+}
+
+impl Default for QTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl QTable {
+    /// Table with every value at zero.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            values: vec![[0.0; 2]; QState::COUNT],
+        }
+    }
+
+    #[must_use]
+    pub fn value(&self, state: QState, accept: bool) -> f32 {
+        self.values[state.index()][usize::from(accept)]
+    }
+
+    /// Returns true if accepting is worth at least as much as denying in `state`.
+    #[must_use]
+    pub fn best_action(&self, state: QState) -> bool {
+        self.best(state.index())
+    }
+
+    fn best(&self, index: usize) -> bool {
+        let [deny, accept] = self.values[index];
+        accept >= deny
+    }
+
+    // Moves the value of a decision towards its reward and the best value of the next state,
+    // `None` when the episode ended
+    fn update(&mut self, transition: &Transition, next: Option<usize>, rate: f32, discount: f32) {
+        let next_value = next.map_or(0.0, |next| {
+            let [deny, accept] = self.values[next];
+            deny.max(accept)
+        });
+        let value = &mut self.values[transition.state][usize::from(transition.accept)];
+        *value += rate * (transition.reward + discount * next_value - *value);
+    }
+
+    fn to_json(&self) -> Json {
+        Json::object([
+            ("version", Json::from(VERSION)),
+            ("states", QState::COUNT.into()),
+            (
+                "values",
+                self.values
+                    .iter()
+                    .map(|values| Json::from(values.to_vec()))
+                    .collect::<Vec<_>>()
+                    .into(),
+            ),
+        ])
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn from_json(json: &Json) -> Result<Self, String> {
+        let version = json.f64_field("version")?;
+        if (version - VERSION).abs() > f64::EPSILON {
+            return Err(format!("Unsupported table version {version}"));
+        }
+        let rows = json
+            .get("values")
+            .and_then(Json::as_array)
+            .ok_or("Missing field values")?;
+        if rows.len() != QState::COUNT {
+            return Err(format!(
+                "Expected {} states, found {}",
+                QState::COUNT,
+                rows.len()
+            ));
+        }
+        let values = rows
+            .iter()
+            .map(|row| match row.as_array() {
+                Some([deny, accept]) => match (deny.as_f64(), accept.as_f64()) {
+                    (Some(deny), Some(accept)) => Ok([deny as f32, accept as f32]),
+                    _ => Err("Invalid value".to_string()),
+                },
+                _ => Err("Invalid state values".to_string()),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { values })
+    }
+
+    /// Loads a table written by [`save`](Self::save).
+    ///
+    /// # Errors
+    /// Returns an error if the file can not be read or is not a valid table.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)
+            .map_err(|err| format!("Can not read Q table {}: {err}", path.display()))?;
+        Json::parse(contents.trim())
+            .and_then(|json| Self::from_json(&json))
+            .map_err(|err| format!("Invalid Q table {}: {err}", path.display()))
+    }
+
+    /// Writes the table to `path`, replacing it atomically so a crash can not leave a
+    /// truncated table.
+    ///
+    /// # Errors
+    /// Returns an error if the file can not be written.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        let path = path.as_ref();
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, format!("{}\n", self.to_json()))
+            .and_then(|()| fs::rename(&tmp, path))
+            .map_err(|err| format!("Can not write Q table {}: {err}", path.display()))
+    }
+}
+
+impl QLearningPolicy {
+    /// Learning policy starting from an empty table, exploring with a generator seeded with
+    /// `seed`.
+    #[must_use]
+    pub fn new(seed: u64) -> Self {
+        Self {
+            table: Arc::new(Mutex::new(QTable::new())),
+            learning_rate: 0.1,
+            discount: 0.9,
+            exploration: 0.1,
+            served_reward: 1.0,
+            destroyed_reward: -10.0,
+            frozen: false,
+            rng: StdRng::seed_from_u64(seed),
+            pending: None,
+        }
+    }
+
+    /// Policy taking the best action of `table`, without learning.
+    #[must_use]
+    pub fn frozen(table: QTable) -> Self {
+        Self::new(0).with_table(table).freeze()
+    }
+
+    /// Starts from `table` instead of an empty one, e.g. to go on training a saved table.
+    #[must_use]
+    pub fn with_table(self, table: QTable) -> Self {
+        *self.lock() = table;
+        self
+    }
+
+    /// Weight of a new reward against the value learned so far, 0.1 by default.
+    #[must_use]
+    pub fn with_learning_rate(mut self, learning_rate: f32) -> Self {
+        self.learning_rate = learning_rate.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Weight of the value of the next state, 0.9 by default.
+    #[must_use]
+    pub fn with_discount(mut self, discount: f32) -> Self {
+        self.discount = discount.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Probability of a random action while learning, 0.1 by default.
+    #[must_use]
+    pub fn with_exploration(mut self, exploration: f32) -> Self {
+        self.exploration = exploration.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Rewards of a served request and of the planet being destroyed, 1 and -10 by default.
+    #[must_use]
+    pub fn with_rewards(mut self, served: f32, destroyed: f32) -> Self {
+        self.served_reward = served;
+        self.destroyed_reward = destroyed;
+        self
+    }
+
+    /// Stops learning and exploring, the policy takes the best action of its table.
+    #[must_use]
+    pub fn freeze(mut self) -> Self {
+        self.frozen = true;
+        self.pending = None;
+        self
+    }
+
+    #[must_use]
+    pub fn is_frozen(&self) -> bool {
+        self.frozen
+    }
+
+    /// Policy with the same parameters learning into the same table, exploring with a
+    /// generator seeded with `seed`, e.g. for another planet or another simulated episode.
+    #[must_use]
+    pub fn share(&self, seed: u64) -> Self {
+        Self {
+            table: Arc::clone(&self.table),
+            rng: StdRng::seed_from_u64(seed),
+            pending: None,
+            ..*self
+        }
+    }
+
+    /// Returns a copy of the table learned so far, by this policy and the ones sharing it.
+    #[must_use]
+    pub fn table(&self) -> QTable {
+        self.lock().clone()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, QTable> {
+        self.table.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl DecisionPolicy for QLearningPolicy {
+    fn name(&self) -> &'static str {
+        "qlearning"
+    }
+
+    fn decide(&mut self, inputs: &PolicyInputs) -> bool {
+        let state = QState::from_inputs(inputs).index();
+        if self.frozen {
+            return self.lock().best(state);
+        }
+
+        let (rate, discount) = (self.learning_rate, self.discount);
+        let pending = self.pending.take();
+        let best = {
+            let mut table = self.lock();
+            if let Some(transition) = pending {
+                table.update(&transition, Some(state), rate, discount);
+            }
+            table.best(state)
+        };
+        let accept = if self.rng.random::<f32>() < self.exploration {
+            self.rng.random()
+        } else {
+            best
+        };
+        self.pending = Some(Transition {
+            state,
+            accept,
+            reward: 0.0,
+        });
+        accept
+    }
+
+    fn observe(&mut self, outcome: PolicyOutcome) {
+        match outcome {
+            PolicyOutcome::Served { .. } => {
+                if let Some(transition) = &mut self.pending {
+                    transition.reward += self.served_reward;
+                }
+            }
+            PolicyOutcome::Asteroid { destroyed: true } => {
+                if let Some(mut transition) = self.pending.take() {
+                    transition.reward += self.destroyed_reward;
+                    let (rate, discount) = (self.learning_rate, self.discount);
+                    self.lock().update(&transition, None, rate, discount);
+                }
+            }
+            PolicyOutcome::Asteroid { destroyed: false } => {}
+        }
+    }
+}
@@ -0,0 +1,253 @@
+use super::QLearningPolicy;
+use crate::MockClock;
+use crate::ai::DecisionPolicy;
+use crate::bridge::{PlanetBridge, PlanetOptions};
+use crate::json::Json;
+use common_game::utils::ID;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::fmt::{self, Display, Formatter};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Random events of a simulated episode, one per step.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Traffic {
+    /// Probability that a step is a sunray
+    pub sunray: f64,
+    /// Probability that a step is an asteroid, the other steps are generation requests
+    pub asteroid: f64,
+    /// Probability that an explorer leaves before a request, replaced by a new one
+    pub churn: f64,
+    /// Explorers visiting the planet at once
+    pub explorers: usize,
+    /// Time between two steps, on the clock of the AI
+    pub step: Duration,
+}
+
+/// Plays random traffic on planets to train and evaluate decision policies.
+///
+/// Each episode runs a new planet, created from the [`PlanetOptions`], through a
+/// [`PlanetBridge`], on a mock clock. It ends when an asteroid destroys the planet or after
+/// the maximum number of steps. Episodes are seeded, so a simulation is reproducible.
+#[derive(Debug, Clone)]
+pub struct Simulator {
+    options: PlanetOptions,
+    traffic: Traffic,
+    episodes: usize,
+    max_steps: usize,
+    seed: u64,
+}
+
+/// What happened in the episodes of a simulation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SimulationReport {
+    pub episodes: usize,
+    pub steps: u64,
+    /// Requests served to the explorers, with the resource they asked for
+    pub served: u64,
+    /// Episodes ended by an asteroid destroying the planet
+    pub destroyed: usize,
+}
+
+impl Default for Traffic {
+    fn default() -> Self {
+        Self {
+            sunray: 0.4,
+            asteroid: 0.1,
+            churn: 0.05,
+            explorers: 3,
+            step: Duration::from_millis(250),
+        }
+    }
+}
+
+impl Simulator {
+    /// 100 episodes of at most 500 steps of the default traffic.
+    #[must_use]
+    pub fn new(options: PlanetOptions) -> Self {
+        Self {
+            options,
+            traffic: Traffic::default(),
+            episodes: 100,
+            max_steps: 500,
+            seed: 0,
+        }
+    }
+
+    #[must_use]
+    pub fn with_traffic(mut self, traffic: Traffic) -> Self {
+        self.traffic = traffic;
+        self
+    }
+
+    #[must_use]
+    pub fn with_episodes(mut self, episodes: usize) -> Self {
+        self.episodes = episodes;
+        self
+    }
+
+    #[must_use]
+    pub fn with_max_steps(mut self, max_steps: usize) -> Self {
+        self.max_steps = max_steps;
+        self
+    }
+
+    /// Seeds the traffic, episode `i` is seeded with `seed + i`.
+    #[must_use]
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Runs the episodes with `policy`, learning into its table unless it is frozen.
+    ///
+    /// # Errors
+    /// Returns an error if a planet can not be created or stops answering.
+    pub fn train(&self, policy: &QLearningPolicy) -> Result<SimulationReport, String> {
+        self.run(|seed| Some(Box::new(policy.share(seed))))
+    }
+
+    /// Runs the episodes with the policy `policy` returns for the seed of each one, or the mode
+    /// of the options for `None`.
+    ///
+    /// # Errors
+    /// Returns an error if a planet can not be created or stops answering.
+    pub fn run(
+        &self,
+        mut policy: impl FnMut(u64) -> Option<Box<dyn DecisionPolicy>>,
+    ) -> Result<SimulationReport, String> {
+        let resource = *self
+            .options
+            .gen_rules
+            .first()
+            .ok_or("The simulated planet generates no resource")?;
+        let resource = format!("{resource:?}");
+
+        let mut report = SimulationReport::default();
+        for episode in 0..self.episodes {
+            let seed = self.seed.wrapping_add(episode as u64);
+            let (steps, served, destroyed) = self.episode(seed, policy(seed), &resource)?;
+            report.episodes += 1;
+            report.steps += steps;
+            report.served += served;
+            report.destroyed += usize::from(destroyed);
+        }
+        Ok(report)
+    }
+
+    // Returns the steps played, the requests served and whether the planet was destroyed
+    fn episode(
+        &self,
+        seed: u64,
+        policy: Option<Box<dyn DecisionPolicy>>,
+        resource: &str,
+    ) -> Result<(u64, u64, bool), String> {
+        let traffic = &self.traffic;
+        let mut rng = StdRng::seed_from_u64(seed);
+        let clock = MockClock::new();
+        let mut ai = self.options.create_ai().with_clock(Arc::new(clock.clone()));
+        if let Some(policy) = policy {
+            ai = ai.with_boxed_decision_policy(policy);
+        }
+        let mut bridge = PlanetBridge::spawn(|orchestrator_channels, explorers_receiver| {
+            self.options
+                .create_planet_with_ai(ai, orchestrator_channels, explorers_receiver)
+        })?;
+
+        send(&mut bridge, "start", None)?;
+        let mut explorers: Vec<ID> = Vec::new();
+        let mut next_id: ID = 1;
+        for _ in 0..traffic.explorers {
+            send(&mut bridge, "explorer_arrival", Some(next_id))?;
+            explorers.push(next_id);
+            next_id += 1;
+        }
+
+        let mut steps = 0;
+        let mut served = 0;
+        let mut destroyed = false;
+        while steps < self.max_steps && !destroyed {
+            steps += 1;
+            clock.advance(traffic.step);
+            let draw: f64 = rng.random();
+            if draw < traffic.sunray {
+                send(&mut bridge, "sunray", None)?;
+            } else if draw < traffic.sunray + traffic.asteroid {
+                let ack = send(&mut bridge, "asteroid", None)?;
+                destroyed = ack.get("rocket").and_then(Json::as_bool) == Some(false);
+            } else if !explorers.is_empty() {
+                if rng.random::<f64>() < traffic.churn {
+                    let leaving = explorers.swap_remove(rng.random_range(0..explorers.len()));
+                    send(&mut bridge, "explorer_departure", Some(leaving))?;
+                    send(&mut bridge, "explorer_arrival", Some(next_id))?;
+                    explorers.push(next_id);
+                    next_id += 1;
+                }
+                let explorer_id = explorers[rng.random_range(0..explorers.len())];
+                let command = Json::object([
+                    ("type", Json::from("generate_resource")),
+                    ("explorer_id", explorer_id.into()),
+                    ("resource", resource.into()),
+                ]);
+                // Accepted requests can still fail to craft, only count the resources given
+                let response = request(&mut bridge, &command)?;
+                if response
+                    .get("resource")
+                    .is_some_and(|resource| *resource != Json::Null)
+                {
+                    served += 1;
+                }
+            }
+        }
+
+        Ok((steps as u64, served, destroyed))
+    }
+}
+
+// Sends a command of type `kind`, with the explorer it is about
+fn send(bridge: &mut PlanetBridge, kind: &str, explorer_id: Option<ID>) -> Result<Json, String> {
+    let mut fields = vec![("type", Json::from(kind))];
+    if let Some(explorer_id) = explorer_id {
+        fields.push(("explorer_id", explorer_id.into()));
+    }
+    request(bridge, &Json::object(fields))
+}
+
+fn request(bridge: &mut PlanetBridge, command: &Json) -> Result<Json, String> {
+    let response = Json::parse(&bridge.handle_line(&command.to_string()))?;
+    if response.get("type").and_then(Json::as_str) == Some("error") {
+        return Err(response
+            .str_field("message")
+            .unwrap_or("Invalid response")
+            .to_string());
+    }
+    Ok(response)
+}
+
+impl SimulationReport {
+    /// Requests served per episode, see [`served`](Self::served).
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn served_per_episode(&self) -> f64 {
+        if self.episodes == 0 {
+            0.0
+        } else {
+            self.served as f64 / self.episodes as f64
+        }
+    }
+}
+
+impl Display for SimulationReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} episodes, {} steps: {} requests served ({:.2} per episode), {} planets destroyed",
+            self.episodes,
+            self.steps,
+            self.served,
+            self.served_per_episode(),
+            self.destroyed
+        )
+    }
+}
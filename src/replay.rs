@@ -0,0 +1,1011 @@
+//! Recording and replay of planet sessions.
+//!
+//! An [`Ai`] built with [`Ai::with_session_recording`] writes every input delivered to its
+//! `PlanetAI` callbacks, with the time it was received, and every decision it took to a
+//! JSON lines file. [`replay`] feeds the inputs of such a file to a fresh [`Ai`] driven by a
+//! [`MockClock`] and seeded with the recorded seed, and reports the first decision that
+//! differs from the recorded one.
+//!
+//! The first line of a session file describes the AI configuration and the planet, every
+//! following line is either an input (`"event":"input"`, with the time `t_ns` elapsed since
+//! the planet was started) or a decision (`"event":"decision"`).
+
+use crate::ai::{
+    Ai, AiSetting, Checkpoint, DecisionOutcome, DecisionPolicy, DecisionReason, DecisionRecord,
+    DecisionRequest,
+};
+use crate::clock::MockClock;
+use crate::frequency_counter::PauseMode;
+use crate::json::Json;
+use crate::logging::LogConfig;
+use crate::priors::EstimatorPrior;
+use crate::rules;
+use common_game::components::energy_cell::EnergyCell;
+use common_game::components::planet::{
+    DummyPlanetState, Planet, PlanetAI, PlanetState, PlanetType,
+};
+use common_game::components::resource::{
+    BasicResource, BasicResourceType, Combinator, ComplexResource, ComplexResourceRequest,
+    ComplexResourceType, Generator,
+};
+use common_game::components::rocket::Rocket;
+use common_game::components::sunray::Sunray;
+use common_game::protocols::orchestrator_planet::{OrchestratorToPlanet, PlanetToOrchestrator};
+use common_game::protocols::planet_explorer::{ExplorerToPlanet, PlanetToExplorer};
+use common_game::utils::ID;
+use crossbeam_channel::Receiver;
+use std::fmt::{self, Display, Formatter};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, LineWriter, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const VERSION: f64 = 1.0;
+
+// Tolerance on the recorded probabilities, which are rounded by the file format
+const PROBABILITY_TOLERANCE: f32 = 1e-4;
+
+/// AI configuration and planet a session was recorded with.
+#[derive(Debug, Clone)]
+pub struct SessionConfig {
+    pub random_mode: bool,
+    pub basic_gen_coeff: f32,
+    pub complex_gen_coeff: f32,
+    pub half_life: Duration,
+    pub min_time_constant: Duration,
+    pub defense_window: Duration,
+    pub seed: u64,
+    pub planet_id: ID,
+    pub planet_type: PlanetType,
+    pub gen_rules: Vec<BasicResourceType>,
+    pub comb_rules: Vec<ComplexResourceType>,
+    pub prior: Option<EstimatorPrior>,
+    pub pause_mode: PauseMode,
+    /// Name of the policy given to [`Ai::with_decision_policy`], if any
+    pub policy: Option<String>,
+    /// Learned state restored at the first start, from [`Ai::with_checkpoint_file`]
+    pub checkpoint: Option<Checkpoint>,
+}
+
+/// Explorer message, without the resources it carries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionRequest {
+    SupportedResource,
+    SupportedCombination,
+    GenerateResource(BasicResourceType),
+    CombineResource(ComplexResourceType),
+    AvailableEnergyCell,
+}
+
+/// Input delivered to a `PlanetAI` callback.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SessionInput {
+    Start,
+    Stop,
+    Sunray,
+    Asteroid,
+    InternalState,
+    ExplorerArrival(ID),
+    ExplorerDeparture(ID),
+    ExplorerMessage(ID, SessionRequest),
+    /// Setting changed through an [`AiControl`](crate::AiControl)
+    Reconfigure(AiSetting),
+}
+
+/// Recorded decision, compared during a replay.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordedDecision {
+    pub explorer_id: ID,
+    pub request: DecisionRequest,
+    pub outcome: DecisionOutcome,
+    pub reason: DecisionReason,
+    pub cell: Option<usize>,
+    pub p_sunray: Option<f32>,
+    pub random_sample: Option<f32>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SessionEntry {
+    Input {
+        /// Time elapsed since the planet was started
+        elapsed: Duration,
+        input: SessionInput,
+    },
+    Decision(RecordedDecision),
+}
+
+/// A recorded session, see the [module documentation](self).
+#[derive(Debug, Clone)]
+pub struct Session {
+    pub config: SessionConfig,
+    pub entries: Vec<SessionEntry>,
+}
+
+/// First decision of a replay that differs from the recording.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Divergence {
+    /// Index of the decision in the session
+    pub decision: usize,
+    /// Index of the input the decision was taken on
+    pub input: usize,
+    pub elapsed: Duration,
+    /// `None` if the replay took more decisions than the recording
+    pub expected: Option<RecordedDecision>,
+    /// `None` if the replay took fewer decisions than the recording
+    pub actual: Option<RecordedDecision>,
+}
+
+/// Result of a replay.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplayReport {
+    pub inputs: usize,
+    pub decisions: usize,
+    pub divergence: Option<Divergence>,
+}
+
+impl RecordedDecision {
+    /// Whether `other` is the same decision, up to the rounding of the probabilities.
+    #[must_use]
+    pub fn matches(&self, other: &RecordedDecision) -> bool {
+        let close = |a: Option<f32>, b: Option<f32>| match (a, b) {
+            (Some(a), Some(b)) => (a - b).abs() <= PROBABILITY_TOLERANCE,
+            (a, b) => a.is_none() && b.is_none(),
+        };
+        self.explorer_id == other.explorer_id
+            && self.request == other.request
+            && self.outcome == other.outcome
+            && self.reason == other.reason
+            && self.cell == other.cell
+            && close(self.p_sunray, other.p_sunray)
+            && close(self.random_sample, other.random_sample)
+    }
+
+    pub(crate) fn to_json(&self) -> Json {
+        Json::object([
+            ("event", Json::from("decision")),
+            ("explorer_id", self.explorer_id.into()),
+            ("request", self.request.label().into()),
+            ("resource", self.request.resource().into()),
+            ("outcome", self.outcome.label().into()),
+            ("reason", self.reason.label().into()),
+            ("cell", self.cell.into()),
+            ("p_sunray", self.p_sunray.into()),
+            ("random_sample", self.random_sample.into()),
+        ])
+    }
+
+    pub(crate) fn from_json(line: &Json) -> Result<Self, String> {
+        let request =
+            DecisionRequest::from_labels(line.str_field("request")?, line.str_field("resource")?)
+                .ok_or("Invalid decision request")?;
+        Ok(Self {
+            explorer_id: id_field(line, "explorer_id")?,
+            request,
+            outcome: DecisionOutcome::from_label(line.str_field("outcome")?)
+                .ok_or("Invalid decision outcome")?,
+            reason: DecisionReason::from_label(line.str_field("reason")?)
+                .ok_or("Invalid decision reason")?,
+            cell: optional_f64(line, "cell").map(float_to_usize),
+            #[allow(clippy::cast_possible_truncation)]
+            p_sunray: optional_f64(line, "p_sunray").map(|p| p as f32),
+            #[allow(clippy::cast_possible_truncation)]
+            random_sample: optional_f64(line, "random_sample").map(|p| p as f32),
+        })
+    }
+}
+
+impl From<&DecisionRecord> for RecordedDecision {
+    fn from(record: &DecisionRecord) -> Self {
+        Self {
+            explorer_id: record.explorer_id,
+            request: record.request,
+            outcome: record.outcome,
+            reason: record.reason,
+            cell: record.cell,
+            p_sunray: record.inputs.p_sunray,
+            random_sample: record.inputs.random_sample,
+        }
+    }
+}
+
+impl Display for RecordedDecision {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "explorer {} {} {}: {} ({})",
+            self.explorer_id,
+            self.request.label(),
+            self.request.resource(),
+            self.outcome.label(),
+            self.reason.label()
+        )?;
+        if let Some(cell) = self.cell {
+            write!(f, ", cell {cell}")?;
+        }
+        if let Some(p_sunray) = self.p_sunray {
+            write!(f, ", p_sunray {p_sunray:.6}")?;
+        }
+        if let Some(sample) = self.random_sample {
+            write!(f, ", random_sample {sample:.6}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Divergence {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "decision #{} diverged on input #{} at {:.6}s",
+            self.decision,
+            self.input,
+            self.elapsed.as_secs_f64()
+        )?;
+        let describe = |decision: &Option<RecordedDecision>| {
+            decision
+                .as_ref()
+                .map_or_else(|| "no decision".to_string(), ToString::to_string)
+        };
+        writeln!(f, "  expected: {}", describe(&self.expected))?;
+        write!(f, "  actual:   {}", describe(&self.actual))
+    }
+}
+
+impl ReplayReport {
+    /// Whether every decision matched the recording.
+    #[must_use]
+    pub fn is_match(&self) -> bool {
+        self.divergence.is_none()
+    }
+}
+
+impl SessionRequest {
+    pub(crate) fn from_message(msg: &ExplorerToPlanet) -> Self {
+        match msg {
+            ExplorerToPlanet::SupportedResourceRequest { .. } => SessionRequest::SupportedResource,
+            ExplorerToPlanet::SupportedCombinationRequest { .. } => {
+                SessionRequest::SupportedCombination
+            }
+            ExplorerToPlanet::GenerateResourceRequest { resource, .. } => {
+                SessionRequest::GenerateResource(*resource)
+            }
+            ExplorerToPlanet::CombineResourceRequest { msg, .. } => {
+                SessionRequest::CombineResource(crate::ai::requested_recipe(msg))
+            }
+            ExplorerToPlanet::AvailableEnergyCellRequest { .. } => {
+                SessionRequest::AvailableEnergyCell
+            }
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            SessionRequest::SupportedResource => "supported_resource",
+            SessionRequest::SupportedCombination => "supported_combination",
+            SessionRequest::GenerateResource(_) => "generate_resource",
+            SessionRequest::CombineResource(_) => "combine_resource",
+            SessionRequest::AvailableEnergyCell => "available_energy_cell",
+        }
+    }
+}
+
+impl SessionInput {
+    fn to_json(self, elapsed: Duration) -> Json {
+        let label = match self {
+            SessionInput::Start => "start",
+            SessionInput::Stop => "stop",
+            SessionInput::Sunray => "sunray",
+            SessionInput::Asteroid => "asteroid",
+            SessionInput::InternalState => "internal_state",
+            SessionInput::ExplorerArrival(_) => "explorer_arrival",
+            SessionInput::ExplorerDeparture(_) => "explorer_departure",
+            SessionInput::ExplorerMessage(..) => "explorer_message",
+            SessionInput::Reconfigure(_) => "reconfigure",
+        };
+        let mut fields = vec![
+            ("event", Json::from("input")),
+            ("t_ns", Json::nanos(elapsed)),
+            ("input", label.into()),
+        ];
+        match self {
+            SessionInput::ExplorerArrival(explorer_id)
+            | SessionInput::ExplorerDeparture(explorer_id) => {
+                fields.push(("explorer_id", explorer_id.into()));
+            }
+            SessionInput::ExplorerMessage(explorer_id, request) => {
+                fields.push(("explorer_id", explorer_id.into()));
+                fields.push(("request", request.label().into()));
+                match request {
+                    SessionRequest::GenerateResource(resource) => {
+                        fields.push(("resource", format!("{resource:?}").into()));
+                    }
+                    SessionRequest::CombineResource(resource) => {
+                        fields.push(("resource", format!("{resource:?}").into()));
+                    }
+                    _ => {}
+                }
+            }
+            SessionInput::Reconfigure(setting) => {
+                fields.push(("setting", setting.name().into()));
+                fields.push((
+                    "value",
+                    match setting {
+                        AiSetting::RandomMode(random_mode) => random_mode.into(),
+                        AiSetting::BasicGenCoeff(coeff) | AiSetting::ComplexGenCoeff(coeff) => {
+                            coeff.into()
+                        }
+                        AiSetting::HalfLife(duration)
+                        | AiSetting::MinTimeConstant(duration)
+                        | AiSetting::DefenseWindow(duration) => Json::nanos(duration),
+                        AiSetting::PauseMode(pause_mode) => pause_mode.label().into(),
+                        AiSetting::LogConfig(config) => config.label().into(),
+                    },
+                ));
+            }
+            _ => {}
+        }
+        Json::object(fields)
+    }
+
+    fn from_json(line: &Json) -> Result<Self, String> {
+        let input = match line.str_field("input")? {
+            "start" => SessionInput::Start,
+            "stop" => SessionInput::Stop,
+            "sunray" => SessionInput::Sunray,
+            "asteroid" => SessionInput::Asteroid,
+            "internal_state" => SessionInput::InternalState,
+            "explorer_arrival" => SessionInput::ExplorerArrival(id_field(line, "explorer_id")?),
+            "explorer_departure" => SessionInput::ExplorerDeparture(id_field(line, "explorer_id")?),
+            "explorer_message" => {
+                let resource = || line.str_field("resource");
+                let request = match line.str_field("request")? {
+                    "supported_resource" => SessionRequest::SupportedResource,
+                    "supported_combination" => SessionRequest::SupportedCombination,
+                    "generate_resource" => SessionRequest::GenerateResource(
+                        rules::basic_from_name(resource()?).ok_or("Invalid basic resource")?,
+                    ),
+                    "combine_resource" => SessionRequest::CombineResource(
+                        rules::complex_from_name(resource()?).ok_or("Invalid complex resource")?,
+                    ),
+                    "available_energy_cell" => SessionRequest::AvailableEnergyCell,
+                    other => return Err(format!("Unknown explorer request {other}")),
+                };
+                SessionInput::ExplorerMessage(id_field(line, "explorer_id")?, request)
+            }
+            "reconfigure" => SessionInput::Reconfigure(setting_field(line)?),
+            other => return Err(format!("Unknown input {other}")),
+        };
+        Ok(input)
+    }
+}
+
+impl SessionConfig {
+    fn to_json(&self) -> Json {
+        let names = |names: Vec<String>| {
+            let mut names = names;
+            names.sort();
+            Json::from(names)
+        };
+        Json::object([
+            ("event", Json::from("session")),
+            ("version", VERSION.into()),
+            ("random_mode", self.random_mode.into()),
+            ("basic_gen_coeff", self.basic_gen_coeff.into()),
+            ("complex_gen_coeff", self.complex_gen_coeff.into()),
+            ("half_life_ns", Json::nanos(self.half_life)),
+            ("min_time_constant_ns", Json::nanos(self.min_time_constant)),
+            ("defense_window_ns", Json::nanos(self.defense_window)),
+            // Seeds do not fit in a JSON number
+            ("seed", self.seed.to_string().into()),
+            ("planet_id", self.planet_id.into()),
+            ("planet_type", format!("{:?}", self.planet_type).into()),
+            (
+                "gen_rules",
+                names(
+                    self.gen_rules
+                        .iter()
+                        .map(|rule| format!("{rule:?}"))
+                        .collect(),
+                ),
+            ),
+            (
+                "comb_rules",
+                names(
+                    self.comb_rules
+                        .iter()
+                        .map(|rule| format!("{rule:?}"))
+                        .collect(),
+                ),
+            ),
+            ("prior", self.prior.map(EstimatorPrior::to_json).into()),
+            ("pause_mode", self.pause_mode.label().into()),
+            ("policy", self.policy.clone().into()),
+            (
+                "checkpoint",
+                self.checkpoint.as_ref().map(Checkpoint::to_json).into(),
+            ),
+        ])
+    }
+
+    fn from_json(line: &Json) -> Result<Self, String> {
+        if line.str_field("event")? != "session" {
+            return Err("Missing session header".to_string());
+        }
+        let version = line.f64_field("version")?;
+        if (version - VERSION).abs() > f64::EPSILON {
+            return Err(format!("Unsupported session version {version}"));
+        }
+
+        let names = |key: &str| -> Result<Vec<&str>, String> {
+            line.get(key)
+                .and_then(Json::as_array)
+                .ok_or(format!("Missing field {key}"))?
+                .iter()
+                .map(|name| name.as_str().ok_or(format!("Invalid field {key}")))
+                .collect()
+        };
+        #[allow(clippy::cast_possible_truncation)]
+        let coeff = |key: &str| line.f64_field(key).map(|coeff| coeff as f32);
+
+        Ok(Self {
+            random_mode: line
+                .get("random_mode")
+                .and_then(Json::as_bool)
+                .ok_or("Missing field random_mode")?,
+            basic_gen_coeff: coeff("basic_gen_coeff")?,
+            complex_gen_coeff: coeff("complex_gen_coeff")?,
+            half_life: line.duration_field("half_life_ns")?,
+            min_time_constant: line.duration_field("min_time_constant_ns")?,
+            defense_window: line.duration_field("defense_window_ns")?,
+            seed: line
+                .str_field("seed")?
+                .parse()
+                .map_err(|_| "Invalid field seed")?,
+            planet_id: id_field(line, "planet_id")?,
+            planet_type: rules::planet_type_from_name(line.str_field("planet_type")?)
+                .ok_or("Invalid planet type")?,
+            gen_rules: names("gen_rules")?
+                .into_iter()
+                .map(|name| rules::basic_from_name(name).ok_or(format!("Invalid rule {name}")))
+                .collect::<Result<_, _>>()?,
+            comb_rules: names("comb_rules")?
+                .into_iter()
+                .map(|name| rules::complex_from_name(name).ok_or(format!("Invalid rule {name}")))
+                .collect::<Result<_, _>>()?,
+            prior: match line.get("prior") {
+                None | Some(Json::Null) => None,
+                Some(prior) => Some(EstimatorPrior::from_json(prior)?),
+            },
+            // Sessions recorded before pause modes were frozen
+            pause_mode: match line.get("pause_mode").and_then(Json::as_str) {
+                None => PauseMode::Freeze,
+                Some(label) => {
+                    PauseMode::from_label(label).ok_or(format!("Invalid pause mode {label}"))?
+                }
+            },
+            policy: line.get("policy").and_then(Json::as_str).map(String::from),
+            checkpoint: match line.get("checkpoint") {
+                None | Some(Json::Null) => None,
+                Some(checkpoint) => Some(Checkpoint::from_json(checkpoint)?),
+            },
+        })
+    }
+}
+
+impl Session {
+    /// Loads a session file.
+    ///
+    /// # Errors
+    /// Returns an error if the file can not be read or is not a valid session.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let file = File::open(path.as_ref()).map_err(|err| err.to_string())?;
+        Self::read(BufReader::new(file))
+    }
+
+    /// Reads a session from JSON lines.
+    ///
+    /// # Errors
+    /// Returns an error if a line is not valid, with its line number.
+    pub fn read(reader: impl BufRead) -> Result<Self, String> {
+        let mut lines = reader
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| line.as_ref().map_or(true, |line| !line.trim().is_empty()));
+        let parse = |(number, line): (usize, io::Result<String>)| {
+            line.map_err(|err| err.to_string())
+                .and_then(|line| Json::parse(&line))
+                .map_err(|err| format!("line {}: {err}", number + 1))
+        };
+
+        let header = lines.next().ok_or("Empty session")?;
+        let config =
+            SessionConfig::from_json(&parse(header)?).map_err(|err| format!("line 1: {err}"))?;
+
+        let mut entries = Vec::new();
+        for (number, line) in lines {
+            let json = parse((number, line))?;
+            let entry = match json.str_field("event") {
+                Ok("input") => json.duration_field("t_ns").and_then(|elapsed| {
+                    SessionInput::from_json(&json)
+                        .map(|input| SessionEntry::Input { elapsed, input })
+                }),
+                Ok("decision") => RecordedDecision::from_json(&json).map(SessionEntry::Decision),
+                Ok(other) => Err(format!("Unknown event {other}")),
+                Err(err) => Err(err),
+            };
+            entries.push(entry.map_err(|err| format!("line {}: {err}", number + 1))?);
+        }
+
+        Ok(Self { config, entries })
+    }
+}
+
+/// Writer of the session file of an [`Ai`].
+pub(crate) struct SessionWriter {
+    file: LineWriter<File>,
+    started: Option<Instant>,
+}
+
+impl SessionWriter {
+    pub fn create(path: &Path) -> Result<Self, String> {
+        let file = File::create(path)
+            .map_err(|err| format!("Can not create session file {}: {err}", path.display()))?;
+        Ok(Self {
+            file: LineWriter::new(file),
+            started: None,
+        })
+    }
+
+    pub fn is_started(&self) -> bool {
+        self.started.is_some()
+    }
+
+    /// Writes the header, inputs are timed from `now`.
+    pub fn start(&mut self, now: Instant, config: &SessionConfig) -> io::Result<()> {
+        self.started = Some(now);
+        writeln!(self.file, "{}", config.to_json())
+    }
+
+    pub fn input(&mut self, now: Instant, input: SessionInput) -> io::Result<()> {
+        let elapsed = self.started.map_or(Duration::ZERO, |started| {
+            now.saturating_duration_since(started)
+        });
+        writeln!(self.file, "{}", input.to_json(elapsed))
+    }
+
+    pub fn decision(&mut self, decision: &RecordedDecision) -> io::Result<()> {
+        writeln!(self.file, "{}", decision.to_json())
+    }
+}
+
+/// Replays `session` against a fresh AI and compares its decisions with the recorded ones.
+///
+/// # Errors
+/// Returns an error if the recorded planet can not be created or the replay can not run, or
+/// the session was recorded with a custom policy, see [`replay_with_policy`].
+pub fn replay(session: &Session) -> Result<ReplayReport, String> {
+    if let Some(name) = &session.config.policy {
+        return Err(format!(
+            "The session was recorded with the {name} policy, replay it with that policy"
+        ));
+    }
+    run_replay(session, None)
+}
+
+/// Replays `session` like [`replay`], deciding with `policy`. It must be in the state the
+/// recorded policy started in, e.g. the same table for a learning policy.
+///
+/// # Errors
+/// Returns an error if the recorded planet can not be created or the replay can not run.
+pub fn replay_with_policy(
+    session: &Session,
+    policy: impl DecisionPolicy + 'static,
+) -> Result<ReplayReport, String> {
+    run_replay(session, Some(Box::new(policy)))
+}
+
+fn run_replay(
+    session: &Session,
+    policy: Option<Box<dyn DecisionPolicy>>,
+) -> Result<ReplayReport, String> {
+    let config = &session.config;
+    let clock = MockClock::new();
+    let (tx_decisions, rx_decisions) = crossbeam_channel::unbounded();
+
+    let mut ai = Ai::new(
+        config.random_mode,
+        config.basic_gen_coeff,
+        config.complex_gen_coeff,
+        config.half_life,
+        config.min_time_constant,
+    );
+    if let Some(prior) = config.prior {
+        ai = ai.with_estimator_prior(prior);
+    }
+    if let Some(policy) = policy {
+        ai = ai.with_boxed_decision_policy(policy);
+    }
+    if let Some(checkpoint) = &config.checkpoint {
+        ai = ai.with_restored_checkpoint(checkpoint.clone());
+    }
+    let ai = ai
+        .with_pause_mode(config.pause_mode)
+        .with_defense_reservation(config.defense_window)
+        .with_clock(Arc::new(clock.clone()))
+        .with_seed(config.seed)
+        .with_decision_sink(tx_decisions);
+
+    let report = Arc::new(Mutex::new(None));
+    let driver = ReplayDriver {
+        ai,
+        clock,
+        decisions: rx_decisions,
+        entries: session.entries.clone(),
+        ingredients: Ingredients::new()?,
+        report: Arc::clone(&report),
+    };
+
+    // The driver runs the whole session inside the planet, where it has access to the state
+    let (tx_orchestrator, rx_orchestrator) = crossbeam_channel::bounded(1);
+    let (tx_planet, rx_planet) = crossbeam_channel::bounded(1);
+    let (_tx_explorer, rx_explorer) = crossbeam_channel::bounded(1);
+    let mut planet = Planet::new(
+        config.planet_id,
+        config.planet_type,
+        Box::new(driver),
+        config.gen_rules.clone(),
+        config.comb_rules.clone(),
+        (rx_orchestrator, tx_planet),
+        rx_explorer,
+    )?;
+    let handle = thread::spawn(move || planet.run());
+
+    let disconnected = "Replay planet disconnected".to_string();
+    for msg in [
+        OrchestratorToPlanet::StartPlanetAI,
+        OrchestratorToPlanet::InternalStateRequest,
+        OrchestratorToPlanet::KillPlanet,
+    ] {
+        tx_orchestrator
+            .send(msg)
+            .map_err(|_| disconnected.clone())?;
+        let _: PlanetToOrchestrator = rx_planet.recv().map_err(|_| disconnected.clone())?;
+    }
+    handle
+        .join()
+        .map_err(|_| "Replay planet panicked".to_string())??;
+
+    report
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .take()
+        .ok_or_else(|| "Replay did not run".to_string())?
+}
+
+/// `PlanetAI` running a recorded session on its inner AI.
+struct ReplayDriver {
+    ai: Ai,
+    clock: MockClock,
+    decisions: Receiver<DecisionRecord>,
+    entries: Vec<SessionEntry>,
+    ingredients: Ingredients,
+    report: Arc<Mutex<Option<Result<ReplayReport, String>>>>,
+}
+
+// (input index, elapsed, decision)
+type TimedDecision = (usize, Duration, RecordedDecision);
+
+impl ReplayDriver {
+    fn run(
+        &mut self,
+        state: &mut PlanetState,
+        generator: &Generator,
+        combinator: &Combinator,
+    ) -> Result<ReplayReport, String> {
+        let mut expected: Vec<TimedDecision> = Vec::new();
+        let mut actual: Vec<TimedDecision> = Vec::new();
+        let mut inputs = 0;
+        let mut last_elapsed = Duration::ZERO;
+
+        for entry in std::mem::take(&mut self.entries) {
+            match entry {
+                SessionEntry::Input { elapsed, input } => {
+                    self.clock.set_elapsed(elapsed);
+                    self.deliver(state, generator, combinator, input)?;
+                    actual.extend(
+                        self.decisions
+                            .try_iter()
+                            .map(|record| (inputs, elapsed, RecordedDecision::from(&record))),
+                    );
+                    inputs += 1;
+                    last_elapsed = elapsed;
+                }
+                SessionEntry::Decision(decision) => {
+                    expected.push((inputs.saturating_sub(1), last_elapsed, decision));
+                }
+            }
+        }
+
+        let divergence = (0..expected.len().max(actual.len())).find_map(|i| {
+            let (expected, actual) = (expected.get(i), actual.get(i));
+            let same = match (expected, actual) {
+                (Some((e_input, _, e)), Some((a_input, _, a))) => {
+                    e_input == a_input && e.matches(a)
+                }
+                _ => false,
+            };
+            let (input, elapsed, _) = expected.or(actual)?;
+            (!same).then(|| Divergence {
+                decision: i,
+                input: *input,
+                elapsed: *elapsed,
+                expected: expected.map(|(_, _, decision)| decision.clone()),
+                actual: actual.map(|(_, _, decision)| decision.clone()),
+            })
+        });
+
+        Ok(ReplayReport {
+            inputs,
+            decisions: expected.len(),
+            divergence,
+        })
+    }
+
+    fn deliver(
+        &mut self,
+        state: &mut PlanetState,
+        generator: &Generator,
+        combinator: &Combinator,
+        input: SessionInput,
+    ) -> Result<(), String> {
+        let ai = &mut self.ai;
+        match input {
+            SessionInput::Start => ai.on_start(state, generator, combinator),
+            SessionInput::Stop => ai.on_stop(state, generator, combinator),
+            SessionInput::Sunray => {
+                ai.handle_sunray(state, generator, combinator, Sunray::default());
+            }
+            SessionInput::Asteroid => {
+                let _ = ai.handle_asteroid(state, generator, combinator);
+            }
+            SessionInput::InternalState => {
+                let _ = ai.handle_internal_state_req(state, generator, combinator);
+            }
+            SessionInput::ExplorerArrival(explorer_id) => {
+                ai.on_explorer_arrival(state, generator, combinator, explorer_id);
+            }
+            SessionInput::ExplorerDeparture(explorer_id) => {
+                ai.on_explorer_departure(state, generator, combinator, explorer_id);
+            }
+            SessionInput::Reconfigure(setting) => ai.apply_setting(state, setting),
+            SessionInput::ExplorerMessage(explorer_id, request) => {
+                let msg = match request {
+                    SessionRequest::SupportedResource => {
+                        ExplorerToPlanet::SupportedResourceRequest { explorer_id }
+                    }
+                    SessionRequest::SupportedCombination => {
+                        ExplorerToPlanet::SupportedCombinationRequest { explorer_id }
+                    }
+                    SessionRequest::GenerateResource(resource) => {
+                        ExplorerToPlanet::GenerateResourceRequest {
+                            explorer_id,
+                            resource,
+                        }
+                    }
+                    SessionRequest::CombineResource(resource) => {
+                        ExplorerToPlanet::CombineResourceRequest {
+                            explorer_id,
+                            msg: self.ingredients.request(resource)?,
+                        }
+                    }
+                    SessionRequest::AvailableEnergyCell => {
+                        ExplorerToPlanet::AvailableEnergyCellRequest { explorer_id }
+                    }
+                };
+                let _: Option<PlanetToExplorer> =
+                    ai.handle_explorer_msg(state, generator, combinator, msg);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl PlanetAI for ReplayDriver {
+    fn handle_explorer_msg(
+        &mut self,
+        _state: &mut PlanetState,
+        _generator: &Generator,
+        _combinator: &Combinator,
+        _msg: ExplorerToPlanet,
+    ) -> Option<PlanetToExplorer> {
+        None
+    }
+
+    fn handle_asteroid(
+        &mut self,
+        _state: &mut PlanetState,
+        _generator: &Generator,
+        _combinator: &Combinator,
+    ) -> Option<Rocket> {
+        None
+    }
+
+    fn handle_sunray(
+        &mut self,
+        _state: &mut PlanetState,
+        _generator: &Generator,
+        _combinator: &Combinator,
+        _sunray: Sunray,
+    ) {
+    }
+
+    fn handle_internal_state_req(
+        &mut self,
+        state: &mut PlanetState,
+        generator: &Generator,
+        combinator: &Combinator,
+    ) -> DummyPlanetState {
+        let report = self.run(state, generator, combinator);
+        *self.report.lock().unwrap_or_else(PoisonError::into_inner) = Some(report);
+        state.to_dummy()
+    }
+
+    fn on_explorer_arrival(
+        &mut self,
+        _state: &mut PlanetState,
+        _generator: &Generator,
+        _combinator: &Combinator,
+        _explorer_id: ID,
+    ) {
+    }
+
+    fn on_explorer_departure(
+        &mut self,
+        _state: &mut PlanetState,
+        _generator: &Generator,
+        _combinator: &Combinator,
+        _explorer_id: ID,
+    ) {
+    }
+
+    fn on_start(&mut self, _state: &PlanetState, _generator: &Generator, _combinator: &Combinator) {
+    }
+
+    fn on_stop(&mut self, _state: &PlanetState, _generator: &Generator, _combinator: &Combinator) {}
+}
+
+/// Crafts the ingredients of combination requests. Resources can only be made by a planet,
+/// so two planets that are never run provide every basic and complex recipe.
+struct Ingredients {
+    basic: Planet,
+    complex: Planet,
+}
+
+impl Ingredients {
+    fn new() -> Result<Self, String> {
+        let planet =
+            |planet_type, gen_rules: &[BasicResourceType], comb_rules: &[ComplexResourceType]| {
+                let (_, rx_orchestrator) = crossbeam_channel::bounded(1);
+                let (tx_planet, _) = crossbeam_channel::bounded(1);
+                let (_, rx_explorer) = crossbeam_channel::bounded(1);
+                Planet::new(
+                    0,
+                    planet_type,
+                    Box::new(Ai::new(
+                        true,
+                        0.0,
+                        0.0,
+                        Duration::from_secs(1),
+                        Duration::ZERO,
+                    )),
+                    gen_rules.to_vec(),
+                    comb_rules.to_vec(),
+                    (rx_orchestrator, tx_planet),
+                    rx_explorer,
+                )
+            };
+        Ok(Self {
+            basic: planet(PlanetType::D, &rules::BASIC_RESOURCES, &[])?,
+            complex: planet(
+                PlanetType::C,
+                &rules::DEFAULT_GEN_RULES,
+                &rules::DEFAULT_COMB_RULES,
+            )?,
+        })
+    }
+
+    fn charged_cell() -> EnergyCell {
+        let mut cell = EnergyCell::new();
+        cell.charge(Sunray::default());
+        cell
+    }
+
+    fn basic(&self, resource: BasicResourceType) -> Result<BasicResource, String> {
+        self.basic
+            .generator()
+            .try_make(resource, &mut Self::charged_cell())
+    }
+
+    fn complex(&self, resource: ComplexResourceType) -> Result<ComplexResource, String> {
+        self.complex
+            .combinator()
+            .try_make(self.request(resource)?, &mut Self::charged_cell())
+            .map_err(|(err, _, _)| err)
+    }
+
+    /// Builds a combination request for `resource`, with freshly crafted ingredients.
+    fn request(&self, resource: ComplexResourceType) -> Result<ComplexResourceRequest, String> {
+        use BasicResourceType::{Carbon, Hydrogen, Oxygen, Silicon};
+        use ComplexResourceType::{Diamond, Life, Robot, Water};
+
+        Ok(match resource {
+            ComplexResourceType::Water => ComplexResourceRequest::Water(
+                self.basic(Hydrogen)?.to_hydrogen()?,
+                self.basic(Oxygen)?.to_oxygen()?,
+            ),
+            ComplexResourceType::Diamond => ComplexResourceRequest::Diamond(
+                self.basic(Carbon)?.to_carbon()?,
+                self.basic(Carbon)?.to_carbon()?,
+            ),
+            ComplexResourceType::Life => ComplexResourceRequest::Life(
+                self.complex(Water)?.to_water()?,
+                self.basic(Carbon)?.to_carbon()?,
+            ),
+            ComplexResourceType::Robot => ComplexResourceRequest::Robot(
+                self.basic(Silicon)?.to_silicon()?,
+                self.complex(Life)?.to_life()?,
+            ),
+            ComplexResourceType::Dolphin => ComplexResourceRequest::Dolphin(
+                self.complex(Water)?.to_water()?,
+                self.complex(Life)?.to_life()?,
+            ),
+            ComplexResourceType::AIPartner => ComplexResourceRequest::AIPartner(
+                self.complex(Robot)?.to_robot()?,
+                self.complex(Diamond)?.to_diamond()?,
+            ),
+        })
+    }
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn setting_field(line: &Json) -> Result<AiSetting, String> {
+    let coeff = || line.f64_field("value").map(|coeff| coeff as f32);
+    let duration = || line.duration_field("value");
+    let setting = match line.str_field("setting")? {
+        "random_mode" => AiSetting::RandomMode(
+            line.get("value")
+                .and_then(Json::as_bool)
+                .ok_or("Missing field value")?,
+        ),
+        "basic_gen_coeff" => AiSetting::BasicGenCoeff(coeff()?),
+        "complex_gen_coeff" => AiSetting::ComplexGenCoeff(coeff()?),
+        "half_life" => AiSetting::HalfLife(duration()?),
+        "min_time_constant" => AiSetting::MinTimeConstant(duration()?),
+        "defense_window" => AiSetting::DefenseWindow(duration()?),
+        "pause_mode" => {
+            let label = line.str_field("value")?;
+            AiSetting::PauseMode(
+                PauseMode::from_label(label).ok_or(format!("Invalid pause mode {label}"))?,
+            )
+        }
+        "log_config" => AiSetting::LogConfig(LogConfig::from_label(line.str_field("value")?)?),
+        other => return Err(format!("Unknown setting {other}")),
+    };
+    setting.validate()
+}
+
+fn optional_f64(line: &Json, key: &str) -> Option<f64> {
+    line.get(key).and_then(Json::as_f64)
+}
+
+fn id_field(line: &Json, key: &str) -> Result<ID, String> {
+    let id = line.f64_field(key)?;
+    ID::try_from(float_to_usize(id)).map_err(|_| format!("Invalid field {key}"))
+}
+
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn float_to_usize(value: f64) -> usize {
+    value.max(0.0) as usize
+}
@@ -0,0 +1,563 @@
+//! Session reports built from the log output of planets.
+//!
+//! A [`ReportBuilder`] reads the `LogEvent` lines written by the AI, in their `Debug` or
+//! `Display` format, and builds a [`SessionReport`] per planet: the timeline of the threat
+//! estimate (from the `update_sunray_counter`/`update_asteroid_counter` actions), the
+//! accept/deny counts per explorer and per recipe (from the `decision` actions), the rocket
+//! launches and missed asteroids, and the time spent without a charged energy cell (from
+//! the `charged_cells` of the `SunrayAck` messages, `energy_consumed` actions and
+//! decisions). Log timestamps are in seconds, and so are the report times.
+//!
+//! Reports are printed as text with their `Display` implementation or written as CSV
+//! tables with [`write_csv`].
+
+use crate::dashboard::ThreatLevel;
+use common_game::utils::ID;
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::io::BufRead;
+use std::path::Path;
+
+/// Estimate of the threat after a sunray or an asteroid.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThreatSample {
+    /// Seconds since the first event of the planet
+    pub time: u64,
+    /// `"sunray"` or `"asteroid"`
+    pub event: &'static str,
+    pub sunray_probability: f32,
+}
+
+/// Outcomes of the generation and combination requests of an explorer or a recipe.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DecisionTally {
+    pub accepted: u64,
+    pub denied: u64,
+    pub unsupported: u64,
+    pub failed: u64,
+}
+
+/// How an asteroid was met.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AsteroidOutcome {
+    /// A rocket built beforehand was launched
+    LaunchedExisting,
+    /// A rocket was built from a charged cell and launched
+    BuiltAndLaunched,
+    /// No rocket could be launched
+    Missed,
+    /// The AI was stopped, no rocket was launched
+    MissedWhileStopped,
+}
+
+/// Report of one planet, see the [module documentation](self).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SessionReport {
+    /// `None` for events without a sender
+    pub planet_id: Option<ID>,
+    /// Unix time of the first and last events
+    pub start: u64,
+    pub end: u64,
+    pub threat: Vec<ThreatSample>,
+    pub explorers: BTreeMap<ID, DecisionTally>,
+    /// Keyed by request and resource, e.g. `generate_resource Carbon`
+    pub recipes: BTreeMap<String, DecisionTally>,
+    /// Seconds since the first event, and outcome of every asteroid
+    pub asteroids: Vec<(u64, AsteroidOutcome)>,
+    /// Seconds the AI was running without a charged cell
+    pub empty_cell_secs: u64,
+    /// Seconds the AI was running
+    pub running_secs: u64,
+}
+
+/// Reads planet logs into [`SessionReport`]s.
+#[derive(Debug, Clone, Default)]
+pub struct ReportBuilder {
+    planets: BTreeMap<Option<ID>, PlanetLog>,
+}
+
+// Report being built, with the state needed to follow the charged cells
+#[derive(Debug, Clone, Default)]
+struct PlanetLog {
+    report: SessionReport,
+    events: usize,
+    // Unix time the AI was started at, `None` while stopped
+    running_since: Option<u64>,
+    // Unknown until the planet is started or the log tells
+    charged_cells: Option<usize>,
+    empty_since: Option<u64>,
+}
+
+/// `LogEvent` line of the log output.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct LogLine {
+    pub timestamp: u64,
+    pub planet_id: Option<ID>,
+    pub event_type: String,
+    pub payload: BTreeMap<String, String>,
+}
+
+impl LogLine {
+    /// Parses the `Debug` or `Display` format of a `LogEvent` anywhere in `line`, `None` if
+    /// there is none.
+    pub(crate) fn parse(line: &str) -> Option<Self> {
+        let event = &line[line.find("LogEvent {")?..];
+        let timestamp =
+            number_after(event, "timestamp_unix: ").or_else(|| number_after(event, "ts: "))?;
+        let planet_id = number_after(event, "sender: Some(Participant { actor_type: Planet, id: ")
+            .or_else(|| number_after(event, "sender: Planet#"))
+            .and_then(|id| ID::try_from(id).ok());
+        let event_type = word_after(event, "event_type: ")
+            .or_else(|| word_after(event, "event: "))?
+            .to_string();
+        let payload = parse_payload(&event[event.find("payload: {")? + "payload: ".len()..])?;
+        Some(Self {
+            timestamp,
+            planet_id,
+            event_type,
+            payload,
+        })
+    }
+
+    pub(crate) fn get(&self, key: &str) -> Option<&str> {
+        self.payload.get(key).map(String::as_str)
+    }
+}
+
+impl DecisionTally {
+    /// Share of the accepted requests among the accepted and denied ones, `None` before any.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn accept_ratio(&self) -> Option<f64> {
+        let decided = self.accepted + self.denied;
+        (decided > 0).then(|| self.accepted as f64 / decided as f64)
+    }
+
+    fn add(&mut self, outcome: &str) {
+        match outcome {
+            "accepted" => self.accepted += 1,
+            "denied" => self.denied += 1,
+            "unsupported" => self.unsupported += 1,
+            _ => self.failed += 1,
+        }
+    }
+}
+
+impl AsteroidOutcome {
+    #[must_use]
+    pub fn label(self) -> &'static str {
+        match self {
+            AsteroidOutcome::LaunchedExisting => "launched_existing",
+            AsteroidOutcome::BuiltAndLaunched => "built_and_launched",
+            AsteroidOutcome::Missed => "missed",
+            AsteroidOutcome::MissedWhileStopped => "missed_while_stopped",
+        }
+    }
+
+    #[must_use]
+    pub fn is_launch(self) -> bool {
+        matches!(
+            self,
+            AsteroidOutcome::LaunchedExisting | AsteroidOutcome::BuiltAndLaunched
+        )
+    }
+}
+
+impl SessionReport {
+    pub fn rocket_launches(&self) -> impl Iterator<Item = u64> {
+        self.asteroids
+            .iter()
+            .filter(|(_, outcome)| outcome.is_launch())
+            .map(|(time, _)| *time)
+    }
+
+    pub fn missed_asteroids(&self) -> impl Iterator<Item = u64> {
+        self.asteroids
+            .iter()
+            .filter(|(_, outcome)| !outcome.is_launch())
+            .map(|(time, _)| *time)
+    }
+
+    fn label(&self) -> String {
+        self.planet_id
+            .map_or_else(|| "unknown planet".to_string(), |id| format!("planet {id}"))
+    }
+}
+
+impl ReportBuilder {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the `InternalPlanetAction` and `MessagePlanetTo*` events of a log, returning the
+    /// number found.
+    ///
+    /// # Errors
+    /// Returns an error if the log can not be read.
+    pub fn read_log(&mut self, reader: impl BufRead) -> Result<usize, String> {
+        let mut found = 0;
+        for line in reader.lines() {
+            let line = line.map_err(|err| err.to_string())?;
+            if let Some(line) = LogLine::parse(&line)
+                && (line.event_type == "InternalPlanetAction"
+                    || line.event_type.starts_with("MessagePlanetTo"))
+            {
+                self.planets.entry(line.planet_id).or_default().add(&line);
+                found += 1;
+            }
+        }
+        Ok(found)
+    }
+
+    /// Adds the events of a log file.
+    ///
+    /// # Errors
+    /// Returns an error if the file can not be read.
+    pub fn load(&mut self, path: impl AsRef<Path>) -> Result<usize, String> {
+        let path = path.as_ref();
+        let file = fs::File::open(path).map_err(|err| format!("{}: {err}", path.display()))?;
+        self.read_log(std::io::BufReader::new(file))
+            .map_err(|err| format!("{}: {err}", path.display()))
+    }
+
+    /// Returns the report of every planet found, by planet id.
+    #[must_use]
+    pub fn finish(self) -> Vec<SessionReport> {
+        self.planets.into_values().map(PlanetLog::finish).collect()
+    }
+}
+
+impl PlanetLog {
+    fn add(&mut self, line: &LogLine) {
+        let report = &mut self.report;
+        if self.events == 0 {
+            report.planet_id = line.planet_id;
+            report.start = line.timestamp;
+        }
+        self.events += 1;
+        report.end = report.end.max(line.timestamp);
+        let time = line.timestamp.saturating_sub(report.start);
+        let charged_cells = line.get("charged_cells").and_then(|n| n.parse().ok());
+
+        match (line.get("message"), line.get("action")) {
+            (Some("StartPlanetAIResult"), _) => {
+                self.running_since = Some(line.timestamp);
+                // A new planet starts with its cells empty
+                self.set_charged_cells(line.timestamp, self.charged_cells.or(Some(0)));
+            }
+            (Some("StopPlanetAIResult"), _) => self.stop(line.timestamp),
+            (Some("SunrayAck"), _) => {
+                // Without the count, the sunray charged a cell
+                let charged = charged_cells.or(Some(self.charged_cells.unwrap_or(0).max(1)));
+                self.set_charged_cells(line.timestamp, charged);
+            }
+            (_, Some(action @ ("update_sunray_counter" | "update_asteroid_counter"))) => {
+                if let Some(probability) =
+                    line.get("sunray_probability").and_then(|p| p.parse().ok())
+                {
+                    report.threat.push(ThreatSample {
+                        time,
+                        event: if action == "update_sunray_counter" {
+                            "sunray"
+                        } else {
+                            "asteroid"
+                        },
+                        sunray_probability: probability,
+                    });
+                }
+            }
+            (_, Some("decision")) => {
+                let outcome = line.get("outcome").unwrap_or_default();
+                if let Some(explorer_id) = line.get("explorer_id").and_then(|id| id.parse().ok()) {
+                    report
+                        .explorers
+                        .entry(explorer_id)
+                        .or_default()
+                        .add(outcome);
+                }
+                let recipe = format!(
+                    "{} {}",
+                    line.get("request").unwrap_or("unknown"),
+                    line.get("resource").unwrap_or("unknown")
+                );
+                report.recipes.entry(recipe).or_default().add(outcome);
+                self.set_charged_cells(line.timestamp, charged_cells);
+            }
+            (_, Some("energy_consumed")) => {
+                let charged = charged_cells.or(self.charged_cells.map(|n| n.saturating_sub(1)));
+                self.set_charged_cells(line.timestamp, charged);
+            }
+            (_, Some(action)) => {
+                let outcome = match action {
+                    "launch_existing_rocket" => AsteroidOutcome::LaunchedExisting,
+                    "build_and_launch_rocket" => AsteroidOutcome::BuiltAndLaunched,
+                    "no_rocket_available" => AsteroidOutcome::Missed,
+                    "ignore_asteroid_ai_inactive" => AsteroidOutcome::MissedWhileStopped,
+                    _ => return,
+                };
+                report.asteroids.push((time, outcome));
+            }
+            _ => {}
+        }
+    }
+
+    fn set_charged_cells(&mut self, timestamp: u64, charged_cells: Option<usize>) {
+        let Some(charged_cells) = charged_cells else {
+            return;
+        };
+        self.charged_cells = Some(charged_cells);
+        if self.running_since.is_none() {
+            return;
+        }
+        if charged_cells == 0 {
+            self.empty_since.get_or_insert(timestamp);
+        } else if let Some(since) = self.empty_since.take() {
+            self.report.empty_cell_secs += timestamp.saturating_sub(since);
+        }
+    }
+
+    fn stop(&mut self, timestamp: u64) {
+        if let Some(since) = self.empty_since.take() {
+            self.report.empty_cell_secs += timestamp.saturating_sub(since);
+        }
+        if let Some(since) = self.running_since.take() {
+            self.report.running_secs += timestamp.saturating_sub(since);
+        }
+    }
+
+    fn finish(mut self) -> SessionReport {
+        // Still running at the end of the log
+        self.stop(self.report.end);
+        self.report
+    }
+}
+
+impl Display for SessionReport {
+    #[allow(clippy::cast_precision_loss)]
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{}: {} s, running {} s",
+            self.label(),
+            self.end - self.start,
+            self.running_secs
+        )?;
+
+        writeln!(
+            f,
+            "  threat timeline (s, event, sunray probability, threat)"
+        )?;
+        for sample in &self.threat {
+            let p_asteroid = 1.0 - sample.sunray_probability;
+            writeln!(
+                f,
+                "    {:>6} {:<8} {:.3} {} ({p_asteroid:.3})",
+                sample.time,
+                sample.event,
+                sample.sunray_probability,
+                ThreatLevel::from_risk(p_asteroid).label()
+            )?;
+        }
+
+        let tally = |f: &mut Formatter<'_>, name: &str, tally: &DecisionTally| {
+            write!(
+                f,
+                "    {name}: {} accepted, {} denied",
+                tally.accepted, tally.denied
+            )?;
+            if let Some(ratio) = tally.accept_ratio() {
+                write!(f, " ({:.0}% accepted)", ratio * 100.0)?;
+            }
+            if tally.unsupported > 0 {
+                write!(f, ", {} unsupported", tally.unsupported)?;
+            }
+            if tally.failed > 0 {
+                write!(f, ", {} failed", tally.failed)?;
+            }
+            writeln!(f)
+        };
+        writeln!(f, "  decisions per explorer")?;
+        for (explorer_id, decisions) in &self.explorers {
+            tally(f, &format!("explorer {explorer_id}"), decisions)?;
+        }
+        writeln!(f, "  decisions per recipe")?;
+        for (recipe, decisions) in &self.recipes {
+            tally(f, recipe, decisions)?;
+        }
+
+        let times = |times: Vec<u64>| {
+            let times: Vec<String> = times.iter().map(|time| format!("{time}s")).collect();
+            times.join(", ")
+        };
+        let launches: Vec<u64> = self.rocket_launches().collect();
+        writeln!(
+            f,
+            "  rocket launches: {} {}",
+            launches.len(),
+            times(launches.clone())
+        )?;
+        let missed: Vec<u64> = self.missed_asteroids().collect();
+        writeln!(
+            f,
+            "  missed asteroids: {} {}",
+            missed.len(),
+            times(missed.clone())
+        )?;
+
+        write!(f, "  time with an empty cell: {} s", self.empty_cell_secs)?;
+        if self.running_secs > 0 {
+            write!(
+                f,
+                " ({:.0}% of the running time)",
+                self.empty_cell_secs as f64 * 100.0 / self.running_secs as f64
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes the reports as CSV tables in `dir`, created if needed: `summary.csv`,
+/// `threat.csv`, `decisions.csv` (per explorer and per recipe) and `asteroids.csv`, each
+/// with a `planet_id` column.
+///
+/// # Errors
+/// Returns an error if a file can not be written.
+#[allow(clippy::cast_precision_loss)]
+pub fn write_csv(reports: &[SessionReport], dir: impl AsRef<Path>) -> Result<(), String> {
+    let dir = dir.as_ref();
+    fs::create_dir_all(dir).map_err(|err| format!("{}: {err}", dir.display()))?;
+
+    let planet = |report: &SessionReport| report.planet_id.map(|id| id.to_string());
+    let mut summary = vec![
+        "planet_id,start_unix,end_unix,running_secs,empty_cell_secs,rocket_launches,missed_asteroids,accepted,denied"
+            .to_string(),
+    ];
+    let mut threat =
+        vec!["planet_id,time_secs,event,sunray_probability,asteroid_probability".to_string()];
+    let mut decisions =
+        vec!["planet_id,group,key,accepted,denied,unsupported,failed,accept_ratio".to_string()];
+    let mut asteroids = vec!["planet_id,time_secs,outcome".to_string()];
+
+    for report in reports {
+        let id = planet(report).unwrap_or_default();
+        let (accepted, denied) = report.explorers.values().fold((0, 0), |(a, d), tally| {
+            (a + tally.accepted, d + tally.denied)
+        });
+        summary.push(format!(
+            "{id},{},{},{},{},{},{},{accepted},{denied}",
+            report.start,
+            report.end,
+            report.running_secs,
+            report.empty_cell_secs,
+            report.rocket_launches().count(),
+            report.missed_asteroids().count(),
+        ));
+        for sample in &report.threat {
+            threat.push(format!(
+                "{id},{},{},{:.6},{:.6}",
+                sample.time,
+                sample.event,
+                sample.sunray_probability,
+                1.0 - sample.sunray_probability
+            ));
+        }
+        let groups = report
+            .explorers
+            .iter()
+            .map(|(explorer_id, tally)| ("explorer", explorer_id.to_string(), tally))
+            .chain(
+                report
+                    .recipes
+                    .iter()
+                    .map(|(recipe, tally)| ("recipe", recipe.clone(), tally)),
+            );
+        for (group, key, tally) in groups {
+            decisions.push(format!(
+                "{id},{group},{key},{},{},{},{},{}",
+                tally.accepted,
+                tally.denied,
+                tally.unsupported,
+                tally.failed,
+                tally
+                    .accept_ratio()
+                    .map(|ratio| format!("{ratio:.6}"))
+                    .unwrap_or_default()
+            ));
+        }
+        for (time, outcome) in &report.asteroids {
+            asteroids.push(format!("{id},{time},{}", outcome.label()));
+        }
+    }
+
+    for (name, lines) in [
+        ("summary.csv", summary),
+        ("threat.csv", threat),
+        ("decisions.csv", decisions),
+        ("asteroids.csv", asteroids),
+    ] {
+        let path = dir.join(name);
+        fs::write(&path, lines.join("\n") + "\n")
+            .map_err(|err| format!("{}: {err}", path.display()))?;
+    }
+    Ok(())
+}
+
+fn number_after(text: &str, prefix: &str) -> Option<u64> {
+    let rest = &text[text.find(prefix)? + prefix.len()..];
+    let end = rest
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(rest.len());
+    rest[..end].parse().ok()
+}
+
+fn word_after<'a>(text: &'a str, prefix: &str) -> Option<&'a str> {
+    let rest = &text[text.find(prefix)? + prefix.len()..];
+    let end = rest
+        .find(|c: char| !c.is_ascii_alphanumeric())
+        .unwrap_or(rest.len());
+    (end > 0).then(|| &rest[..end])
+}
+
+// Parses the `Debug` format of the payload map, `{"key": "value", ...}`
+fn parse_payload(text: &str) -> Option<BTreeMap<String, String>> {
+    let mut chars = text.strip_prefix('{')?.chars().peekable();
+    let mut payload = BTreeMap::new();
+    loop {
+        match chars.next()? {
+            '}' => return Some(payload),
+            ' ' | ',' => {}
+            '"' => {
+                let key = debug_string(&mut chars)?;
+                if chars.next()? != ':' || chars.next()? != ' ' || chars.next()? != '"' {
+                    return None;
+                }
+                payload.insert(key, debug_string(&mut chars)?);
+            }
+            _ => return None,
+        }
+    }
+}
+
+// Reads a string escaped by `Debug` up to its closing quote
+fn debug_string(chars: &mut impl Iterator<Item = char>) -> Option<String> {
+    let mut value = String::new();
+    loop {
+        match chars.next()? {
+            '"' => return Some(value),
+            '\\' => match chars.next()? {
+                'n' => value.push('\n'),
+                'r' => value.push('\r'),
+                't' => value.push('\t'),
+                '0' => value.push('\0'),
+                'u' => {
+                    let code: String = chars.by_ref().skip(1).take_while(|c| *c != '}').collect();
+                    value.push(char::from_u32(u32::from_str_radix(&code, 16).ok()?)?);
+                }
+                other => value.push(other),
+            },
+            c => value.push(c),
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use common_game::components::planet::PlanetType;
+use common_game::components::resource::{BasicResourceType, ComplexResourceType};
+use std::collections::HashSet;
+
+/// Generation rules of the original ICB planet (type C).
+pub const DEFAULT_GEN_RULES: [BasicResourceType; 1] = [BasicResourceType::Hydrogen];
+
+/// Combination rules of the original ICB planet (type C).
+pub const DEFAULT_COMB_RULES: [ComplexResourceType; 6] = [
+    ComplexResourceType::AIPartner,
+    ComplexResourceType::Diamond,
+    ComplexResourceType::Dolphin,
+    ComplexResourceType::Life,
+    ComplexResourceType::Robot,
+    ComplexResourceType::Water,
+];
+
+/// Combination rules of a planet of type `planet_type` when none are given, as many as the
+/// project specifications allow: all for type C, water for B and none for A and D.
+pub(crate) fn default_comb_rules(planet_type: PlanetType) -> Vec<ComplexResourceType> {
+    match planet_type {
+        PlanetType::A | PlanetType::D => Vec::new(),
+        PlanetType::B => vec![ComplexResourceType::Water],
+        PlanetType::C => DEFAULT_COMB_RULES.to_vec(),
+    }
+}
+
+/// Every basic resource type, in declaration order.
+pub(crate) const BASIC_RESOURCES: [BasicResourceType; 4] = [
+    BasicResourceType::Oxygen,
+    BasicResourceType::Hydrogen,
+    BasicResourceType::Carbon,
+    BasicResourceType::Silicon,
+];
+
+/// Parses a basic resource type from its name, e.g. `"Hydrogen"`.
+pub(crate) fn basic_from_name(name: &str) -> Option<BasicResourceType> {
+    BASIC_RESOURCES
+        .into_iter()
+        .find(|resource| format!("{resource:?}") == name)
+}
+
+/// Parses a complex resource type from its name, e.g. `"Water"`.
+pub(crate) fn complex_from_name(name: &str) -> Option<ComplexResourceType> {
+    DEFAULT_COMB_RULES
+        .into_iter()
+        .find(|resource| format!("{resource:?}") == name)
+}
+
+/// Planet type with `cells` energy cells and the given rocket capability, which is unique.
+pub(crate) fn planet_type_of(cells: usize, can_have_rocket: bool) -> PlanetType {
+    match (cells > 1, can_have_rocket) {
+        (true, true) => PlanetType::A,
+        (false, false) => PlanetType::B,
+        (false, true) => PlanetType::C,
+        (true, false) => PlanetType::D,
+    }
+}
+
+/// Parses a planet type from its name, e.g. `"C"`.
+pub(crate) fn planet_type_from_name(name: &str) -> Option<PlanetType> {
+    [PlanetType::A, PlanetType::B, PlanetType::C, PlanetType::D]
+        .into_iter()
+        .find(|planet_type| format!("{planet_type:?}") == name)
+}
+
+/// Checks that no generation or combination rule is repeated. The limits of each planet type
+/// are left to `Planet::new`, which takes them from `PlanetType::constraints`.
+///
+/// # Errors
+/// * `Err(String)` - If a rule is repeated
+pub fn validate_rules(
+    gen_rules: &[BasicResourceType],
+    comb_rules: &[ComplexResourceType],
+) -> Result<(), String> {
+    if gen_rules.iter().collect::<HashSet<_>>().len() != gen_rules.len() {
+        return Err(format!("gen_rules contains duplicates: {gen_rules:?}"));
+    }
+    if comb_rules.iter().collect::<HashSet<_>>().len() != comb_rules.len() {
+        return Err(format!("comb_rules contains duplicates: {comb_rules:?}"));
+    }
+    Ok(())
+}
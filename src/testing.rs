@@ -0,0 +1,477 @@
+//! Synchronous harness to test a planet, enabled by the `testing` feature.
+//!
+//! A [`PlanetHarness`] owns the four channels of a planet and runs it on its own thread.
+//! Every helper sends a message and blocks until the answer, which it checks with an
+//! [`Expect`] matcher returning the content of the expected variant, so a test never needs
+//! to sleep. The timeout only turns a planet that never answers into a failure instead of a
+//! hang.
+//!
+//! ```no_run
+//! use immutable_cosmic_borrow::bridge::PlanetOptions;
+//! use immutable_cosmic_borrow::testing::{PlanetHarness, expect};
+//! use common_game::protocols::planet_explorer::ExplorerToPlanet;
+//!
+//! let mut harness = PlanetHarness::spawn(|orchestrator_channels, explorers_receiver| {
+//!     PlanetOptions::default().create_planet(orchestrator_channels, explorers_receiver)
+//! })
+//! .unwrap();
+//! harness.start();
+//! harness.arrive(1);
+//! harness.sunray();
+//! let cells = harness.explorer_send_and_expect(
+//!     ExplorerToPlanet::AvailableEnergyCellRequest { explorer_id: 1 },
+//!     expect::available_cells(),
+//! );
+//! assert_eq!(cells, 1);
+//! // The planet is killed and its thread joined on drop
+//! ```
+
+use common_game::components::asteroid::Asteroid;
+use common_game::components::planet::Planet;
+use common_game::components::rocket::Rocket;
+use common_game::components::sunray::Sunray;
+use common_game::protocols::orchestrator_planet::{OrchestratorToPlanet, PlanetToOrchestrator};
+use common_game::protocols::planet_explorer::{ExplorerToPlanet, PlanetToExplorer};
+use common_game::utils::ID;
+use crossbeam_channel::{Receiver, RecvTimeoutError, Sender};
+use std::fmt::Debug;
+use std::panic;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+pub mod sequences;
+
+/// Default time to wait for an answer of the planet.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Planet running on its own thread with all its channels, see the
+/// [module documentation](self).
+pub struct PlanetHarness {
+    planet_id: ID,
+    to_planet: Sender<OrchestratorToPlanet>,
+    from_planet: Receiver<PlanetToOrchestrator>,
+    explorers_to_planet: Sender<ExplorerToPlanet>,
+    // Shared by every explorer registered through the harness
+    to_explorers: Sender<PlanetToExplorer>,
+    from_planet_to_explorers: Receiver<PlanetToExplorer>,
+    handle: Option<JoinHandle<Result<(), String>>>,
+    timeout: Duration,
+}
+
+/// Matcher of a message of type `M`, returning the content `T` of the expected variant.
+///
+/// The constructors are in [`expect`].
+pub struct Expect<M, T> {
+    what: &'static str,
+    extract: fn(M) -> Result<T, M>,
+}
+
+// Derived, they would require `M` and `T` to be `Copy`
+impl<M, T> Clone for Expect<M, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<M, T> Copy for Expect<M, T> {}
+
+impl<M: Debug, T> Expect<M, T> {
+    /// Matcher described as `what` in failures, `extract` gives back the messages it rejects.
+    #[must_use]
+    pub fn new(what: &'static str, extract: fn(M) -> Result<T, M>) -> Self {
+        Self { what, extract }
+    }
+
+    /// Content of `msg`.
+    ///
+    /// # Errors
+    /// Returns a description of the mismatch.
+    pub fn check(&self, msg: M) -> Result<T, String> {
+        (self.extract)(msg).map_err(|msg| format!("Expected {}, got {msg:?}", self.what))
+    }
+}
+
+/// Matchers of the answers of a planet.
+pub mod expect {
+    use super::Expect;
+    use common_game::components::planet::DummyPlanetState;
+    use common_game::components::resource::{
+        BasicResource, BasicResourceType, ComplexResource, ComplexResourceType, GenericResource,
+    };
+    use common_game::components::rocket::Rocket;
+    use common_game::protocols::orchestrator_planet::PlanetToOrchestrator as P2O;
+    use common_game::protocols::planet_explorer::PlanetToExplorer as P2E;
+    use std::collections::HashSet;
+
+    /// Any answer of the planet to the orchestrator.
+    #[must_use]
+    pub fn any() -> Expect<P2O, P2O> {
+        Expect::new("any answer", Ok)
+    }
+
+    /// Any answer of the planet to an explorer.
+    #[must_use]
+    pub fn any_explorer() -> Expect<P2E, P2E> {
+        Expect::new("any answer", Ok)
+    }
+
+    #[must_use]
+    pub fn start_result() -> Expect<P2O, ()> {
+        Expect::new("StartPlanetAIResult", |msg| match msg {
+            P2O::StartPlanetAIResult { .. } => Ok(()),
+            msg => Err(msg),
+        })
+    }
+
+    #[must_use]
+    pub fn stop_result() -> Expect<P2O, ()> {
+        Expect::new("StopPlanetAIResult", |msg| match msg {
+            P2O::StopPlanetAIResult { .. } => Ok(()),
+            msg => Err(msg),
+        })
+    }
+
+    #[must_use]
+    pub fn kill_result() -> Expect<P2O, ()> {
+        Expect::new("KillPlanetResult", |msg| match msg {
+            P2O::KillPlanetResult { .. } => Ok(()),
+            msg => Err(msg),
+        })
+    }
+
+    /// Answer of a stopped planet to the orchestrator.
+    #[must_use]
+    pub fn stopped() -> Expect<P2O, ()> {
+        Expect::new("Stopped", |msg| match msg {
+            P2O::Stopped { .. } => Ok(()),
+            msg => Err(msg),
+        })
+    }
+
+    #[must_use]
+    pub fn sunray_ack() -> Expect<P2O, ()> {
+        Expect::new("SunrayAck", |msg| match msg {
+            P2O::SunrayAck { .. } => Ok(()),
+            msg => Err(msg),
+        })
+    }
+
+    /// Rocket launched against the asteroid, if any.
+    #[must_use]
+    pub fn asteroid_ack() -> Expect<P2O, Option<Rocket>> {
+        Expect::new("AsteroidAck", |msg| match msg {
+            P2O::AsteroidAck { rocket, .. } => Ok(rocket),
+            msg => Err(msg),
+        })
+    }
+
+    #[must_use]
+    pub fn internal_state() -> Expect<P2O, DummyPlanetState> {
+        Expect::new("InternalStateResponse", |msg| match msg {
+            P2O::InternalStateResponse { planet_state, .. } => Ok(planet_state),
+            msg => Err(msg),
+        })
+    }
+
+    /// Result of an explorer arrival.
+    #[must_use]
+    pub fn incoming_explorer() -> Expect<P2O, Result<(), String>> {
+        Expect::new("IncomingExplorerResponse", |msg| match msg {
+            P2O::IncomingExplorerResponse { res, .. } => Ok(res),
+            msg => Err(msg),
+        })
+    }
+
+    /// Result of an explorer departure.
+    #[must_use]
+    pub fn outgoing_explorer() -> Expect<P2O, Result<(), String>> {
+        Expect::new("OutgoingExplorerResponse", |msg| match msg {
+            P2O::OutgoingExplorerResponse { res, .. } => Ok(res),
+            msg => Err(msg),
+        })
+    }
+
+    #[must_use]
+    pub fn supported_resources() -> Expect<P2E, HashSet<BasicResourceType>> {
+        Expect::new("SupportedResourceResponse", |msg| match msg {
+            P2E::SupportedResourceResponse { resource_list } => Ok(resource_list),
+            msg => Err(msg),
+        })
+    }
+
+    #[must_use]
+    pub fn supported_combinations() -> Expect<P2E, HashSet<ComplexResourceType>> {
+        Expect::new("SupportedCombinationResponse", |msg| match msg {
+            P2E::SupportedCombinationResponse { combination_list } => Ok(combination_list),
+            msg => Err(msg),
+        })
+    }
+
+    /// Generated resource, `None` if the request was denied.
+    #[must_use]
+    pub fn generated() -> Expect<P2E, Option<BasicResource>> {
+        Expect::new("GenerateResourceResponse", |msg| match msg {
+            P2E::GenerateResourceResponse { resource } => Ok(resource),
+            msg => Err(msg),
+        })
+    }
+
+    /// Combined resource, or the error and the ingredients given back.
+    #[must_use]
+    #[allow(clippy::type_complexity)]
+    pub fn combined()
+    -> Expect<P2E, Result<ComplexResource, (String, GenericResource, GenericResource)>> {
+        Expect::new("CombineResourceResponse", |msg| match msg {
+            P2E::CombineResourceResponse { complex_response } => Ok(complex_response),
+            msg => Err(msg),
+        })
+    }
+
+    #[must_use]
+    pub fn available_cells() -> Expect<P2E, u32> {
+        Expect::new("AvailableEnergyCellResponse", |msg| match msg {
+            P2E::AvailableEnergyCellResponse { available_cells } => Ok(available_cells),
+            msg => Err(msg),
+        })
+    }
+
+    /// Answer of a stopped planet to an explorer.
+    #[must_use]
+    pub fn explorer_stopped() -> Expect<P2E, ()> {
+        Expect::new("Stopped", |msg| match msg {
+            P2E::Stopped => Ok(()),
+            msg => Err(msg),
+        })
+    }
+}
+
+impl PlanetHarness {
+    /// Creates the planet with `build`, given its channels, and runs it on a new thread. The
+    /// planet is stopped until [`start`](Self::start).
+    ///
+    /// # Errors
+    /// Returns the error of `build`.
+    pub fn spawn(
+        build: impl FnOnce(
+            (Receiver<OrchestratorToPlanet>, Sender<PlanetToOrchestrator>),
+            Receiver<ExplorerToPlanet>,
+        ) -> Result<Planet, String>,
+    ) -> Result<Self, String> {
+        let (to_planet, rx_orchestrator) = crossbeam_channel::unbounded();
+        let (tx_orchestrator, from_planet) = crossbeam_channel::unbounded();
+        let (explorers_to_planet, rx_explorers) = crossbeam_channel::unbounded();
+        let (to_explorers, from_planet_to_explorers) = crossbeam_channel::unbounded();
+
+        let mut planet = build((rx_orchestrator, tx_orchestrator), rx_explorers)?;
+        let planet_id = planet.id();
+        let handle = thread::spawn(move || planet.run());
+
+        Ok(Self {
+            planet_id,
+            to_planet,
+            from_planet,
+            explorers_to_planet,
+            to_explorers,
+            from_planet_to_explorers,
+            handle: Some(handle),
+            timeout: DEFAULT_TIMEOUT,
+        })
+    }
+
+    /// Time to wait for every answer, [`DEFAULT_TIMEOUT`] by default.
+    #[must_use]
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    #[must_use]
+    pub fn planet_id(&self) -> ID {
+        self.planet_id
+    }
+
+    /// Sender of the explorers, for the messages sent without waiting for the answer.
+    #[must_use]
+    pub fn explorers_sender(&self) -> &Sender<ExplorerToPlanet> {
+        &self.explorers_to_planet
+    }
+
+    /// Sends `msg` to the planet without waiting for the answer.
+    ///
+    /// # Panics
+    /// Panics if the planet thread ended, with its panic if it panicked.
+    pub fn send(&mut self, msg: OrchestratorToPlanet) {
+        if self.to_planet.send(msg).is_err() {
+            self.planet_gone("The planet disconnected");
+        }
+    }
+
+    /// Next answer of the planet to the orchestrator.
+    ///
+    /// # Panics
+    /// Panics if the planet does not answer in time, or with the panic of the planet thread.
+    pub fn recv(&mut self) -> PlanetToOrchestrator {
+        match self.from_planet.recv_timeout(self.timeout) {
+            Ok(msg) => msg,
+            Err(RecvTimeoutError::Timeout) => self.timed_out(),
+            Err(RecvTimeoutError::Disconnected) => self.planet_gone("The planet disconnected"),
+        }
+    }
+
+    /// Next answer of the planet to an explorer registered through the harness.
+    ///
+    /// # Panics
+    /// Panics if the planet does not answer in time, or with the panic of the planet thread.
+    pub fn explorer_recv(&mut self) -> PlanetToExplorer {
+        match self.from_planet_to_explorers.recv_timeout(self.timeout) {
+            Ok(msg) => msg,
+            // The harness keeps a sender, so the channel never disconnects
+            Err(_) => self.timed_out(),
+        }
+    }
+
+    /// Sends `msg` from the orchestrator and checks the answer with `expect`.
+    ///
+    /// # Panics
+    /// Panics if the answer does not match, see also [`recv`](Self::recv).
+    pub fn send_and_expect<T>(
+        &mut self,
+        msg: OrchestratorToPlanet,
+        expect: Expect<PlanetToOrchestrator, T>,
+    ) -> T {
+        self.send(msg);
+        let answer = self.recv();
+        expect.check(answer).unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Sends `msg` from an explorer and checks the answer with `expect`.
+    ///
+    /// # Panics
+    /// Panics if the answer does not match, see also [`explorer_recv`](Self::explorer_recv).
+    pub fn explorer_send_and_expect<T>(
+        &mut self,
+        msg: ExplorerToPlanet,
+        expect: Expect<PlanetToExplorer, T>,
+    ) -> T {
+        if self.explorers_to_planet.send(msg).is_err() {
+            self.planet_gone("The planet disconnected");
+        }
+        let answer = self.explorer_recv();
+        expect.check(answer).unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    pub fn start(&mut self) {
+        self.send_and_expect(OrchestratorToPlanet::StartPlanetAI, expect::start_result());
+    }
+
+    pub fn stop(&mut self) {
+        self.send_and_expect(OrchestratorToPlanet::StopPlanetAI, expect::stop_result());
+    }
+
+    /// Kills the planet and joins its thread.
+    ///
+    /// # Panics
+    /// Panics if the planet does not confirm, or if its thread ended with an error.
+    pub fn kill(&mut self) {
+        self.send_and_expect(OrchestratorToPlanet::KillPlanet, expect::kill_result());
+        if let Err(err) = self.join() {
+            panic!("The planet ended with an error: {err}");
+        }
+    }
+
+    pub fn sunray(&mut self) {
+        self.send_and_expect(
+            OrchestratorToPlanet::Sunray(Sunray::default()),
+            expect::sunray_ack(),
+        );
+    }
+
+    /// Sends an asteroid and returns the rocket launched against it, if any.
+    pub fn asteroid(&mut self) -> Option<Rocket> {
+        self.send_and_expect(
+            OrchestratorToPlanet::Asteroid(Asteroid::default()),
+            expect::asteroid_ack(),
+        )
+    }
+
+    /// Arrival request of an explorer whose answers are read with
+    /// [`explorer_recv`](Self::explorer_recv), for a planet expected to refuse it.
+    #[must_use]
+    pub fn arrival(&self, explorer_id: ID) -> OrchestratorToPlanet {
+        OrchestratorToPlanet::IncomingExplorerRequest {
+            explorer_id,
+            new_sender: self.to_explorers.clone(),
+        }
+    }
+
+    /// Registers the explorer on the planet, its answers are read with
+    /// [`explorer_recv`](Self::explorer_recv).
+    ///
+    /// # Panics
+    /// Panics if the planet refuses the explorer.
+    pub fn arrive(&mut self, explorer_id: ID) {
+        let res = self.send_and_expect(self.arrival(explorer_id), expect::incoming_explorer());
+        if let Err(err) = res {
+            panic!("Explorer {explorer_id} refused: {err}");
+        }
+    }
+
+    /// # Panics
+    /// Panics if the planet refuses the departure.
+    pub fn depart(&mut self, explorer_id: ID) {
+        let res = self.send_and_expect(
+            OrchestratorToPlanet::OutgoingExplorerRequest { explorer_id },
+            expect::outgoing_explorer(),
+        );
+        if let Err(err) = res {
+            panic!("Departure of explorer {explorer_id} refused: {err}");
+        }
+    }
+
+    /// Waits for the end of the planet thread and returns the result of the planet.
+    ///
+    /// # Errors
+    /// Returns the error of the planet, or an error if it was already joined.
+    ///
+    /// # Panics
+    /// Resumes the panic of the planet thread.
+    pub fn join(&mut self) -> Result<(), String> {
+        let handle = self.handle.take().ok_or("The planet was already joined")?;
+        match handle.join() {
+            Ok(result) => result,
+            Err(payload) => panic::resume_unwind(payload),
+        }
+    }
+
+    fn timed_out(&mut self) -> ! {
+        if self.handle.as_ref().is_some_and(JoinHandle::is_finished) {
+            self.planet_gone("The planet stopped");
+        }
+        panic!("The planet did not answer within {:?}", self.timeout);
+    }
+
+    // Surfaces the panic or the error that ended the planet thread
+    fn planet_gone(&mut self, why: &str) -> ! {
+        match self.join() {
+            Ok(()) => panic!("{why}: the planet ended"),
+            Err(err) => panic!("{why}: {err}"),
+        }
+    }
+}
+
+impl Drop for PlanetHarness {
+    fn drop(&mut self) {
+        let Some(handle) = self.handle.take() else {
+            return;
+        };
+        // The planet answers a kill both stopped and running
+        let _ = self.to_planet.send(OrchestratorToPlanet::KillPlanet);
+        let result = handle.join();
+        // A second panic would abort the test run
+        if let Err(payload) = result
+            && !thread::panicking()
+        {
+            panic::resume_unwind(payload);
+        }
+    }
+}
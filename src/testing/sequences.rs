@@ -0,0 +1,514 @@
+//! Property checks of message sequences.
+//!
+//! [`SequenceCheck`] draws random sequences of steps from a seed, runs a property on each one
+//! and shrinks the first failing sequence to a minimal one before failing, like a small
+//! proptest. [`run_on_planet`] is such a property for sequences of [`Step`]s: it plays them on
+//! a planet through a [`PlanetHarness`] and checks every answer, the planet state and the AI
+//! statistics against a model of the planet.
+//!
+//! The seed and the number of cases can be changed with the `ICB_SEQUENCE_SEED` and
+//! `ICB_SEQUENCE_CASES` environment variables, e.g. to replay a failing case.
+
+use super::{PlanetHarness, expect};
+use crate::ai::Ai;
+use crate::bridge::{PlanetOptions, ResourceBag};
+use crate::rules::BASIC_RESOURCES;
+use crate::{DEFAULT_COMB_RULES, StatsHandle};
+use common_game::components::asteroid::Asteroid;
+use common_game::components::planet::PlanetType;
+use common_game::components::resource::{BasicResourceType, ComplexResourceType, GenericResource};
+use common_game::components::sunray::Sunray;
+use common_game::protocols::orchestrator_planet::{OrchestratorToPlanet, PlanetToOrchestrator};
+use common_game::protocols::planet_explorer::{ExplorerToPlanet, PlanetToExplorer};
+use common_game::utils::ID;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Debug;
+use std::panic::{self, AssertUnwindSafe};
+
+pub const DEFAULT_SEED: u64 = 0x1CB;
+pub const DEFAULT_CASES: u64 = 64;
+pub const DEFAULT_MAX_LEN: usize = 40;
+
+// Runs of the property allowed to shrink a failing sequence
+const MAX_SHRINK_RUNS: usize = 1000;
+
+/// Message sent to a planet, see [`run_on_planet`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+    Start,
+    Stop,
+    Sunray,
+    Asteroid,
+    InternalState,
+    Arrive(ID),
+    Depart(ID),
+    SupportedResources(ID),
+    SupportedCombinations(ID),
+    Generate(ID, BasicResourceType),
+    /// Sent only if the explorer holds the ingredients
+    Combine(ID, ComplexResourceType),
+    AvailableCells(ID),
+}
+
+/// Random sequences checked against a property, see the [module documentation](self).
+#[derive(Debug, Clone)]
+pub struct SequenceCheck {
+    seed: u64,
+    cases: u64,
+    max_len: usize,
+}
+
+impl Step {
+    /// Random step from one of the explorers `0..explorers`, over every resource type
+    /// including the ones the planet has no recipe for.
+    pub fn random(rng: &mut impl Rng, explorers: ID) -> Self {
+        let explorer = rng.random_range(0..explorers.max(1));
+        // Sunrays and generations are drawn more often, so that combinations have ingredients
+        match rng.random_range(0..16) {
+            0 => Step::Start,
+            1 => Step::Stop,
+            2..=4 => Step::Sunray,
+            5 | 6 => Step::Asteroid,
+            7 => Step::InternalState,
+            8 => Step::Arrive(explorer),
+            9 => Step::Depart(explorer),
+            10 => Step::SupportedResources(explorer),
+            11 => Step::SupportedCombinations(explorer),
+            12 | 13 => Step::Generate(
+                explorer,
+                BASIC_RESOURCES[rng.random_range(0..BASIC_RESOURCES.len())],
+            ),
+            14 => Step::Combine(
+                explorer,
+                DEFAULT_COMB_RULES[rng.random_range(0..DEFAULT_COMB_RULES.len())],
+            ),
+            _ => Step::AvailableCells(explorer),
+        }
+    }
+}
+
+impl Default for SequenceCheck {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SequenceCheck {
+    /// Check of [`DEFAULT_CASES`] sequences of up to [`DEFAULT_MAX_LEN`] steps, unless changed
+    /// by the environment.
+    #[must_use]
+    pub fn new() -> Self {
+        let env = |name: &str| {
+            std::env::var(name)
+                .ok()
+                .and_then(|value| value.parse().ok())
+        };
+        Self {
+            seed: env("ICB_SEQUENCE_SEED").unwrap_or(DEFAULT_SEED),
+            cases: env("ICB_SEQUENCE_CASES").unwrap_or(DEFAULT_CASES),
+            max_len: DEFAULT_MAX_LEN,
+        }
+    }
+
+    #[must_use]
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    #[must_use]
+    pub fn with_cases(mut self, cases: u64) -> Self {
+        self.cases = cases;
+        self
+    }
+
+    #[must_use]
+    pub fn with_max_len(mut self, max_len: usize) -> Self {
+        self.max_len = max_len.max(1);
+        self
+    }
+
+    /// Runs `property` on sequences of steps drawn with `step`. Case `n` is drawn from the
+    /// seed plus `n`, so it is replayed with that seed and a single case.
+    ///
+    /// # Panics
+    /// Panics with the smallest failing sequence found and its error. A panic of the property
+    /// counts as a failure.
+    pub fn run<S: Clone + Debug>(
+        &self,
+        mut step: impl FnMut(&mut StdRng) -> S,
+        property: impl Fn(&[S]) -> Result<(), String>,
+    ) {
+        for case in 0..self.cases {
+            let seed = self.seed.wrapping_add(case);
+            let mut rng = StdRng::seed_from_u64(seed);
+            let len = rng.random_range(1..=self.max_len);
+            let steps: Vec<S> = (0..len).map(|_| step(&mut rng)).collect();
+
+            if let Err(err) = check(&property, &steps) {
+                let (steps, err) = shrink(&property, steps, err);
+                panic!(
+                    "Property failed for case {case} (seed {seed}), shrunk to {} steps: {err}\n{steps:#?}",
+                    steps.len()
+                );
+            }
+        }
+    }
+}
+
+fn check<S>(property: &impl Fn(&[S]) -> Result<(), String>, steps: &[S]) -> Result<(), String> {
+    match panic::catch_unwind(AssertUnwindSafe(|| property(steps))) {
+        Ok(result) => result,
+        Err(payload) => Err(payload
+            .downcast_ref::<&str>()
+            .map(ToString::to_string)
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "panic".to_string())),
+    }
+}
+
+// Removes chunks of steps, halving their size, as long as the property still fails
+fn shrink<S: Clone>(
+    property: &impl Fn(&[S]) -> Result<(), String>,
+    mut steps: Vec<S>,
+    mut err: String,
+) -> (Vec<S>, String) {
+    let mut runs = 0;
+    let mut chunk = steps.len().div_ceil(2);
+    while chunk > 0 && runs < MAX_SHRINK_RUNS {
+        let mut removed = false;
+        let mut start = 0;
+        while start < steps.len() && runs < MAX_SHRINK_RUNS {
+            let mut candidate = steps.clone();
+            candidate.drain(start..(start + chunk).min(steps.len()));
+            runs += 1;
+            match check(property, &candidate) {
+                Err(candidate_err) => {
+                    steps = candidate;
+                    err = candidate_err;
+                    removed = true;
+                }
+                Ok(()) => start += chunk,
+            }
+        }
+        if !removed {
+            chunk /= 2;
+        }
+    }
+    (steps, err)
+}
+
+/// Plays `steps` on a planet built from `options` with `ai`, then kills it.
+///
+/// Every answer is checked against a model of the planet: a stopped planet only answers
+/// `Stopped`, explorers get the answer to their request, resources are given only by a
+/// charged cell and from a recipe of the planet, a rocket is launched exactly when one was
+/// ready or could be built, and after every step the planet state and the AI statistics
+/// match the model. Steps that the protocol leaves unanswered, a start while running or a
+/// message from an explorer not on the planet, are skipped.
+///
+/// # Errors
+/// Returns the first mismatch.
+///
+/// # Panics
+/// Panics if the planet does not answer, or with the panic of the planet thread.
+pub fn run_on_planet(options: &PlanetOptions, ai: Ai, steps: &[Step]) -> Result<(), String> {
+    let stats = ai.stats_handle();
+    let mut harness = PlanetHarness::spawn(|orchestrator_channels, explorers_receiver| {
+        options.create_planet_with_ai(ai, orchestrator_channels, explorers_receiver)
+    })?;
+    let mut model = Model::new(options, stats);
+
+    for (index, step) in steps.iter().enumerate() {
+        model
+            .play(&mut harness, *step)
+            .map_err(|err| format!("step {index} {step:?}: {err}"))?;
+    }
+    harness.kill();
+    Ok(())
+}
+
+// What the planet should look like after the steps played so far
+struct Model {
+    running: bool,
+    cells: usize,
+    can_have_rocket: bool,
+    charged: usize,
+    rocket: bool,
+    gen_rules: HashSet<BasicResourceType>,
+    comb_rules: HashSet<ComplexResourceType>,
+    explorers: BTreeMap<ID, ResourceBag>,
+    stats: StatsHandle,
+}
+
+impl Model {
+    fn new(options: &PlanetOptions, stats: StatsHandle) -> Self {
+        // (energy cells, rockets), as defined by the project specifications
+        let (cells, can_have_rocket) = match options.planet_type {
+            PlanetType::A => (5, true),
+            PlanetType::B => (1, false),
+            PlanetType::C => (1, true),
+            PlanetType::D => (5, false),
+        };
+        Self {
+            running: false,
+            cells,
+            can_have_rocket,
+            charged: 0,
+            rocket: false,
+            gen_rules: options.gen_rules.iter().copied().collect(),
+            comb_rules: options.comb_rules.iter().copied().collect(),
+            explorers: BTreeMap::new(),
+            stats,
+        }
+    }
+
+    fn play(&mut self, harness: &mut PlanetHarness, step: Step) -> Result<(), String> {
+        if let Some(msg) = self.orchestrator_message(harness, step) {
+            if !self.running {
+                // Only a start or a kill brings the planet out of its wait
+                let answer = ask(harness, msg);
+                return if matches!(step, Step::Start) {
+                    expect::start_result().check(answer)?;
+                    self.running = true;
+                    self.check_state(harness)
+                } else {
+                    expect::stopped().check(answer)
+                };
+            }
+            self.play_orchestrator(harness, step, msg)?;
+        } else if let Some(msg) = self.explorer_message(step) {
+            harness
+                .explorers_sender()
+                .send(msg)
+                .map_err(|_| "The planet disconnected")?;
+            let answer = harness.explorer_recv();
+            if !self.running {
+                return expect::explorer_stopped().check(answer);
+            }
+            self.check_explorer_answer(step, answer)?;
+        } else {
+            return Ok(());
+        }
+
+        if self.running {
+            self.check_state(harness)?;
+        }
+        Ok(())
+    }
+
+    // `None` for the explorer steps and the steps left unanswered
+    fn orchestrator_message(
+        &self,
+        harness: &PlanetHarness,
+        step: Step,
+    ) -> Option<OrchestratorToPlanet> {
+        Some(match step {
+            Step::Start if self.running => return None,
+            Step::Start => OrchestratorToPlanet::StartPlanetAI,
+            Step::Stop => OrchestratorToPlanet::StopPlanetAI,
+            Step::Sunray => OrchestratorToPlanet::Sunray(Sunray::default()),
+            Step::Asteroid => OrchestratorToPlanet::Asteroid(Asteroid::default()),
+            Step::InternalState => OrchestratorToPlanet::InternalStateRequest,
+            Step::Arrive(explorer_id) => harness.arrival(explorer_id),
+            Step::Depart(explorer_id) => {
+                OrchestratorToPlanet::OutgoingExplorerRequest { explorer_id }
+            }
+            _ => return None,
+        })
+    }
+
+    fn play_orchestrator(
+        &mut self,
+        harness: &mut PlanetHarness,
+        step: Step,
+        msg: OrchestratorToPlanet,
+    ) -> Result<(), String> {
+        let answer = ask(harness, msg);
+        match step {
+            Step::Stop => {
+                expect::stop_result().check(answer)?;
+                self.running = false;
+            }
+            Step::Sunray => {
+                expect::sunray_ack().check(answer)?;
+                // An existing charge becomes a rocket before the new one is stored
+                if !self.rocket && self.can_have_rocket && self.charged > 0 {
+                    self.rocket = true;
+                    self.charged -= 1;
+                }
+                self.charged = (self.charged + 1).min(self.cells);
+            }
+            Step::Asteroid => {
+                let launched = expect::asteroid_ack().check(answer)?.is_some();
+                let expected = self.rocket || (self.can_have_rocket && self.charged > 0);
+                if launched != expected {
+                    return Err(format!(
+                        "Rocket launched: {launched}, with a rocket ready: {}, {} charged cells",
+                        self.rocket, self.charged
+                    ));
+                }
+                if self.rocket {
+                    self.rocket = false;
+                } else if launched {
+                    self.charged -= 1;
+                }
+            }
+            Step::Arrive(explorer_id) => {
+                expect::incoming_explorer().check(answer)??;
+                self.explorers.entry(explorer_id).or_default();
+            }
+            Step::Depart(explorer_id) => {
+                expect::outgoing_explorer().check(answer)??;
+                self.explorers.remove(&explorer_id);
+            }
+            _ => {
+                expect::internal_state().check(answer)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn explorer_message(&mut self, step: Step) -> Option<ExplorerToPlanet> {
+        let (Step::SupportedResources(explorer_id)
+        | Step::SupportedCombinations(explorer_id)
+        | Step::Generate(explorer_id, _)
+        | Step::Combine(explorer_id, _)
+        | Step::AvailableCells(explorer_id)) = step
+        else {
+            return None;
+        };
+        // The planet ignores explorers it does not know
+        let bag = self.explorers.get_mut(&explorer_id)?;
+        Some(match step {
+            Step::SupportedResources(_) => {
+                ExplorerToPlanet::SupportedResourceRequest { explorer_id }
+            }
+            Step::SupportedCombinations(_) => {
+                ExplorerToPlanet::SupportedCombinationRequest { explorer_id }
+            }
+            Step::Generate(_, resource) => ExplorerToPlanet::GenerateResourceRequest {
+                explorer_id,
+                resource,
+            },
+            Step::Combine(_, resource) => ExplorerToPlanet::CombineResourceRequest {
+                explorer_id,
+                msg: bag.combination_request(resource).ok()?,
+            },
+            _ => ExplorerToPlanet::AvailableEnergyCellRequest { explorer_id },
+        })
+    }
+
+    fn check_explorer_answer(
+        &mut self,
+        step: Step,
+        answer: PlanetToExplorer,
+    ) -> Result<(), String> {
+        match step {
+            Step::SupportedResources(_) => {
+                let resources = expect::supported_resources().check(answer)?;
+                if resources != self.gen_rules {
+                    return Err(format!("Supported resources {resources:?}"));
+                }
+            }
+            Step::SupportedCombinations(_) => {
+                let combinations = expect::supported_combinations().check(answer)?;
+                if combinations != self.comb_rules {
+                    return Err(format!("Supported combinations {combinations:?}"));
+                }
+            }
+            Step::Generate(explorer_id, requested) => {
+                if let Some(resource) = expect::generated().check(answer)? {
+                    self.spend(
+                        &format!("{requested:?}"),
+                        self.gen_rules.contains(&requested),
+                        resource.get_type() == requested,
+                    )?;
+                    self.give(explorer_id, GenericResource::BasicResources(resource));
+                }
+            }
+            Step::Combine(explorer_id, requested) => match expect::combined().check(answer)? {
+                Ok(resource) => {
+                    self.spend(
+                        &format!("{requested:?}"),
+                        self.comb_rules.contains(&requested),
+                        resource.get_type() == requested,
+                    )?;
+                    self.give(explorer_id, GenericResource::ComplexResources(resource));
+                }
+                // The ingredients are given back
+                Err((_, first, second)) => {
+                    self.give(explorer_id, first);
+                    self.give(explorer_id, second);
+                }
+            },
+            _ => {
+                let cells = expect::available_cells().check(answer)?;
+                if usize::try_from(cells).ok() != Some(self.charged) {
+                    return Err(format!(
+                        "{cells} available cells, {} expected",
+                        self.charged
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Accounts for a resource given to an explorer
+    fn spend(&mut self, resource: &str, has_recipe: bool, right_type: bool) -> Result<(), String> {
+        if !has_recipe {
+            return Err(format!("{resource} given without a recipe"));
+        }
+        if !right_type {
+            return Err(format!("Another resource given for {resource}"));
+        }
+        if self.charged == 0 {
+            return Err(format!("{resource} given without a charged cell"));
+        }
+        self.charged -= 1;
+        Ok(())
+    }
+
+    fn give(&mut self, explorer_id: ID, resource: GenericResource) {
+        if let Some(bag) = self.explorers.get_mut(&explorer_id) {
+            bag.push(resource);
+        }
+    }
+
+    // Compares the planet state and the AI statistics with the model
+    fn check_state(&self, harness: &mut PlanetHarness) -> Result<(), String> {
+        let state = expect::internal_state()
+            .check(ask(harness, OrchestratorToPlanet::InternalStateRequest))?;
+        let actual = (
+            state.energy_cells.len(),
+            state.charged_cells_count,
+            state.has_rocket,
+        );
+        let expected = (self.cells, self.charged, self.rocket);
+        if actual != expected {
+            return Err(format!(
+                "Planet state (cells, charged, rocket) {actual:?}, {expected:?} expected"
+            ));
+        }
+
+        let planet = self.stats.snapshot().planet;
+        let explorers: Vec<ID> = self.explorers.keys().copied().collect();
+        if (planet.charged_cells, planet.has_rocket) != (self.charged, self.rocket)
+            || planet.explorers != explorers
+        {
+            return Err(format!(
+                "AI statistics {planet:?}, {} charged, rocket {}, explorers {explorers:?} expected",
+                self.charged, self.rocket
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn ask(harness: &mut PlanetHarness, msg: OrchestratorToPlanet) -> PlanetToOrchestrator {
+    harness.send(msg);
+    harness.recv()
+}
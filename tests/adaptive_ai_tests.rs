@@ -4,10 +4,11 @@ mod common;
 
 use common::*;
 use common_game::components::forge::Forge;
+use common_game::components::planet::PlanetType;
 use common_game::components::resource::BasicResourceType;
 use common_game::protocols::orchestrator_planet::{OrchestratorToPlanet, PlanetToOrchestrator};
 use common_game::protocols::planet_explorer::{ExplorerToPlanet, PlanetToExplorer};
-use immutable_cosmic_borrow::create_planet;
+use immutable_cosmic_borrow::{DEFAULT_COMB_RULES, DEFAULT_GEN_RULES, create_planet};
 use std::thread;
 use std::time::Duration;
 
@@ -50,6 +51,9 @@ fn test_adaptive_ai() {
         Duration::from_secs(1),
         Duration::from_millis(100),
         1,
+        PlanetType::C,
+        DEFAULT_GEN_RULES.to_vec(),
+        DEFAULT_COMB_RULES.to_vec(),
         (rx_orchestrator_to_planet, tx_planet_to_orchestrator),
         rx_explorer_to_planet,
     )
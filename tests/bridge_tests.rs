@@ -0,0 +1,261 @@
+#![allow(clippy::pedantic)]
+
+mod common;
+
+use common::planet_options;
+use immutable_cosmic_borrow::bridge::{PlanetBridge, PlanetOptions};
+use immutable_cosmic_borrow::{DecisionPolicy, DecisionRequest, PolicyInputs};
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
+// Denies the first combination after keeping the planet busy past the bridge timeout, then
+// accepts everything
+#[derive(Default)]
+struct SlowCombination {
+    slept: bool,
+}
+
+impl DecisionPolicy for SlowCombination {
+    fn name(&self) -> &str {
+        "slow_combination"
+    }
+
+    fn decide(&mut self, inputs: &PolicyInputs) -> bool {
+        if matches!(inputs.request, DecisionRequest::CombineResource(_)) && !self.slept {
+            self.slept = true;
+            thread::sleep(Duration::from_millis(2500));
+            return false;
+        }
+        true
+    }
+}
+
+/// Test a whole explorer visit through the bridge, with a combination of generated resources
+#[test]
+fn test_bridge_explorer_visit() {
+    let options = planet_options(&[
+        "--id",
+        "4",
+        "--random",
+        "--basic-coeff",
+        "0",
+        "--complex-coeff",
+        "0",
+        "--gen",
+        "Carbon",
+        "--comb",
+        "Diamond,Water",
+    ]);
+    let mut bridge = PlanetBridge::spawn(|orchestrator_channels, explorers_receiver| {
+        options.create_planet(orchestrator_channels, explorers_receiver)
+    })
+    .expect("Planet creation failed");
+    assert_eq!(bridge.planet_id(), 4);
+
+    let mut send = |line: &str| bridge.handle_line(line);
+    assert_eq!(
+        send(r#"{"type":"start"}"#),
+        r#"{"planet_id":4,"type":"start_result"}"#
+    );
+    assert!(send(r#"{"type":"start"}"#).contains("already running"));
+    assert!(send(r#"{"type":"explorer_arrival","explorer_id":3}"#).contains(r#""ok":true"#));
+    assert_eq!(
+        send(r#"{"type":"supported_combination","explorer_id":3}"#),
+        r#"{"combinations":["Diamond","Water"],"explorer_id":3,"type":"supported_combination_response"}"#
+    );
+
+    for _ in 0..2 {
+        assert!(send(r#"{"type":"sunray"}"#).contains("sunray_ack"));
+        assert!(
+            send(r#"{"type":"generate_resource","explorer_id":3,"resource":"Carbon"}"#)
+                .contains(r#""resource":"Carbon""#)
+        );
+    }
+    assert!(send(r#"{"type":"sunray"}"#).contains("sunray_ack"));
+    assert_eq!(
+        send(r#"{"type":"combine_resource","explorer_id":3,"resource":"Diamond"}"#),
+        r#"{"error":null,"explorer_id":3,"resource":"Diamond","type":"combine_resource_response"}"#
+    );
+    // Both carbons were used
+    assert!(
+        send(r#"{"type":"combine_resource","explorer_id":3,"resource":"Diamond"}"#)
+            .contains("Missing ingredient Carbon")
+    );
+
+    assert_eq!(
+        send(r#"{"type":"internal_state"}"#),
+        r#"{"charged_cells_count":0,"energy_cells":[false],"has_rocket":false,"planet_id":4,"type":"internal_state"}"#
+    );
+    assert!(send(r#"{"type":"explorer_departure","explorer_id":3}"#).contains(r#""ok":true"#));
+    assert!(
+        send(r#"{"type":"available_energy_cell","explorer_id":3}"#).contains("not on the planet")
+    );
+
+    assert!(send(r#"{"type":"stop"}"#).contains("stop_result"));
+    assert!(send(r#"{"type":"sunray"}"#).contains(r#""type":"stopped""#));
+    assert!(send(r#"{"type":"kill"}"#).contains("kill_result"));
+    assert!(bridge.is_killed());
+    assert!(
+        bridge
+            .handle_line(r#"{"type":"sunray"}"#)
+            .contains("killed")
+    );
+}
+
+/// Test that the late answer to a command that timed out is not taken for the answer to the
+/// next one, and that the ingredients it gives back can be combined again
+#[test]
+fn test_bridge_late_answer() {
+    let options = planet_options(&["--gen", "Carbon", "--comb", "Diamond"]);
+    let ai = options
+        .create_ai()
+        .with_decision_policy(SlowCombination::default());
+    let mut bridge = PlanetBridge::spawn(|orchestrator_channels, explorers_receiver| {
+        options.create_planet_with_ai(ai, orchestrator_channels, explorers_receiver)
+    })
+    .expect("Planet creation failed");
+
+    let mut send = |line: &str| bridge.handle_line(line);
+    assert!(send(r#"{"type":"start"}"#).contains("start_result"));
+    assert!(send(r#"{"type":"explorer_arrival","explorer_id":1}"#).contains(r#""ok":true"#));
+    for _ in 0..2 {
+        assert!(send(r#"{"type":"sunray"}"#).contains("sunray_ack"));
+        assert!(
+            send(r#"{"type":"generate_resource","explorer_id":1,"resource":"Carbon"}"#)
+                .contains(r#""resource":"Carbon""#)
+        );
+    }
+    assert!(send(r#"{"type":"sunray"}"#).contains("sunray_ack"));
+
+    let response = send(r#"{"type":"combine_resource","explorer_id":1,"resource":"Diamond"}"#);
+    assert!(response.contains("did not answer in time"), "{response}");
+    // Sent while the planet is still busy, it is answered after the denied combination
+    let response = send(r#"{"type":"available_energy_cell","explorer_id":1}"#);
+    assert!(
+        response.contains(r#""type":"available_energy_cell_response""#),
+        "{response}"
+    );
+    assert_eq!(
+        send(r#"{"type":"combine_resource","explorer_id":1,"resource":"Diamond"}"#),
+        r#"{"error":null,"explorer_id":1,"resource":"Diamond","type":"combine_resource_response"}"#
+    );
+}
+
+/// Test that a planet of each type can be created from the command line, with the
+/// combination rules the type allows by default or none at all
+#[test]
+fn test_bridge_planet_types() {
+    for (planet_type, cells, combinations) in [
+        ("A", 5, "[]"),
+        ("B", 1, r#"["Water"]"#),
+        (
+            "C",
+            1,
+            r#"["AIPartner","Diamond","Dolphin","Life","Robot","Water"]"#,
+        ),
+        ("D", 5, "[]"),
+    ] {
+        for args in [
+            vec!["--type", planet_type],
+            vec!["--comb", "none", "--type", planet_type],
+            vec!["--type", planet_type, "--comb", ""],
+        ] {
+            let options = planet_options(&args);
+            let mut bridge = PlanetBridge::spawn(|orchestrator_channels, explorers_receiver| {
+                options.create_planet(orchestrator_channels, explorers_receiver)
+            })
+            .unwrap_or_else(|err| panic!("{args:?}: {err}"));
+            let mut send = |line: &str| bridge.handle_line(line);
+            assert!(send(r#"{"type":"start"}"#).contains("start_result"));
+            let cells = vec!["false"; cells].join(",");
+            let state = send(r#"{"type":"internal_state"}"#);
+            assert!(
+                state.contains(&format!(r#""energy_cells":[{cells}]"#)),
+                "{args:?}: {state}"
+            );
+            assert!(
+                send(r#"{"type":"explorer_arrival","explorer_id":1}"#).contains(r#""ok":true"#)
+            );
+            let expected = if args.len() == 2 { combinations } else { "[]" };
+            assert!(
+                send(r#"{"type":"supported_combination","explorer_id":1}"#)
+                    .contains(&format!(r#""combinations":{expected}"#)),
+                "{args:?}"
+            );
+        }
+    }
+}
+
+/// Test that invalid commands and options are rejected
+#[test]
+fn test_bridge_invalid_input() {
+    let mut bridge = PlanetBridge::spawn(|orchestrator_channels, explorers_receiver| {
+        PlanetOptions::default().create_planet(orchestrator_channels, explorers_receiver)
+    })
+    .expect("Planet creation failed");
+
+    for line in [
+        "not json",
+        r#"{"type":"teleport"}"#,
+        r#"{"type":"explorer_arrival"}"#,
+        r#"{"type":"explorer_arrival","explorer_id":-1}"#,
+        r#"{"type":"generate_resource","explorer_id":1,"resource":"Gold"}"#,
+    ] {
+        let response = bridge.handle_line(line);
+        assert!(response.starts_with(r#"{"message":"#), "{line}: {response}");
+        assert!(
+            response.ends_with(r#""type":"error"}"#),
+            "{line}: {response}"
+        );
+    }
+
+    let mut options = PlanetOptions::default();
+    assert!(options.parse_args(["--type".to_string()]).is_err());
+    assert!(
+        options
+            .parse_args(["--gen".to_string(), "Gold".to_string()])
+            .is_err()
+    );
+    // Rules not allowed for the planet type
+    let options = planet_options(&["--type", "C", "--gen", "Carbon,Oxygen"]);
+    assert!(
+        PlanetBridge::spawn(|orchestrator_channels, explorers_receiver| {
+            options.create_planet(orchestrator_channels, explorers_receiver)
+        })
+        .is_err()
+    );
+}
+
+/// Test the binary end to end on stdin and stdout
+#[test]
+fn test_planet_binary() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_icb-planet"))
+        .args(["--id", "9"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("Binary not started");
+
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"{\"type\":\"start\"}\n\n{\"type\":\"asteroid\"}\n{\"type\":\"kill\"}\n")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(
+        lines,
+        [
+            r#"{"planet_id":9,"type":"start_result"}"#,
+            r#"{"planet_id":9,"rocket":false,"type":"asteroid_ack"}"#,
+            r#"{"planet_id":9,"type":"kill_result"}"#,
+        ]
+    );
+}
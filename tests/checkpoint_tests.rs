@@ -0,0 +1,245 @@
+#![allow(clippy::pedantic)]
+
+mod common;
+
+use common::*;
+use common_game::components::asteroid::Asteroid;
+use common_game::components::planet::{Planet, PlanetType};
+use common_game::components::resource::BasicResourceType;
+use common_game::components::sunray::Sunray;
+use common_game::protocols::orchestrator_planet::{OrchestratorToPlanet, PlanetToOrchestrator};
+use common_game::protocols::planet_explorer::ExplorerToPlanet;
+use immutable_cosmic_borrow::bridge::PlanetOptions;
+use immutable_cosmic_borrow::qlearning::ExplorerTier;
+use immutable_cosmic_borrow::testing::{PlanetHarness, expect};
+use immutable_cosmic_borrow::{
+    Ai, DEFAULT_COMB_RULES, DEFAULT_GEN_RULES, DecisionPolicy, PolicyInputs, StatsHandle,
+};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use std::{fs, process};
+
+fn checkpoint_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("icb_checkpoint_{name}_{}.json", process::id()));
+    let _ = fs::remove_file(&path);
+    path
+}
+
+fn new_ai(path: &PathBuf) -> Ai {
+    Ai::new(
+        false,
+        0.5,
+        0.5,
+        Duration::from_secs(10),
+        Duration::from_secs(1),
+    )
+    .with_checkpoint_file(path)
+    .expect("Checkpoint not loaded")
+}
+
+// Runs a planet with `ai`, sending `events` (true for a sunray) before stopping it
+fn run_planet(ai: Ai, events: &[bool]) -> StatsHandle {
+    let stats = ai.stats_handle();
+    let (tx_orchestrator, rx_orchestrator_to_planet) =
+        crossbeam_channel::bounded::<OrchestratorToPlanet>(1);
+    let (tx_planet_to_orchestrator, rx_orchestrator) =
+        crossbeam_channel::bounded::<PlanetToOrchestrator>(1);
+    let (_tx_explorer, rx_explorer_to_planet) = crossbeam_channel::bounded::<ExplorerToPlanet>(1);
+
+    let planet = Planet::new(
+        0,
+        PlanetType::C,
+        Box::new(ai),
+        DEFAULT_GEN_RULES.to_vec(),
+        DEFAULT_COMB_RULES.to_vec(),
+        (rx_orchestrator_to_planet, tx_planet_to_orchestrator),
+        rx_explorer_to_planet,
+    )
+    .expect("Planet creation failed");
+
+    let handle = start_thread(planet);
+    orchestrator_start_planet(&tx_orchestrator, &rx_orchestrator);
+    for &sunray in events {
+        let msg = if sunray {
+            OrchestratorToPlanet::Sunray(Sunray::default())
+        } else {
+            OrchestratorToPlanet::Asteroid(Asteroid::default())
+        };
+        orchestrator_send(&tx_orchestrator, &rx_orchestrator, msg);
+    }
+    orchestrator_stop_planet(&tx_orchestrator, &rx_orchestrator);
+
+    drop(tx_orchestrator);
+    let _ = handle.join();
+    stats
+}
+
+/// Test that a relaunched planet starts from the learned state of the previous one
+#[test]
+fn test_checkpoint_restored_on_relaunch() {
+    let path = checkpoint_path("relaunch");
+
+    let stats = run_planet(new_ai(&path), &[false, false, true, false]);
+    let learned = stats.snapshot().estimator.unwrap();
+    assert!(
+        learned.sunray_probability < 0.5,
+        "Expected a learned threat"
+    );
+
+    let contents = fs::read_to_string(&path).expect("Checkpoint not saved");
+    assert!(contents.contains("\"version\":2"), "{contents}");
+
+    // Nothing learned before the planet is started
+    let ai = new_ai(&path);
+    assert_eq!(ai.stats().estimator.unwrap().sunray_probability, 0.5);
+
+    let stats = run_planet(ai, &[]);
+    let restored = stats.snapshot().estimator.unwrap();
+    assert_eq!(restored.sunray_probability, learned.sunray_probability);
+    assert!((restored.sun_intensity - learned.sun_intensity).abs() < 1e-3);
+    assert!((restored.asteroid_intensity - learned.asteroid_intensity).abs() < 1e-3);
+
+    let _ = fs::remove_file(path);
+}
+
+/// Test that a planet dropped before being started keeps the previous checkpoint
+#[test]
+fn test_checkpoint_kept_if_never_started() {
+    let path = checkpoint_path("unstarted");
+
+    run_planet(new_ai(&path), &[false, false]);
+    let saved = fs::read_to_string(&path).expect("Checkpoint not saved");
+
+    drop(new_ai(&path));
+    let after = fs::read_to_string(&path).expect("Checkpoint removed");
+    let strip = |contents: &str| {
+        contents
+            .split("\"saved_at_ms\"")
+            .next()
+            .unwrap()
+            .to_string()
+    };
+    assert_eq!(strip(&saved), strip(&after));
+    assert!(after.contains("\"estimator\":{"));
+
+    let _ = fs::remove_file(path);
+}
+
+/// Test that missing checkpoints start cold and invalid ones are rejected
+#[test]
+fn test_invalid_checkpoint_rejected() {
+    let path = checkpoint_path("invalid");
+    assert!(
+        Ai::new(false, 0.5, 0.5, Duration::from_secs(1), Duration::ZERO)
+            .with_checkpoint_file(&path)
+            .is_ok()
+    );
+
+    fs::write(&path, "not json").unwrap();
+    assert!(
+        Ai::new(false, 0.5, 0.5, Duration::from_secs(1), Duration::ZERO)
+            .with_checkpoint_file(&path)
+            .is_err()
+    );
+
+    fs::write(&path, "{\"version\":99}").unwrap();
+    let err = Ai::new(false, 0.5, 0.5, Duration::from_secs(1), Duration::ZERO)
+        .with_checkpoint_file(&path)
+        .err()
+        .expect("Expected a version error");
+    assert!(err.contains("version"), "{err}");
+
+    let _ = fs::remove_file(path);
+}
+
+// Accepts everything, keeping the tier of each explorer it decides for
+#[derive(Clone, Default)]
+struct Tiers(Arc<Mutex<Vec<(u32, ExplorerTier)>>>);
+
+impl DecisionPolicy for Tiers {
+    fn name(&self) -> &str {
+        "tiers"
+    }
+
+    fn decide(&mut self, inputs: &PolicyInputs) -> bool {
+        let tier = ExplorerTier::of(inputs.served_requests);
+        self.0.lock().unwrap().push((inputs.explorer_id, tier));
+        true
+    }
+}
+
+// Serves `requests` (explorer, count) on a planet checkpointed at `path`, returning the
+// tiers the policy saw
+fn serve(path: &PathBuf, requests: &[(u32, usize)]) -> Vec<(u32, ExplorerTier)> {
+    let options = PlanetOptions {
+        gen_rules: vec![BasicResourceType::Hydrogen],
+        comb_rules: vec![],
+        ..PlanetOptions::default()
+    };
+    let tiers = Tiers::default();
+    let ai = options
+        .create_ai()
+        .with_decision_policy(tiers.clone())
+        .with_checkpoint_file(path)
+        .expect("Checkpoint not loaded");
+    let mut harness = PlanetHarness::spawn(|orchestrator_channels, explorers_receiver| {
+        options.create_planet_with_ai(ai, orchestrator_channels, explorers_receiver)
+    })
+    .unwrap();
+    harness.start();
+    // The first charge builds the rocket
+    harness.sunray();
+    for &(explorer_id, count) in requests {
+        harness.arrive(explorer_id);
+        for _ in 0..count {
+            harness.sunray();
+            harness.explorer_send_and_expect(
+                ExplorerToPlanet::GenerateResourceRequest {
+                    explorer_id,
+                    resource: BasicResourceType::Hydrogen,
+                },
+                expect::generated(),
+            );
+        }
+    }
+    harness.stop();
+    drop(harness);
+    tiers.0.lock().unwrap().clone()
+}
+
+/// Test that the requests served to each explorer survive a relaunch, so the explorers keep
+/// their tier, and that version 1 checkpoints start without them
+#[test]
+fn test_explorer_tiers_restored_on_relaunch() {
+    let path = checkpoint_path("tiers");
+    let first = serve(&path, &[(1, 5), (2, 1)]);
+    assert_eq!(first[0], (1, ExplorerTier::New));
+    assert_eq!(first[4], (1, ExplorerTier::Returning));
+    assert_eq!(first[5], (2, ExplorerTier::New));
+
+    let relaunched = serve(&path, &[(1, 1), (2, 1), (3, 1)]);
+    assert_eq!(
+        relaunched,
+        [
+            (1, ExplorerTier::Regular),
+            (2, ExplorerTier::Returning),
+            (3, ExplorerTier::New),
+        ]
+    );
+
+    fs::write(
+        &path,
+        r#"{"version":1,"estimator":null,"reserved_for_ns":null}"#,
+    )
+    .unwrap();
+    assert_eq!(serve(&path, &[(1, 1)]), [(1, ExplorerTier::New)]);
+    let contents = fs::read_to_string(&path).unwrap();
+    assert!(contents.contains(r#""version":2"#), "{contents}");
+    assert!(
+        contents.contains(r#""served":[{"explorer_id":1,"served":1}]"#),
+        "{contents}"
+    );
+
+    let _ = fs::remove_file(path);
+}
@@ -1,14 +1,19 @@
 #![allow(clippy::pedantic)]
 
 use common_game::components::planet::Planet;
+use common_game::components::planet::PlanetType;
+use common_game::components::resource::BasicResourceType;
 use common_game::protocols::orchestrator_planet::{OrchestratorToPlanet, PlanetToOrchestrator};
 use common_game::protocols::planet_explorer::{ExplorerToPlanet, PlanetToExplorer};
-use immutable_cosmic_borrow::create_planet;
+use immutable_cosmic_borrow::bridge::PlanetOptions;
+use immutable_cosmic_borrow::testing::{DEFAULT_TIMEOUT, PlanetHarness, expect};
+use immutable_cosmic_borrow::{Ai, DEFAULT_COMB_RULES, DEFAULT_GEN_RULES, create_planet};
 use std::thread;
 use std::thread::JoinHandle;
 use std::time::Duration;
 
-// Helper functions to test the planet AI behaviour
+// Helper functions to test the planet AI behaviour, every answer is awaited so no sleep is
+// needed (see also `immutable_cosmic_borrow::testing`)
 
 #[allow(dead_code)]
 pub fn create_test_planet() -> (
@@ -40,6 +45,9 @@ pub fn create_test_planet() -> (
         Duration::from_millis(100),
         Duration::from_secs(1),
         0,
+        PlanetType::C,
+        DEFAULT_GEN_RULES.to_vec(),
+        DEFAULT_COMB_RULES.to_vec(),
         (rx_orchestrator_to_planet, tx_planet_to_orchestrator),
         rx_explorer_to_planet,
     );
@@ -51,6 +59,7 @@ pub fn create_test_planet() -> (
         tx_explorer_to_planet,
     )
 }
+#[allow(dead_code)]
 pub fn orchestrator_start_planet(
     tx_orchestrator: &crossbeam_channel::Sender<OrchestratorToPlanet>,
     rx_orchestrator: &crossbeam_channel::Receiver<PlanetToOrchestrator>,
@@ -58,10 +67,8 @@ pub fn orchestrator_start_planet(
     tx_orchestrator
         .send(OrchestratorToPlanet::StartPlanetAI)
         .expect("Orchestrator failed to send");
-    thread::sleep(Duration::from_millis(50));
-
     rx_orchestrator
-        .recv_timeout(Duration::from_millis(200))
+        .recv_timeout(DEFAULT_TIMEOUT)
         .expect("Orchestrator failed to receive");
 }
 
@@ -75,7 +82,6 @@ pub fn orchestrator_kill_planet(
         rx_orchestrator,
         OrchestratorToPlanet::KillPlanet,
     );
-    thread::sleep(Duration::from_millis(200));
 }
 
 #[allow(dead_code)]
@@ -86,9 +92,8 @@ pub fn orchestrator_stop_planet(
     tx_orchestrator
         .send(OrchestratorToPlanet::StopPlanetAI)
         .expect("Orchestrator failed to send");
-    thread::sleep(Duration::from_millis(50));
     rx_orchestrator
-        .recv_timeout(Duration::from_millis(200))
+        .recv_timeout(DEFAULT_TIMEOUT)
         .expect("Orchestrator failed to receive");
 
     orchestrator_send(
@@ -97,15 +102,14 @@ pub fn orchestrator_stop_planet(
         OrchestratorToPlanet::KillPlanet,
     );
 }
+#[allow(dead_code)]
 pub fn orchestrator_send(
     tx: &crossbeam_channel::Sender<OrchestratorToPlanet>,
     rx: &crossbeam_channel::Receiver<PlanetToOrchestrator>,
     msg: OrchestratorToPlanet,
 ) -> PlanetToOrchestrator {
     tx.send(msg).expect("Orchestrator failed to send");
-    thread::sleep(Duration::from_millis(50));
-
-    rx.recv_timeout(Duration::from_millis(200))
+    rx.recv_timeout(DEFAULT_TIMEOUT)
         .expect("Orchestrator failed to receive")
 }
 
@@ -116,11 +120,59 @@ pub fn explorer_send(
     msg: ExplorerToPlanet,
 ) -> PlanetToExplorer {
     tx.send(msg).expect("Explorer failed to send");
-    thread::sleep(Duration::from_millis(50));
-
-    rx.recv_timeout(Duration::from_millis(2000))
+    rx.recv_timeout(DEFAULT_TIMEOUT)
         .expect("Explorer failed to receive")
 }
+#[allow(dead_code)]
 pub fn start_thread(mut planet: Planet) -> JoinHandle<Result<(), String>> {
     thread::spawn(move || planet.run())
 }
+
+// Planet options parsed from command line arguments, which must all be options
+#[allow(dead_code)]
+pub fn planet_options(args: &[&str]) -> PlanetOptions {
+    let mut options = PlanetOptions::default();
+    let rest = options
+        .parse_args(args.iter().map(|arg| arg.to_string()))
+        .expect("Invalid options");
+    assert!(rest.is_empty());
+    options
+}
+
+// Planet options generating only Hydrogen, with no combination
+#[allow(dead_code)]
+pub fn hydrogen_options(planet_type: PlanetType, basic_gen_coeff: f32) -> PlanetOptions {
+    PlanetOptions {
+        planet_type,
+        basic_gen_coeff,
+        gen_rules: vec![BasicResourceType::Hydrogen],
+        comb_rules: vec![],
+        ..PlanetOptions::default()
+    }
+}
+
+// Started planet running the given AI, with explorer 1 already arrived
+#[allow(dead_code)]
+pub fn spawn_started(options: &PlanetOptions, ai: Ai) -> PlanetHarness {
+    let mut harness = PlanetHarness::spawn(|orchestrator_channels, explorers_receiver| {
+        options.create_planet_with_ai(ai, orchestrator_channels, explorers_receiver)
+    })
+    .unwrap();
+    harness.start();
+    harness.arrive(1);
+    harness
+}
+
+// Asks Hydrogen for explorer 1, returning whether it was generated
+#[allow(dead_code)]
+pub fn generate(harness: &mut PlanetHarness) -> bool {
+    harness
+        .explorer_send_and_expect(
+            ExplorerToPlanet::GenerateResourceRequest {
+                explorer_id: 1,
+                resource: BasicResourceType::Hydrogen,
+            },
+            expect::generated(),
+        )
+        .is_some()
+}
@@ -0,0 +1,159 @@
+#![allow(clippy::pedantic)]
+
+mod common;
+
+use common::*;
+use common_game::components::planet::{Planet, PlanetType};
+use common_game::components::resource::BasicResourceType;
+use common_game::components::sunray::Sunray;
+use common_game::protocols::orchestrator_planet::{OrchestratorToPlanet, PlanetToOrchestrator};
+use common_game::protocols::planet_explorer::{ExplorerToPlanet, PlanetToExplorer};
+use immutable_cosmic_borrow::replay::{self, Session, SessionEntry, SessionInput};
+use immutable_cosmic_borrow::{
+    Ai, AiSetting, DEFAULT_COMB_RULES, DEFAULT_GEN_RULES, DecisionPolicyKind, DecisionRecord,
+    LogAction, LogConfig, PauseMode,
+};
+use std::time::Duration;
+use std::{fs, process};
+
+/// Test that settings changed through the control handle apply to the next requests, and
+/// are recorded so the session still replays
+#[test]
+fn test_reconfigure_running_planet() {
+    let path = std::env::temp_dir().join(format!("icb_control_{}.jsonl", process::id()));
+    let (tx_orchestrator, rx_orchestrator_to_planet) =
+        crossbeam_channel::bounded::<OrchestratorToPlanet>(1);
+    let (tx_planet_to_orchestrator, rx_orchestrator) =
+        crossbeam_channel::bounded::<PlanetToOrchestrator>(1);
+    let (tx_explorer, rx_explorer_to_planet) = crossbeam_channel::bounded::<ExplorerToPlanet>(1);
+    let (tx_records, rx_records) = crossbeam_channel::unbounded::<DecisionRecord>();
+
+    let ai = Ai::new(false, 0.5, 0.5, Duration::from_secs(1), Duration::ZERO)
+        .with_seed(7)
+        .with_decision_sink(tx_records)
+        .with_session_recording(&path)
+        .expect("Session file not created");
+    let control = ai.control_handle();
+
+    let planet = Planet::new(
+        2,
+        PlanetType::C,
+        Box::new(ai),
+        DEFAULT_GEN_RULES.to_vec(),
+        DEFAULT_COMB_RULES.to_vec(),
+        (rx_orchestrator_to_planet, tx_planet_to_orchestrator),
+        rx_explorer_to_planet,
+    )
+    .expect("Planet creation failed");
+
+    let handle = start_thread(planet);
+    orchestrator_start_planet(&tx_orchestrator, &rx_orchestrator);
+
+    let (tx_to_explorer, rx_explorer) = crossbeam_channel::unbounded::<PlanetToExplorer>();
+    orchestrator_send(
+        &tx_orchestrator,
+        &rx_orchestrator,
+        OrchestratorToPlanet::IncomingExplorerRequest {
+            explorer_id: 5,
+            new_sender: tx_to_explorer,
+        },
+    );
+    let generate = || ExplorerToPlanet::GenerateResourceRequest {
+        explorer_id: 5,
+        resource: BasicResourceType::Hydrogen,
+    };
+
+    orchestrator_send(
+        &tx_orchestrator,
+        &rx_orchestrator,
+        OrchestratorToPlanet::Sunray(Sunray::default()),
+    );
+    explorer_send(&tx_explorer, &rx_explorer, generate());
+    assert_eq!(
+        rx_records.try_recv().unwrap().policy,
+        DecisionPolicyKind::Adaptive
+    );
+
+    control.set(AiSetting::RandomMode(true)).unwrap();
+    control.set(AiSetting::BasicGenCoeff(1.0)).unwrap();
+    control
+        .set(AiSetting::HalfLife(Duration::from_millis(200)))
+        .unwrap();
+    control
+        .set(AiSetting::PauseMode(PauseMode::Forget))
+        .unwrap();
+    let log_config = LogConfig::new()
+        .with_action(LogAction::Acks, false)
+        .with_sampling(LogAction::Decisions, 5);
+    control.set(AiSetting::LogConfig(log_config)).unwrap();
+
+    orchestrator_send(
+        &tx_orchestrator,
+        &rx_orchestrator,
+        OrchestratorToPlanet::Sunray(Sunray::default()),
+    );
+    explorer_send(&tx_explorer, &rx_explorer, generate());
+    let record = rx_records.try_recv().unwrap();
+    assert_eq!(record.policy, DecisionPolicyKind::Random);
+    assert_eq!(record.inputs.threshold, 1.0);
+
+    orchestrator_stop_planet(&tx_orchestrator, &rx_orchestrator);
+    drop(tx_orchestrator);
+    let _ = handle.join();
+
+    // The planet is gone, so is the AI
+    assert!(control.set(AiSetting::RandomMode(false)).is_err());
+
+    let session = Session::load(&path).expect("Session not loaded");
+    let _ = fs::remove_file(&path);
+    let settings: Vec<AiSetting> = session
+        .entries
+        .iter()
+        .filter_map(|entry| match entry {
+            SessionEntry::Input {
+                input: SessionInput::Reconfigure(setting),
+                ..
+            } => Some(*setting),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        settings,
+        [
+            AiSetting::RandomMode(true),
+            AiSetting::BasicGenCoeff(1.0),
+            AiSetting::HalfLife(Duration::from_millis(200)),
+            AiSetting::PauseMode(PauseMode::Forget),
+            AiSetting::LogConfig(log_config),
+        ]
+    );
+
+    let report = replay::replay(&session).expect("Replay failed");
+    assert_eq!(report.decisions, 2);
+    assert!(
+        report.is_match(),
+        "Unexpected divergence: {}",
+        report.divergence.unwrap()
+    );
+}
+
+/// Test that invalid settings are rejected when sent
+#[test]
+fn test_invalid_settings_rejected() {
+    let ai = Ai::new(false, 0.5, 0.5, Duration::from_secs(1), Duration::ZERO);
+    let control = ai.control_handle();
+
+    assert!(control.set(AiSetting::BasicGenCoeff(1.5)).is_err());
+    assert!(control.set(AiSetting::ComplexGenCoeff(-0.1)).is_err());
+    assert!(control.set(AiSetting::BasicGenCoeff(f32::NAN)).is_err());
+    assert!(control.set(AiSetting::HalfLife(Duration::ZERO)).is_err());
+    assert!(
+        control
+            .set(AiSetting::DefenseWindow(Duration::ZERO))
+            .is_ok()
+    );
+    assert!(control.set(AiSetting::ComplexGenCoeff(0.0)).is_ok());
+
+    drop(ai);
+    assert!(control.set(AiSetting::RandomMode(true)).is_err());
+}
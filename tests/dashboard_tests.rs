@@ -0,0 +1,169 @@
+#![allow(clippy::pedantic)]
+
+mod common;
+
+use common::planet_options;
+use immutable_cosmic_borrow::bridge::socket::{BridgeClient, BridgeServer};
+use immutable_cosmic_borrow::bridge::{PlanetBridge, PlanetOptions};
+use immutable_cosmic_borrow::dashboard::{
+    BridgeFeed, Dashboard, DashboardFeed, LocalFeed, ThreatLevel,
+};
+use immutable_cosmic_borrow::{AiStats, DecisionOutcome, EstimatorStats};
+use std::process::Command;
+
+/// Test the threat level and the rendering of the statistics
+#[test]
+fn test_dashboard_render() {
+    let mut dashboard = Dashboard::new(Some(7));
+    assert!(dashboard.threat().is_none());
+
+    let mut stats = AiStats {
+        estimator: Some(EstimatorStats {
+            sun_intensity: 0.6,
+            asteroid_intensity: 0.4,
+            sunray_probability: 0.6,
+            time_constant_secs: 1.0,
+        }),
+        ..AiStats::default()
+    };
+    stats.planet.energy_cells = 5;
+    stats.planet.charged_cells = 2;
+    stats.planet.explorers = vec![2, 9];
+    dashboard.update(stats.clone());
+
+    // One asteroid without a rocket, two in a row with one
+    let (risk, level) = dashboard.threat().unwrap();
+    assert!((risk - 0.4).abs() < 1e-6);
+    assert_eq!(level, ThreatLevel::High);
+    stats.planet.has_rocket = true;
+    dashboard.update(stats);
+    let (risk, level) = dashboard.threat().unwrap();
+    assert!((risk - 0.16).abs() < 1e-6);
+    assert_eq!(level, ThreatLevel::Elevated);
+
+    let frame = dashboard.render(80, 20);
+    assert!(frame.starts_with("ICB planet 7\n"));
+    assert!(frame.contains("Sunray probability 0.600"));
+    assert!(frame.contains("Threat elevated (risk 0.160)"));
+    assert!(frame.contains("Cells [##---] 2/5 charged  Rocket ready"));
+    assert!(frame.contains("Explorers 2, 9"));
+
+    // Lines are cut to the size given
+    let frame = dashboard.render(10, 3);
+    assert_eq!(frame.lines().count(), 3);
+    assert!(frame.lines().all(|line| line.chars().count() <= 10));
+}
+
+/// Test the feed of a planet in the same process and of one served on a socket
+#[test]
+fn test_dashboard_feeds() {
+    let options = planet_options(&[
+        "--id",
+        "5",
+        "--random",
+        "--basic-coeff",
+        "0",
+        "--gen",
+        "Carbon",
+    ]);
+    let ai = options.create_ai();
+    let stats = ai.stats_handle();
+    // The local feed and the bridge each get their own channel
+    let (tx_local, rx_local) = crossbeam_channel::unbounded();
+    let (tx_bridge, rx_bridge) = crossbeam_channel::unbounded();
+    let ai = ai
+        .with_decision_sink(tx_local)
+        .with_decision_sink(tx_bridge);
+    let bridge = PlanetBridge::spawn(|orchestrator_channels, explorers_receiver| {
+        options.create_planet_with_ai(ai, orchestrator_channels, explorers_receiver)
+    })
+    .expect("Planet creation failed");
+
+    let mut local = LocalFeed::new(stats.clone(), rx_local);
+    let server = BridgeServer::bind_tcp(bridge.with_feed(stats, rx_bridge), 0).unwrap();
+
+    let mut client = BridgeClient::connect(server.address()).unwrap();
+    for command in [
+        r#"{"type":"start"}"#,
+        r#"{"type":"explorer_arrival","explorer_id":3}"#,
+        r#"{"type":"explorer_arrival","explorer_id":8}"#,
+        r#"{"type":"explorer_departure","explorer_id":3}"#,
+        r#"{"type":"sunray"}"#,
+        r#"{"type":"generate_resource","explorer_id":8,"resource":"Carbon"}"#,
+        r#"{"type":"generate_resource","explorer_id":8,"resource":"Carbon"}"#,
+    ] {
+        assert!(
+            !client
+                .request(command)
+                .unwrap()
+                .contains(r#""type":"error""#)
+        );
+    }
+
+    let mut dashboard = Dashboard::new(Some(5));
+    local.poll(&mut dashboard).unwrap();
+    let outcomes: Vec<DecisionOutcome> = dashboard
+        .decisions()
+        .map(|decision| decision.outcome)
+        .collect();
+    assert_eq!(
+        outcomes,
+        [DecisionOutcome::Accepted, DecisionOutcome::Denied]
+    );
+    assert_eq!(dashboard.stats().planet.explorers, [8]);
+    assert_eq!(dashboard.stats().planet.charged_cells, 0);
+
+    let mut remote = Dashboard::new(None);
+    BridgeFeed::connect(server.address())
+        .unwrap()
+        .poll(&mut remote)
+        .unwrap();
+    assert!(remote.render(100, 30).starts_with("ICB planet 5\n"));
+    assert_eq!(remote.stats().planet, dashboard.stats().planet);
+    assert_eq!(remote.stats().decisions, dashboard.stats().decisions);
+    assert_eq!(remote.stats().sunrays_received, 1);
+    assert!(remote.decisions().eq(dashboard.decisions()));
+    server.shutdown();
+}
+
+/// Test that the bridge answers the feed commands only when given a feed
+#[test]
+fn test_bridge_without_feed() {
+    let mut bridge = PlanetBridge::spawn(|orchestrator_channels, explorers_receiver| {
+        PlanetOptions::default().create_planet(orchestrator_channels, explorers_receiver)
+    })
+    .unwrap();
+    assert!(
+        bridge
+            .handle_line(r#"{"type":"stats"}"#)
+            .contains("no feed")
+    );
+    assert!(
+        bridge
+            .handle_line(r#"{"type":"decisions"}"#)
+            .contains("no feed")
+    );
+}
+
+/// Test the binary with a planet in the same process
+#[test]
+fn test_dashboard_binary_demo() {
+    let output = Command::new(env!("CARGO_BIN_EXE_icb-dashboard"))
+        .args([
+            "--demo",
+            "--id",
+            "2",
+            "--frames",
+            "2",
+            "--interval-ms",
+            "50",
+            "--plain",
+        ])
+        .output()
+        .expect("Binary not started");
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout.matches("ICB planet 2\n").count(), 2);
+    assert!(stdout.contains("Sunray probability"));
+    assert!(!stdout.contains('\x1b'));
+}
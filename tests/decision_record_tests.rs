@@ -0,0 +1,168 @@
+#![allow(clippy::pedantic)]
+
+mod common;
+
+use common::*;
+use common_game::components::planet::{Planet, PlanetType};
+use common_game::components::resource::BasicResourceType;
+use common_game::components::sunray::Sunray;
+use common_game::protocols::orchestrator_planet::{OrchestratorToPlanet, PlanetToOrchestrator};
+use common_game::protocols::planet_explorer::{ExplorerToPlanet, PlanetToExplorer};
+use immutable_cosmic_borrow::{
+    Ai, DEFAULT_COMB_RULES, DEFAULT_GEN_RULES, DecisionInputs, DecisionOutcome, DecisionPolicyKind,
+    DecisionReason, DecisionRecord, DecisionRequest,
+};
+use std::time::{Duration, UNIX_EPOCH};
+
+/// Test the conversion of a record to a log payload
+#[test]
+fn test_record_to_payload() {
+    let record = DecisionRecord {
+        timestamp: UNIX_EPOCH + Duration::from_millis(1500),
+        explorer_id: 3,
+        request: DecisionRequest::GenerateResource(BasicResourceType::Hydrogen),
+        inputs: DecisionInputs {
+            has_rocket: true,
+            charged_cells: 1,
+            reserved: false,
+            threshold: 0.25,
+            random_sample: None,
+            p_sunray: Some(0.75),
+            p_asteroid: Some(0.25),
+            p_asteroid_squared: Some(0.0625),
+        },
+        policy: DecisionPolicyKind::Adaptive,
+        outcome: DecisionOutcome::Accepted,
+        reason: DecisionReason::DoubleAsteroidRisk,
+        cell: Some(0),
+    };
+
+    let payload = record.to_payload();
+    let expected = [
+        ("action", "decision"),
+        ("timestamp_ms", "1500"),
+        ("explorer_id", "3"),
+        ("request", "generate_resource"),
+        ("resource", "Hydrogen"),
+        ("policy", "adaptive"),
+        ("outcome", "accepted"),
+        ("reason", "double_asteroid_risk"),
+        ("has_rocket", "true"),
+        ("charged_cells", "1"),
+        ("reserved", "false"),
+        ("threshold", "0.250000"),
+        ("cell", "0"),
+        ("p_sunray", "0.750000"),
+        ("p_asteroid", "0.250000"),
+        ("p_asteroid_squared", "0.062500"),
+    ];
+    for (key, value) in expected {
+        assert_eq!(
+            payload.get(key).map(String::as_str),
+            Some(value),
+            "Wrong value for key {key}"
+        );
+    }
+    assert!(!payload.contains_key("random_sample"));
+}
+
+/// Test that decisions taken by a running planet reach the decision sink
+#[test]
+fn test_decision_sink() {
+    let (tx_orchestrator, rx_orchestrator_to_planet) =
+        crossbeam_channel::bounded::<OrchestratorToPlanet>(1);
+    let (tx_planet_to_orchestrator, rx_orchestrator) =
+        crossbeam_channel::bounded::<PlanetToOrchestrator>(1);
+    let (tx_explorer, rx_explorer_to_planet) = crossbeam_channel::bounded::<ExplorerToPlanet>(1);
+    let (tx_records, rx_records) = crossbeam_channel::unbounded::<DecisionRecord>();
+
+    let ai = Ai::new(
+        true,
+        0.0,
+        0.0,
+        Duration::from_millis(100),
+        Duration::from_secs(1),
+    )
+    .with_decision_sink(tx_records);
+
+    let planet = Planet::new(
+        0,
+        PlanetType::C,
+        Box::new(ai),
+        DEFAULT_GEN_RULES.to_vec(),
+        DEFAULT_COMB_RULES.to_vec(),
+        (rx_orchestrator_to_planet, tx_planet_to_orchestrator),
+        rx_explorer_to_planet,
+    )
+    .expect("Planet creation failed");
+
+    // 1. Start thread
+    let handle = start_thread(planet);
+
+    // 2. Orchestrator starts the planet
+    orchestrator_start_planet(&tx_orchestrator, &rx_orchestrator);
+
+    // 3. Orchestrator tells the planet that an explorer arrived
+    let (tx_to_explorer, rx_explorer) = crossbeam_channel::unbounded::<PlanetToExplorer>();
+    orchestrator_send(
+        &tx_orchestrator,
+        &rx_orchestrator,
+        OrchestratorToPlanet::IncomingExplorerRequest {
+            explorer_id: 5,
+            new_sender: tx_to_explorer,
+        },
+    );
+
+    let generate = |resource| ExplorerToPlanet::GenerateResourceRequest {
+        explorer_id: 5,
+        resource,
+    };
+
+    // 4. Hydrogen without charge, then with charge, then unsupported Oxygen
+    explorer_send(
+        &tx_explorer,
+        &rx_explorer,
+        generate(BasicResourceType::Hydrogen),
+    );
+    orchestrator_send(
+        &tx_orchestrator,
+        &rx_orchestrator,
+        OrchestratorToPlanet::Sunray(Sunray::default()),
+    );
+    explorer_send(
+        &tx_explorer,
+        &rx_explorer,
+        generate(BasicResourceType::Hydrogen),
+    );
+    explorer_send(
+        &tx_explorer,
+        &rx_explorer,
+        generate(BasicResourceType::Oxygen),
+    );
+
+    let records: Vec<DecisionRecord> = rx_records.try_iter().collect();
+    assert_eq!(records.len(), 3, "Expected one record per request");
+
+    assert_eq!(records[0].explorer_id, 5);
+    assert_eq!(records[0].outcome, DecisionOutcome::Denied);
+    assert_eq!(records[0].reason, DecisionReason::CellNotCharged);
+
+    assert_eq!(records[1].outcome, DecisionOutcome::Accepted);
+    assert_eq!(records[1].reason, DecisionReason::RandomSample);
+    assert_eq!(records[1].policy, DecisionPolicyKind::Random);
+    assert_eq!(records[1].cell, Some(0));
+    assert!(records[1].inputs.random_sample.is_some());
+
+    assert_eq!(
+        records[2].request,
+        DecisionRequest::GenerateResource(BasicResourceType::Oxygen)
+    );
+    assert_eq!(records[2].outcome, DecisionOutcome::Unsupported);
+    assert_eq!(records[2].reason, DecisionReason::MissingRecipe);
+
+    // 5. Orchestrator kills planet
+    orchestrator_kill_planet(&tx_orchestrator, &rx_orchestrator);
+
+    drop(tx_orchestrator);
+    let _ = handle.join();
+}
@@ -0,0 +1,147 @@
+#![allow(clippy::pedantic)]
+
+mod common;
+
+use common::*;
+use common_game::components::asteroid::Asteroid;
+use common_game::components::planet::{Planet, PlanetType};
+use common_game::components::resource::{BasicResourceType, ComplexResourceType};
+use common_game::components::sunray::Sunray;
+use common_game::protocols::orchestrator_planet::{OrchestratorToPlanet, PlanetToOrchestrator};
+use common_game::protocols::planet_explorer::{ExplorerToPlanet, PlanetToExplorer};
+use immutable_cosmic_borrow::Ai;
+use std::time::Duration;
+
+/// Test that a manual reservation can be queried and released
+#[test]
+fn test_manual_reservation() {
+    let mut ai = Ai::new(
+        true,
+        0.0,
+        0.0,
+        Duration::from_secs(1),
+        Duration::from_millis(100),
+    );
+
+    assert!(
+        !ai.is_energy_reserved(),
+        "A new AI should not reserve energy"
+    );
+    assert_eq!(ai.defense_reservation_window(), Duration::ZERO);
+
+    ai.reserve_energy_for(Duration::from_secs(60));
+    assert!(ai.is_energy_reserved(), "Energy should be reserved");
+
+    ai.release_energy_reservation();
+    assert!(!ai.is_energy_reserved(), "Energy should have been released");
+}
+
+/// Test that explorer requests are denied while the charge is reserved after an asteroid
+#[test]
+fn test_reservation_after_asteroid() {
+    let (tx_orchestrator, rx_orchestrator_to_planet) =
+        crossbeam_channel::bounded::<OrchestratorToPlanet>(1);
+    let (tx_planet_to_orchestrator, rx_orchestrator) =
+        crossbeam_channel::bounded::<PlanetToOrchestrator>(1);
+    let (tx_explorer, rx_explorer_to_planet) = crossbeam_channel::bounded::<ExplorerToPlanet>(1);
+
+    let ai = Ai::new(
+        true,
+        0.0,
+        0.0,
+        Duration::from_millis(100),
+        Duration::from_secs(1),
+    )
+    .with_defense_reservation(Duration::from_secs(60));
+
+    let planet = Planet::new(
+        0,
+        PlanetType::C,
+        Box::new(ai),
+        vec![BasicResourceType::Hydrogen],
+        vec![ComplexResourceType::Water],
+        (rx_orchestrator_to_planet, tx_planet_to_orchestrator),
+        rx_explorer_to_planet,
+    )
+    .expect("Planet creation failed");
+
+    // 1. Start thread
+    let handle = start_thread(planet);
+
+    // 2. Orchestrator starts the planet
+    orchestrator_start_planet(&tx_orchestrator, &rx_orchestrator);
+
+    // 3. Orchestrator tells the planet that an explorer arrived
+    let (tx_to_explorer, rx_explorer) = crossbeam_channel::unbounded::<PlanetToExplorer>();
+    orchestrator_send(
+        &tx_orchestrator,
+        &rx_orchestrator,
+        OrchestratorToPlanet::IncomingExplorerRequest {
+            explorer_id: 0,
+            new_sender: tx_to_explorer,
+        },
+    );
+
+    // 4. Sunray, then asteroid: the planet builds and launches a rocket
+    orchestrator_send(
+        &tx_orchestrator,
+        &rx_orchestrator,
+        OrchestratorToPlanet::Sunray(Sunray::default()),
+    );
+    let response = orchestrator_send(
+        &tx_orchestrator,
+        &rx_orchestrator,
+        OrchestratorToPlanet::Asteroid(Asteroid::default()),
+    );
+    match response {
+        PlanetToOrchestrator::AsteroidAck { rocket, .. } => assert!(
+            rocket.is_some(),
+            "Expected a rocket but Planet responded with None"
+        ),
+        _ => panic!("Expected AsteroidAck but got a different message"),
+    }
+
+    // 5. The cell is charged again
+    orchestrator_send(
+        &tx_orchestrator,
+        &rx_orchestrator,
+        OrchestratorToPlanet::Sunray(Sunray::default()),
+    );
+
+    // 6. The charge is reserved for defense, so Hydrogen is denied
+    let response = explorer_send(
+        &tx_explorer,
+        &rx_explorer,
+        ExplorerToPlanet::GenerateResourceRequest {
+            explorer_id: 0,
+            resource: BasicResourceType::Hydrogen,
+        },
+    );
+    match response {
+        PlanetToExplorer::GenerateResourceResponse { resource } => assert!(
+            resource.is_none(),
+            "Planet spent a charge reserved for defense"
+        ),
+        _ => panic!("Expected a generate resource response but received a different one"),
+    }
+
+    // 7. The charge is still available for the next asteroid
+    let response = orchestrator_send(
+        &tx_orchestrator,
+        &rx_orchestrator,
+        OrchestratorToPlanet::Asteroid(Asteroid::default()),
+    );
+    match response {
+        PlanetToOrchestrator::AsteroidAck { rocket, .. } => assert!(
+            rocket.is_some(),
+            "Expected a rocket but Planet responded with None"
+        ),
+        _ => panic!("Expected AsteroidAck but got a different message"),
+    }
+
+    // 8. Orchestrator kills planet
+    orchestrator_kill_planet(&tx_orchestrator, &rx_orchestrator);
+
+    drop(tx_orchestrator);
+    let _ = handle.join();
+}
@@ -0,0 +1,236 @@
+#![allow(clippy::pedantic)]
+
+mod common;
+
+use common::{generate, hydrogen_options, spawn_started};
+use common_game::components::planet::PlanetType;
+use common_game::components::resource::BasicResourceType;
+use immutable_cosmic_borrow::bridge::{PlanetBridge, PlanetOptions};
+use immutable_cosmic_borrow::testing::PlanetHarness;
+use immutable_cosmic_borrow::{
+    DecisionExplanation, DecisionInputs, DecisionOutcome, DecisionPolicyKind, DecisionReason,
+    DecisionRecord, DecisionRequest, OutcomeChange, StatsHandle,
+};
+use std::time::SystemTime;
+
+fn spawn(planet_type: PlanetType, basic_gen_coeff: f32) -> (PlanetHarness, StatsHandle) {
+    let options = hydrogen_options(planet_type, basic_gen_coeff);
+    let ai = options.create_ai();
+    let stats = ai.stats_handle();
+    (spawn_started(&options, ai), stats)
+}
+
+// Decision of the adaptive policy on a generation request, with a rocket ready or not
+fn adaptive(
+    outcome: DecisionOutcome,
+    p_asteroid: f32,
+    threshold: f32,
+    rocket: bool,
+) -> DecisionExplanation {
+    DecisionExplanation {
+        record: DecisionRecord {
+            timestamp: SystemTime::now(),
+            explorer_id: 1,
+            request: DecisionRequest::GenerateResource(BasicResourceType::Hydrogen),
+            inputs: DecisionInputs {
+                has_rocket: rocket,
+                charged_cells: 1,
+                threshold,
+                p_sunray: Some(1.0 - p_asteroid),
+                p_asteroid: Some(p_asteroid),
+                p_asteroid_squared: rocket.then_some(p_asteroid * p_asteroid),
+                ..DecisionInputs::default()
+            },
+            policy: DecisionPolicyKind::Adaptive,
+            outcome,
+            reason: if rocket {
+                DecisionReason::DoubleAsteroidRisk
+            } else {
+                DecisionReason::AsteroidRisk
+            },
+            cell: None,
+        },
+        estimator: None,
+        can_have_rocket: true,
+    }
+}
+
+/// Test the explanation of a request denied for lack of charge
+#[test]
+fn test_explain_no_charge() {
+    let (mut harness, stats) = spawn(PlanetType::C, 0.5);
+    assert!(stats.explain_last_decision(1).is_none());
+    assert!(!generate(&mut harness));
+
+    let explanation = stats.explain_last_decision(1).unwrap();
+    assert_eq!(explanation.record.outcome, DecisionOutcome::Denied);
+    assert_eq!(explanation.record.reason, DecisionReason::CellNotCharged);
+    assert!(!explanation.rocket_squared());
+    assert_eq!(explanation.changes(), [OutcomeChange::ChargedCell]);
+    let text = explanation.to_string();
+    assert!(
+        text.starts_with("Explorer 1 generate_resource Hydrogen: denied by the adaptive policy.")
+    );
+    assert!(text.contains("No energy cell was charged."), "{text}");
+    assert!(
+        text.ends_with("It would have been accepted with a charged energy cell."),
+        "{text}"
+    );
+    assert!(stats.explain_last_decision(2).is_none());
+}
+
+/// Test the explanation of a denial by the risk of one asteroid, on a planet without rockets
+#[test]
+fn test_explain_asteroid_risk() {
+    let (mut harness, stats) = spawn(PlanetType::B, 0.2);
+    harness.sunray();
+    assert!(!generate(&mut harness));
+
+    let explanation = stats.explain_last_decision(1).unwrap();
+    let record = &explanation.record;
+    assert_eq!(record.policy, DecisionPolicyKind::Adaptive);
+    assert_eq!(record.reason, DecisionReason::AsteroidRisk);
+    assert_eq!(record.inputs.threshold, 0.2);
+    assert!(!explanation.can_have_rocket);
+    let estimator = explanation.estimator.as_ref().unwrap();
+    let p_asteroid = record.inputs.p_asteroid.unwrap();
+    assert_eq!(p_asteroid, 1.0 - estimator.sunray_probability);
+
+    // B planets have no rocket to switch to the squared risk
+    assert_eq!(
+        explanation.changes(),
+        [
+            OutcomeChange::CoefficientAtLeast(p_asteroid),
+            OutcomeChange::SunrayProbabilityAtLeast(0.8),
+        ]
+    );
+    let text = explanation.to_string();
+    assert!(text.contains("No rocket was ready"), "{text}");
+    assert!(text.contains("above the coefficient 0.200"), "{text}");
+    assert!(
+        text.contains("a sunray probability of at least 0.800."),
+        "{text}"
+    );
+}
+
+/// Test the explanation of an acceptance by the rocket-squared rule
+#[test]
+fn test_explain_rocket_squared() {
+    let (mut harness, stats) = spawn(PlanetType::C, 0.3);
+    // The first charge builds the rocket, the second one is left to the explorer
+    harness.sunray();
+    assert!(!generate(&mut harness));
+    harness.sunray();
+    assert!(generate(&mut harness));
+
+    let explanation = stats.explain_last_decision(1).unwrap();
+    let inputs = &explanation.record.inputs;
+    assert_eq!(explanation.record.outcome, DecisionOutcome::Accepted);
+    assert!(explanation.rocket_squared());
+    assert!(inputs.has_rocket);
+    let p_squared = inputs.p_asteroid_squared.unwrap();
+    assert_eq!(p_squared, inputs.p_asteroid.unwrap().powi(2));
+
+    let changes = explanation.changes();
+    assert_eq!(changes[0], OutcomeChange::CoefficientBelow(p_squared));
+    assert!(
+        matches!(changes[1], OutcomeChange::SunrayProbabilityBelow(p) if (p - (1.0 - 0.3f32.sqrt())).abs() < 1e-6)
+    );
+    assert_eq!(changes[2], OutcomeChange::NoRocket);
+    let text = explanation.to_string();
+    assert!(
+        text.contains("A rocket was ready, so the risk of two asteroids"),
+        "{text}"
+    );
+    assert!(text.contains("It would have been denied with"), "{text}");
+    assert!(text.ends_with("or no rocket ready."), "{text}");
+}
+
+/// Test what would change the decisions of the policies, near their bounds
+#[test]
+fn test_outcome_changes() {
+    // A rocket makes the squared risk low enough
+    let denied = adaptive(DecisionOutcome::Denied, 0.6, 0.4, false);
+    assert_eq!(
+        denied.changes(),
+        [
+            OutcomeChange::CoefficientAtLeast(0.6),
+            OutcomeChange::SunrayProbabilityAtLeast(0.6),
+            OutcomeChange::RocketReady,
+        ]
+    );
+    // Not when it is still too high
+    let denied = adaptive(DecisionOutcome::Denied, 0.8, 0.4, false);
+    assert!(!denied.changes().contains(&OutcomeChange::RocketReady));
+    // Without a rocket, the single risk was within the coefficient too
+    let accepted = adaptive(DecisionOutcome::Accepted, 0.3, 0.4, true);
+    assert!(!accepted.changes().contains(&OutcomeChange::NoRocket));
+    // Nothing lowers a zero risk below the coefficient
+    let accepted = adaptive(DecisionOutcome::Accepted, 0.0, 1.0, false);
+    assert!(accepted.changes().is_empty());
+    assert!(
+        accepted
+            .to_string()
+            .ends_with("No single change of its inputs would have changed it.")
+    );
+
+    // The random policy accepts samples above the coefficient
+    let mut random = adaptive(DecisionOutcome::Denied, 0.5, 0.7, false);
+    random.record.policy = DecisionPolicyKind::Random;
+    random.record.reason = DecisionReason::RandomSample;
+    random.record.inputs.random_sample = Some(0.6);
+    assert_eq!(random.changes(), [OutcomeChange::CoefficientBelow(0.6)]);
+    random.record.outcome = DecisionOutcome::Accepted;
+    assert_eq!(random.changes(), [OutcomeChange::CoefficientAtLeast(0.6)]);
+
+    let mut reserved = adaptive(DecisionOutcome::Denied, 0.5, 0.5, false);
+    reserved.record.reason = DecisionReason::ReservedForDefense;
+    assert_eq!(
+        reserved.changes(),
+        [
+            OutcomeChange::NoDefenseReservation,
+            OutcomeChange::AnotherChargedCell,
+        ]
+    );
+}
+
+/// Test the explanation command of the bridge
+#[test]
+fn test_bridge_explain() {
+    let options = PlanetOptions {
+        id: 4,
+        ..PlanetOptions::default()
+    };
+    let (tx_records, rx_records) = crossbeam_channel::unbounded();
+    let ai = options.create_ai().with_decision_sink(tx_records);
+    let stats = ai.stats_handle();
+    let mut bridge = PlanetBridge::spawn(|orchestrator_channels, explorers_receiver| {
+        options.create_planet_with_ai(ai, orchestrator_channels, explorers_receiver)
+    })
+    .unwrap()
+    .with_feed(stats, rx_records);
+
+    bridge.handle_line(r#"{"type":"start"}"#);
+    bridge.handle_line(r#"{"type":"explorer_arrival","explorer_id":3}"#);
+    let response = bridge.handle_line(r#"{"type":"explain","explorer_id":3}"#);
+    assert!(response.contains(r#""type":"error""#), "{response}");
+    assert!(
+        response.contains("No decision taken for explorer 3"),
+        "{response}"
+    );
+
+    bridge.handle_line(r#"{"type":"generate_resource","explorer_id":3,"resource":"Hydrogen"}"#);
+    let response = bridge.handle_line(r#"{"type":"explain","explorer_id":3}"#);
+    for field in [
+        r#""type":"explanation""#,
+        r#""planet_id":4"#,
+        r#""explorer_id":3"#,
+        r#""outcome":"denied""#,
+        r#""reason":"cell_not_charged""#,
+        r#""rocket_squared":false"#,
+        r#""changes":["a charged energy cell"]"#,
+        r#""text":"Explorer 3 generate_resource Hydrogen: denied"#,
+    ] {
+        assert!(response.contains(field), "{field} not in {response}");
+    }
+}
@@ -395,3 +395,71 @@ fn test_available_cell_response() {
     drop(tx_orchestrator);
     let _ = handle.join();
 }
+
+#[test]
+fn test_unsupported_resource_response() {
+    let (planet, (tx_orchestrator, rx_orchestrator), tx_explorer) = create_test_planet();
+
+    // 1. Start thread
+    let handle = start_thread(planet);
+
+    // 2. Orchestrator starts the planet
+    orchestrator_start_planet(&tx_orchestrator, &rx_orchestrator);
+
+    // 3. Orchestrator tells the planet that an explorer arrived
+    let (tx_to_explorer, rx_explorer) = crossbeam_channel::unbounded::<PlanetToExplorer>();
+    orchestrator_send(
+        &tx_orchestrator,
+        &rx_orchestrator,
+        OrchestratorToPlanet::IncomingExplorerRequest {
+            explorer_id: 0,
+            new_sender: tx_to_explorer,
+        },
+    );
+
+    // 4. Orchestrator sends a sunray
+    orchestrator_send(
+        &tx_orchestrator,
+        &rx_orchestrator,
+        OrchestratorToPlanet::Sunray(Sunray::default()),
+    );
+
+    // 5. Request from explorer to generate Oxygen, which the planet has no rule for
+    let response = explorer_send(
+        &tx_explorer,
+        &rx_explorer,
+        ExplorerToPlanet::GenerateResourceRequest {
+            explorer_id: 0,
+            resource: BasicResourceType::Oxygen,
+        },
+    );
+
+    // 6. Planet should respond with None
+    match response {
+        PlanetToExplorer::GenerateResourceResponse { resource } => assert_eq!(
+            resource, None,
+            "Planet generated a resource it has no rule for"
+        ),
+        _ => panic!("Expected a generate resource response but received a different one"),
+    }
+
+    // 7. The energy cell should still be charged
+    let response = explorer_send(
+        &tx_explorer,
+        &rx_explorer,
+        ExplorerToPlanet::AvailableEnergyCellRequest { explorer_id: 0 },
+    );
+    match response {
+        PlanetToExplorer::AvailableEnergyCellResponse { available_cells } => assert_eq!(
+            available_cells, 1,
+            "Planet spent its charge on an unsupported resource"
+        ),
+        _ => panic! {"Expected an AvailableEnergyCellResponse but received a different one"},
+    }
+
+    // 8. Orchestrator kills planet
+    orchestrator_kill_planet(&tx_orchestrator, &rx_orchestrator);
+
+    drop(tx_orchestrator);
+    let _ = handle.join();
+}
@@ -0,0 +1,187 @@
+#![allow(clippy::pedantic)]
+
+mod common;
+
+use common::*;
+use common_game::components::asteroid::Asteroid;
+use common_game::components::planet::{Planet, PlanetType};
+use common_game::components::sunray::Sunray;
+use common_game::protocols::orchestrator_planet::{OrchestratorToPlanet, PlanetToOrchestrator};
+use common_game::protocols::planet_explorer::{ExplorerToPlanet, PlanetToExplorer};
+use immutable_cosmic_borrow::{
+    Ai, DEFAULT_COMB_RULES, DEFAULT_GEN_RULES, FlightEvent, FlightRecorder,
+};
+use std::path::PathBuf;
+use std::thread::JoinHandle;
+use std::time::Duration;
+use std::{fs, process};
+
+struct TestPlanet {
+    tx_orchestrator: crossbeam_channel::Sender<OrchestratorToPlanet>,
+    rx_orchestrator: crossbeam_channel::Receiver<PlanetToOrchestrator>,
+    tx_explorer: crossbeam_channel::Sender<ExplorerToPlanet>,
+    handle: JoinHandle<Result<(), String>>,
+}
+
+fn start_planet(recorder: FlightRecorder) -> TestPlanet {
+    let (tx_orchestrator, rx_orchestrator_to_planet) =
+        crossbeam_channel::bounded::<OrchestratorToPlanet>(1);
+    let (tx_planet_to_orchestrator, rx_orchestrator) =
+        crossbeam_channel::bounded::<PlanetToOrchestrator>(1);
+    let (tx_explorer, rx_explorer_to_planet) = crossbeam_channel::bounded::<ExplorerToPlanet>(1);
+
+    let ai = Ai::new(
+        true,
+        0.0,
+        0.0,
+        Duration::from_millis(100),
+        Duration::from_secs(1),
+    )
+    .with_flight_recorder(recorder);
+
+    let planet = Planet::new(
+        0,
+        PlanetType::C,
+        Box::new(ai),
+        DEFAULT_GEN_RULES.to_vec(),
+        DEFAULT_COMB_RULES.to_vec(),
+        (rx_orchestrator_to_planet, tx_planet_to_orchestrator),
+        rx_explorer_to_planet,
+    )
+    .expect("Planet creation failed");
+
+    let handle = start_thread(planet);
+    orchestrator_start_planet(&tx_orchestrator, &rx_orchestrator);
+
+    TestPlanet {
+        tx_orchestrator,
+        rx_orchestrator,
+        tx_explorer,
+        handle,
+    }
+}
+
+fn dump_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("icb_{name}_{}.jsonl", process::id()));
+    let _ = fs::remove_file(&path);
+    path
+}
+
+/// Test that only the last events are kept, oldest first
+#[test]
+fn test_ring_buffer_keeps_last_events() {
+    let recorder = FlightRecorder::new(4, None);
+    let planet = start_planet(recorder.clone());
+
+    let (tx_to_explorer, rx_explorer) = crossbeam_channel::unbounded::<PlanetToExplorer>();
+    orchestrator_send(
+        &planet.tx_orchestrator,
+        &planet.rx_orchestrator,
+        OrchestratorToPlanet::IncomingExplorerRequest {
+            explorer_id: 2,
+            new_sender: tx_to_explorer,
+        },
+    );
+    for _ in 0..3 {
+        orchestrator_send(
+            &planet.tx_orchestrator,
+            &planet.rx_orchestrator,
+            OrchestratorToPlanet::Sunray(Sunray::default()),
+        );
+    }
+    explorer_send(
+        &planet.tx_explorer,
+        &rx_explorer,
+        ExplorerToPlanet::AvailableEnergyCellRequest { explorer_id: 2 },
+    );
+
+    let entries = recorder.entries();
+    assert_eq!(entries.len(), 4, "Expected the buffer to be full");
+    assert!(matches!(entries[0].event, FlightEvent::Estimator(_)));
+    assert_eq!(entries[1].event, FlightEvent::Sunray);
+    assert!(matches!(entries[2].event, FlightEvent::Estimator(_)));
+    assert_eq!(
+        entries[3].event,
+        FlightEvent::ExplorerRequest {
+            explorer_id: 2,
+            request: "available_energy_cell",
+        }
+    );
+    assert!(
+        entries
+            .windows(2)
+            .all(|pair| pair[0].timestamp <= pair[1].timestamp)
+    );
+
+    let mut dump = Vec::new();
+    recorder.dump(&mut dump).expect("Dump failed");
+    let dump = String::from_utf8(dump).unwrap();
+    let lines: Vec<&str> = dump.lines().collect();
+    assert_eq!(lines.len(), 4);
+    assert!(lines[1].contains("\"event\":\"sunray\""), "{}", lines[1]);
+    assert!(lines[3].contains("\"request\":\"available_energy_cell\""));
+
+    orchestrator_kill_planet(&planet.tx_orchestrator, &planet.rx_orchestrator);
+    drop(planet.tx_orchestrator);
+    let _ = planet.handle.join();
+}
+
+/// Test the automatic dump when an asteroid hits without a rocket
+#[test]
+fn test_dump_on_asteroid_without_rocket() {
+    let path = dump_path("asteroid");
+    let recorder = FlightRecorder::new(16, Some(path.clone()));
+    let planet = start_planet(recorder);
+
+    let response = orchestrator_send(
+        &planet.tx_orchestrator,
+        &planet.rx_orchestrator,
+        OrchestratorToPlanet::Asteroid(Asteroid::default()),
+    );
+    assert!(matches!(
+        response,
+        PlanetToOrchestrator::AsteroidAck { rocket: None, .. }
+    ));
+
+    let dump = fs::read_to_string(&path).expect("Dump file not written");
+    let lines: Vec<&str> = dump.lines().collect();
+    assert!(
+        lines[0].contains("\"reason\":\"no_rocket_available\""),
+        "{}",
+        lines[0]
+    );
+    assert!(lines[0].contains("\"event\":\"dump\""));
+    assert!(lines[1].contains("\"event\":\"start\""));
+    assert!(lines.last().unwrap().contains("\"event\":\"asteroid\""));
+    assert!(lines.last().unwrap().contains("\"rocket\":false"));
+
+    drop(planet.tx_orchestrator);
+    let _ = planet.handle.join();
+    let _ = fs::remove_file(path);
+}
+
+/// Test the automatic dump when the planet is stopped
+#[test]
+fn test_dump_on_stop() {
+    let path = dump_path("stop");
+    let recorder = FlightRecorder::new(16, Some(path.clone()));
+    let planet = start_planet(recorder);
+
+    orchestrator_send(
+        &planet.tx_orchestrator,
+        &planet.rx_orchestrator,
+        OrchestratorToPlanet::Sunray(Sunray::default()),
+    );
+    orchestrator_stop_planet(&planet.tx_orchestrator, &planet.rx_orchestrator);
+
+    let dump = fs::read_to_string(&path).expect("Dump file not written");
+    let lines: Vec<&str> = dump.lines().collect();
+    assert!(lines[0].contains("\"reason\":\"stop\""), "{}", lines[0]);
+    assert!(lines[0].contains("\"entries\":4"), "{}", lines[0]);
+    assert_eq!(lines.len(), 5);
+    assert!(lines[4].contains("\"event\":\"stop\""));
+
+    drop(planet.tx_orchestrator);
+    let _ = planet.handle.join();
+    let _ = fs::remove_file(path);
+}
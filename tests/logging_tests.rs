@@ -0,0 +1,290 @@
+#![allow(clippy::pedantic)]
+
+use common_game::components::resource::BasicResourceType;
+use common_game::logging::Channel;
+use common_game::protocols::planet_explorer::ExplorerToPlanet;
+use immutable_cosmic_borrow::bridge::PlanetOptions;
+use immutable_cosmic_borrow::testing::{PlanetHarness, expect};
+use immutable_cosmic_borrow::{AiSetting, LogAction, LogConfig};
+use std::sync::{Mutex, MutexGuard, Once};
+
+// Keeps the events of every planet, the tests tell their planets apart by id
+struct CaptureLogger;
+
+static LOGGER: CaptureLogger = CaptureLogger;
+static EVENTS: Mutex<Vec<(log::Level, String)>> = Mutex::new(Vec::new());
+// The logger level is global, the tests changing it run alone
+static LEVEL: Mutex<()> = Mutex::new(());
+
+impl log::Log for CaptureLogger {
+    fn enabled(&self, _: &log::Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record) {
+        EVENTS
+            .lock()
+            .unwrap()
+            .push((record.level(), record.args().to_string()));
+    }
+
+    fn flush(&self) {}
+}
+
+fn capture(level: log::LevelFilter) -> MutexGuard<'static, ()> {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| log::set_logger(&LOGGER).unwrap());
+    let guard = LEVEL.lock().unwrap_or_else(|err| err.into_inner());
+    log::set_max_level(level);
+    guard
+}
+
+fn planet_events(planet_id: u32) -> Vec<log::Level> {
+    let sender = format!("actor_type: Planet, id: {planet_id} }}");
+    EVENTS
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(_, event)| event.contains(&sender))
+        .map(|(level, _)| *level)
+        .collect()
+}
+
+// Number of events of the planet with `text` in their payload
+fn count_events(planet_id: u32, text: &str) -> usize {
+    let sender = format!("actor_type: Planet, id: {planet_id} }}");
+    EVENTS
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(_, event)| event.contains(&sender) && event.contains(text))
+        .count()
+}
+
+// Goes through every handler, and reconfigures the AI for an event on the info channel
+fn session(id: u32, log_config: LogConfig) {
+    let options = PlanetOptions {
+        id,
+        log_config,
+        ..PlanetOptions::default()
+    };
+    let ai = options.create_ai();
+    let control = ai.control_handle();
+    let mut harness = PlanetHarness::spawn(|orchestrator_channels, explorers_receiver| {
+        options.create_planet_with_ai(ai, orchestrator_channels, explorers_receiver)
+    })
+    .unwrap();
+
+    harness.start();
+    control.set(AiSetting::BasicGenCoeff(0.0)).unwrap();
+    harness.arrive(1);
+    harness.sunray();
+    harness.explorer_send_and_expect(
+        ExplorerToPlanet::GenerateResourceRequest {
+            explorer_id: 1,
+            resource: BasicResourceType::Hydrogen,
+        },
+        expect::generated(),
+    );
+    harness.sunray();
+    harness.asteroid();
+    harness.stop();
+}
+
+/// Test that the events below the minimum channel are not logged
+#[test]
+fn test_min_channel() {
+    let _guard = capture(log::LevelFilter::Trace);
+    session(41, LogConfig::new().with_min_channel(&Channel::Info));
+
+    let levels = planet_events(41);
+    assert!(!levels.is_empty());
+    assert!(levels.iter().all(|level| *level <= log::Level::Info));
+}
+
+/// Test that every channel is logged by default
+#[test]
+fn test_default_logs_everything() {
+    let _guard = capture(log::LevelFilter::Trace);
+    session(42, LogConfig::default());
+
+    let levels = planet_events(42);
+    for level in [log::Level::Info, log::Level::Debug, log::Level::Trace] {
+        assert!(levels.contains(&level), "No {level} event");
+    }
+}
+
+/// Test that the level of the logger is respected too
+#[test]
+fn test_logger_level() {
+    let _guard = capture(log::LevelFilter::Info);
+    session(43, LogConfig::default());
+    assert!(LogConfig::new().enabled(LogAction::Acks, &Channel::Info));
+    assert!(!LogConfig::new().enabled(LogAction::Acks, &Channel::Debug));
+
+    let levels = planet_events(43);
+    assert!(levels.contains(&log::Level::Info));
+    assert!(levels.iter().all(|level| *level <= log::Level::Info));
+}
+
+/// Test that the disabled actions are not logged, and the others still are
+#[test]
+fn test_disabled_actions() {
+    let _guard = capture(log::LevelFilter::Trace);
+    let log_config = LogConfig::new()
+        .with_action(LogAction::CounterUpdates, false)
+        .with_action(LogAction::Acks, false);
+    session(44, log_config);
+
+    assert_eq!(count_events(44, "update_sunray_counter"), 0);
+    assert_eq!(count_events(44, "update_asteroid_counter"), 0);
+    assert_eq!(count_events(44, "SunrayAck"), 0);
+    assert_eq!(count_events(44, "\"response\""), 0);
+    assert_eq!(count_events(44, "\"decision\""), 1);
+    assert_eq!(count_events(44, "reconfigure"), 1);
+    assert_eq!(count_events(44, "launch_existing_rocket"), 1);
+}
+
+/// Test that a sampled action logs its first event and then one in every n
+#[test]
+fn test_sampling() {
+    let _guard = capture(log::LevelFilter::Trace);
+    let options = PlanetOptions {
+        id: 45,
+        log_config: LogConfig::new().with_sampling(LogAction::Acks, 3),
+        ..PlanetOptions::default()
+    };
+    let mut harness = PlanetHarness::spawn(|orchestrator_channels, explorers_receiver| {
+        options.create_planet(orchestrator_channels, explorers_receiver)
+    })
+    .unwrap();
+
+    harness.start();
+    for _ in 0..7 {
+        harness.sunray();
+    }
+    harness.stop();
+
+    // Start, sunrays 3 and 6 (the 4th and 7th acks), none of the stop
+    assert_eq!(count_events(45, "StartPlanetAIResult"), 1);
+    assert_eq!(count_events(45, "SunrayAck"), 2);
+    assert_eq!(count_events(45, "StopPlanetAIResult"), 0);
+    assert_eq!(count_events(45, "update_sunray_counter"), 7);
+}
+
+/// Test that the log configuration can be changed while the planet runs
+#[test]
+fn test_runtime_log_config() {
+    let _guard = capture(log::LevelFilter::Trace);
+    let options = PlanetOptions {
+        id: 46,
+        ..PlanetOptions::default()
+    };
+    let ai = options.create_ai();
+    let control = ai.control_handle();
+    let mut harness = PlanetHarness::spawn(|orchestrator_channels, explorers_receiver| {
+        options.create_planet_with_ai(ai, orchestrator_channels, explorers_receiver)
+    })
+    .unwrap();
+
+    harness.start();
+    harness.sunray();
+    assert_eq!(count_events(46, "update_sunray_counter"), 1);
+
+    let log_config = LogConfig::new().with_action(LogAction::CounterUpdates, false);
+    control.set(AiSetting::LogConfig(log_config)).unwrap();
+    harness.sunray();
+    harness.sunray();
+    assert_eq!(count_events(46, "update_sunray_counter"), 1);
+    assert_eq!(count_events(46, "SunrayAck"), 3);
+    assert_eq!(count_events(46, "log_config"), 1);
+
+    control
+        .set(AiSetting::LogConfig(LogConfig::default()))
+        .unwrap();
+    harness.sunray();
+    harness.stop();
+    assert_eq!(count_events(46, "update_sunray_counter"), 2);
+}
+
+/// Test the text form of the configuration
+#[test]
+fn test_log_config_label() {
+    let log_config = LogConfig::new()
+        .with_min_channel(&Channel::Debug)
+        .with_action(LogAction::Acks, false)
+        .with_action(LogAction::Energy, false)
+        .with_sampling(LogAction::Decisions, 10);
+    assert_eq!(
+        log_config.label(),
+        "channel=debug;off=acks,energy;sample=decisions/10"
+    );
+    assert_eq!(LogConfig::from_label(&log_config.label()), Ok(log_config));
+    assert_eq!(LogConfig::default().label(), "channel=trace");
+    assert_eq!(LogConfig::from_label(""), Ok(LogConfig::default()));
+
+    for label in [
+        "channel=verbose",
+        "off=sunrays",
+        "sample=decisions",
+        "sample=decisions/often",
+        "level=info",
+        "acks",
+    ] {
+        assert!(LogConfig::from_label(label).is_err(), "{label} parsed");
+    }
+}
+
+/// Test the channel option of the planet runner
+#[test]
+fn test_log_channel_option() {
+    let mut options = PlanetOptions::default();
+    let rest = options
+        .parse_args(["--log-channel", "warning"].map(String::from))
+        .unwrap();
+    assert!(rest.is_empty());
+    assert_eq!(options.log_config.min_channel(), Channel::Warning);
+    assert!(!options.log_config.enabled(LogAction::Acks, &Channel::Info));
+
+    assert!(
+        options
+            .parse_args(["--log-channel", "verbose"].map(String::from))
+            .is_err()
+    );
+}
+
+/// Test the action and sampling options of the planet runner
+#[test]
+fn test_log_action_options() {
+    let mut options = PlanetOptions::default();
+    let rest = options
+        .parse_args(
+            [
+                "--log-off",
+                "counter_updates,acks",
+                "--log-sample",
+                "decisions/4,defense/2",
+            ]
+            .map(String::from),
+        )
+        .unwrap();
+    assert!(rest.is_empty());
+    let log_config = options.log_config;
+    assert!(!log_config.is_action_enabled(LogAction::CounterUpdates));
+    assert!(!log_config.is_action_enabled(LogAction::Acks));
+    assert!(log_config.is_action_enabled(LogAction::Decisions));
+    assert_eq!(log_config.sampling(LogAction::Decisions), 4);
+    assert_eq!(log_config.sampling(LogAction::Defense), 2);
+    assert_eq!(log_config.sampling(LogAction::Energy), 1);
+
+    assert!(
+        options
+            .parse_args(["--log-off", "sunrays"].map(String::from))
+            .is_err()
+    );
+    assert!(
+        options
+            .parse_args(["--log-sample", "acks"].map(String::from))
+            .is_err()
+    );
+}
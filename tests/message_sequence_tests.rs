@@ -0,0 +1,164 @@
+#![allow(clippy::pedantic)]
+
+use common_game::components::planet::PlanetType;
+use common_game::components::resource::{BasicResourceType, ComplexResourceType};
+use immutable_cosmic_borrow::bridge::PlanetOptions;
+use immutable_cosmic_borrow::testing::sequences::{SequenceCheck, Step, run_on_planet};
+use immutable_cosmic_borrow::{DEFAULT_GEN_RULES, MockClock};
+use rand::Rng;
+use rand::rngs::StdRng;
+use std::cell::RefCell;
+use std::sync::Arc;
+
+const ALL_BASIC: [BasicResourceType; 4] = [
+    BasicResourceType::Oxygen,
+    BasicResourceType::Hydrogen,
+    BasicResourceType::Carbon,
+    BasicResourceType::Silicon,
+];
+
+fn options(
+    planet_type: PlanetType,
+    gen_rules: &[BasicResourceType],
+    comb_rules: &[ComplexResourceType],
+) -> PlanetOptions {
+    PlanetOptions {
+        planet_type,
+        gen_rules: gen_rules.to_vec(),
+        comb_rules: comb_rules.to_vec(),
+        ..PlanetOptions::default()
+    }
+}
+
+// Three explorers, so that some messages come from explorers not on the planet
+fn check_sequences(options: PlanetOptions) {
+    check_sequences_of(SequenceCheck::new(), options, |rng| Step::random(rng, 3));
+}
+
+fn check_sequences_of(
+    check: SequenceCheck,
+    options: PlanetOptions,
+    step: impl FnMut(&mut StdRng) -> Step,
+) {
+    check.run(step, |steps| {
+        let ai = options
+            .create_ai()
+            .with_clock(Arc::new(MockClock::new()))
+            .with_seed(steps.len() as u64);
+        run_on_planet(&options, ai, steps)
+    });
+}
+
+/// Test random sequences on the default planet
+#[test]
+fn test_sequences_adaptive_c() {
+    check_sequences(PlanetOptions::default());
+}
+
+/// Test random sequences in random mode, every request with a charge is accepted
+#[test]
+fn test_sequences_random_c() {
+    check_sequences(PlanetOptions {
+        random_mode: true,
+        basic_gen_coeff: 0.0,
+        complex_gen_coeff: 0.0,
+        ..PlanetOptions::default()
+    });
+}
+
+/// Test random sequences on five cells with rockets
+#[test]
+fn test_sequences_a() {
+    check_sequences(options(PlanetType::A, &DEFAULT_GEN_RULES, &[]));
+}
+
+/// Test random sequences where combinations succeed, or fail for a missing recipe
+#[test]
+fn test_sequences_b() {
+    let options = PlanetOptions {
+        random_mode: true,
+        basic_gen_coeff: 0.0,
+        complex_gen_coeff: 0.0,
+        ..options(PlanetType::B, &ALL_BASIC, &[ComplexResourceType::Water])
+    };
+    // Water is rarely drawn otherwise, and needs a running planet, an explorer on it and
+    // both ingredients
+    let check = SequenceCheck::new().with_max_len(100);
+    check_sequences_of(check, options, |rng| match rng.random_range(0..12) {
+        0 => Step::Start,
+        1 => Step::Arrive(0),
+        2 | 3 => Step::Sunray,
+        4 => Step::Combine(0, ComplexResourceType::Water),
+        5 => Step::Generate(0, BasicResourceType::Hydrogen),
+        6 => Step::Generate(0, BasicResourceType::Oxygen),
+        _ => Step::random(rng, 2),
+    });
+}
+
+/// Test random sequences on five cells without rockets
+#[test]
+fn test_sequences_d() {
+    check_sequences(options(PlanetType::D, &ALL_BASIC, &[]));
+}
+
+/// Test that a failing sequence is shrunk to the steps needed to fail
+#[test]
+#[should_panic(expected = "shrunk to 2 steps: two asteroids")]
+fn test_sequence_check_shrinks() {
+    SequenceCheck::new().with_max_len(40).run(
+        |rng| Step::random(rng, 3),
+        |steps| {
+            let asteroids = steps.iter().filter(|step| **step == Step::Asteroid).count();
+            if asteroids >= 2 {
+                panic!("two asteroids");
+            }
+            Ok(())
+        },
+    );
+}
+
+/// Test that the sequences only depend on the seed
+#[test]
+fn test_sequence_check_seeded() {
+    let draw = |seed| {
+        let sequences = RefCell::new(Vec::new());
+        SequenceCheck::new().with_seed(seed).with_cases(8).run(
+            |rng| Step::random(rng, 2),
+            |steps| {
+                sequences.borrow_mut().push(steps.to_vec());
+                Ok(())
+            },
+        );
+        sequences.into_inner()
+    };
+    assert_eq!(draw(5), draw(5));
+    assert_ne!(draw(5), draw(6));
+}
+
+/// Test that the model follows an explicit session
+#[test]
+fn test_run_on_planet_session() {
+    let options = options(PlanetType::B, &ALL_BASIC, &[ComplexResourceType::Water]);
+    let steps = [
+        Step::Arrive(1),
+        Step::Start,
+        Step::Arrive(1),
+        Step::Sunray,
+        Step::Generate(1, BasicResourceType::Hydrogen),
+        Step::Sunray,
+        Step::Generate(1, BasicResourceType::Oxygen),
+        Step::Sunray,
+        Step::Combine(1, ComplexResourceType::Water),
+        Step::Combine(1, ComplexResourceType::Diamond),
+        Step::AvailableCells(1),
+        Step::Stop,
+        Step::Generate(1, BasicResourceType::Carbon),
+        Step::Start,
+        Step::Start,
+        Step::Depart(1),
+        Step::AvailableCells(1),
+        Step::Asteroid,
+    ];
+    let ai = options.create_ai();
+    run_on_planet(&options, ai, &steps).unwrap();
+}
@@ -0,0 +1,102 @@
+#![allow(clippy::pedantic)]
+
+use immutable_cosmic_borrow::Ai;
+use immutable_cosmic_borrow::metrics::{MetricsExporter, MetricsRegistry};
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+use std::{fs, thread};
+
+fn test_ai() -> Ai {
+    Ai::new(
+        false,
+        0.5,
+        0.5,
+        Duration::from_secs(1),
+        Duration::from_millis(100),
+    )
+}
+
+/// Test that every metric family is rendered and labelled by planet id
+#[test]
+fn test_render_registry() {
+    let registry = MetricsRegistry::new();
+    registry.register(7, test_ai().stats_handle());
+    registry.register(8, test_ai().stats_handle());
+
+    let text = registry.render();
+
+    for family in [
+        "icb_explorer_requests_total",
+        "icb_decisions_total",
+        "icb_accept_ratio",
+        "icb_threat_estimate",
+        "icb_sunrays_received_total",
+        "icb_rockets_built_total",
+        "icb_rocket_launches_total",
+        "icb_asteroids_total",
+        "icb_energy_charges_consumed_total",
+        "icb_decision_latency_seconds",
+    ] {
+        assert!(
+            text.contains(&format!("# TYPE {family} ")),
+            "Missing metric family {family} in:\n{text}"
+        );
+    }
+
+    assert!(text.contains("icb_asteroids_total{planet=\"7\",outcome=\"lost\"} 0"));
+    assert!(text.contains("icb_threat_estimate{planet=\"8\"} 0.5"));
+    assert!(text.contains("icb_decision_latency_seconds_bucket{planet=\"7\",le=\"+Inf\"} 0"));
+    // No request decided yet, so no ratio can be computed
+    assert!(!text.contains("icb_accept_ratio{"));
+
+    registry.unregister(7);
+    assert!(!registry.render().contains("planet=\"7\""));
+}
+
+/// Test that the metrics are written to a file
+#[test]
+fn test_file_exporter() {
+    let registry = MetricsRegistry::new();
+    registry.register(1, test_ai().stats_handle());
+
+    let path = std::env::temp_dir().join(format!("icb_metrics_{}.prom", std::process::id()));
+    let exporter = MetricsExporter::write_to_file(registry, &path, Duration::from_millis(50))
+        .expect("Failed to start the file exporter");
+    assert!(exporter.local_addr().is_none());
+
+    thread::sleep(Duration::from_millis(120));
+    exporter.stop();
+
+    let text = fs::read_to_string(&path).expect("Metrics file was not written");
+    assert!(text.contains("icb_sunrays_received_total{planet=\"1\"} 0"));
+    let _ = fs::remove_file(path);
+}
+
+/// Test that the metrics are served on localhost only
+#[test]
+fn test_http_exporter() {
+    let registry = MetricsRegistry::new();
+    registry.register(2, test_ai().stats_handle());
+
+    assert!(
+        MetricsExporter::serve_http(registry.clone(), "0.0.0.0:0").is_err(),
+        "Metrics should not be served on a public address"
+    );
+
+    let exporter = MetricsExporter::serve_http(registry, "127.0.0.1:0")
+        .expect("Failed to start the HTTP exporter");
+    let addr = exporter.local_addr().expect("HTTP exporter has no address");
+
+    let mut stream = TcpStream::connect(addr).expect("Failed to connect");
+    stream
+        .write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n")
+        .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+
+    assert!(response.starts_with("HTTP/1.1 200 OK"), "Got {response}");
+    assert!(response.contains("icb_rockets_built_total{planet=\"2\"} 0"));
+
+    exporter.stop();
+}
@@ -0,0 +1,187 @@
+#![allow(clippy::pedantic)]
+
+mod common;
+
+use common::*;
+use common_game::components::asteroid::Asteroid;
+use common_game::components::planet::{Planet, PlanetType};
+use common_game::components::resource::BasicResourceType;
+use common_game::components::sunray::Sunray;
+use common_game::protocols::orchestrator_planet::{OrchestratorToPlanet, PlanetToOrchestrator};
+use common_game::protocols::planet_explorer::{ExplorerToPlanet, PlanetToExplorer};
+use crossbeam_channel::{Receiver, Sender};
+use immutable_cosmic_borrow::Ai;
+use std::time::Duration;
+
+fn create_multi_cell_planet(
+    planet_type: PlanetType,
+) -> (
+    Planet,
+    (Sender<OrchestratorToPlanet>, Receiver<PlanetToOrchestrator>),
+    Sender<ExplorerToPlanet>,
+) {
+    let (tx_orchestrator, rx_orchestrator_to_planet) =
+        crossbeam_channel::bounded::<OrchestratorToPlanet>(1);
+    let (tx_planet_to_orchestrator, rx_orchestrator) =
+        crossbeam_channel::bounded::<PlanetToOrchestrator>(1);
+    let (tx_explorer, rx_explorer_to_planet) = crossbeam_channel::bounded::<ExplorerToPlanet>(1);
+
+    let planet = Planet::new(
+        0,
+        planet_type,
+        Box::new(Ai::new(
+            true,
+            0.0,
+            0.0,
+            Duration::from_millis(100),
+            Duration::from_secs(1),
+        )),
+        vec![BasicResourceType::Hydrogen],
+        vec![],
+        (rx_orchestrator_to_planet, tx_planet_to_orchestrator),
+        rx_explorer_to_planet,
+    )
+    .expect("Planet creation failed");
+
+    (planet, (tx_orchestrator, rx_orchestrator), tx_explorer)
+}
+
+fn available_cells(
+    tx_explorer: &Sender<ExplorerToPlanet>,
+    rx_explorer: &Receiver<PlanetToExplorer>,
+) -> u32 {
+    match explorer_send(
+        tx_explorer,
+        rx_explorer,
+        ExplorerToPlanet::AvailableEnergyCellRequest { explorer_id: 0 },
+    ) {
+        PlanetToExplorer::AvailableEnergyCellResponse { available_cells } => available_cells,
+        _ => panic!("Expected an AvailableEnergyCellResponse but received a different one"),
+    }
+}
+
+/// Test that a type A planet charges, spends and reports all of its cells
+#[test]
+fn test_multi_cell_planet_with_rocket() {
+    let (planet, (tx_orchestrator, rx_orchestrator), tx_explorer) =
+        create_multi_cell_planet(PlanetType::A);
+
+    // 1. Start thread
+    let handle = start_thread(planet);
+
+    // 2. Orchestrator starts the planet
+    orchestrator_start_planet(&tx_orchestrator, &rx_orchestrator);
+
+    // 3. Orchestrator tells the planet that an explorer arrived
+    let (tx_to_explorer, rx_explorer) = crossbeam_channel::unbounded::<PlanetToExplorer>();
+    orchestrator_send(
+        &tx_orchestrator,
+        &rx_orchestrator,
+        OrchestratorToPlanet::IncomingExplorerRequest {
+            explorer_id: 0,
+            new_sender: tx_to_explorer,
+        },
+    );
+
+    // 4. Three sunrays: one charge goes into the rocket, two cells stay charged
+    for _ in 0..3 {
+        orchestrator_send(
+            &tx_orchestrator,
+            &rx_orchestrator,
+            OrchestratorToPlanet::Sunray(Sunray::default()),
+        );
+    }
+    assert_eq!(available_cells(&tx_explorer, &rx_explorer), 2);
+
+    // 5. Explorer asks for Hydrogen, which spends one of the cells
+    match explorer_send(
+        &tx_explorer,
+        &rx_explorer,
+        ExplorerToPlanet::GenerateResourceRequest {
+            explorer_id: 0,
+            resource: BasicResourceType::Hydrogen,
+        },
+    ) {
+        PlanetToExplorer::GenerateResourceResponse { resource } => assert!(
+            resource.is_some(),
+            "Expected Hydrogen but Planet returned None"
+        ),
+        _ => panic!("Expected a generate resource response but received a different one"),
+    }
+    assert_eq!(available_cells(&tx_explorer, &rx_explorer), 1);
+
+    // 6. Two asteroids: the stored rocket, then one built from the remaining cell
+    for _ in 0..2 {
+        match orchestrator_send(
+            &tx_orchestrator,
+            &rx_orchestrator,
+            OrchestratorToPlanet::Asteroid(Asteroid::default()),
+        ) {
+            PlanetToOrchestrator::AsteroidAck { rocket, .. } => assert!(
+                rocket.is_some(),
+                "Expected a rocket but Planet responded with None"
+            ),
+            _ => panic!("Expected AsteroidAck but got a different message"),
+        }
+    }
+    assert_eq!(available_cells(&tx_explorer, &rx_explorer), 0);
+
+    // 7. Orchestrator kills planet
+    orchestrator_kill_planet(&tx_orchestrator, &rx_orchestrator);
+
+    drop(tx_orchestrator);
+    let _ = handle.join();
+}
+
+/// Test that a type D planet, which can not have rockets, keeps its charges
+#[test]
+fn test_multi_cell_planet_without_rocket() {
+    let (planet, (tx_orchestrator, rx_orchestrator), tx_explorer) =
+        create_multi_cell_planet(PlanetType::D);
+
+    // 1. Start thread
+    let handle = start_thread(planet);
+
+    // 2. Orchestrator starts the planet
+    orchestrator_start_planet(&tx_orchestrator, &rx_orchestrator);
+
+    // 3. Orchestrator tells the planet that an explorer arrived
+    let (tx_to_explorer, rx_explorer) = crossbeam_channel::unbounded::<PlanetToExplorer>();
+    orchestrator_send(
+        &tx_orchestrator,
+        &rx_orchestrator,
+        OrchestratorToPlanet::IncomingExplorerRequest {
+            explorer_id: 0,
+            new_sender: tx_to_explorer,
+        },
+    );
+
+    // 4. Every sunray charges a new cell
+    for _ in 0..3 {
+        orchestrator_send(
+            &tx_orchestrator,
+            &rx_orchestrator,
+            OrchestratorToPlanet::Sunray(Sunray::default()),
+        );
+    }
+    assert_eq!(available_cells(&tx_explorer, &rx_explorer), 3);
+
+    // 5. No rocket can be built, the charges are not touched
+    match orchestrator_send(
+        &tx_orchestrator,
+        &rx_orchestrator,
+        OrchestratorToPlanet::Asteroid(Asteroid::default()),
+    ) {
+        PlanetToOrchestrator::AsteroidAck { rocket, .. } => {
+            assert!(rocket.is_none(), "Planet type D can not have rockets")
+        }
+        _ => panic!("Expected AsteroidAck but got a different message"),
+    }
+    assert_eq!(available_cells(&tx_explorer, &rx_explorer), 3);
+
+    // 6. Orchestrator kills planet
+    orchestrator_kill_planet(&tx_orchestrator, &rx_orchestrator);
+
+    drop(tx_orchestrator);
+    let _ = handle.join();
+}
@@ -0,0 +1,196 @@
+#![allow(clippy::pedantic)]
+
+mod common;
+
+use common::*;
+use common_game::components::planet::{Planet, PlanetType};
+use common_game::components::sunray::Sunray;
+use common_game::protocols::orchestrator_planet::{OrchestratorToPlanet, PlanetToOrchestrator};
+use common_game::protocols::planet_explorer::ExplorerToPlanet;
+use immutable_cosmic_borrow::replay::Session;
+use immutable_cosmic_borrow::{Ai, DEFAULT_COMB_RULES, DEFAULT_GEN_RULES, MockClock, PauseMode};
+use std::sync::Arc;
+use std::time::Duration;
+
+const HALF_LIFE_SECS: u64 = 10;
+
+enum Step {
+    Sunray,
+    Advance(u64),
+    Stop,
+    Start,
+}
+
+// Waits until the planet handled the previous message. It acks a start or a stop before
+// calling the AI, so the clock must not move before the next message is answered.
+fn barrier(
+    tx: &crossbeam_channel::Sender<OrchestratorToPlanet>,
+    rx: &crossbeam_channel::Receiver<PlanetToOrchestrator>,
+    running: bool,
+) {
+    let response = orchestrator_send(tx, rx, OrchestratorToPlanet::InternalStateRequest);
+    if running {
+        assert!(matches!(
+            response,
+            PlanetToOrchestrator::InternalStateResponse { .. }
+        ));
+    } else {
+        assert!(matches!(response, PlanetToOrchestrator::Stopped { .. }));
+    }
+}
+
+// Runs the steps on a started planet and returns the final sun intensity
+fn run(pause_mode: PauseMode, steps: &[Step]) -> f32 {
+    let clock = MockClock::new();
+    let ai = Ai::new(
+        false,
+        0.5,
+        0.5,
+        Duration::from_secs(HALF_LIFE_SECS),
+        Duration::ZERO,
+    )
+    .with_clock(Arc::new(clock.clone()))
+    .with_pause_mode(pause_mode);
+    let stats = ai.stats_handle();
+
+    let (tx_orchestrator, rx_orchestrator_to_planet) =
+        crossbeam_channel::bounded::<OrchestratorToPlanet>(1);
+    let (tx_planet_to_orchestrator, rx_orchestrator) =
+        crossbeam_channel::bounded::<PlanetToOrchestrator>(1);
+    let (_tx_explorer, rx_explorer_to_planet) = crossbeam_channel::bounded::<ExplorerToPlanet>(1);
+    let planet = Planet::new(
+        0,
+        PlanetType::C,
+        Box::new(ai),
+        DEFAULT_GEN_RULES.to_vec(),
+        DEFAULT_COMB_RULES.to_vec(),
+        (rx_orchestrator_to_planet, tx_planet_to_orchestrator),
+        rx_explorer_to_planet,
+    )
+    .expect("Planet creation failed");
+
+    let handle = start_thread(planet);
+    orchestrator_start_planet(&tx_orchestrator, &rx_orchestrator);
+    barrier(&tx_orchestrator, &rx_orchestrator, true);
+    for step in steps {
+        match step {
+            Step::Sunray => {
+                orchestrator_send(
+                    &tx_orchestrator,
+                    &rx_orchestrator,
+                    OrchestratorToPlanet::Sunray(Sunray::default()),
+                );
+            }
+            Step::Advance(secs) => clock.advance(Duration::from_secs(*secs)),
+            Step::Stop => {
+                let response = orchestrator_send(
+                    &tx_orchestrator,
+                    &rx_orchestrator,
+                    OrchestratorToPlanet::StopPlanetAI,
+                );
+                assert!(matches!(
+                    response,
+                    PlanetToOrchestrator::StopPlanetAIResult { .. }
+                ));
+                barrier(&tx_orchestrator, &rx_orchestrator, false);
+            }
+            Step::Start => {
+                orchestrator_start_planet(&tx_orchestrator, &rx_orchestrator);
+                barrier(&tx_orchestrator, &rx_orchestrator, true);
+            }
+        }
+    }
+    orchestrator_kill_planet(&tx_orchestrator, &rx_orchestrator);
+    drop(tx_orchestrator);
+    let _ = handle.join();
+
+    stats.snapshot().estimator.unwrap().sun_intensity
+}
+
+// Sun intensity after two sunrays `active_secs` apart, starting from 0.5
+fn expected(active_secs: f32) -> f32 {
+    let tau = HALF_LIFE_SECS as f32 / std::f32::consts::LN_2;
+    let impulse = 1.0 / tau;
+    (0.5 + impulse) * (-active_secs / tau).exp() + impulse
+}
+
+fn assert_close(actual: f32, expected: f32) {
+    assert!(
+        (actual - expected).abs() < 1e-4,
+        "expected {expected}, got {actual}"
+    );
+}
+
+/// Test that the time stopped does not decay a frozen estimate
+#[test]
+fn test_freeze_while_paused() {
+    let sun = run(
+        PauseMode::Freeze,
+        &[
+            Step::Sunray,
+            Step::Advance(5),
+            Step::Stop,
+            Step::Advance(100),
+            Step::Start,
+            Step::Advance(5),
+            Step::Sunray,
+        ],
+    );
+    assert_close(sun, expected(10.0));
+}
+
+/// Test that the time stopped decays the estimate when forgetting
+#[test]
+fn test_forget_while_paused() {
+    let sun = run(
+        PauseMode::Forget,
+        &[
+            Step::Sunray,
+            Step::Advance(5),
+            Step::Stop,
+            Step::Advance(100),
+            Step::Start,
+            Step::Advance(5),
+            Step::Sunray,
+        ],
+    );
+    assert_close(sun, expected(110.0));
+}
+
+/// Test that each pause of several stop/start cycles is compensated exactly once
+#[test]
+fn test_repeated_pauses() {
+    let steps = [
+        Step::Sunray,
+        Step::Stop,
+        Step::Advance(50),
+        Step::Start,
+        Step::Advance(5),
+        Step::Stop,
+        Step::Advance(50),
+        Step::Start,
+        Step::Advance(5),
+        Step::Sunray,
+    ];
+    assert_close(run(PauseMode::Freeze, &steps), expected(10.0));
+    assert_close(run(PauseMode::Forget, &steps), expected(110.0));
+
+    // Without any pause both modes agree
+    let steps = [Step::Sunray, Step::Advance(7), Step::Sunray];
+    assert_close(run(PauseMode::Freeze, &steps), expected(7.0));
+    assert_close(run(PauseMode::Forget, &steps), expected(7.0));
+}
+
+/// Test that sessions record the pause mode, frozen when missing
+#[test]
+fn test_session_pause_mode() {
+    let header = r#"{"event":"session","version":1,"random_mode":false,"basic_gen_coeff":1,"complex_gen_coeff":1,"half_life_ns":1000000000,"min_time_constant_ns":0,"defense_window_ns":0,"seed":"1","planet_id":1,"planet_type":"C","gen_rules":["Hydrogen"],"comb_rules":[]"#;
+
+    let session = Session::read(format!("{header}}}").as_bytes()).unwrap();
+    assert_eq!(session.config.pause_mode, PauseMode::Freeze);
+
+    let session = Session::read(format!(r#"{header},"pause_mode":"forget"}}"#).as_bytes()).unwrap();
+    assert_eq!(session.config.pause_mode, PauseMode::Forget);
+
+    assert!(Session::read(format!(r#"{header},"pause_mode":"nap"}}"#).as_bytes()).is_err());
+}
@@ -1,9 +1,13 @@
 #![allow(clippy::pedantic)]
 
+use common_game::components::planet::PlanetType;
+use common_game::components::resource::{BasicResourceType, ComplexResourceType};
 use common_game::protocols::orchestrator_planet::{OrchestratorToPlanet, PlanetToOrchestrator};
 use common_game::protocols::planet_explorer::ExplorerToPlanet;
 use crossbeam_channel::unbounded;
-use immutable_cosmic_borrow::create_planet;
+use immutable_cosmic_borrow::{
+    DEFAULT_COMB_RULES, DEFAULT_GEN_RULES, create_planet, validate_rules,
+};
 use std::time::Duration;
 /// Test that a planet can be successfully created using the create_planet function
 #[test]
@@ -19,14 +23,17 @@ fn test_planet_creation() {
     // Fill in the missing arguments with placeholder/example values as per the function signature
     // Example: (bool, f32, f32, Duration, Duration, i32, (Receiver, Sender), Receiver)
     let planet = create_planet(
-        false,                      // Example bool
-        0.0,                        // Example f32
-        0.0,                        // Example f32
-        Duration::from_secs(1),     // Example Duration
-        Duration::from_millis(100), // Example Duration
-        2,                          // Example i32 (number of resources)
-        (rx_orch_in, tx_orch_out),  // Channels
-        rx_expl_in,                 // Channel
+        false,                       // Example bool
+        0.0,                         // Example f32
+        0.0,                         // Example f32
+        Duration::from_secs(1),      // Example Duration
+        Duration::from_millis(100),  // Example Duration
+        2,                           // Example i32 (number of resources)
+        PlanetType::C,               // Planet type
+        DEFAULT_GEN_RULES.to_vec(),  // Generation rules
+        DEFAULT_COMB_RULES.to_vec(), // Combination rules
+        (rx_orch_in, tx_orch_out),   // Channels
+        rx_expl_in,                  // Channel
     );
 
     assert!(
@@ -35,3 +42,141 @@ fn test_planet_creation() {
         planet.err()
     );
 }
+
+/// Test that every planet type can be created with rules it allows
+#[test]
+fn test_planet_creation_with_type_and_rules() {
+    let test_cases = [
+        (PlanetType::A, vec![BasicResourceType::Oxygen], vec![]),
+        (
+            PlanetType::B,
+            vec![BasicResourceType::Carbon, BasicResourceType::Silicon],
+            vec![ComplexResourceType::Diamond],
+        ),
+        (
+            PlanetType::C,
+            vec![BasicResourceType::Hydrogen],
+            vec![ComplexResourceType::Water, ComplexResourceType::Life],
+        ),
+        (
+            PlanetType::D,
+            vec![
+                BasicResourceType::Oxygen,
+                BasicResourceType::Hydrogen,
+                BasicResourceType::Carbon,
+                BasicResourceType::Silicon,
+            ],
+            vec![],
+        ),
+    ];
+
+    for (planet_type, gen_rules, comb_rules) in test_cases {
+        let (_tx_orch_in, rx_orch_in) = unbounded::<OrchestratorToPlanet>();
+        let (tx_orch_out, _rx_orch_out) = unbounded::<PlanetToOrchestrator>();
+        let (_tx_expl_in, rx_expl_in) = unbounded::<ExplorerToPlanet>();
+
+        let planet = create_planet(
+            false,
+            0.0,
+            0.0,
+            Duration::from_secs(1),
+            Duration::from_millis(100),
+            3,
+            planet_type,
+            gen_rules,
+            comb_rules,
+            (rx_orch_in, tx_orch_out),
+            rx_expl_in,
+        );
+
+        assert!(
+            planet.is_ok(),
+            "Planet type {:?} creation should succeed, but got: {:?}",
+            planet_type,
+            planet.err()
+        );
+    }
+}
+
+/// Test that rules not allowed by the planet type are rejected
+#[test]
+fn test_invalid_rules_rejected() {
+    let test_cases = [
+        // Empty generation rules
+        (PlanetType::C, vec![], vec![]),
+        // Type A and C can only generate one resource
+        (
+            PlanetType::A,
+            vec![BasicResourceType::Oxygen, BasicResourceType::Carbon],
+            vec![],
+        ),
+        (
+            PlanetType::C,
+            vec![BasicResourceType::Hydrogen, BasicResourceType::Carbon],
+            vec![],
+        ),
+        // Type A and D can not combine
+        (
+            PlanetType::A,
+            vec![BasicResourceType::Oxygen],
+            vec![ComplexResourceType::Water],
+        ),
+        (
+            PlanetType::D,
+            vec![BasicResourceType::Oxygen],
+            vec![ComplexResourceType::Water],
+        ),
+        // Type B can combine only one resource
+        (
+            PlanetType::B,
+            vec![BasicResourceType::Oxygen],
+            vec![ComplexResourceType::Water, ComplexResourceType::Life],
+        ),
+        // Duplicated rules
+        (
+            PlanetType::D,
+            vec![BasicResourceType::Oxygen, BasicResourceType::Oxygen],
+            vec![],
+        ),
+        (
+            PlanetType::C,
+            vec![BasicResourceType::Hydrogen],
+            vec![ComplexResourceType::Water, ComplexResourceType::Water],
+        ),
+    ];
+
+    for (planet_type, gen_rules, comb_rules) in test_cases {
+        let (_tx_orch_in, rx_orch_in) = unbounded::<OrchestratorToPlanet>();
+        let (tx_orch_out, _rx_orch_out) = unbounded::<PlanetToOrchestrator>();
+        let (_tx_expl_in, rx_expl_in) = unbounded::<ExplorerToPlanet>();
+
+        let planet = create_planet(
+            false,
+            0.0,
+            0.0,
+            Duration::from_secs(1),
+            Duration::from_millis(100),
+            4,
+            planet_type,
+            gen_rules,
+            comb_rules,
+            (rx_orch_in, tx_orch_out),
+            rx_expl_in,
+        );
+
+        assert!(planet.is_err(), "Planet creation should fail");
+    }
+}
+
+/// Test that only repeated rules are rejected before the planet checks its type constraints
+#[test]
+fn test_validate_rules() {
+    use BasicResourceType::{Carbon, Oxygen};
+    use ComplexResourceType::{Life, Water};
+
+    assert!(validate_rules(&[Oxygen, Carbon], &[Water, Life]).is_ok());
+    // Missing rules and rules not allowed by a planet type are up to the planet
+    assert!(validate_rules(&[], &[]).is_ok());
+    assert!(validate_rules(&[Oxygen, Oxygen], &[]).is_err());
+    assert!(validate_rules(&[Oxygen], &[Water, Water]).is_err());
+}
@@ -0,0 +1,175 @@
+#![allow(clippy::pedantic)]
+
+use immutable_cosmic_borrow::Ai;
+use immutable_cosmic_borrow::priors::{EstimatorPrior, PriorFit, PriorFitter};
+use immutable_cosmic_borrow::replay::Session;
+use std::time::Duration;
+use std::{fs, process};
+
+fn debug_line(timestamp: u64, planet_id: u32, action: &str) -> String {
+    format!(
+        "[DEBUG immutable_cosmic_borrow] LogEvent {{ timestamp_unix: {timestamp}, sender: Some(Participant {{ actor_type: Planet, id: {planet_id} }}), receiver: Some(Participant {{ actor_type: Orchestrator, id: 0 }}), event_type: InternalPlanetAction, channel: Debug, payload: {{\"action\": \"{action}\", \"asteroid_intensity\": \"0.500000\", \"sun_intensity\": \"0.500000\", \"sunray_probability\": \"0.500000\"}} }}"
+    )
+}
+
+fn ack_line(timestamp: u64, planet_id: u32, message: &str) -> String {
+    format!(
+        "[TRACE immutable_cosmic_borrow] LogEvent {{ timestamp_unix: {timestamp}, sender: Some(Participant {{ actor_type: Planet, id: {planet_id} }}), receiver: Some(Participant {{ actor_type: Orchestrator, id: 0 }}), event_type: MessagePlanetToOrchestrator, channel: Trace, payload: {{\"message\": \"{message}\"}} }}"
+    )
+}
+
+/// Test that counter updates are fitted per planet, from both log formats
+#[test]
+fn test_fit_from_logs() {
+    let sunray = "update_sunray_counter";
+    let asteroid = "update_asteroid_counter";
+    let lines = [
+        debug_line(100, 1, sunray),
+        debug_line(101, 2, asteroid),
+        "unrelated line".to_string(),
+        debug_line(102, 1, asteroid),
+        debug_line(103, 1, asteroid),
+        debug_line(103, 1, "sunray"),
+        debug_line(104, 1, asteroid),
+        debug_line(106, 1, sunray),
+        format!(
+            "LogEvent {{ ts: 111, sender: Planet#2, receiver: Orchestrator#0, event: InternalPlanetAction, channel: Debug, payload: {{\"action\": \"{sunray}\"}} }}"
+        ),
+    ];
+
+    let mut fitter = PriorFitter::new();
+    let found = fitter.read_log(lines.join("\n").as_bytes()).unwrap();
+    assert_eq!(found, 7);
+
+    let fit = fitter.fit().expect("Fit failed");
+    assert_eq!(fit.sunrays, 3);
+    assert_eq!(fit.asteroids, 4);
+    // 6s for planet 1 and 10s for planet 2
+    assert_eq!(fit.span, Duration::from_secs(16));
+    assert!((fit.sunray_rate - 3.5 / 16.0).abs() < 1e-9);
+    assert!((fit.asteroid_rate - 4.5 / 16.0).abs() < 1e-9);
+
+    // Bursts of 3 on planet 1 and 1 on planet 2
+    assert_eq!(fit.max_asteroid_burst, 3);
+    assert!((fit.mean_asteroid_burst - 2.0).abs() < 1e-9);
+    assert!((fit.asteroid_after_asteroid - 2.0 / 4.0).abs() < 1e-9);
+
+    let prior = fit.prior().unwrap();
+    assert!(prior.sunray_probability() < 0.5);
+    assert!(fit.to_string().contains("prior: "));
+}
+
+/// Test that logs are split at the start and stop acks, so the time stopped is not counted
+#[test]
+fn test_log_split_at_stops() {
+    let lines = [
+        ack_line(199, 3, "StartPlanetAIResult"),
+        debug_line(200, 3, "update_sunray_counter"),
+        debug_line(202, 3, "update_asteroid_counter"),
+        ack_line(203, 3, "StopPlanetAIResult"),
+        ack_line(300, 3, "StartPlanetAIResult"),
+        debug_line(301, 3, "update_sunray_counter"),
+        debug_line(304, 3, "update_sunray_counter"),
+    ];
+    let mut fitter = PriorFitter::new();
+    assert_eq!(fitter.read_log(lines.join("\n").as_bytes()).unwrap(), 4);
+    let fit = fitter.fit().expect("Fit failed");
+    assert_eq!(fit.span, Duration::from_secs(5));
+    assert!((fit.sunray_rate - 3.5 / 5.0).abs() < 1e-9);
+}
+
+/// Test that sessions are split at stops, and that a fitted prior warms up a new AI
+#[test]
+fn test_fit_from_session_and_apply() {
+    let lines = [
+        r#"{"event":"session","version":1,"random_mode":false,"basic_gen_coeff":1,"complex_gen_coeff":1,"half_life_ns":1000000000,"min_time_constant_ns":0,"defense_window_ns":0,"seed":"1","planet_id":1,"planet_type":"C","gen_rules":["Hydrogen"],"comb_rules":[]}"#,
+        r#"{"event":"input","t_ns":0,"input":"start"}"#,
+        r#"{"event":"input","t_ns":1000000000,"input":"sunray"}"#,
+        r#"{"event":"input","t_ns":2000000000,"input":"sunray"}"#,
+        r#"{"event":"input","t_ns":3000000000,"input":"sunray"}"#,
+        r#"{"event":"input","t_ns":4000000000,"input":"stop"}"#,
+        r#"{"event":"input","t_ns":50000000000,"input":"start"}"#,
+        r#"{"event":"input","t_ns":51000000000,"input":"asteroid"}"#,
+        r#"{"event":"input","t_ns":52000000000,"input":"sunray"}"#,
+    ];
+    let path = std::env::temp_dir().join(format!("icb_prior_session_{}.jsonl", process::id()));
+    fs::write(&path, lines.join("\n")).unwrap();
+
+    let mut fitter = PriorFitter::new();
+    assert_eq!(fitter.load(&path).unwrap(), 5);
+    let _ = fs::remove_file(&path);
+
+    // The time stopped is not counted
+    let fit = fitter.fit().expect("Fit failed");
+    assert_eq!(fit.span, Duration::from_secs(3));
+    assert_eq!(fit.max_asteroid_burst, 1);
+
+    let prior_path = std::env::temp_dir().join(format!("icb_prior_{}.json", process::id()));
+    fit.save(&prior_path).unwrap();
+    let prior = EstimatorPrior::load(&prior_path).expect("Prior not loaded");
+    let _ = fs::remove_file(&prior_path);
+    assert!((prior.sun_intensity() - 4.5 / 3.0).abs() < 1e-6);
+    assert!((prior.asteroid_intensity() - 1.5 / 3.0).abs() < 1e-6);
+
+    let ai = Ai::new(false, 0.5, 0.5, Duration::from_secs(10), Duration::ZERO)
+        .with_estimator_prior(prior);
+    let estimator = ai.stats().estimator.unwrap();
+    assert_eq!(estimator.sunray_probability, prior.sunray_probability());
+    assert!((estimator.sunray_probability - 0.75).abs() < 1e-6);
+
+    // Sessions record the prior so they replay with it
+    let header = lines[0].replace(
+        r#""comb_rules":[]"#,
+        r#""comb_rules":[],"prior":{"sun_intensity":3,"asteroid_intensity":1}"#,
+    );
+    let session = Session::read(header.as_bytes()).expect("Session not parsed");
+    assert_eq!(
+        session.config.prior,
+        Some(EstimatorPrior::new(3.0, 1.0).unwrap())
+    );
+}
+
+/// Test that invalid inputs are rejected
+#[test]
+fn test_invalid_priors_rejected() {
+    assert!(PriorFitter::new().fit().is_err());
+
+    // Every event at the same time
+    let mut fitter = PriorFitter::new();
+    fitter
+        .read_log(debug_line(5, 1, "update_sunray_counter").as_bytes())
+        .unwrap();
+    assert!(fitter.fit().is_err());
+
+    assert!(EstimatorPrior::new(-1.0, 1.0).is_err());
+    assert!(EstimatorPrior::new(0.0, 0.0).is_err());
+    assert!(EstimatorPrior::new(f32::NAN, 1.0).is_err());
+    assert!(EstimatorPrior::new(0.0, 1.0).is_ok());
+
+    let path = std::env::temp_dir().join(format!("icb_prior_invalid_{}.json", process::id()));
+    fs::write(
+        &path,
+        "{\"version\":1,\"sun_intensity\":-2,\"asteroid_intensity\":1}",
+    )
+    .unwrap();
+    assert!(EstimatorPrior::load(&path).is_err());
+    let _ = fs::remove_file(&path);
+
+    // Fitted rates are validated as intensities
+    let fit = PriorFit {
+        sunrays: 1,
+        asteroids: 0,
+        span: Duration::from_secs(1),
+        sunray_rate: f64::INFINITY,
+        asteroid_rate: 0.5,
+        mean_asteroid_burst: 0.0,
+        max_asteroid_burst: 0,
+        asteroid_after_asteroid: 0.0,
+    };
+    assert!(fit.prior().is_err());
+    assert!(fit.save(&path).is_err());
+    assert!(
+        fit.to_string()
+            .ends_with("prior: Intensities must be finite and non-negative")
+    );
+}
@@ -0,0 +1,269 @@
+#![allow(clippy::pedantic)]
+
+mod common;
+
+use common::{generate, hydrogen_options, spawn_started};
+use common_game::components::planet::PlanetType;
+use common_game::components::resource::BasicResourceType;
+use immutable_cosmic_borrow::qlearning::{
+    ExplorerTier, QLearningPolicy, QState, QTable, Simulator, THREAT_BUCKETS, Traffic,
+};
+use immutable_cosmic_borrow::replay::{self, Session};
+use immutable_cosmic_borrow::{
+    DecisionPolicy, DecisionPolicyKind, DecisionReason, DecisionRecord, DecisionRequest,
+    PolicyInputs, PolicyOutcome,
+};
+use std::sync::{Arc, Mutex};
+use std::{fs, process};
+
+// Accepts everything, keeping what it was given and told
+#[derive(Clone, Default)]
+struct Recording {
+    inputs: Arc<Mutex<Vec<PolicyInputs>>>,
+    outcomes: Arc<Mutex<Vec<PolicyOutcome>>>,
+}
+
+impl DecisionPolicy for Recording {
+    fn name(&self) -> &str {
+        "recording"
+    }
+
+    fn decide(&mut self, inputs: &PolicyInputs) -> bool {
+        self.inputs.lock().unwrap().push(*inputs);
+        true
+    }
+
+    fn observe(&mut self, outcome: PolicyOutcome) {
+        self.outcomes.lock().unwrap().push(outcome);
+    }
+}
+
+fn inputs(p_sunray: Option<f32>, has_rocket: bool, served_requests: u64) -> PolicyInputs {
+    PolicyInputs {
+        explorer_id: 1,
+        request: DecisionRequest::GenerateResource(BasicResourceType::Hydrogen),
+        coefficient: 0.5,
+        has_rocket,
+        charged_cells: 1,
+        sunray_probability: p_sunray,
+        served_requests,
+    }
+}
+
+/// Test that the decision inputs are discretized in threat buckets and explorer tiers
+#[test]
+fn test_q_states() {
+    let state = QState::from_inputs(&inputs(Some(0.95), false, 0));
+    assert_eq!(state.threat, 0);
+    assert!(!state.has_rocket);
+    assert!(!state.spare_charge);
+    assert_eq!(state.tier, ExplorerTier::New);
+
+    assert_eq!(QState::from_inputs(&inputs(Some(0.5), true, 1)).threat, 2);
+    assert_eq!(
+        QState::from_inputs(&inputs(Some(0.0), true, 1)).threat,
+        THREAT_BUCKETS - 1
+    );
+    // Without an estimator the risk is the highest
+    assert_eq!(
+        QState::from_inputs(&inputs(None, true, 1)).threat,
+        THREAT_BUCKETS - 1
+    );
+
+    let mut spare = inputs(Some(0.5), true, 4);
+    spare.charged_cells = 2;
+    assert!(QState::from_inputs(&spare).spare_charge);
+    assert_eq!(ExplorerTier::of(4), ExplorerTier::Returning);
+    assert_eq!(ExplorerTier::of(5), ExplorerTier::Regular);
+}
+
+/// Test that the values are learned from the rewards, and that a frozen policy does not learn
+#[test]
+fn test_learning_from_rewards() {
+    let safe = inputs(Some(0.9), false, 0);
+    let state = QState::from_inputs(&safe);
+    let mut policy = QLearningPolicy::new(1)
+        .with_exploration(0.0)
+        .with_learning_rate(1.0)
+        .with_discount(0.0);
+    assert_eq!(policy.name(), "qlearning");
+
+    // Ties are accepted
+    assert!(policy.decide(&safe));
+    policy.observe(PolicyOutcome::Served { explorer_id: 1 });
+    assert!(policy.decide(&safe));
+    assert_eq!(policy.table().value(state, true), 1.0);
+
+    // Surviving an asteroid is not rewarded, being destroyed ends the episode
+    policy.observe(PolicyOutcome::Asteroid { destroyed: false });
+    policy.observe(PolicyOutcome::Asteroid { destroyed: true });
+    let table = policy.table();
+    assert_eq!(table.value(state, true), -10.0);
+    assert_eq!(table.value(state, false), 0.0);
+    assert!(!table.best_action(state));
+    assert!(!policy.decide(&safe));
+
+    let mut frozen = QLearningPolicy::frozen(table.clone());
+    assert!(frozen.is_frozen());
+    for _ in 0..3 {
+        assert!(!frozen.decide(&safe));
+        frozen.observe(PolicyOutcome::Asteroid { destroyed: true });
+    }
+    assert_eq!(frozen.table(), table);
+}
+
+/// Test that a table is saved and loaded back, and that invalid tables are rejected
+#[test]
+fn test_save_and_load_table() {
+    let path = std::env::temp_dir().join(format!("icb_q_table_{}.json", process::id()));
+    let mut policy = QLearningPolicy::new(3).with_exploration(1.0);
+    for round in 0..50 {
+        let p_sunray = (round % 10) as f32 / 10.0;
+        policy.decide(&inputs(Some(p_sunray), round % 3 == 0, round % 7));
+        policy.observe(PolicyOutcome::Served { explorer_id: 1 });
+    }
+    policy.observe(PolicyOutcome::Asteroid { destroyed: true });
+    let table = policy.table();
+    assert_ne!(table, QTable::new());
+
+    table.save(&path).unwrap();
+    let loaded = QTable::load(&path).unwrap();
+    assert_eq!(loaded, table);
+
+    fs::write(&path, r#"{"version":1,"states":2,"values":[[0,0],[0,0]]}"#).unwrap();
+    let err = QTable::load(&path).unwrap_err();
+    assert!(err.contains("Expected 60 states, found 2"), "{err}");
+    fs::write(&path, r#"{"version":2,"values":[]}"#).unwrap();
+    let err = QTable::load(&path).unwrap_err();
+    let _ = fs::remove_file(&path);
+    assert!(err.contains("Unsupported table version 2"), "{err}");
+}
+
+/// Test that an active policy decides once a charge can be spent, is told the outcomes and
+/// is needed to replay its sessions
+#[test]
+fn test_active_policy() {
+    let path = std::env::temp_dir().join(format!("icb_policy_session_{}.jsonl", process::id()));
+    let options = hydrogen_options(PlanetType::C, 0.5);
+    let policy = Recording::default();
+    let (tx_records, rx_records) = crossbeam_channel::unbounded();
+    let ai = options
+        .create_ai()
+        .with_seed(5)
+        .with_decision_policy(policy.clone())
+        .with_decision_sink(tx_records)
+        .with_session_recording(&path)
+        .unwrap();
+    let mut harness = spawn_started(&options, ai);
+
+    assert!(!generate(&mut harness));
+    // The first charge builds the rocket
+    harness.sunray();
+    harness.sunray();
+    assert!(generate(&mut harness));
+    harness.sunray();
+    assert!(generate(&mut harness));
+    harness.asteroid();
+    harness.asteroid();
+    harness.stop();
+    drop(harness);
+
+    let records: Vec<DecisionRecord> = rx_records.try_iter().collect();
+    assert!(
+        records
+            .iter()
+            .all(|record| record.policy == DecisionPolicyKind::Custom)
+    );
+    let reasons: Vec<_> = records.iter().map(|record| record.reason).collect();
+    assert_eq!(
+        reasons,
+        [
+            DecisionReason::CellNotCharged,
+            DecisionReason::CustomPolicy,
+            DecisionReason::CustomPolicy,
+        ]
+    );
+    assert!(records[1].inputs.p_sunray.is_some());
+
+    let served: Vec<u64> = policy
+        .inputs
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|inputs| inputs.served_requests)
+        .collect();
+    assert_eq!(served, [0, 1]);
+    assert_eq!(
+        *policy.outcomes.lock().unwrap(),
+        [
+            PolicyOutcome::Served { explorer_id: 1 },
+            PolicyOutcome::Served { explorer_id: 1 },
+            PolicyOutcome::Asteroid { destroyed: false },
+            PolicyOutcome::Asteroid { destroyed: true },
+        ]
+    );
+
+    let session = Session::load(&path).unwrap();
+    let _ = fs::remove_file(&path);
+    assert_eq!(session.config.policy.as_deref(), Some("recording"));
+    let err = replay::replay(&session).unwrap_err();
+    assert!(err.contains("recorded with the recording policy"), "{err}");
+    let report = replay::replay_with_policy(&session, Recording::default()).unwrap();
+    assert!(report.is_match(), "{report:?}");
+    assert_eq!(report.decisions, 3);
+}
+
+/// Test that training in the simulator is reproducible and the frozen table loads in the
+/// planet options
+#[test]
+fn test_simulator_training() {
+    let simulator = Simulator::new(hydrogen_options(PlanetType::C, 0.5))
+        .with_traffic(Traffic {
+            explorers: 2,
+            ..Traffic::default()
+        })
+        .with_episodes(60)
+        .with_max_steps(200)
+        .with_seed(7);
+
+    let policy = QLearningPolicy::new(7);
+    let report = simulator.train(&policy).unwrap();
+    assert_eq!(report.episodes, 60);
+    assert!(report.destroyed <= 60);
+    assert!(report.served > 0);
+    assert!(report.to_string().starts_with("60 episodes, "), "{report}");
+    let table = policy.table();
+    assert_ne!(table, QTable::new());
+
+    let again = QLearningPolicy::new(7);
+    assert_eq!(simulator.train(&again).unwrap(), report);
+    assert_eq!(again.table(), table);
+
+    // Evaluating the frozen table leaves it as it is
+    let frozen = QLearningPolicy::frozen(table.clone());
+    let evaluation = simulator.train(&frozen).unwrap();
+    assert_eq!(frozen.table(), table);
+    let baseline = simulator.run(|_| None).unwrap();
+    assert_eq!(baseline.episodes, evaluation.episodes);
+
+    let path = std::env::temp_dir().join(format!("icb_trained_table_{}.json", process::id()));
+    table.save(&path).unwrap();
+    let mut options = hydrogen_options(PlanetType::C, 0.5);
+    let rest = options
+        .parse_args(["--q-table".to_string(), path.display().to_string()])
+        .unwrap();
+    let _ = fs::remove_file(&path);
+    assert!(rest.is_empty());
+    assert_eq!(options.q_table.as_ref(), Some(&table));
+    let loaded = Simulator::new(options)
+        .with_traffic(Traffic {
+            explorers: 2,
+            ..Traffic::default()
+        })
+        .with_episodes(60)
+        .with_max_steps(200)
+        .with_seed(7)
+        .run(|_| None)
+        .unwrap();
+    assert_eq!(loaded, evaluation);
+}
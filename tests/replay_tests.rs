@@ -0,0 +1,273 @@
+#![allow(clippy::pedantic)]
+
+mod common;
+
+use common::*;
+use common_game::components::asteroid::Asteroid;
+use common_game::components::planet::{Planet, PlanetType};
+use common_game::components::resource::{BasicResourceType, ComplexResourceType};
+use common_game::components::sunray::Sunray;
+use common_game::protocols::orchestrator_planet::{OrchestratorToPlanet, PlanetToOrchestrator};
+use common_game::protocols::planet_explorer::{ExplorerToPlanet, PlanetToExplorer};
+use immutable_cosmic_borrow::replay::{self, Session, SessionEntry, SessionInput, SessionRequest};
+use immutable_cosmic_borrow::testing::{PlanetHarness, expect};
+use immutable_cosmic_borrow::{
+    Ai, DEFAULT_COMB_RULES, DEFAULT_GEN_RULES, DecisionOutcome, DecisionRequest, MockClock,
+};
+use std::time::Duration;
+use std::{fs, process};
+
+/// Test that a recorded session replays with the same decisions, and that a tampered one
+/// reports the first divergence
+#[test]
+fn test_record_and_replay_session() {
+    let path = std::env::temp_dir().join(format!("icb_session_{}.jsonl", process::id()));
+
+    let (tx_orchestrator, rx_orchestrator_to_planet) =
+        crossbeam_channel::bounded::<OrchestratorToPlanet>(1);
+    let (tx_planet_to_orchestrator, rx_orchestrator) =
+        crossbeam_channel::bounded::<PlanetToOrchestrator>(1);
+    let (tx_explorer, rx_explorer_to_planet) = crossbeam_channel::bounded::<ExplorerToPlanet>(1);
+
+    let ai = Ai::new(
+        true,
+        0.5,
+        0.5,
+        Duration::from_millis(100),
+        Duration::from_secs(1),
+    )
+    .with_seed(42)
+    .with_session_recording(&path)
+    .expect("Session file not created");
+
+    let planet = Planet::new(
+        3,
+        PlanetType::C,
+        Box::new(ai),
+        DEFAULT_GEN_RULES.to_vec(),
+        DEFAULT_COMB_RULES.to_vec(),
+        (rx_orchestrator_to_planet, tx_planet_to_orchestrator),
+        rx_explorer_to_planet,
+    )
+    .expect("Planet creation failed");
+
+    let handle = start_thread(planet);
+    orchestrator_start_planet(&tx_orchestrator, &rx_orchestrator);
+
+    let (tx_to_explorer, rx_explorer) = crossbeam_channel::unbounded::<PlanetToExplorer>();
+    orchestrator_send(
+        &tx_orchestrator,
+        &rx_orchestrator,
+        OrchestratorToPlanet::IncomingExplorerRequest {
+            explorer_id: 9,
+            new_sender: tx_to_explorer,
+        },
+    );
+    let generate = || ExplorerToPlanet::GenerateResourceRequest {
+        explorer_id: 9,
+        resource: BasicResourceType::Hydrogen,
+    };
+    for _ in 0..4 {
+        orchestrator_send(
+            &tx_orchestrator,
+            &rx_orchestrator,
+            OrchestratorToPlanet::Sunray(Sunray::default()),
+        );
+        explorer_send(&tx_explorer, &rx_explorer, generate());
+    }
+    orchestrator_send(
+        &tx_orchestrator,
+        &rx_orchestrator,
+        OrchestratorToPlanet::Asteroid(Asteroid::default()),
+    );
+    explorer_send(&tx_explorer, &rx_explorer, generate());
+    orchestrator_stop_planet(&tx_orchestrator, &rx_orchestrator);
+    drop(tx_orchestrator);
+    let _ = handle.join();
+
+    let session = Session::load(&path).expect("Session not loaded");
+    let _ = fs::remove_file(&path);
+
+    assert_eq!(session.config.seed, 42);
+    assert_eq!(session.config.planet_id, 3);
+    assert_eq!(session.config.basic_gen_coeff, 0.5);
+    assert_eq!(session.config.half_life, Duration::from_millis(100));
+    let inputs: Vec<(Duration, SessionInput)> = session
+        .entries
+        .iter()
+        .filter_map(|entry| match entry {
+            SessionEntry::Input { elapsed, input } => Some((*elapsed, *input)),
+            SessionEntry::Decision(_) => None,
+        })
+        .collect();
+    assert_eq!(inputs[0].1, SessionInput::Start);
+    assert_eq!(inputs[1].1, SessionInput::ExplorerArrival(9));
+    assert_eq!(inputs.last().unwrap().1, SessionInput::Stop);
+    assert!(inputs.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+
+    let report = replay::replay(&session).expect("Replay failed");
+    assert_eq!(report.decisions, 5);
+    assert!(
+        report.is_match(),
+        "Unexpected divergence: {}",
+        report.divergence.unwrap()
+    );
+
+    // Flip the outcome of the third decision
+    let mut tampered = session.clone();
+    let decision = tampered
+        .entries
+        .iter_mut()
+        .filter_map(|entry| match entry {
+            SessionEntry::Decision(decision) => Some(decision),
+            SessionEntry::Input { .. } => None,
+        })
+        .nth(2)
+        .unwrap();
+    decision.outcome = match decision.outcome {
+        DecisionOutcome::Accepted => DecisionOutcome::Denied,
+        _ => DecisionOutcome::Accepted,
+    };
+
+    let report = replay::replay(&tampered).expect("Replay failed");
+    let divergence = report.divergence.expect("Expected a divergence");
+    assert_eq!(divergence.decision, 2);
+    assert!(divergence.expected.is_some() && divergence.actual.is_some());
+    assert!(divergence.to_string().contains("expected:"));
+}
+
+/// Test the replay of a hand-written session with a combination request
+#[test]
+fn test_replay_combination_request() {
+    let lines = [
+        r#"{"event":"session","version":1,"random_mode":false,"basic_gen_coeff":1,"complex_gen_coeff":1,"half_life_ns":1000000000,"min_time_constant_ns":0,"defense_window_ns":0,"seed":"1","planet_id":1,"planet_type":"C","gen_rules":["Hydrogen"],"comb_rules":["Water"]}"#,
+        r#"{"event":"input","t_ns":0,"input":"start"}"#,
+        r#"{"event":"input","t_ns":1000000,"input":"sunray"}"#,
+        r#"{"event":"input","t_ns":2000000,"input":"explorer_message","explorer_id":4,"request":"combine_resource","resource":"Water"}"#,
+    ];
+    let session = Session::read(lines.join("\n").as_bytes()).expect("Session not parsed");
+    assert_eq!(
+        session.entries[2],
+        SessionEntry::Input {
+            elapsed: Duration::from_millis(2),
+            input: SessionInput::ExplorerMessage(
+                4,
+                SessionRequest::CombineResource(ComplexResourceType::Water)
+            ),
+        }
+    );
+
+    // The recording has no decision, so the replayed one diverges
+    let report = replay::replay(&session).expect("Replay failed");
+    assert_eq!(report.inputs, 3);
+    let divergence = report.divergence.expect("Expected a divergence");
+    assert_eq!(divergence.input, 2);
+    assert!(divergence.expected.is_none());
+    let actual = divergence.actual.expect("Expected a replayed decision");
+    assert_eq!(
+        actual.request,
+        DecisionRequest::CombineResource(ComplexResourceType::Water)
+    );
+    assert_eq!(actual.outcome, DecisionOutcome::Accepted);
+}
+
+/// Test that a session recorded by an AI restored from a checkpoint replays from the restored
+/// state, recorded in the session header
+#[test]
+fn test_replay_with_checkpoint() {
+    let dir = std::env::temp_dir();
+    let checkpoint = dir.join(format!("icb_replay_checkpoint_{}.json", process::id()));
+    let path = dir.join(format!("icb_checkpoint_session_{}.jsonl", process::id()));
+    let _ = fs::remove_file(&checkpoint);
+    let options = planet_options(&[]);
+    let run = |ai: Ai, steps: &dyn Fn(&mut PlanetHarness)| {
+        let mut harness = PlanetHarness::spawn(|orchestrator_channels, explorers_receiver| {
+            options.create_planet_with_ai(ai, orchestrator_channels, explorers_receiver)
+        })
+        .expect("Planet creation failed");
+        harness.start();
+        steps(&mut harness);
+        harness.stop();
+        harness.kill();
+    };
+
+    // The first planet learns a threat
+    let ai = options
+        .create_ai()
+        .with_checkpoint_file(&checkpoint)
+        .unwrap();
+    run(ai, &|harness| {
+        harness.asteroid();
+        harness.asteroid();
+        harness.sunray();
+        harness.asteroid();
+    });
+
+    let ai = options
+        .create_ai()
+        .with_seed(3)
+        .with_checkpoint_file(&checkpoint)
+        .unwrap()
+        .with_session_recording(&path)
+        .unwrap();
+    run(ai, &|harness| {
+        harness.arrive(1);
+        for _ in 0..3 {
+            harness.sunray();
+            harness.explorer_send_and_expect(
+                ExplorerToPlanet::GenerateResourceRequest {
+                    explorer_id: 1,
+                    resource: BasicResourceType::Hydrogen,
+                },
+                expect::generated(),
+            );
+        }
+    });
+    let session = Session::load(&path).expect("Session not loaded");
+    let _ = fs::remove_file(&path);
+    let _ = fs::remove_file(&checkpoint);
+
+    assert!(session.config.checkpoint.is_some());
+    let report = replay::replay(&session).expect("Replay failed");
+    assert_eq!(report.decisions, 3);
+    assert!(report.is_match(), "{report:?}");
+
+    // From a cold estimator the threat differs
+    let mut cold = session.clone();
+    cold.config.checkpoint = None;
+    let report = replay::replay(&cold).expect("Replay failed");
+    assert!(!report.is_match());
+}
+
+/// Test that invalid session files are rejected with the line number
+#[test]
+fn test_invalid_session_rejected() {
+    assert!(Session::read("".as_bytes()).is_err());
+    assert!(Session::read(r#"{"event":"input"}"#.as_bytes()).is_err());
+
+    let lines = [
+        r#"{"event":"session","version":1,"random_mode":true,"basic_gen_coeff":0,"complex_gen_coeff":0,"half_life_ns":1,"min_time_constant_ns":0,"defense_window_ns":0,"seed":"1","planet_id":1,"planet_type":"C","gen_rules":["Hydrogen"],"comb_rules":[]}"#,
+        r#"{"event":"input","t_ns":0,"input":"teleport"}"#,
+    ];
+    let err = Session::read(lines.join("\n").as_bytes()).unwrap_err();
+    assert!(err.starts_with("line 2"), "{err}");
+}
+
+/// Test that the mock clock only moves forward when told to
+#[test]
+fn test_mock_clock() {
+    use immutable_cosmic_borrow::Clock;
+
+    let clock = MockClock::new();
+    let start = clock.now();
+    clock.advance(Duration::from_secs(2));
+    assert_eq!(clock.now() - start, Duration::from_secs(2));
+
+    let shared = clock.clone();
+    shared.set_elapsed(Duration::from_secs(5));
+    assert_eq!(clock.elapsed(), Duration::from_secs(5));
+
+    // Never goes back
+    shared.set_elapsed(Duration::from_secs(1));
+    assert_eq!(clock.elapsed(), Duration::from_secs(5));
+}
@@ -0,0 +1,173 @@
+#![allow(clippy::pedantic)]
+
+use immutable_cosmic_borrow::report::{AsteroidOutcome, ReportBuilder};
+use std::process::Command;
+use std::{fs, process};
+
+fn line(timestamp: u64, planet_id: u32, event_type: &str, payload: &[(&str, &str)]) -> String {
+    let payload: Vec<String> = payload
+        .iter()
+        .map(|(key, value)| format!("{key:?}: {value:?}"))
+        .collect();
+    format!(
+        "[DEBUG immutable_cosmic_borrow] LogEvent {{ timestamp_unix: {timestamp}, sender: Some(Participant {{ actor_type: Planet, id: {planet_id} }}), receiver: None, event_type: {event_type}, channel: Debug, payload: {{{}}} }}",
+        payload.join(", ")
+    )
+}
+
+fn action(timestamp: u64, planet_id: u32, payload: &[(&str, &str)]) -> String {
+    line(timestamp, planet_id, "InternalPlanetAction", payload)
+}
+
+fn message(timestamp: u64, planet_id: u32, payload: &[(&str, &str)]) -> String {
+    line(timestamp, planet_id, "MessagePlanetToOrchestrator", payload)
+}
+
+fn decision(
+    timestamp: u64,
+    explorer_id: &str,
+    resource: &str,
+    outcome: &str,
+    charged_cells: &str,
+) -> String {
+    action(
+        timestamp,
+        1,
+        &[
+            ("action", "decision"),
+            ("charged_cells", charged_cells),
+            ("explorer_id", explorer_id),
+            ("outcome", outcome),
+            ("request", "generate_resource"),
+            ("resource", resource),
+        ],
+    )
+}
+
+fn session_log() -> String {
+    let sunray = |timestamp, probability| {
+        action(
+            timestamp,
+            1,
+            &[
+                ("action", "update_sunray_counter"),
+                ("sunray_probability", probability),
+            ],
+        )
+    };
+    [
+        message(100, 1, &[("message", "StartPlanetAIResult")]),
+        sunray(102, "0.700000"),
+        message(102, 1, &[("charged_cells", "1"), ("message", "SunrayAck")]),
+        decision(103, "4", "Carbon", "accepted", "1"),
+        action(
+            103,
+            1,
+            &[("action", "energy_consumed"), ("charged_cells", "0")],
+        ),
+        decision(104, "4", "Carbon", "denied", "0"),
+        decision(105, "6", "Hydrogen", "unsupported", "0"),
+        sunray(106, "0.800000"),
+        message(106, 1, &[("charged_cells", "1"), ("message", "SunrayAck")]),
+        action(
+            107,
+            1,
+            &[
+                ("action", "update_asteroid_counter"),
+                ("sunray_probability", "0.400000"),
+            ],
+        ),
+        action(
+            107,
+            1,
+            &[("action", "energy_consumed"), ("charged_cells", "0")],
+        ),
+        action(107, 1, &[("action", "build_and_launch_rocket")]),
+        action(109, 1, &[("action", "no_rocket_available")]),
+        message(110, 1, &[("message", "StopPlanetAIResult")]),
+        // Events of other kinds or from other actors are ignored
+        line(111, 1, "MessageOrchestratorToPlanet", &[("message", "Sunray")]),
+        "unrelated line".to_string(),
+        // Another planet, in the Display format
+        "LogEvent { ts: 200, sender: Planet#2, receiver: none, event: InternalPlanetAction, channel: Debug, payload: {\"action\": \"ignore_asteroid_ai_inactive\", \"note\": \"a \\\"quoted\\\" value\"} }".to_string(),
+    ]
+    .join("\n")
+}
+
+/// Test the report built from a log with two planets
+#[test]
+fn test_session_report() {
+    let mut builder = ReportBuilder::new();
+    assert_eq!(builder.read_log(session_log().as_bytes()).unwrap(), 15);
+    let reports = builder.finish();
+    assert_eq!(reports.len(), 2);
+
+    let report = &reports[0];
+    assert_eq!(report.planet_id, Some(1));
+    assert_eq!((report.start, report.end), (100, 110));
+    assert_eq!(report.running_secs, 10);
+
+    let threat: Vec<(u64, &str, f32)> = report
+        .threat
+        .iter()
+        .map(|sample| (sample.time, sample.event, sample.sunray_probability))
+        .collect();
+    assert_eq!(
+        threat,
+        [(2, "sunray", 0.7), (6, "sunray", 0.8), (7, "asteroid", 0.4)]
+    );
+
+    let explorer = &report.explorers[&4];
+    assert_eq!((explorer.accepted, explorer.denied), (1, 1));
+    assert_eq!(explorer.accept_ratio(), Some(0.5));
+    assert_eq!(report.explorers[&6].unsupported, 1);
+    assert_eq!(report.explorers[&6].accept_ratio(), None);
+    assert_eq!(report.recipes["generate_resource Carbon"].accepted, 1);
+
+    assert_eq!(report.rocket_launches().collect::<Vec<_>>(), [7]);
+    assert_eq!(report.missed_asteroids().collect::<Vec<_>>(), [9]);
+
+    // Empty from the start to the first sunray, after the first generation, after the rocket
+    assert_eq!(report.empty_cell_secs, 2 + 3 + 3);
+
+    let text = report.to_string();
+    assert!(text.starts_with("planet 1: 10 s, running 10 s"));
+    assert!(text.contains("explorer 4: 1 accepted, 1 denied (50% accepted)"));
+    assert!(text.contains("rocket launches: 1 7s"));
+    assert!(text.contains("time with an empty cell: 8 s (80% of the running time)"));
+
+    assert_eq!(reports[1].planet_id, Some(2));
+    assert_eq!(
+        reports[1].asteroids,
+        [(0, AsteroidOutcome::MissedWhileStopped)]
+    );
+}
+
+/// Test the binary with CSV output
+#[test]
+fn test_report_binary_csv() {
+    let dir = std::env::temp_dir().join(format!("icb_report_{}", process::id()));
+    let log = dir.with_extension("log");
+    fs::write(&log, session_log()).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_icb-report"))
+        .arg("--csv")
+        .arg(&dir)
+        .arg(&log)
+        .output()
+        .expect("Binary not started");
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("planet 2: 0 s"));
+
+    let read = |name: &str| fs::read_to_string(dir.join(name)).unwrap();
+    let summary = read("summary.csv");
+    assert!(summary.contains("\n1,100,110,10,8,1,1,1,1\n"), "{summary}");
+    assert!(read("threat.csv").contains("\n1,7,asteroid,0.400000,0.600000\n"));
+    let decisions = read("decisions.csv");
+    assert!(decisions.contains("\n1,explorer,4,1,1,0,0,0.500000\n"));
+    assert!(decisions.contains("\n1,recipe,generate_resource Hydrogen,0,0,1,0,\n"));
+    assert!(read("asteroids.csv").contains("\n2,0,missed_while_stopped\n"));
+
+    let _ = fs::remove_dir_all(&dir);
+    let _ = fs::remove_file(&log);
+}
@@ -0,0 +1,163 @@
+#![allow(clippy::pedantic)]
+
+mod common;
+
+use common::{hydrogen_options, spawn_started};
+use common_game::components::planet::PlanetType;
+use common_game::components::resource::BasicResourceType;
+use common_game::protocols::planet_explorer::ExplorerToPlanet;
+use immutable_cosmic_borrow::bridge::PlanetOptions;
+use immutable_cosmic_borrow::testing::expect;
+use immutable_cosmic_borrow::{
+    AdaptivePolicy, Ai, DISAGREEMENT_BACKLOG, DecisionOutcome, DecisionPolicy, DecisionRecord,
+    PolicyInputs, RandomPolicy, StatsHandle,
+};
+
+// Accepts or denies everything
+struct Fixed(bool);
+
+impl DecisionPolicy for Fixed {
+    fn name(&self) -> &str {
+        if self.0 { "always" } else { "never" }
+    }
+
+    fn decide(&mut self, _: &PolicyInputs) -> bool {
+        self.0
+    }
+}
+
+// Plays sunrays, asteroids and generation requests of two explorers, returning the active
+// decisions
+fn play(options: &PlanetOptions, ai: Ai, rounds: usize) -> Vec<DecisionRecord> {
+    let (tx_records, rx_records) = crossbeam_channel::unbounded();
+    let ai = ai.with_decision_sink(tx_records);
+    let mut harness = spawn_started(options, ai);
+    harness.arrive(2);
+    for round in 0..rounds {
+        if round % 5 == 3 {
+            harness.asteroid();
+        } else {
+            harness.sunray();
+        }
+        for explorer_id in [1, 2] {
+            harness.explorer_send_and_expect(
+                ExplorerToPlanet::GenerateResourceRequest {
+                    explorer_id,
+                    resource: BasicResourceType::Hydrogen,
+                },
+                expect::generated(),
+            );
+        }
+    }
+    harness.stop();
+    rx_records.try_iter().collect()
+}
+
+/// Test that the adaptive policy run as a shadow of the adaptive mode always agrees with it
+#[test]
+fn test_adaptive_shadow_agrees() {
+    for coeff in [0.2, 0.4, 0.6] {
+        let options = hydrogen_options(PlanetType::D, coeff);
+        let ai = options.create_ai().with_shadow_policy(AdaptivePolicy);
+        let stats = ai.stats_handle();
+        let records = play(&options, ai, 40);
+
+        let shadow = &stats.snapshot().shadows[0];
+        assert_eq!(shadow.name, "adaptive");
+        let decided = records
+            .iter()
+            .filter(|record| record.inputs.p_sunray.is_some())
+            .count();
+        assert!(decided > 0);
+        assert_eq!(shadow.decisions, decided as u64);
+        assert_eq!(shadow.disagreement_count(), 0, "{shadow}");
+        assert_eq!(shadow.disagreement_ratio(), Some(0.0));
+    }
+}
+
+/// Test that the shadows count the disagreements without changing the active decisions
+#[test]
+fn test_shadow_disagreements() {
+    let options = PlanetOptions {
+        random_mode: true,
+        ..hydrogen_options(PlanetType::C, 0.5)
+    };
+    let ai = options
+        .create_ai()
+        .with_seed(11)
+        .with_shadow_policy(Fixed(true))
+        .with_shadow_policy(RandomPolicy::new(3))
+        .with_shadow_policy(AdaptivePolicy);
+    let stats = ai.stats_handle();
+    let records = play(&options, ai, 30);
+
+    let without_shadows = play(&options, options.create_ai().with_seed(11), 30);
+    let outcomes = |records: &[DecisionRecord]| -> Vec<_> {
+        records
+            .iter()
+            .map(|record| (record.outcome, record.inputs.random_sample))
+            .collect()
+    };
+    assert_eq!(outcomes(&records), outcomes(&without_shadows));
+
+    let snapshot = stats.snapshot();
+    let names: Vec<&str> = snapshot.shadows.iter().map(|s| s.name.as_str()).collect();
+    assert_eq!(names, ["always", "random", "adaptive"]);
+
+    // Every request denied by the random sample is accepted by the shadow accepting everything
+    let denied: Vec<&DecisionRecord> = records
+        .iter()
+        .filter(|record| {
+            record.outcome == DecisionOutcome::Denied && record.inputs.random_sample.is_some()
+        })
+        .collect();
+    let always = &snapshot.shadows[0];
+    assert!(!denied.is_empty());
+    assert_eq!(always.accepted_by_shadow_only, denied.len() as u64);
+    assert_eq!(always.denied_by_shadow_only, 0);
+    assert_eq!(always.disagreements.len(), denied.len());
+    assert!(
+        always
+            .disagreements
+            .iter()
+            .zip(&denied)
+            .all(|(disagreement, record)| disagreement.inputs.random_sample
+                == record.inputs.random_sample)
+    );
+
+    for shadow in &snapshot.shadows {
+        assert_eq!(shadow.decisions, snapshot.shadows[0].decisions);
+    }
+    let random = &snapshot.shadows[1];
+    assert!(random.disagreement_count() > 0);
+    assert!(random.to_string().starts_with(&format!(
+        "random: {} disagreements in {} decisions (",
+        random.disagreement_count(),
+        random.decisions
+    )));
+}
+
+/// Test that only the last disagreements are kept
+#[test]
+fn test_disagreement_backlog() {
+    let options = hydrogen_options(PlanetType::B, 1.0);
+    let ai = options.create_ai().with_shadow_policy(Fixed(false));
+    let stats: StatsHandle = ai.stats_handle();
+    let records = play(&options, ai, 100);
+
+    let accepted = records
+        .iter()
+        .filter(|record| record.outcome == DecisionOutcome::Accepted)
+        .count();
+    assert!(accepted > DISAGREEMENT_BACKLOG);
+    let never = &stats.snapshot().shadows[0];
+    assert_eq!(never.denied_by_shadow_only, accepted as u64);
+    assert_eq!(never.disagreements.len(), DISAGREEMENT_BACKLOG);
+    assert_eq!(
+        never.disagreements.back(),
+        records
+            .iter()
+            .rev()
+            .find(|record| record.outcome == DecisionOutcome::Accepted)
+    );
+}
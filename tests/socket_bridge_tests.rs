@@ -0,0 +1,198 @@
+#![allow(clippy::pedantic)]
+
+use immutable_cosmic_borrow::bridge::socket::{
+    BridgeAddress, BridgeClient, BridgeServer, MAX_FRAME_LEN, read_frame, write_frame,
+};
+use immutable_cosmic_borrow::bridge::{PlanetBridge, PlanetOptions};
+use std::io::Cursor;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
+fn spawn_bridge(args: &[&str]) -> PlanetBridge {
+    let mut options = PlanetOptions::default();
+    let rest = options
+        .parse_args(args.iter().map(|arg| arg.to_string()))
+        .expect("Invalid options");
+    assert!(rest.is_empty());
+    PlanetBridge::spawn(|orchestrator_channels, explorers_receiver| {
+        options.create_planet(orchestrator_channels, explorers_receiver)
+    })
+    .expect("Planet creation failed")
+}
+
+/// Test the frame encoding
+#[test]
+fn test_frames() {
+    let mut buffer = Vec::new();
+    write_frame(&mut buffer, r#"{"type":"start"}"#).unwrap();
+    write_frame(&mut buffer, "").unwrap();
+    assert_eq!(&buffer[..4], &[0, 0, 0, 16]);
+
+    let mut reader = Cursor::new(buffer);
+    assert_eq!(
+        read_frame(&mut reader).unwrap().as_deref(),
+        Some(r#"{"type":"start"}"#)
+    );
+    assert_eq!(read_frame(&mut reader).unwrap().as_deref(), Some(""));
+    assert_eq!(read_frame(&mut reader).unwrap(), None);
+
+    // Truncated payload, oversized frame
+    assert!(read_frame(&mut Cursor::new(vec![0, 0, 0, 5, b'{'])).is_err());
+    assert!(read_frame(&mut Cursor::new(vec![0xff, 0, 0, 0])).is_err());
+}
+
+/// Test that a frame nested too deep gets an error instead of overflowing the stack of the
+/// bridge, which keeps serving
+#[test]
+fn test_deeply_nested_frame() {
+    let bridge = spawn_bridge(&["--id", "2"]);
+    let server = BridgeServer::bind_tcp(bridge, 0).expect("Server not started");
+    let mut client = BridgeClient::connect(server.address()).unwrap();
+
+    let response = client.request(&"[".repeat(MAX_FRAME_LEN)).unwrap();
+    assert!(response.contains(r#""type":"error""#), "{response}");
+    assert!(response.contains("Nested deeper than"), "{response}");
+    assert_eq!(
+        client.request(r#"{"type":"start"}"#).unwrap(),
+        r#"{"planet_id":2,"type":"start_result"}"#
+    );
+}
+
+/// Test an orchestrator stand-in and two explorers, each on its own TCP connection
+#[test]
+fn test_tcp_bridge_multiple_explorers() {
+    let bridge = spawn_bridge(&[
+        "--id",
+        "6",
+        "--random",
+        "--basic-coeff",
+        "0",
+        "--gen",
+        "Carbon",
+    ]);
+    let server = BridgeServer::bind_tcp(bridge, 0).expect("Server not started");
+    let address = server.address().clone();
+    assert!(matches!(address, BridgeAddress::Tcp(address) if address.ip().is_loopback()));
+
+    let mut orchestrator = BridgeClient::connect(&address).unwrap();
+    assert_eq!(
+        orchestrator.request(r#"{"type":"start"}"#).unwrap(),
+        r#"{"planet_id":6,"type":"start_result"}"#
+    );
+    for explorer_id in [1, 2] {
+        let arrival = format!(r#"{{"type":"explorer_arrival","explorer_id":{explorer_id}}}"#);
+        assert!(
+            orchestrator
+                .request(&arrival)
+                .unwrap()
+                .contains(r#""ok":true"#)
+        );
+    }
+
+    let explorers: Vec<_> = [1, 2]
+        .into_iter()
+        .map(|explorer_id| {
+            let address = address.clone();
+            thread::spawn(move || {
+                let mut client = BridgeClient::connect(&address).unwrap();
+                let request =
+                    format!(r#"{{"type":"supported_resource","explorer_id":{explorer_id}}}"#);
+                let response = client.request(&request).unwrap();
+                assert_eq!(
+                    response,
+                    format!(
+                        r#"{{"explorer_id":{explorer_id},"resources":["Carbon"],"type":"supported_resource_response"}}"#
+                    )
+                );
+            })
+        })
+        .collect();
+    for explorer in explorers {
+        explorer.join().unwrap();
+    }
+
+    // The first explorer gets the only charged cell
+    assert!(
+        orchestrator
+            .request(r#"{"type":"sunray"}"#)
+            .unwrap()
+            .contains("sunray_ack")
+    );
+    let mut first = BridgeClient::connect(&address).unwrap();
+    assert!(
+        first
+            .request(r#"{"type":"generate_resource","explorer_id":1,"resource":"Carbon"}"#)
+            .unwrap()
+            .contains(r#""resource":"Carbon""#)
+    );
+    assert!(
+        first
+            .request(r#"{"type":"generate_resource","explorer_id":2,"resource":"Carbon"}"#)
+            .unwrap()
+            .contains(r#""explorer_id":2,"resource":null"#)
+    );
+    assert!(
+        first
+            .request("not json")
+            .unwrap()
+            .contains(r#""type":"error""#)
+    );
+
+    assert!(
+        orchestrator
+            .request(r#"{"type":"kill"}"#)
+            .unwrap()
+            .contains("kill_result")
+    );
+    // Other connections are answered until the server is shut down
+    assert!(
+        first
+            .request(r#"{"type":"available_energy_cell","explorer_id":1}"#)
+            .unwrap()
+            .contains("killed")
+    );
+    server.shutdown();
+}
+
+/// Test the binary serving a Unix domain socket
+#[cfg(unix)]
+#[test]
+fn test_planet_binary_unix_socket() {
+    let path = std::env::temp_dir().join(format!("icb_socket_{}.sock", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let mut child = Command::new(env!("CARGO_BIN_EXE_icb-planet"))
+        .args(["--id", "3", "--unix"])
+        .arg(&path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("Binary not started");
+
+    let address = BridgeAddress::Unix(path.clone());
+    let mut client = None;
+    for _ in 0..100 {
+        if let Ok(connected) = BridgeClient::connect(&address) {
+            client = Some(connected);
+            break;
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+    let mut client = client.expect("Socket not served");
+
+    assert_eq!(
+        client.request(r#"{"type":"start"}"#).unwrap(),
+        r#"{"planet_id":3,"type":"start_result"}"#
+    );
+    assert_eq!(
+        client.request(r#"{"type":"asteroid"}"#).unwrap(),
+        r#"{"planet_id":3,"rocket":false,"type":"asteroid_ack"}"#
+    );
+    assert_eq!(
+        client.request(r#"{"type":"kill"}"#).unwrap(),
+        r#"{"planet_id":3,"type":"kill_result"}"#
+    );
+    assert!(child.wait().unwrap().success());
+    assert!(!path.exists());
+}
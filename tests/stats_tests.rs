@@ -0,0 +1,161 @@
+#![allow(clippy::pedantic)]
+
+mod common;
+
+use common::*;
+use common_game::components::asteroid::Asteroid;
+use common_game::components::planet::{Planet, PlanetType};
+use common_game::components::resource::BasicResourceType;
+use common_game::components::sunray::Sunray;
+use common_game::protocols::orchestrator_planet::{OrchestratorToPlanet, PlanetToOrchestrator};
+use common_game::protocols::planet_explorer::{ExplorerToPlanet, PlanetToExplorer};
+use immutable_cosmic_borrow::{Ai, DEFAULT_COMB_RULES, DEFAULT_GEN_RULES};
+use std::time::Duration;
+
+/// Test that a new AI starts with empty counters and the initial estimator values
+#[test]
+fn test_initial_stats() {
+    let ai = Ai::new(
+        false,
+        0.5,
+        0.5,
+        Duration::from_secs(1),
+        Duration::from_millis(100),
+    );
+
+    let stats = ai.stats();
+    assert_eq!(stats.requests.generate_resource, 0);
+    assert_eq!(stats.decisions.accepted, 0);
+    assert_eq!(stats.asteroids_survived, 0);
+    assert_eq!(stats.rockets_built, 0);
+
+    let estimator = stats
+        .estimator
+        .expect("Adaptive AI should expose its estimator");
+    assert_eq!(estimator.sun_intensity, 0.5);
+    assert_eq!(estimator.asteroid_intensity, 0.5);
+    assert_eq!(estimator.sunray_probability, 0.5);
+}
+
+/// Test that the stats handle keeps tracking the AI after it is moved into a running planet
+#[test]
+fn test_stats_handle() {
+    let (tx_orchestrator, rx_orchestrator_to_planet) =
+        crossbeam_channel::bounded::<OrchestratorToPlanet>(1);
+    let (tx_planet_to_orchestrator, rx_orchestrator) =
+        crossbeam_channel::bounded::<PlanetToOrchestrator>(1);
+    let (tx_explorer, rx_explorer_to_planet) = crossbeam_channel::bounded::<ExplorerToPlanet>(1);
+
+    let ai = Ai::new(
+        true,
+        0.0,
+        0.0,
+        Duration::from_millis(100),
+        Duration::from_secs(1),
+    );
+    let stats_handle = ai.stats_handle();
+
+    let planet = Planet::new(
+        0,
+        PlanetType::C,
+        Box::new(ai),
+        DEFAULT_GEN_RULES.to_vec(),
+        DEFAULT_COMB_RULES.to_vec(),
+        (rx_orchestrator_to_planet, tx_planet_to_orchestrator),
+        rx_explorer_to_planet,
+    )
+    .expect("Planet creation failed");
+
+    // 1. Start thread
+    let handle = start_thread(planet);
+
+    // 2. Orchestrator starts the planet
+    orchestrator_start_planet(&tx_orchestrator, &rx_orchestrator);
+
+    // 3. Orchestrator tells the planet that an explorer arrived
+    let (tx_to_explorer, rx_explorer) = crossbeam_channel::unbounded::<PlanetToExplorer>();
+    orchestrator_send(
+        &tx_orchestrator,
+        &rx_orchestrator,
+        OrchestratorToPlanet::IncomingExplorerRequest {
+            explorer_id: 0,
+            new_sender: tx_to_explorer,
+        },
+    );
+
+    // 4. Explorer asks for Hydrogen without any charge
+    explorer_send(
+        &tx_explorer,
+        &rx_explorer,
+        ExplorerToPlanet::GenerateResourceRequest {
+            explorer_id: 0,
+            resource: BasicResourceType::Hydrogen,
+        },
+    );
+
+    // 5. Two sunrays: a rocket is built and the cell is charged again
+    for _ in 0..2 {
+        orchestrator_send(
+            &tx_orchestrator,
+            &rx_orchestrator,
+            OrchestratorToPlanet::Sunray(Sunray::default()),
+        );
+    }
+
+    // 6. Explorer asks for Hydrogen, then for Oxygen which is not supported
+    explorer_send(
+        &tx_explorer,
+        &rx_explorer,
+        ExplorerToPlanet::GenerateResourceRequest {
+            explorer_id: 0,
+            resource: BasicResourceType::Hydrogen,
+        },
+    );
+    explorer_send(
+        &tx_explorer,
+        &rx_explorer,
+        ExplorerToPlanet::GenerateResourceRequest {
+            explorer_id: 0,
+            resource: BasicResourceType::Oxygen,
+        },
+    );
+    explorer_send(
+        &tx_explorer,
+        &rx_explorer,
+        ExplorerToPlanet::AvailableEnergyCellRequest { explorer_id: 0 },
+    );
+
+    // 7. Two asteroids: the first is stopped by the rocket, the second destroys the planet
+    for _ in 0..2 {
+        orchestrator_send(
+            &tx_orchestrator,
+            &rx_orchestrator,
+            OrchestratorToPlanet::Asteroid(Asteroid::default()),
+        );
+    }
+
+    let stats = stats_handle.snapshot();
+    assert_eq!(stats.requests.generate_resource, 3);
+    assert_eq!(stats.requests.available_energy_cell, 1);
+    assert_eq!(stats.decisions.denied_no_charge, 1);
+    assert_eq!(stats.decisions.accepted, 1);
+    assert_eq!(stats.decisions.unsupported, 1);
+    assert_eq!(stats.sunrays_received, 2);
+    assert_eq!(stats.rockets_built, 1);
+    assert_eq!(stats.charges_consumed, 2);
+    assert_eq!(stats.asteroids_survived, 1);
+    assert_eq!(stats.asteroids_lost, 1);
+
+    let estimator = stats.estimator.expect("AI should expose its estimator");
+    assert!(
+        estimator.asteroid_intensity > estimator.sun_intensity,
+        "The last events were asteroids, but got {:?}",
+        estimator
+    );
+
+    // 8. Orchestrator kills planet
+    orchestrator_kill_planet(&tx_orchestrator, &rx_orchestrator);
+
+    drop(tx_orchestrator);
+    let _ = handle.join();
+}
@@ -0,0 +1,170 @@
+#![allow(clippy::pedantic)]
+
+use common_game::components::planet::{
+    DummyPlanetState, Planet, PlanetAI, PlanetState, PlanetType,
+};
+use common_game::components::resource::{BasicResourceType, Combinator, Generator};
+use common_game::components::rocket::Rocket;
+use common_game::components::sunray::Sunray;
+use common_game::protocols::orchestrator_planet::OrchestratorToPlanet;
+use common_game::protocols::planet_explorer::{ExplorerToPlanet, PlanetToExplorer};
+use immutable_cosmic_borrow::bridge::PlanetOptions;
+use immutable_cosmic_borrow::testing::{PlanetHarness, expect};
+use immutable_cosmic_borrow::{DEFAULT_COMB_RULES, DEFAULT_GEN_RULES};
+use std::collections::HashSet;
+use std::time::Duration;
+
+fn harness() -> PlanetHarness {
+    let options = PlanetOptions {
+        random_mode: true,
+        basic_gen_coeff: 0.0,
+        ..PlanetOptions::default()
+    };
+    PlanetHarness::spawn(|orchestrator_channels, explorers_receiver| {
+        options.create_planet(orchestrator_channels, explorers_receiver)
+    })
+    .expect("Planet creation failed")
+}
+
+fn generate(explorer_id: u32) -> ExplorerToPlanet {
+    ExplorerToPlanet::GenerateResourceRequest {
+        explorer_id,
+        resource: BasicResourceType::Hydrogen,
+    }
+}
+
+// AI whose asteroid handler panics, the rest is never called in these tests
+struct PanickingAi;
+
+impl PlanetAI for PanickingAi {
+    fn handle_sunray(&mut self, _: &mut PlanetState, _: &Generator, _: &Combinator, _: Sunray) {}
+
+    fn handle_asteroid(
+        &mut self,
+        _: &mut PlanetState,
+        _: &Generator,
+        _: &Combinator,
+    ) -> Option<Rocket> {
+        panic!("asteroid handler exploded");
+    }
+
+    fn handle_internal_state_req(
+        &mut self,
+        state: &mut PlanetState,
+        _: &Generator,
+        _: &Combinator,
+    ) -> DummyPlanetState {
+        state.to_dummy()
+    }
+
+    fn handle_explorer_msg(
+        &mut self,
+        _: &mut PlanetState,
+        _: &Generator,
+        _: &Combinator,
+        _: ExplorerToPlanet,
+    ) -> Option<PlanetToExplorer> {
+        None
+    }
+}
+
+fn panicking_harness() -> PlanetHarness {
+    PlanetHarness::spawn(|orchestrator_channels, explorers_receiver| {
+        Planet::new(
+            3,
+            PlanetType::C,
+            Box::new(PanickingAi),
+            DEFAULT_GEN_RULES.to_vec(),
+            DEFAULT_COMB_RULES.to_vec(),
+            orchestrator_channels,
+            explorers_receiver,
+        )
+    })
+    .expect("Planet creation failed")
+}
+
+/// Test the typed helpers through a whole session
+#[test]
+fn test_harness_session() {
+    let mut harness = harness();
+
+    // Stopped until started
+    harness.send_and_expect(
+        OrchestratorToPlanet::Sunray(Sunray::default()),
+        expect::stopped(),
+    );
+    harness.start();
+
+    harness.arrive(4);
+    let resources = harness.explorer_send_and_expect(
+        ExplorerToPlanet::SupportedResourceRequest { explorer_id: 4 },
+        expect::supported_resources(),
+    );
+    assert_eq!(resources, HashSet::from([BasicResourceType::Hydrogen]));
+    assert!(
+        harness
+            .explorer_send_and_expect(generate(4), expect::generated())
+            .is_none()
+    );
+
+    harness.sunray();
+    let state = harness.send_and_expect(
+        OrchestratorToPlanet::InternalStateRequest,
+        expect::internal_state(),
+    );
+    assert_eq!(state.charged_cells_count, 1);
+    let hydrogen = harness.explorer_send_and_expect(generate(4), expect::generated());
+    assert!(hydrogen.unwrap().to_hydrogen().is_ok());
+
+    // No charge left for a rocket
+    assert!(harness.asteroid().is_none());
+
+    harness.stop();
+    harness.explorer_send_and_expect(generate(4), expect::explorer_stopped());
+    harness.start();
+    harness.depart(4);
+    harness.kill();
+    assert!(harness.join().is_err(), "The planet was joined twice");
+}
+
+/// Test that the planet thread is joined when the harness is dropped
+#[test]
+fn test_harness_drop_joins() {
+    let mut harness = harness();
+    harness.start();
+    harness.sunray();
+    drop(harness);
+
+    // Dropped while stopped too
+    drop(self::harness());
+}
+
+/// Test the failure of an unexpected answer
+#[test]
+#[should_panic(expected = "Expected StartPlanetAIResult, got SunrayAck")]
+fn test_harness_mismatch() {
+    let mut harness = harness();
+    harness.start();
+    harness.send_and_expect(
+        OrchestratorToPlanet::Sunray(Sunray::default()),
+        expect::start_result(),
+    );
+}
+
+/// Test that the planet does not answer a start while running
+#[test]
+#[should_panic(expected = "The planet did not answer within 100ms")]
+fn test_harness_timeout() {
+    let mut harness = harness().with_timeout(Duration::from_millis(100));
+    harness.start();
+    harness.start();
+}
+
+/// Test that a panic of the planet thread fails the test with its message
+#[test]
+#[should_panic(expected = "asteroid handler exploded")]
+fn test_harness_surfaces_panics() {
+    let mut harness = panicking_harness();
+    harness.start();
+    harness.asteroid();
+}